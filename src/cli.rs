@@ -1,8 +1,11 @@
+use crate::i18n::Message;
+use crate::robustness::Attack;
+use crate::watermark::repetition::Repetition;
 use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
 /// 数字水印CLI工具
-#[derive(Parser)]
+#[derive(Parser, Clone)]
 #[command(author, version, about, long_about = None)]
 pub struct Cli {
     #[command(subcommand)]
@@ -11,39 +14,241 @@ pub struct Cli {
     /// 详细输出
     #[arg(short, long)]
     pub verbose: bool,
+
+    /// 只检查输入、容量和预估质量，不嵌入也不写出任何文件（仅用于 embed）
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    /// 允许覆盖已存在的输出文件（默认拒绝，仅用于 embed 和 extract）
+    #[arg(long, global = true, conflicts_with = "no_clobber")]
+    pub overwrite: bool,
+
+    /// 拒绝覆盖已存在的输出文件，与默认行为相同
+    #[arg(long, global = true)]
+    pub no_clobber: bool,
+
+    /// 单个FFmpeg进程的最长运行秒数，超时后终止进程并报错，0 表示不限制
+    #[arg(long, global = true, value_name = "SECS", default_value_t = crate::media::ffmpeg::DEFAULT_FFMPEG_TIMEOUT.as_secs())]
+    pub timeout: u64,
+
+    /// FFmpeg因资源暂时不足等原因失败时的重试次数（指数退避），0 表示不重试
+    #[arg(long, global = true, value_name = "N", default_value_t = crate::media::ffmpeg::DEFAULT_FFMPEG_RETRIES)]
+    pub ffmpeg_retries: u32,
+
+    /// 结果在stdout上的输出格式：json（完整的单行JSON）、text（提取到的水印或简要结果）、csv（固定表头，每个结果一行）
+    #[arg(long, global = true, value_enum, default_value = "json")]
+    pub format: OutputFormat,
+
+    /// 错误、警告和进度信息使用的语言，未指定时按 LANG 环境变量选择，默认中文
+    #[arg(long, global = true, value_enum)]
+    pub lang: Option<Lang>,
 }
 
-#[derive(Subcommand)]
+#[derive(Subcommand, Clone)]
 pub enum Commands {
     /// 嵌入水印
     Embed {
-        /// 输入文件路径
+        /// 输入文件路径；也可以是目录或通配符（如 "photos/*.png"，通配符只能用于文件名部分），此时批量处理
         #[arg(short, long)]
         input: PathBuf,
 
-        /// 输出文件路径
+        /// 输出文件路径；批量处理时作为输出目录，保留原文件名
         #[arg(short, long)]
         output: PathBuf,
 
-        /// 水印内容（文本或文件路径）
-        #[arg(short, long)]
-        watermark: String,
+        /// 水印内容（文本或文件路径）；重复指定时按顺序嵌入多个水印，各占容量中连续的一段（仅图片和音频）
+        #[arg(short, long, required = true)]
+        watermark: Vec<String>,
 
         /// 使用的算法
         #[arg(short, long, default_value = "dct")]
         algorithm: Algorithm,
 
-        /// 水印强度 (0.0-1.0)
-        #[arg(short, long, default_value = "0.1")]
+        /// 水印强度 (0.0-1.0)，也可写作百分比，如 10%
+        #[arg(short, long, default_value_t = crate::media::options::DEFAULT_STRENGTH, value_parser = parse_strength)]
         strength: f64,
 
         /// 是否使用无损压缩（仅对视频有效）
         #[arg(long)]
         lossless: bool,
 
+        /// 视频编码格式（仅对视频有效，默认沿用源视频的编码；容器由输出文件扩展名决定）
+        #[arg(long, value_enum)]
+        codec: Option<VideoCodec>,
+
+        /// 有损编码的质量参数 CRF，越小画质越好（仅对视频有效，H.264/HEVC 为 0-51，VP9/AV1 为 0-63）
+        #[arg(long, conflicts_with = "lossless", value_parser = clap::value_parser!(u8).range(0..=63))]
+        crf: Option<u8>,
+
         /// 视频水印处理模式（仅对视频文件有效）
         #[arg(long, default_value = "video")]
         video_mode: VideoWatermarkMode,
+
+        /// 限制 FFmpeg 编码视频时使用的线程数（仅对视频有效，默认占满所有核心）
+        #[arg(long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+        ffmpeg_threads: Option<usize>,
+
+        /// 视频帧先落盘为PNG再重新组合，而不是经管道在内存中处理（仅对 video 模式有效，用于调试）
+        #[arg(long)]
+        disk_frames: bool,
+
+        /// 中断后以相同参数重新运行时跳过已嵌入的帧（仅对视频的 video 模式有效，帧落盘处理，进度保存在由输出路径决定的临时目录中）
+        #[arg(long)]
+        resume: bool,
+
+        /// 在每帧的水印后追加该帧的原始序号，提取时可判断片段来自哪些帧（仅对视频有效，每帧多占6字节）
+        #[arg(long)]
+        frame_index: bool,
+
+        /// 每N帧只嵌入一帧，其余帧原样编码，首帧和最后一帧总是嵌入（仅对视频有效，提取时传入相同的N）
+        #[arg(long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+        embed_every: Option<usize>,
+
+        /// 块置换种子（提取时必须使用相同的种子）
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// DCT块边长（8的倍数，8-64，默认8）：块越大越稳健但容量越小，提取时必须一致
+        #[arg(long, value_parser = parse_block_size)]
+        block_size: Option<usize>,
+
+        /// 自定义DCT系数位置，格式 "u,v;u,v;..."（提取时必须使用相同列表）
+        #[arg(long, value_parser = parse_positions)]
+        positions: Option<CoefficientPositions>,
+
+        /// DCT分块模式承载水印的频带：low 抗模糊和缩放，mid 兼顾，high 最不易察觉（提取时必须一致）
+        #[arg(long, value_enum, default_value = "mid", conflicts_with = "positions")]
+        band: FrequencyBand,
+
+        /// DCT扩频模式每个比特的码片数（提取时必须一致），以容量换取抗噪声和压缩能力
+        #[arg(long)]
+        spread_chips: Option<usize>,
+
+        /// DCT差分模式：用块内一对中频系数的大小关系表示比特，抗亮度和对比度变化（提取时必须一致）
+        #[arg(long, conflicts_with = "spread_chips")]
+        differential: bool,
+
+        /// DCT量化索引调制（QIM）的步长：系数量化到步长的整数倍，奇偶表示比特，步长越大越稳健（提取时必须一致）
+        #[arg(long, value_parser = parse_qim_step, conflicts_with_all = ["spread_chips", "differential"])]
+        qim_step: Option<f64>,
+
+        /// DCT变换方式：block 分块DCT，global 整幅一次DCT，抗缩放但容量只有约一千比特（提取时必须一致）
+        #[arg(long, value_enum, default_value = "block")]
+        dct_mode: DctMode,
+
+        /// DCT感知加权阈值：块方差（按8位灰度计）低于此值的平坦块只做一半的修改，默认10，0为不区分
+        #[arg(long, value_name = "VARIANCE", value_parser = parse_perceptual_threshold)]
+        perceptual_threshold: Option<f64>,
+
+        /// LSB算法使用的位平面数（1-8，默认1）
+        #[arg(long)]
+        bit_planes: Option<usize>,
+
+        /// 图片重复编码次数：auto 按容量填满，或指定正整数N（提取时必须一致）
+        #[arg(long, value_parser = parse_repeat)]
+        repeat: Option<Repetition>,
+
+        /// 图片分块嵌入的图块边长（如256），每个完整图块独立携带载荷和CRC，抗裁剪（提取时必须一致）
+        #[arg(long, value_parser = parse_tile_size, conflicts_with_all = ["spread_chips", "repeat"])]
+        tile: Option<usize>,
+
+        /// 音频使用心理声学模式：在STFT的1-6kHz子频带能量上嵌入，替代 -a 选择的算法
+        #[arg(long)]
+        psychoacoustic: bool,
+
+        /// 音频原始嵌入模式：跳过限幅、滤波和淡入淡出等后处理，输出只含算法本身的修改，结果可复现（仅支持 wav/flac 输出）
+        #[arg(long)]
+        raw_embed: bool,
+
+        /// 音频叠加周期性同步标记，开头被裁剪或插入样本后提取时用 --sync 重新对齐（仅音频）
+        #[arg(long)]
+        sync: bool,
+
+        /// 图片嵌入的颜色通道：all、r、g、b 或 luma（仅对彩色图片有效，提取时必须一致）
+        #[arg(long, value_enum, default_value = "all")]
+        channel: ImageChannel,
+
+        /// 不保留原图的EXIF、ICC颜色配置等元数据（默认在JPEG/PNG输出中保留）
+        #[arg(long)]
+        strip_metadata: bool,
+
+        /// 输出为JPEG或AVIF时的编码质量（1-100），过低会破坏DCT水印
+        #[arg(long, default_value_t = crate::media::image::DEFAULT_JPEG_QUALITY, value_parser = clap::value_parser!(u8).range(1..=100))]
+        jpeg_quality: u8,
+
+        /// 图片嵌入后的最低PSNR (dB)，低于此值则中止且不写出文件
+        #[arg(long)]
+        min_psnr: Option<f64>,
+
+        /// 按目标PSNR (dB) 自动选择强度：二分查找使PSNR不低于且尽量接近目标的强度，替代 -s（仅图片）
+        #[arg(long, value_name = "DB", conflicts_with_all = ["strength", "split"])]
+        target_psnr: Option<f64>,
+
+        /// 音频嵌入后的最低SNR (dB)，低于此值则中止且不写出文件
+        #[arg(long)]
+        min_snr: Option<f64>,
+
+        /// 音频强度系数：DCT、LSB等算法在音频上的实际强度为 强度x系数（默认0.05），
+        /// 嘈杂的音乐可调高，安静的人声可调低；不影响 echo 和 --psychoacoustic
+        #[arg(long, default_value_t = crate::media::audio::DEFAULT_AUDIO_STRENGTH_SCALE, value_parser = parse_audio_strength_scale)]
+        audio_strength_scale: f64,
+
+        /// 使用口令加密水印载荷（AES-256-GCM，暂不支持视频）
+        #[arg(long)]
+        password: Option<String>,
+
+        /// 用该密钥对载荷签名，附加8字节的HMAC-SHA256截断值（暂不支持视频）
+        #[arg(long, value_name = "KEY")]
+        sign: Option<String>,
+
+        /// 对载荷做纠错编码：hamming 每4比特附加3个校验比特，每个码字可纠正1个错误比特（提取时必须一致，暂不支持视频）
+        #[arg(long, value_enum)]
+        ecc: Option<EccScheme>,
+
+        /// 纠错编码后按该深度交织比特，把不超过深度的连续错误分散到不同码字（提取时必须一致）
+        #[arg(long, value_name = "DEPTH", requires = "ecc", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(2..))]
+        interleave: Option<usize>,
+
+        /// 将 --watermark 视为文件路径，按原样嵌入其中的二进制内容（暂不支持视频）
+        #[arg(long)]
+        binary: bool,
+
+        /// 在结果中给出容量和扣除签名、加密开销后最多可嵌入的水印字节数
+        #[arg(long)]
+        show_capacity: bool,
+
+        /// 载荷拆分到多张图片：-i 为目录或通配符时按文件名顺序依次写入带分片头部的分片（仅图片）
+        #[arg(long, conflicts_with_all = ["repeat", "tile"])]
+        split: bool,
+
+        /// 按每段约N行的水平条带分段嵌入超大图片，降低峰值内存，结果与整幅处理相同（仅图片DCT分块模式）
+        #[arg(long, value_name = "N", conflicts_with_all = ["repeat", "tile", "spread_chips", "split"], value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+        strip_rows: Option<usize>,
+
+        /// 在图片顶部写入记录算法、块边长、纠错编码、通道和载荷长度的自描述头部，提取时省略 -l 即可自动配置（仅图片）
+        #[arg(long, conflicts_with_all = ["seed", "positions", "band", "spread_chips", "differential", "qim_step", "dct_mode", "bit_planes", "repeat", "tile", "split", "strip_rows"])]
+        header: bool,
+
+        /// 只在图片的矩形区域 x,y,w,h（像素）内嵌入，左上角须对齐到8像素，如 "256,128,512,512"（提取时须指定相同区域）
+        #[arg(long, value_name = "X,Y,W,H", value_parser = parse_region, conflicts_with_all = ["split", "strip_rows", "header"])]
+        region: Option<ImageRegion>,
+
+        /// 先把图片缩放到规范尺寸 WxH 再嵌入，如 "1024x1024"，水印在之后缩放图片时仍可提取（仅图片，提取时须指定相同尺寸）
+        #[arg(long, value_name = "WxH", value_parser = parse_normalize_size, conflicts_with_all = ["split", "strip_rows", "region"])]
+        normalize_size: Option<(u32, u32)>,
+
+        /// 输入图片已带有自描述头部（已经嵌入过水印）时仍然嵌入，默认拒绝以免破坏原有的水印
+        #[arg(long)]
+        force: bool,
+
+        /// 同时把结果 JSON 写入报告文件；不带路径时写到输出文件旁的 `<输出>.embed.json`
+        #[arg(long, num_args = 0..=1, value_name = "PATH")]
+        report: Option<Option<PathBuf>>,
+
+        /// 写出描述本次嵌入的元数据文件（算法参数、时间戳、载荷和输入输出文件的SHA-256）；
+        /// 不带路径时写到输出文件旁的 `<输出>.metadata.json`，批量模式下视为目录
+        #[arg(long, num_args = 0..=1, value_name = "PATH", conflicts_with = "split")]
+        metadata: Option<Option<PathBuf>>,
     },
     /// 提取水印
     Extract {
@@ -55,9 +260,10 @@ pub enum Commands {
         #[arg(short, long, default_value = "dct")]
         algorithm: Algorithm,
 
-        /// 期望的水印文本长度（字符数）
+        /// 期望的水印文本长度（字符数）；嵌入了多个水印时按嵌入顺序重复指定每个水印的长度。
+        /// 省略时从嵌入时用 --header 写入的自描述头部读取算法和长度等参数（仅图片）
         #[arg(short, long)]
-        length: usize,
+        length: Vec<usize>,
 
         /// 输出水印到文件（可选）
         #[arg(short, long)]
@@ -67,13 +273,260 @@ pub enum Commands {
         #[arg(long, default_value = "0")]
         sample_frames: usize,
 
-        /// 置信度阈值（仅对视频有效，0.0-1.0，默认0.6）
-        #[arg(long, default_value = "0.6")]
-        confidence_threshold: f64,
+        /// 视频采样帧的选择方式（仅对视频有效，--sample-frames 为0时不生效）
+        #[arg(long, value_enum, default_value = "uniform")]
+        sampling: FrameSampling,
+
+        /// 嵌入时使用了 --frame-index，同时恢复各帧的原始序号（仅对视频有效）
+        #[arg(long)]
+        frame_index: bool,
+
+        /// 嵌入时使用的 --embed-every，只在带水印的帧上采样（仅对视频有效）
+        #[arg(long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+        embed_every: Option<usize>,
+
+        /// 置信度阈值（0.0-1.0），低于阈值时警告并在JSON中标记 low_confidence；
+        /// 视频默认0.6，图片和音频只在指定时检查
+        #[arg(long)]
+        confidence_threshold: Option<f64>,
 
         /// 视频水印处理模式（仅对视频文件有效）
         #[arg(long, default_value = "video")]
         video_mode: VideoWatermarkMode,
+
+        /// 嵌入时使用的块置换种子
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// 嵌入时使用的DCT块边长
+        #[arg(long, value_parser = parse_block_size)]
+        block_size: Option<usize>,
+
+        /// 自定义DCT系数位置，格式 "u,v;u,v;..."（提取时必须使用相同列表）
+        #[arg(long, value_parser = parse_positions)]
+        positions: Option<CoefficientPositions>,
+
+        /// DCT分块模式承载水印的频带：low 抗模糊和缩放，mid 兼顾，high 最不易察觉（提取时必须一致）
+        #[arg(long, value_enum, default_value = "mid", conflicts_with = "positions")]
+        band: FrequencyBand,
+
+        /// DCT扩频模式每个比特的码片数（提取时必须一致），以容量换取抗噪声和压缩能力
+        #[arg(long)]
+        spread_chips: Option<usize>,
+
+        /// 按DCT差分模式提取（须与嵌入时一致）
+        #[arg(long, conflicts_with = "spread_chips")]
+        differential: bool,
+
+        /// 嵌入时使用的DCT量化索引调制（QIM）步长（须与嵌入时一致）
+        #[arg(long, value_parser = parse_qim_step, conflicts_with_all = ["spread_chips", "differential"])]
+        qim_step: Option<f64>,
+
+        /// 嵌入时使用的DCT变换方式（block 或 global，须与嵌入时一致）
+        #[arg(long, value_enum, default_value = "block")]
+        dct_mode: DctMode,
+
+        /// LSB算法使用的位平面数（1-8，默认1）
+        #[arg(long)]
+        bit_planes: Option<usize>,
+
+        /// 嵌入时使用的图片重复编码次数（auto 或 N），提取时对各副本多数表决
+        #[arg(long, value_parser = parse_repeat)]
+        repeat: Option<Repetition>,
+
+        /// 嵌入时使用的图块边长，提取时搜索所有图块位置并返回第一个通过CRC校验的结果
+        #[arg(long, value_parser = parse_tile_size, conflicts_with_all = ["spread_chips", "repeat"])]
+        tile: Option<usize>,
+
+        /// 按心理声学模式提取音频水印（须与嵌入时一致）
+        #[arg(long)]
+        psychoacoustic: bool,
+
+        /// 先按嵌入时 --sync 叠加的同步标记找到原始起点再提取，适用于开头被裁剪或插入样本的音频
+        #[arg(long)]
+        sync: bool,
+
+        /// 解密水印载荷所用的口令
+        #[arg(long)]
+        password: Option<String>,
+
+        /// 嵌入时使用的签名密钥，提取后校验载荷的签名
+        #[arg(long, value_name = "KEY")]
+        sign: Option<String>,
+
+        /// 嵌入时使用的纠错编码，提取后纠正错误比特并在结果中给出纠正的比特数
+        #[arg(long, value_enum)]
+        ecc: Option<EccScheme>,
+
+        /// 嵌入时使用的比特交织深度（须与嵌入时一致）
+        #[arg(long, value_name = "DEPTH", requires = "ecc", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(2..))]
+        interleave: Option<usize>,
+
+        /// 按原始字节提取（-l 为字节数），JSON 中以 base64 输出，-o 写出原始字节
+        #[arg(long)]
+        binary: bool,
+
+        /// 从 -i 指定的一组图片中读取 --split 写入的分片并按序号拼接，-l 为拼接后的总长度
+        #[arg(long, conflicts_with_all = ["repeat", "tile", "confidence_map", "channels_vote_threshold"])]
+        split: bool,
+
+        /// 同时把结果 JSON 写入报告文件；不带路径时写到 -o 输出文件（未指定则为输入文件）旁的 `<文件>.extract.json`
+        #[arg(long, num_args = 0..=1, value_name = "PATH")]
+        report: Option<Option<PathBuf>>,
+
+        /// 嵌入时使用的图片颜色通道（须与嵌入时一致），all 从R通道提取
+        #[arg(long, value_enum, default_value = "all")]
+        channel: ImageChannel,
+
+        /// 彩色图片多通道投票阈值（1-3）：至少K个通道一致的比特才被接受，否则标记为不确定
+        #[arg(long, conflicts_with = "channel", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..=3))]
+        channels_vote_threshold: Option<usize>,
+
+        /// 参与多通道投票的颜色通道，逗号分隔，如 r,b（默认R、G、B都参与）
+        #[arg(long, value_name = "CHANNELS", value_delimiter = ',', value_parser = parse_vote_channel, conflicts_with_all = ["channel", "split", "confidence_map", "robust", "strip_rows", "region", "normalize_size"])]
+        extract_channels: Vec<ImageChannel>,
+
+        /// 多通道投票方式，指定后即对各通道分别提取并投票
+        #[arg(long, value_enum, conflicts_with_all = ["channel", "split", "confidence_map", "robust", "strip_rows", "region", "normalize_size"])]
+        vote: Option<VoteStrategy>,
+
+        /// 把每个块的提取置信度保存为图片（每块一个像素，越亮越可靠，未承载比特的块为深蓝色；仅图片DCT）
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["spread_chips", "tile"])]
+        confidence_map: Option<PathBuf>,
+
+        /// 依次尝试缩放到常见尺寸、摆正轻微旋转和锐化等预处理后提取，返回置信度最高的结果（仅图片，耗时更长）
+        #[arg(long, conflicts_with_all = ["split", "channels_vote_threshold", "confidence_map"])]
+        robust: bool,
+
+        /// 按每段约N行的水平条带分段提取超大图片，降低峰值内存（仅图片DCT分块模式）
+        #[arg(long, value_name = "N", conflicts_with_all = ["repeat", "tile", "spread_chips", "split", "channels_vote_threshold", "confidence_map", "robust"], value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+        strip_rows: Option<usize>,
+
+        /// 只从嵌入时 --region 指定的矩形区域 x,y,w,h 提取（仅图片）
+        #[arg(long, value_name = "X,Y,W,H", value_parser = parse_region, conflicts_with_all = ["split", "strip_rows", "channels_vote_threshold", "confidence_map", "robust"])]
+        region: Option<ImageRegion>,
+
+        /// 先把图片缩放到嵌入时 --normalize-size 指定的规范尺寸 WxH 再提取（仅图片）
+        #[arg(long, value_name = "WxH", value_parser = parse_normalize_size, conflicts_with_all = ["split", "strip_rows", "channels_vote_threshold", "confidence_map", "robust", "region"])]
+        normalize_size: Option<(u32, u32)>,
+    },
+    /// 检测文件中是否存在水印
+    Detect {
+        /// 输入文件路径
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// 使用的算法
+        #[arg(short, long, default_value = "dct")]
+        algorithm: Algorithm,
+
+        /// 嵌入时使用的块置换种子
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// 嵌入时使用的DCT块边长
+        #[arg(long, value_parser = parse_block_size)]
+        block_size: Option<usize>,
+
+        /// 视频采样帧数（仅对视频有效）
+        #[arg(long, default_value = "7")]
+        sample_frames: usize,
+    },
+    /// 对已嵌入水印的图片模拟常见攻击，报告每种攻击后的误码率
+    Verify {
+        /// 已嵌入水印的图片路径
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// 嵌入时使用的水印文本，用于逐比特比对
+        #[arg(short, long)]
+        watermark: String,
+
+        /// 使用的算法
+        #[arg(short, long, default_value = "dct")]
+        algorithm: Algorithm,
+
+        /// 嵌入时使用的块置换种子
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// 嵌入时使用的DCT块边长
+        #[arg(long, value_parser = parse_block_size)]
+        block_size: Option<usize>,
+
+        /// 嵌入时使用的DCT量化索引调制（QIM）步长
+        #[arg(long, value_parser = parse_qim_step)]
+        qim_step: Option<f64>,
+
+        /// 要模拟的攻击，可重复指定，如 --attack jpeg:50 --attack noise:0.02；
+        /// 可选 jpeg:质量、noise:标准差、resize:比例、rotate:角度、crop:比例
+        #[arg(long = "attack", required = true, value_parser = parse_attack)]
+        attacks: Vec<Attack>,
+
+        /// 误码率不超过该值时视为水印存活
+        #[arg(long, default_value = "0.1")]
+        max_ber: f64,
+    },
+    /// 查看媒体属性和可嵌入的水印容量
+    Info {
+        /// 输入文件路径
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// 按该算法计算容量
+        #[arg(short, long, default_value = "dct")]
+        algorithm: Algorithm,
+
+        /// 按该DCT变换方式计算容量
+        #[arg(long, value_enum, default_value = "block")]
+        dct_mode: DctMode,
+    },
+    /// 比较原始文件和嵌入水印后的文件，画出水印修改量的分布，用于调整强度和系数位置
+    Diff {
+        /// 原始文件路径
+        #[arg(long)]
+        original: PathBuf,
+
+        /// 嵌入水印后的文件路径
+        #[arg(long)]
+        watermarked: PathBuf,
+
+        /// 差异图（PNG）的保存路径：图片为逐块DCT系数差的热力图，音频为样本差的包络曲线
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// 计算DCT系数差时的块边长（仅图片）
+        #[arg(long, value_parser = parse_block_size, default_value = "8")]
+        block_size: usize,
+
+        /// 比较的图片颜色通道（仅图片），all 比较R通道
+        #[arg(long, value_enum, default_value = "all")]
+        channel: ImageChannel,
+    },
+    /// 以JSON列出可用的水印算法、支持的媒体类型和能力概要
+    ListAlgorithms,
+    /// 对输入文件反复嵌入并提取，报告耗时的中位数、p95 和吞吐量，用于跟踪性能回归
+    #[command(hide = true)]
+    Bench {
+        /// 输入文件路径
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// 嵌入加提取的重复次数
+        #[arg(short = 'n', long, default_value = "5", value_parser = clap::value_parser!(u32).range(1..))]
+        iterations: u32,
+
+        /// 使用的算法
+        #[arg(short, long, default_value = "dct")]
+        algorithm: Algorithm,
+
+        /// 嵌入的水印文本
+        #[arg(short, long, default_value = "seal-bench")]
+        watermark: String,
+
+        /// 水印强度 (0.0-1.0)，也可写作百分比，如 10%
+        #[arg(short, long, default_value_t = crate::media::options::DEFAULT_STRENGTH, value_parser = parse_strength)]
+        strength: f64,
     },
 }
 
@@ -82,6 +535,91 @@ pub enum Commands {
 pub enum Algorithm {
     /// 离散余弦变换
     Dct,
+    /// 离散傅里叶变换幅度（对平移不敏感，不抗旋转和缩放）
+    Dft,
+    /// 回声隐藏（仅适用于音频）
+    Echo,
+    /// 最低有效位隐写（仅适用于无损格式）
+    Lsb,
+}
+
+/// 视频提取时采样帧的选择方式
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FrameSampling {
+    /// 从开头按固定位置选帧
+    #[default]
+    Uniform,
+    /// 在各场景切换点之后画面稳定的帧中，挑选画面质量最好的帧
+    Scene,
+}
+
+/// 重新编码视频时使用的编码格式
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VideoCodec {
+    /// H.264 (libx264)
+    H264,
+    /// H.265/HEVC (libx265)
+    #[value(alias = "h265")]
+    Hevc,
+    /// VP9 (libvpx-vp9)
+    Vp9,
+    /// AV1 (libaom-av1)
+    Av1,
+}
+
+/// DCT水印的变换方式
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DctMode {
+    /// 分块DCT，每块一个比特，容量大
+    #[default]
+    Block,
+    /// 整幅图像一次DCT，在中低频环带内嵌入，抗缩放但容量只有约一千比特
+    Global,
+}
+
+/// DCT分块模式承载水印的系数频带，三者的系数个数相同，容量不变
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FrequencyBand {
+    /// 最低的交流系数，抗模糊和缩放，但修改更容易被看出
+    Low,
+    /// 中频系数，兼顾不可见性和抗压缩能力
+    #[default]
+    Mid,
+    /// 中频以上的系数，最不易察觉，但容易被模糊、缩放和有损压缩抹掉
+    High,
+}
+
+/// 图片嵌入和提取水印使用的颜色通道，灰度图片总是使用亮度
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ImageChannel {
+    /// R、G、B 三个通道分别嵌入同一份水印，提取时读取R通道
+    #[default]
+    All,
+    /// 只使用红色通道
+    R,
+    /// 只使用绿色通道，人眼对绿色最敏感，须用更低的强度，但JPEG压缩的损失最小
+    G,
+    /// 只使用蓝色通道，人眼最不敏感，改动最难察觉，但JPEG色度抽样的损失较大
+    B,
+    /// 使用按 BT.601 加权的亮度，三个通道同步改动，同一强度下抗压缩能力最强
+    Luma,
+}
+
+/// 多通道投票时合并各通道比特的方式
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VoteStrategy {
+    /// 每个通道一票，按同意的通道数多数表决
+    Majority,
+    /// 按各通道软判决的幅度加权，可靠的通道权重更大
+    #[default]
+    Confidence,
+}
+
+/// 载荷的纠错编码方式
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EccScheme {
+    /// Hamming(7,4)：开销为 3/4，每7比特的码字纠正1个错误比特，适合短ID
+    Hamming,
 }
 
 /// 视频水印处理模式
@@ -93,4 +631,290 @@ pub enum VideoWatermarkMode {
     Audio,
     /// 同时处理视频和音频
     Both,
+    /// 仅重新编码首个关键帧区间的视频帧，其余码流直接复制
+    Keyframe,
+    /// 同时写入视频帧、音频和容器元数据，提取时三路投票
+    All,
+}
+
+/// 结果在stdout上的输出格式
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// 单行JSON，包含全部字段
+    #[default]
+    Json,
+    /// 纯文本：提取时只输出水印，其他命令输出 `字段: 值`
+    Text,
+    /// CSV：固定的表头，每个结果一行
+    Csv,
+}
+
+/// 面向用户的消息语言
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Lang {
+    /// 中文
+    #[default]
+    Zh,
+    /// 英文
+    En,
+}
+
+/// 解析水印强度，支持小数（0.1）和百分比（10%）两种写法，结果必须落在 [0.0, 1.0] 内
+pub fn parse_strength(value: &str) -> Result<f64, String> {
+    let value = value.trim();
+    let strength = match value.strip_suffix('%') {
+        Some(percent) => percent
+            .trim()
+            .parse::<f64>()
+            .map(|p| p / 100.0)
+            .map_err(|_| Message::InvalidPercentStrength.format(&[&value])),
+        None => value
+            .parse::<f64>()
+            .map_err(|_| Message::InvalidStrength.format(&[&value])),
+    }?;
+
+    if !strength.is_finite() || !(0.0..=1.0).contains(&strength) {
+        return Err(Message::StrengthRange.format(&[&value]));
+    }
+    Ok(strength)
+}
+
+/// 解析音频强度系数，必须是有限的正数
+pub fn parse_audio_strength_scale(value: &str) -> Result<f64, String> {
+    let value = value.trim();
+    match value.parse::<f64>() {
+        Ok(scale) if scale.is_finite() && scale > 0.0 => Ok(scale),
+        _ => Err(Message::InvalidAudioStrengthScale.format(&[&value])),
+    }
+}
+
+/// 解析QIM步长，必须是正数
+pub fn parse_qim_step(value: &str) -> Result<f64, String> {
+    let value = value.trim();
+    match value.parse::<f64>() {
+        Ok(step) if step.is_finite() && step > 0.0 => Ok(step),
+        _ => Err(Message::InvalidQimStep.format(&[&value])),
+    }
+}
+
+/// 解析感知加权阈值，必须是非负数
+pub fn parse_perceptual_threshold(value: &str) -> Result<f64, String> {
+    let value = value.trim();
+    match value.parse::<f64>() {
+        Ok(threshold) if threshold.is_finite() && threshold >= 0.0 => Ok(threshold),
+        _ => Err(Message::InvalidPerceptualThreshold.format(&[&value])),
+    }
+}
+
+/// 解析重复编码次数，`auto` 或正整数
+pub fn parse_repeat(value: &str) -> Result<Repetition, String> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("auto") {
+        return Ok(Repetition::Auto);
+    }
+    match value.parse::<usize>() {
+        Ok(times) if times > 0 => Ok(Repetition::Times(times)),
+        _ => Err(Message::InvalidRepeat.format(&[&value])),
+    }
+}
+
+/// 解析攻击描述，如 `jpeg:50`、`noise:0.02`
+pub fn parse_attack(value: &str) -> Result<Attack, String> {
+    Attack::parse(value)
+}
+
+/// DCT块边长的上限
+pub const MAX_BLOCK_SIZE: usize = 64;
+
+/// 解析DCT块边长，必须是8的倍数且不超过 `MAX_BLOCK_SIZE`，默认系数位置表按8x8等比放大
+pub fn parse_block_size(value: &str) -> Result<usize, String> {
+    match value.trim().parse::<usize>() {
+        Ok(size) if (8..=MAX_BLOCK_SIZE).contains(&size) && size.is_multiple_of(8) => Ok(size),
+        _ => Err(Message::InvalidBlockSize.format(&[&value, &MAX_BLOCK_SIZE])),
+    }
+}
+
+/// 解析分块模式的图块边长，必须是8的倍数且不小于64
+pub fn parse_tile_size(value: &str) -> Result<usize, String> {
+    match value.trim().parse::<usize>() {
+        Ok(size) if size >= 64 && size.is_multiple_of(8) => Ok(size),
+        _ => Err(Message::InvalidTileSize.format(&[&value])),
+    }
+}
+
+/// 图片中的矩形区域（像素）
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImageRegion {
+    /// 左上角的列
+    pub x: u32,
+    /// 左上角的行
+    pub y: u32,
+    /// 宽度
+    pub width: u32,
+    /// 高度
+    pub height: u32,
+}
+
+/// 区域左上角须对齐的块边长，与JPEG的8x8块网格一致
+const REGION_ALIGN: u32 = 8;
+
+/// 解析图片区域 `x,y,w,h`，左上角须对齐到8像素、宽高不小于8像素
+///
+/// 区域是否落在图片内由嵌入和提取时按实际尺寸检查。
+pub fn parse_region(value: &str) -> Result<ImageRegion, String> {
+    let numbers = value
+        .split(',')
+        .map(|part| part.trim().parse::<u32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| Message::InvalidRegion.format(&[&value]))?;
+    let &[x, y, width, height] = numbers.as_slice() else {
+        return Err(Message::InvalidRegion.format(&[&value]));
+    };
+    if !x.is_multiple_of(REGION_ALIGN) || !y.is_multiple_of(REGION_ALIGN) {
+        return Err(Message::RegionUnaligned.format(&[&x, &y, &REGION_ALIGN]));
+    }
+    if width < REGION_ALIGN || height < REGION_ALIGN {
+        return Err(Message::RegionTooSmall.format(&[&width, &height, &REGION_ALIGN]));
+    }
+    Ok(ImageRegion {
+        x,
+        y,
+        width,
+        height,
+    })
+}
+
+/// 解析参与投票的单个颜色通道，只接受 r、g、b
+pub fn parse_vote_channel(value: &str) -> Result<ImageChannel, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "r" => Ok(ImageChannel::R),
+        "g" => Ok(ImageChannel::G),
+        "b" => Ok(ImageChannel::B),
+        _ => Err(Message::InvalidVoteChannel.format(&[&value])),
+    }
+}
+
+/// 解析规范尺寸 `WxH`，宽高须在 8 到 65535 像素之间（自描述头部按16位记录）
+pub fn parse_normalize_size(value: &str) -> Result<(u32, u32), String> {
+    let (width, height) = value
+        .split_once(['x', 'X'])
+        .and_then(|(width, height)| Some((width.trim().parse().ok()?, height.trim().parse().ok()?)))
+        .ok_or_else(|| Message::InvalidSize.format(&[&value]))?;
+    let valid = |n: u32| (8..=u16::MAX as u32).contains(&n);
+    if !valid(width) || !valid(height) {
+        return Err(Message::NormalizeSizeRange.format(&[&width, &height]));
+    }
+    Ok((width, height))
+}
+
+/// 用户指定的DCT系数位置列表
+#[derive(Clone, Debug)]
+pub struct CoefficientPositions(pub Vec<(usize, usize)>);
+
+/// 解析系数位置列表，如 `2,1;1,2;3,1`，每个位置必须落在最大的DCT块内
+///
+/// 是否落在实际使用的块内由算法在嵌入和提取时检查。
+pub fn parse_positions(value: &str) -> Result<CoefficientPositions, String> {
+    const BLOCK_SIZE: usize = MAX_BLOCK_SIZE;
+
+    let positions = value
+        .split(';')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (u, v) = pair
+                .split_once(',')
+                .ok_or_else(|| Message::InvalidPositionPair.format(&[&pair]))?;
+            let u = u
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| Message::InvalidPosition.format(&[&pair]))?;
+            let v = v
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| Message::InvalidPosition.format(&[&pair]))?;
+            if u >= BLOCK_SIZE || v >= BLOCK_SIZE {
+                return Err(Message::PositionOutsideBlock.format(&[
+                    &u,
+                    &v,
+                    &BLOCK_SIZE,
+                    &BLOCK_SIZE,
+                ]));
+            }
+            Ok((u, v))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    if positions.is_empty() {
+        return Err(Message::PositionsEmpty.text().to_string());
+    }
+
+    Ok(CoefficientPositions(positions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VIDEO_MODES: [&str; 5] = ["video", "audio", "both", "keyframe", "all"];
+
+    fn embed_video_mode(extra: &[&str]) -> Result<VideoWatermarkMode, clap::Error> {
+        let args = ["seal", "embed", "-i", "in.mp4", "-o", "out.mp4", "-w", "hi"];
+        let cli = Cli::try_parse_from(args.iter().chain(extra))?;
+        match cli.command {
+            Commands::Embed { video_mode, .. } => Ok(video_mode),
+            _ => unreachable!("解析结果不是 embed 子命令"),
+        }
+    }
+
+    fn extract_video_mode(extra: &[&str]) -> Result<VideoWatermarkMode, clap::Error> {
+        let args = ["seal", "extract", "-i", "in.mp4", "-l", "2"];
+        let cli = Cli::try_parse_from(args.iter().chain(extra))?;
+        match cli.command {
+            Commands::Extract { video_mode, .. } => Ok(video_mode),
+            _ => unreachable!("解析结果不是 extract 子命令"),
+        }
+    }
+
+    fn mode_name(mode: &VideoWatermarkMode) -> String {
+        mode.to_possible_value().unwrap().get_name().to_string()
+    }
+
+    #[test]
+    fn video_mode_defaults_to_video() {
+        assert!(matches!(
+            embed_video_mode(&[]).unwrap(),
+            VideoWatermarkMode::Video
+        ));
+        assert!(matches!(
+            extract_video_mode(&[]).unwrap(),
+            VideoWatermarkMode::Video
+        ));
+    }
+
+    #[test]
+    fn every_video_mode_parses_on_embed_and_extract() {
+        for name in VIDEO_MODES {
+            let args = ["--video-mode", name];
+            assert_eq!(mode_name(&embed_video_mode(&args).unwrap()), name);
+            assert_eq!(mode_name(&extract_video_mode(&args).unwrap()), name);
+        }
+    }
+
+    #[test]
+    fn unknown_video_mode_is_rejected() {
+        let args = ["--video-mode", "subtitles"];
+        assert!(embed_video_mode(&args).is_err());
+        assert!(extract_video_mode(&args).is_err());
+    }
+
+    #[test]
+    fn strength_accepts_the_closed_unit_range() {
+        assert_eq!(parse_strength("0"), Ok(0.0));
+        assert_eq!(parse_strength("1"), Ok(1.0));
+        assert_eq!(parse_strength("100%"), Ok(1.0));
+        for value in ["-0.01", "1.01", "150%", "NaN", "inf", "NaN%", "inf%"] {
+            assert!(parse_strength(value).is_err(), "{value} was accepted");
+        }
+    }
 }
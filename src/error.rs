@@ -1,34 +1,88 @@
+use crate::i18n::Message;
+use std::fmt;
 use thiserror::Error;
 
 /// 项目中的错误类型定义
+///
+/// 错误信息按当前界面语言从 `i18n` 消息目录中取得，见下方的 `Display` 实现。
 #[derive(Error, Debug)]
 pub enum WatermarkError {
-    #[error("IO错误: {0}")]
     Io(#[from] std::io::Error),
 
-    #[error("图像处理错误: {0}")]
     Image(#[from] image::ImageError),
 
-    #[error("音频处理错误: {0}")]
     Audio(#[from] hound::Error),
 
-    #[error("不支持的文件格式: {0}")]
     UnsupportedFormat(String),
 
-    #[error("无效的水印数据")]
     InvalidWatermark,
 
-    #[error("水印提取失败")]
     ExtractionFailed,
 
-    #[error("算法错误: {0}")]
     Algorithm(String),
 
-    #[error("参数错误: {0}")]
     InvalidArgument(String),
 
-    #[error("处理错误: {0}")]
     ProcessingError(String),
+
+    PasswordRequired,
+
+    DecryptionFailed,
+
+    SignatureInvalid,
+
+    OutputNotWritable {
+        path: std::path::PathBuf,
+        reason: String,
+    },
+
+    QualityTooLow {
+        measured: f64,
+        floor: f64,
+    },
+
+    CapacityExceeded {
+        needed: usize,
+        available: usize,
+    },
+
+    /// 批处理中有文件失败，各文件的结果已经逐项输出
+    BatchFailed {
+        failed: usize,
+        total: usize,
+    },
+}
+
+impl fmt::Display for WatermarkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            WatermarkError::Io(e) => Message::IoError.format(&[e]),
+            WatermarkError::Image(e) => Message::ImageError.format(&[e]),
+            WatermarkError::Audio(e) => Message::AudioError.format(&[e]),
+            WatermarkError::UnsupportedFormat(s) => Message::UnsupportedFormat.format(&[s]),
+            WatermarkError::InvalidWatermark => Message::InvalidWatermark.text().to_string(),
+            WatermarkError::ExtractionFailed => Message::ExtractionFailed.text().to_string(),
+            WatermarkError::Algorithm(s) => Message::AlgorithmError.format(&[s]),
+            WatermarkError::InvalidArgument(s) => Message::InvalidArgument.format(&[s]),
+            WatermarkError::ProcessingError(s) => Message::ProcessingError.format(&[s]),
+            WatermarkError::PasswordRequired => Message::PasswordRequired.text().to_string(),
+            WatermarkError::DecryptionFailed => Message::DecryptionFailed.text().to_string(),
+            WatermarkError::SignatureInvalid => Message::SignatureInvalid.text().to_string(),
+            WatermarkError::OutputNotWritable { path, reason } => {
+                Message::OutputNotWritable.format(&[&path.display(), reason])
+            }
+            WatermarkError::QualityTooLow { measured, floor } => {
+                Message::QualityTooLow.format(&[&format!("{measured:.2}"), &format!("{floor:.2}")])
+            }
+            WatermarkError::CapacityExceeded { needed, available } => {
+                Message::CapacityExceeded.format(&[needed, available])
+            }
+            WatermarkError::BatchFailed { failed, total } => {
+                Message::BatchFailed.format(&[failed, total])
+            }
+        };
+        f.write_str(&message)
+    }
 }
 
 pub type Result<T> = std::result::Result<T, WatermarkError>;
@@ -0,0 +1,1196 @@
+use crate::cli::Lang;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// 当前界面语言，进程内全局生效
+static LANG: AtomicU8 = AtomicU8::new(Lang::Zh as u8);
+
+/// 设置界面语言
+pub fn set_lang(lang: Lang) {
+    LANG.store(lang as u8, Ordering::Relaxed);
+}
+
+/// 当前界面语言
+pub fn lang() -> Lang {
+    match LANG.load(Ordering::Relaxed) {
+        x if x == Lang::En as u8 => Lang::En,
+        _ => Lang::Zh,
+    }
+}
+
+/// 按 `--lang` > `LANG` 环境变量 > 中文 的顺序确定界面语言
+///
+/// `LANG` 只看前缀：`zh*` 为中文，`en*` 为英文，其他值（包括 `C`、`POSIX`）沿用默认的中文。
+pub fn resolve_lang(flag: Option<Lang>) -> Lang {
+    flag.or_else(|| {
+        std::env::var("LANG")
+            .ok()
+            .and_then(|value| lang_from_env(&value))
+    })
+    .unwrap_or_default()
+}
+
+fn lang_from_env(value: &str) -> Option<Lang> {
+    let value = value.to_ascii_lowercase();
+    if value.starts_with("zh") {
+        Some(Lang::Zh)
+    } else if value.starts_with("en") {
+        Some(Lang::En)
+    } else {
+        None
+    }
+}
+
+/// 面向用户的消息
+///
+/// 文本中的 `{}` 按顺序由 `format` 的参数替换，数值精度由调用方在传入前确定。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Message {
+    // 错误
+    IoError,
+    ImageError,
+    AudioError,
+    UnsupportedFormat,
+    InvalidWatermark,
+    ExtractionFailed,
+    AlgorithmError,
+    InvalidArgument,
+    ProcessingError,
+    PasswordRequired,
+    DecryptionFailed,
+    SignatureInvalid,
+    OutputNotWritable,
+    QualityTooLow,
+    CapacityExceeded,
+    BatchFailed,
+
+    // 命令行
+    ErrorLabel,
+    WarningLabel,
+    FfmpegDownloadFailed,
+    FfmpegInstallHint,
+    FfmpegRetry,
+    SplitPartWritten,
+    SplitHeaderMissing,
+    WatermarkSaved,
+    BatchSummary,
+    DryRun,
+    DryRunSummary,
+    ProcessingImage,
+    ProcessingAudio,
+    ProcessingVideo,
+    AutoStrength,
+    Snr,
+    ExtractingFrom,
+    UsingAlgorithm,
+    ConfidenceMapSaved,
+    BitsCorrected,
+    RobustWinner,
+    RobustAttempts,
+    Detecting,
+    Attack,
+    AttackExtractionFailed,
+    DiffSaved,
+    CapacitySummary,
+    LowConfidence,
+    Reassembling,
+    Frames,
+
+    // 媒体处理
+    AnimatedGifFirstFrameOnly,
+    PaletteExpanded,
+    SixteenBitDowngraded,
+    LowJpegQuality,
+    LossyImageFormat,
+    AlreadyWatermarked,
+    ExifTooLarge,
+    IccTooLarge,
+    EightBitPcm,
+    EarlyStop,
+    SceneDetectionFailed,
+    KeyframeNotFound,
+    ProcessAllFrames,
+    KeyframeJoinFailed,
+    DecodeErrors,
+    FrameCountMismatch,
+    VideoLowConfidence,
+    FusedLowConfidence,
+    TempFilesCleaned,
+    ResumingFrames,
+    VideoEmbedDone,
+
+    // 命令行检查
+    DryRunEmbedOnly,
+    SplitEmbedExtractOnly,
+    InputNotFound,
+    SplitImagesOnly,
+    SplitSingleWatermark,
+    SplitNeedsMoreImages,
+    SplitSingleExtract,
+    SplitLengthMismatch,
+    InvalidInputFile,
+    ResumeVideoModeOnly,
+    HeaderSingleWatermark,
+    ReembedNeedsForce,
+    HeaderWithDryRun,
+    RegionWithDryRun,
+    MetadataWithDryRun,
+    HeaderSizeMismatch,
+    SignedNeedsKey,
+    RobustImagesOnly,
+    ConfidenceMapDctOnly,
+    MultipleWithOutput,
+    LsbCannotDetect,
+    MaxBerRange,
+    VerifyImagesOnly,
+    DiffMediaMismatch,
+    DiffImageAudioOnly,
+    VideoNoPassword,
+    VideoNoSign,
+    VideoNoEcc,
+    VideoNoBinary,
+    RepeatImagesOnly,
+    VideoNoMultiple,
+    BlockSizeNoAudio,
+    TileImagesOnly,
+    TileDctOnly,
+    DftNoAudio,
+    EchoAudioOnly,
+    FlagAudioOnly,
+    LengthRequired,
+    FlagWithoutLength,
+    FlagImagesOnly,
+    VideoNoBytes,
+
+    // 命令行参数
+    InvalidPercentStrength,
+    InvalidStrength,
+    StrengthRange,
+    InvalidAudioStrengthScale,
+    InvalidQimStep,
+    InvalidPerceptualThreshold,
+    InvalidRepeat,
+    InvalidBlockSize,
+    InvalidTileSize,
+    InvalidRegion,
+    RegionUnaligned,
+    RegionTooSmall,
+    InvalidVoteChannel,
+    InvalidSize,
+    NormalizeSizeRange,
+    InvalidPositionPair,
+    InvalidPosition,
+    PositionOutsideBlock,
+    PositionsEmpty,
+
+    // 媒体处理错误
+    AudioStrengthScaleRange,
+    RawOutputLossless,
+    RawFloatWavOnly,
+    SyncNotSupported,
+    AudioTooShortForSync,
+    AudioFormatsDiffer,
+    AudioNormalizeFailed,
+    UnsupportedBitDepth,
+    UnsupportedAudioOutput,
+    AudioConvertFailed,
+    SyncNotFound,
+    InputPathInvalid,
+    FfmpegOutputUnreadable,
+    NoAudioStream,
+    AudioDurationUnknown,
+    ConvertingToMono,
+    AudioEmbedDone,
+    SmoothingPipeline,
+    ProtectiveNormalization,
+    SmoothingDone,
+    GentleLowpass,
+    AdaptiveCompression,
+    BoundarySmoothing,
+    PerceptualLimiting,
+    RegionOutsideImage,
+    HeaderDoesNotFit,
+    CannotWriteImage,
+    EmptyImage,
+    ImageSizesDiffer,
+    DebugImageInfo,
+    DebugExtracting,
+    DebugExtracted,
+    DebugAlgorithm,
+    DebugWatermark,
+    DebugStrictUtf8Failed,
+    DebugExtractedLossy,
+    DebugStandardFailed,
+    DebugTryVoting,
+    DebugVotingResult,
+    DebugExtractedVoting,
+    CannotRebuildImage,
+    FfmpegImageConvertFailed,
+    AvifEncodeFailed,
+    ImageFormatUnknown,
+    StripColorMismatch,
+    PngMissingIhdr,
+    FileTypeUnknown,
+    UnsupportedExtension,
+    WildcardInDirectory,
+    NoMatchingFiles,
+    OutputExists,
+    FfmpegWatcherPanicked,
+    KilledBySignal,
+    FfmpegTimeout,
+    FfmpegInterrupted,
+    CodecContainerMismatch,
+    CrfRange,
+    KeyframeSourceH264Only,
+    UnknownCodec,
+    KeyframeOutputH264Only,
+    KeyframeFrameIndexUnsupported,
+    NoSampledFrames,
+    OutputPathInvalid,
+    VideoPathInvalid,
+    AudioPathInvalid,
+    NoVideoStream,
+    FrameExtractionFailed,
+    SingleFrameFailed,
+    AllSampledFramesFailed,
+    SceneSampling,
+    SceneDetectionError,
+    NoFramesExtracted,
+    FramesProcessedSoFar,
+    AllFramesFailed,
+    FramesProcessedSummary,
+    ImageUnreadable,
+    ImageUndecodable,
+    ReassembleFailed,
+    ResumeFrameMismatch,
+    ResumeParamsMismatch,
+    HeadEncodeFailed,
+    TailCopyFailed,
+    SegmentJoinFailed,
+    MuxFailed,
+    NoAudioTrackForEmbed,
+    FrameSizeMismatch,
+    NoFramesDecoded,
+    VideoDecodeFailed,
+    VideoEncodeFailed,
+    FramesProcessed,
+    EncoderExited,
+    EncoderStdinUnavailable,
+    AudioExtractFailed,
+    VideoStreamExtractFailed,
+    NoAudioTrackForExtract,
+    WritingContainerTag,
+    ContainerTagFailed,
+    ChoseAudioResult,
+    ChoseVideoResult,
+    VideoResultOnly,
+    BothBelowThreshold,
+    BestBelowThreshold,
+    ContainerTagFound,
+    ContainerTagMissing,
+    AudioResultConfidence,
+    AudioResultFailed,
+    VideoResultFailed,
+    VideoResultConfidence,
+    AllCopiesFailed,
+    CopiesVoted,
+
+    // 进度
+    StageTempDir,
+    StageExtractFrames,
+    StageProcessFrames,
+    StageCopyAudio,
+    StageVideoEmbedDone,
+    StageLocateKeyframe,
+    StageKeyframeDelta,
+    StageEncodeHead,
+    StageJoin,
+    StageExtractAudio,
+    StageProcessAudio,
+    StageExtractVideoStream,
+    StageMux,
+    StageAudioEmbedDone,
+    StageSinglePass,
+    StageAudioWatermark,
+    StageBothEmbedDone,
+    StageAnalyze,
+    StageExtractAll,
+    StageExtractSamples,
+    StageVote,
+    StageVideoExtractDone,
+    StageExtractAudioWatermark,
+    StageAudioExtractDone,
+    StageExtractAudioMark,
+    StageFuse,
+    StageBothExtractDone,
+
+    // 鲁棒性测试
+    AttackSpecInvalid,
+    AttackValueInvalid,
+    AttackJpegQuality,
+    AttackNoiseNegative,
+    AttackResizeScale,
+    AttackCropFraction,
+    AttackUnknown,
+    ResizeScaleInvalid,
+    ResizeTargetInvalid,
+    CropFractionInvalid,
+    BerLengthInvalid,
+
+    // 算法
+    RandomUnavailable,
+    EncryptionFailed,
+    KeyInvalid,
+    DifferentialWithSpread,
+    DifferentialNeedsTwoPositions,
+    QimStepInvalid,
+    QimWithOtherModes,
+    PositionOutOfBlock,
+    ConfidenceMapModeUnsupported,
+    StripModeUnsupported,
+    StripPlanBitsMismatch,
+    ExpectedExceedsBlocks,
+    SpreadChipsZero,
+    ExpectedExceedsSpread,
+    TileSizeInvalid,
+    TilesWithSpread,
+    ImageSmallerThanTile,
+    ExpectedExceedsTiles,
+    NoValidTile,
+    GlobalWithOtherModes,
+    ExpectedExceedsGlobal,
+    ExpectedExceedsDftRing,
+    EccPayloadTooShort,
+    HeaderFieldTooLarge,
+    HeaderPayloadTooLarge,
+    HeaderSizeTooLarge,
+    HeaderBlockSize,
+    HeaderInterleave,
+    BitPlanesInvalid,
+    ExpectedExceedsBits,
+    SampleReshapeFailed,
+    RepetitionTooFew,
+    SplitCapacityShort,
+    SplitTooManyParts,
+    SplitCountMismatch,
+    SplitPartsMissing,
+    SamplesUnsupported,
+    ConfidenceMapUnsupported,
+    StripUnsupported,
+    StrengthOutOfRange,
+    BitsNotByteAligned,
+    BitsAnalysis,
+    BitsTotal,
+    BitsBytesComplete,
+    BitsBytesIncomplete,
+    BitsOnes,
+    BitsZeros,
+    BitsByteValues,
+    BitsUtf8,
+    BitsUtf8Lossy,
+}
+
+impl Message {
+    /// 当前语言下的消息模板
+    pub fn text(self) -> &'static str {
+        match lang() {
+            Lang::Zh => self.zh(),
+            Lang::En => self.en(),
+        }
+    }
+
+    /// 用 `args` 依次替换模板中的 `{}`，多余的占位符保持原样
+    pub fn format(self, args: &[&dyn Display]) -> String {
+        let mut args = args.iter();
+        let mut pieces = self.text().split("{}");
+        let mut out = pieces.next().unwrap_or_default().to_string();
+        for piece in pieces {
+            match args.next() {
+                Some(arg) => out.push_str(&arg.to_string()),
+                None => out.push_str("{}"),
+            }
+            out.push_str(piece);
+        }
+        out
+    }
+
+    fn zh(self) -> &'static str {
+        match self {
+            Message::IoError => "IO错误: {}",
+            Message::ImageError => "图像处理错误: {}",
+            Message::AudioError => "音频处理错误: {}",
+            Message::UnsupportedFormat => "不支持的文件格式: {}",
+            Message::InvalidWatermark => "无效的水印数据",
+            Message::ExtractionFailed => "水印提取失败",
+            Message::AlgorithmError => "算法错误: {}",
+            Message::InvalidArgument => "参数错误: {}",
+            Message::ProcessingError => "处理错误: {}",
+            Message::PasswordRequired => "水印已加密，需要提供密码",
+            Message::DecryptionFailed => "水印解密失败：密码错误或水印已损坏",
+            Message::SignatureInvalid => "水印签名校验失败：密钥错误或水印已被篡改",
+            Message::OutputNotWritable => "输出目录不可写: {} ({})",
+            Message::QualityTooLow => "嵌入质量低于下限: 实测 {}dB, 要求不低于 {}dB",
+            Message::CapacityExceeded => "水印数据太长: 需要 {} 比特, 最多可嵌入 {} 比特",
+            Message::BatchFailed => "批处理中有{}个文件失败（共{}个）",
+
+            Message::ErrorLabel => "错误:",
+            Message::WarningLabel => "警告:",
+            Message::FfmpegDownloadFailed => "无法下载 FFmpeg: {}",
+            Message::FfmpegInstallHint => "请确保系统中已安装 FFmpeg，或者检查网络连接",
+            Message::FfmpegRetry => "FFmpeg 暂时失败（{}），{}ms 后第{}次重试",
+            Message::SplitPartWritten => "分片 {}/{} 已写入: {}",
+            Message::SplitHeaderMissing => "未找到分片头部，已跳过: {}",
+            Message::WatermarkSaved => "提取的水印已保存到: {}",
+            Message::BatchSummary => "共{}个文件，成功{}个，失败{}个",
+            Message::DryRun => "🔍  试运行:",
+            Message::DryRunSummary => "载荷{}比特，容量{}比特，未写出任何文件",
+            Message::ProcessingImage => "🖼️  处理图片文件:",
+            Message::ProcessingAudio => "🎧  处理音频文件:",
+            Message::ProcessingVideo => "🎥  处理视频文件:",
+            Message::AutoStrength => "🎚️  自动选择的强度:",
+            Message::Snr => "📈  信噪比:",
+            Message::ExtractingFrom => "🔍  从文件提取水印:",
+            Message::UsingAlgorithm => "🔧  使用算法:",
+            Message::ConfidenceMapSaved => "块置信度图已保存到: {}",
+            Message::BitsCorrected => "🩹  纠正的比特数:",
+            Message::RobustWinner => "🩹  置信度最高的预处理:",
+            Message::RobustAttempts => "{}（共尝试{}种）",
+            Message::Detecting => "🔍  检测水印:",
+            Message::Attack => "🛡️  攻击:",
+            Message::AttackExtractionFailed => "提取失败",
+            Message::DiffSaved => "差异图已保存到: {}",
+            Message::CapacitySummary => "最多可嵌入{}字节的水印（容量{}字节）",
+            Message::LowConfidence => "警告：置信度较低 ({}%，阈值 {}%)，提取结果可能不可靠",
+            Message::Reassembling => "🎞️  重新组合视频",
+            Message::Frames => "帧",
+
+            Message::AnimatedGifFirstFrameOnly => {
+                "输入为{}帧的动画GIF，输出格式不支持动画，只嵌入并保存第一帧"
+            }
+            Message::PaletteExpanded => "调色板PNG已展开为{}后嵌入，输出不再使用调色板",
+            Message::SixteenBitDowngraded => {
+                "输出格式不支持每通道16位，已降为8位保存，建议使用PNG或TIFF"
+            }
+            Message::LowJpegQuality => {
+                "以较低的JPEG质量{}保存DCT水印，有损压缩可能破坏水印，建议使用 {} 以上"
+            }
+            Message::LossyImageFormat => {
+                "{} 是有损格式，编码会削弱水印：DCT水印的鲁棒性可能下降，LSB水印无法保留"
+            }
+            Message::AlreadyWatermarked => {
+                "输入图片已带有自描述头部，说明已经嵌入过水印，再次嵌入会破坏原有的水印"
+            }
+            Message::ExifTooLarge => {
+                "EXIF 数据有 {} 字节，超过 JPEG 单个段约64KB的上限，输出的JPEG中没有保留EXIF"
+            }
+            Message::IccTooLarge => {
+                "ICC 颜色配置文件有 {} 字节，超过 JPEG 可分段写入的上限，输出的JPEG中没有保留该配置文件"
+            }
+            Message::EightBitPcm => {
+                "源文件为8位PCM，输出沿用该位深度，量化误差可能大于水印本身，提取时容易失败"
+            }
+            Message::EarlyStop => "第{}帧通过帧序号校验（置信度 {}%），跳过其余{}个采样帧",
+            Message::SceneDetectionFailed => "场景切换检测失败，改用均匀采样: {}",
+            Message::KeyframeNotFound => "未找到 {} 秒之后的关键帧，改为处理全部视频帧",
+            Message::ProcessAllFrames => "改为处理全部视频帧",
+            Message::KeyframeJoinFailed => {
+                "拼接后的视频未通过解码校验（{}），改为重新编码全部视频帧"
+            }
+            Message::DecodeErrors => "解码出错",
+            Message::FrameCountMismatch => "解码出 {} 帧，源视频为 {} 帧",
+            Message::VideoLowConfidence => {
+                "警告：样本修正后置信度较低 ({}%，原始 {}%，{} 帧)，建议检查视频质量或增加采样帧数"
+            }
+            Message::FusedLowConfidence => "警告：提取置信度较低 ({}%)，建议检查媒体质量",
+            Message::TempFilesCleaned => "临时文件已清理",
+            Message::ResumingFrames => "从断点继续：上次已嵌入 {} 帧（共 {} 帧）",
+            Message::VideoEmbedDone => "视频水印嵌入完成!",
+
+            Message::DryRunEmbedOnly => "--dry-run 仅用于 embed 命令，且不能与 --split 同时使用",
+            Message::SplitEmbedExtractOnly => "--split 仅用于 embed 和 extract",
+            Message::InputNotFound => "输入文件不存在: {}",
+            Message::SplitImagesOnly => "--split 仅支持图片: {}",
+            Message::SplitSingleWatermark => "--split 只能嵌入一个水印",
+            Message::SplitNeedsMoreImages => "载荷{}字节超出单张图片的容量{}字节，按该尺寸需要{}张图片；请把 -i 指向包含这些图片的目录或通配符",
+            Message::SplitSingleExtract => "--split 只能提取一个水印",
+            Message::SplitLengthMismatch => "拼接后的载荷为{}字节，与 -l 对应的{}字节不一致",
+            Message::InvalidInputFile => "无效的输入文件: {}",
+            Message::ResumeVideoModeOnly => "--resume 仅支持视频的 video 模式",
+            Message::HeaderSingleWatermark => "--header 只能记录一个水印",
+            Message::ReembedNeedsForce => "输入图片已经嵌入过水印，确需再次嵌入请使用 --force",
+            Message::HeaderWithDryRun => "--header 不能与 --dry-run 同时使用",
+            Message::RegionWithDryRun => "--region 和 --normalize-size 不能与 --dry-run 同时使用",
+            Message::MetadataWithDryRun => "--metadata 不能与 --dry-run 同时使用",
+            Message::HeaderSizeMismatch => "自描述头部记录的规范尺寸与 --normalize-size 不一致",
+            Message::SignedNeedsKey => "水印带有签名，须用 --sign 提供嵌入时的密钥",
+            Message::RobustImagesOnly => "--robust 仅支持图片",
+            Message::ConfidenceMapDctOnly => "--confidence-map 目前仅支持图片的 DCT 算法",
+            Message::MultipleWithOutput => "提取多个水印时不支持 -o",
+            Message::LsbCannotDetect => "LSB 算法无法检测水印是否存在",
+            Message::MaxBerRange => "--max-ber 应在0-1之间，实际为{}",
+            Message::VerifyImagesOnly => "verify 仅支持图片",
+            Message::DiffMediaMismatch => "diff 的两个文件必须是同一种媒体",
+            Message::DiffImageAudioOnly => "diff 仅支持图片和音频",
+            Message::VideoNoPassword => "视频暂不支持 --password 加密水印",
+            Message::VideoNoSign => "视频暂不支持 --sign 签名水印",
+            Message::VideoNoEcc => "视频暂不支持 --ecc 纠错编码",
+            Message::VideoNoBinary => "视频暂不支持 --binary 二进制水印",
+            Message::RepeatImagesOnly => "--repeat 仅支持图片",
+            Message::VideoNoMultiple => "视频暂不支持多个水印",
+            Message::BlockSizeNoAudio => "--block-size 暂不支持音频",
+            Message::TileImagesOnly => "--tile 仅支持图片",
+            Message::TileDctOnly => "--tile 仅支持DCT算法",
+            Message::DftNoAudio => "DFT 算法暂不支持音频",
+            Message::EchoAudioOnly => "回声隐藏算法仅支持音频",
+            Message::FlagAudioOnly => "{} 仅支持音频",
+            Message::LengthRequired => "只有图片可以省略 -l 按自描述头部提取，音频和视频须指定 -l",
+            Message::FlagWithoutLength => "省略 -l 按自描述头部提取时不能使用 {}",
+            Message::FlagImagesOnly => "{} 仅支持图片",
+            Message::VideoNoBytes => "视频暂不支持按字节提取",
+
+            Message::InvalidPercentStrength => "无效的百分比强度: {}",
+            Message::InvalidStrength => "无效的强度值: {}",
+            Message::StrengthRange => "强度必须在0.0-1.0（0%-100%）之间: {}",
+            Message::InvalidAudioStrengthScale => "音频强度系数必须是正数: {}",
+            Message::InvalidQimStep => "QIM步长必须是正数: {}",
+            Message::InvalidPerceptualThreshold => "感知加权阈值必须是非负数: {}",
+            Message::InvalidRepeat => "无效的重复次数: {}，应为 auto 或正整数",
+            Message::InvalidBlockSize => "无效的块边长: {}，应为8到{}之间的8的倍数",
+            Message::InvalidTileSize => "无效的图块边长: {}，应为不小于64的8的倍数",
+            Message::InvalidRegion => "无效的区域: {}，应为 x,y,w,h",
+            Message::RegionUnaligned => "区域左上角({},{})须对齐到{}像素的块边界",
+            Message::RegionTooSmall => "区域{}x{}太小，宽高都不能小于{}像素",
+            Message::InvalidVoteChannel => "无效的投票通道: {}，应为 r、g 或 b",
+            Message::InvalidSize => "无效的尺寸: {}，应为 WxH，如 1024x1024",
+            Message::NormalizeSizeRange => "规范尺寸{}x{}的宽高须在8到65535像素之间",
+            Message::InvalidPositionPair => "无效的系数位置: {}，应为 u,v",
+            Message::InvalidPosition => "无效的系数位置: {}",
+            Message::PositionOutsideBlock => "系数位置({},{})超出了{}x{}的DCT块",
+            Message::PositionsEmpty => "系数位置列表不能为空",
+
+            Message::AudioStrengthScaleRange => "音频强度系数必须是正数，实际为{}",
+            Message::RawOutputLossless => "原始嵌入模式只能输出无损的 wav 或 flac，不能输出 {}",
+            Message::RawFloatWavOnly => "原始嵌入模式下浮点采样的音频只能输出 wav",
+            Message::SyncNotSupported => "{} 算法不能叠加同步标记",
+            Message::AudioTooShortForSync => "音频太短，同步标记至少需要{}个样本，实际为{}个",
+            Message::AudioFormatsDiffer => "两段音频的格式不同: {}声道 {}Hz 与 {}声道 {}Hz",
+            Message::AudioNormalizeFailed => "音频格式标准化失败",
+            Message::UnsupportedBitDepth => "不支持的位深度: {} bits",
+            Message::UnsupportedAudioOutput => "音频输出 '{}'，可选 wav、mp3、aac、m4a、flac",
+            Message::AudioConvertFailed => "音频格式转换失败 (目标格式: {})",
+            Message::SyncNotFound => "没有检测到同步标记，嵌入时是否使用了 --sync",
+            Message::InputPathInvalid => "输入路径包含无效字符",
+            Message::FfmpegOutputUnreadable => "无法读取FFmpeg输出: {}",
+            Message::NoAudioStream => "输入文件不包含音频流",
+            Message::AudioDurationUnknown => "无法获取音频时长",
+            Message::ConvertingToMono => "将音频转换为单声道...",
+            Message::AudioEmbedDone => "✅ 通用音频水印嵌入完成",
+            Message::SmoothingPipeline => "🔧 应用高级音频平滑处理流水线...",
+            Message::ProtectiveNormalization => "  📊 应用了保护性归一化，因子: {}",
+            Message::SmoothingDone => "✅ 高级音频平滑处理完成",
+            Message::GentleLowpass => "  🎛️ 应用了全局温和低通滤波",
+            Message::AdaptiveCompression => "  🎚️ 应用了自适应动态范围压缩",
+            Message::BoundarySmoothing => "  🎭 应用了边界平滑处理，淡入淡出长度: {}样本",
+            Message::PerceptualLimiting => "  🔊 应用了感知优化限制",
+            Message::RegionOutsideImage => "区域{},{},{},{}超出了图片范围{}x{}",
+            Message::HeaderDoesNotFit => "图片尺寸{}x{}放不下自描述头部（需要宽度至少8像素、高度超过{}行）",
+            Message::CannotWriteImage => "无法写出 .{} 图片，请把输出改为 .avif、.png 或 .jpg",
+            Message::EmptyImage => "图片为空",
+            Message::ImageSizesDiffer => "两张图片的尺寸不同: {} 与 {}",
+            Message::DebugImageInfo => "图片信息: {}x{} 像素, 格式: {}",
+            Message::DebugExtracting => "尝试提取 {} 字符的水印 ({} 比特)...",
+            Message::DebugExtracted => "水印提取完成:",
+            Message::DebugAlgorithm => "使用算法: {}",
+            Message::DebugWatermark => "提取到的水印: {}",
+            Message::DebugStrictUtf8Failed => "严格UTF-8转换失败，尝试宽松模式...",
+            Message::DebugExtractedLossy => "水印提取完成 (宽松模式):",
+            Message::DebugStandardFailed => "标准提取失败: {}",
+            Message::DebugTryVoting => "尝试投票提取方法...",
+            Message::DebugVotingResult => "投票提取结果:",
+            Message::DebugExtractedVoting => "水印提取完成 (投票模式):",
+            Message::CannotRebuildImage => "无法由{}个通道还原图片",
+            Message::FfmpegImageConvertFailed => "FFmpeg 图片格式转换失败",
+            Message::AvifEncodeFailed => "FFmpeg AVIF编码失败，请确认FFmpeg启用了 libaom-av1",
+            Message::ImageFormatUnknown => "无法确定图片格式",
+            Message::StripColorMismatch => "嵌入部分的颜色类型{}与输出图片的{}不一致",
+            Message::PngMissingIhdr => "PNG缺少IHDR数据块",
+            Message::FileTypeUnknown => "无法确定文件类型",
+            Message::UnsupportedExtension => "不支持的文件格式: {}",
+            Message::WildcardInDirectory => "通配符只能出现在文件名部分，不能用于目录: {}",
+            Message::NoMatchingFiles => "没有找到可处理的文件: {}",
+            Message::OutputExists => "输出文件已存在: {}，使用 --overwrite 覆盖",
+            Message::FfmpegWatcherPanicked => "FFmpeg监视线程异常退出",
+            Message::KilledBySignal => "进程被信号终止",
+            Message::FfmpegTimeout => "ffmpeg 超时",
+            Message::FfmpegInterrupted => "ffmpeg 已被中断",
+            Message::CodecContainerMismatch => "{} 编码无法写入 .{} 容器",
+            Message::CrfRange => "{} 编码的 CRF 取值范围为 0-{}，当前为 {}",
+            Message::KeyframeSourceH264Only => "关键帧区间模式仅支持 H.264 视频，当前编码: {}",
+            Message::UnknownCodec => "未知",
+            Message::KeyframeOutputH264Only => "关键帧区间模式只能输出 H.264，不能使用 {} 编码写入 {}",
+            Message::KeyframeFrameIndexUnsupported => "关键帧区间模式下开头片段的各帧共用同一份水印，不支持 --frame-index",
+            Message::NoSampledFrames => "无法从视频中读取任何采样帧",
+            Message::OutputPathInvalid => "输出路径包含无效字符",
+            Message::VideoPathInvalid => "视频路径包含无效字符",
+            Message::AudioPathInvalid => "音频路径包含无效字符",
+            Message::NoVideoStream => "输入文件不包含视频流",
+            Message::FrameExtractionFailed => "视频帧提取失败",
+            Message::SingleFrameFailed => "单帧提取失败",
+            Message::AllSampledFramesFailed => "所有采样帧的水印提取都失败",
+            Message::SceneSampling => "场景采样：{}个候选帧中保留画面质量最好的{}帧",
+            Message::SceneDetectionError => "场景切换检测失败",
+            Message::NoFramesExtracted => "未能提取任何视频帧",
+            Message::FramesProcessedSoFar => "🎬 已处理 {} 帧",
+            Message::AllFramesFailed => "所有帧的水印提取都失败",
+            Message::FramesProcessedSummary => "🎬 总共处理了 {} 帧，成功提取水印的帧数: {}",
+            Message::ImageUnreadable => "无法读取图像: {}",
+            Message::ImageUndecodable => "无法解码图像: {}",
+            Message::ReassembleFailed => "视频重组失败",
+            Message::ResumeFrameMismatch => "断点目录 {} 中的帧数{}与清单记录的{}不一致，请删除该目录后重新开始",
+            Message::ResumeParamsMismatch => "断点目录 {} 中的进度与本次的输入、水印、强度或算法参数不一致，请删除该目录或去掉 --resume 重新开始",
+            Message::HeadEncodeFailed => "开头片段编码失败",
+            Message::TailCopyFailed => "剩余片段复制失败",
+            Message::SegmentJoinFailed => "视频片段拼接失败",
+            Message::MuxFailed => "音视频合并失败",
+            Message::NoAudioTrackForEmbed => "视频文件不包含音频轨道，无法嵌入音频水印",
+            Message::FrameSizeMismatch => "视频帧数据长度与尺寸不符",
+            Message::NoFramesDecoded => "未能从视频中解码出任何帧",
+            Message::VideoDecodeFailed => "视频解码失败",
+            Message::VideoEncodeFailed => "视频编码失败",
+            Message::FramesProcessed => "已处理 {} 帧",
+            Message::EncoderExited => "视频编码进程意外退出",
+            Message::EncoderStdinUnavailable => "无法打开编码进程的标准输入",
+            Message::AudioExtractFailed => "音频提取失败: FFmpeg 命令执行失败, 错误码: {}",
+            Message::VideoStreamExtractFailed => "视频流提取失败",
+            Message::NoAudioTrackForExtract => "视频文件不包含音频轨道，无法提取音频水印",
+            Message::WritingContainerTag => "写入容器元数据水印",
+            Message::ContainerTagFailed => "写入容器元数据失败",
+            Message::ChoseAudioResult => "选择音频水印结果（置信度: {}%）",
+            Message::ChoseVideoResult => "选择视频水印结果（置信度: {}%）",
+            Message::VideoResultOnly => "仅使用视频水印结果",
+            Message::BothBelowThreshold => "❌ 音频和视频水印的置信度均低于阈值",
+            Message::BestBelowThreshold => "最高置信度 {}% 低于阈值 {}%",
+            Message::ContainerTagFound => "容器元数据中找到水印",
+            Message::ContainerTagMissing => "容器元数据中没有水印",
+            Message::AudioResultConfidence => "音频水印置信度: {}%",
+            Message::AudioResultFailed => "音频水印提取失败: {}",
+            Message::VideoResultFailed => "视频帧水印提取失败: {}",
+            Message::VideoResultConfidence => "视频帧水印置信度: {}%（{}帧）",
+            Message::AllCopiesFailed => "容器元数据、音频和视频帧中均未能提取到水印",
+            Message::CopiesVoted => "{}路结果投票完成，置信度: {}%",
+
+            Message::StageTempDir => "🗂️  创建临时目录",
+            Message::StageExtractFrames => "🎬  提取视频帧",
+            Message::StageProcessFrames => "🎯  处理视频帧",
+            Message::StageCopyAudio => "🎵  复用原始音轨",
+            Message::StageVideoEmbedDone => "🎉 视频水印嵌入完成!",
+            Message::StageLocateKeyframe => "🔍  定位关键帧",
+            Message::StageKeyframeDelta => "🎯  在首帧上计算水印差值",
+            Message::StageEncodeHead => "🎞️  编码开头片段",
+            Message::StageJoin => "🔗  拼接视频",
+            Message::StageExtractAudio => "🎵  提取音频轨道",
+            Message::StageProcessAudio => "🎯  处理音频水印",
+            Message::StageExtractVideoStream => "🎬  提取视频流",
+            Message::StageMux => "🎞️  合并音视频",
+            Message::StageAudioEmbedDone => "🎉 音频水印嵌入完成!",
+            Message::StageSinglePass => "🎯  单次解码并处理视频帧水印",
+            Message::StageAudioWatermark => "🎵  处理音频水印",
+            Message::StageBothEmbedDone => "🎉 音视频水印嵌入完成!",
+            Message::StageAnalyze => "📊  分析视频信息",
+            Message::StageExtractAll => "🎬  提取所有帧",
+            Message::StageExtractSamples => "🎬  提取{}个样本帧",
+            Message::StageVote => "🗳️  多帧投票分析",
+            Message::StageVideoExtractDone => "🎉 视频水印提取完成! 置信度: {}%",
+            Message::StageExtractAudioWatermark => "🎯  提取音频水印",
+            Message::StageAudioExtractDone => "🎉 音频水印提取完成!",
+            Message::StageExtractAudioMark => "🎵  提取音频水印",
+            Message::StageFuse => "🔀  融合音视频水印结果",
+            Message::StageBothExtractDone => "🎉 音视频水印提取完成! 置信度: {}%",
+
+            Message::AttackSpecInvalid => "无效的攻击: {}，应为 名称:参数，如 jpeg:50",
+            Message::AttackValueInvalid => "无效的攻击参数: {}",
+            Message::AttackJpegQuality => "JPEG质量应为1-100之间的整数: {}",
+            Message::AttackNoiseNegative => "噪声标准差不能为负: {}",
+            Message::AttackResizeScale => "缩放比例必须大于0: {}",
+            Message::AttackCropFraction => "裁剪比例应在0-1之间: {}",
+            Message::AttackUnknown => "未知的攻击: {}，可选 jpeg、noise、resize、rotate、crop",
+            Message::ResizeScaleInvalid => "缩放比例必须大于0，实际为{}",
+            Message::ResizeTargetInvalid => "缩放后的尺寸必须大于0，实际为{}x{}",
+            Message::CropFractionInvalid => "裁剪比例应在0-1之间，实际为{}",
+            Message::BerLengthInvalid => "比特数应在1-{}之间，实际为{}",
+
+            Message::RandomUnavailable => "无法生成随机数",
+            Message::EncryptionFailed => "水印加密失败",
+            Message::KeyInvalid => "无效的密钥",
+            Message::DifferentialWithSpread => "差分模式不能与扩频模式同时使用",
+            Message::DifferentialNeedsTwoPositions => "差分模式至少需要两个系数位置",
+            Message::QimStepInvalid => "QIM步长必须是正数，实际为{}",
+            Message::QimWithOtherModes => "QIM嵌入不能与扩频或差分模式同时使用",
+            Message::PositionOutOfBlock => "系数位置({},{})超出了{}x{}的DCT块",
+            Message::ConfidenceMapModeUnsupported => "图块模式、扩频模式和全图模式不支持块置信度图",
+            Message::StripModeUnsupported => "图块模式、扩频模式和全图模式不支持按条带处理",
+            Message::StripPlanBitsMismatch => "条带规划承载{}比特，实际水印为{}比特",
+            Message::ExpectedExceedsBlocks => "期望长度{}超过了可提取的块数{}",
+            Message::SpreadChipsZero => "扩频码片数必须大于0",
+            Message::ExpectedExceedsSpread => "期望长度{}超过了扩频模式的容量{}",
+            Message::TileSizeInvalid => "图块边长{}必须是DCT块大小{}的正整数倍",
+            Message::TilesWithSpread => "分块模式不能与扩频模式同时使用",
+            Message::ImageSmallerThanTile => "图像尺寸{}x{}小于一个{}x{}的图块",
+            Message::ExpectedExceedsTiles => "期望长度{}超过了分块模式的容量{}",
+            Message::NoValidTile => "没有找到通过CRC校验的完整图块，图像可能被裁剪得过小或已损坏",
+            Message::GlobalWithOtherModes => "全图模式不能与自定义系数位置、分块、扩频、差分或QIM模式同时使用",
+            Message::ExpectedExceedsGlobal => "期望长度{}超过了全图模式的容量{}",
+            Message::ExpectedExceedsDftRing => "期望的水印长度超过了DFT环带的容量。最多{}比特，实际需要{}比特",
+            Message::EccPayloadTooShort => "纠错编码后的载荷只有{}字节，还原{}字节需要{}字节",
+            Message::HeaderFieldTooLarge => "自描述头部无法记录大于255的{}",
+            Message::HeaderPayloadTooLarge => "自描述头部最多记录{}字节的载荷，实际为{}字节",
+            Message::HeaderSizeTooLarge => "自描述头部无法记录大于{}像素的规范尺寸",
+            Message::HeaderBlockSize => "块边长",
+            Message::HeaderInterleave => "交织深度",
+            Message::BitPlanesInvalid => "位平面数必须在1-{}之间，实际为{}",
+            Message::ExpectedExceedsBits => "期望长度{}超过了可提取的位数{}",
+            Message::SampleReshapeFailed => "样本重排失败: {}",
+            Message::RepetitionTooFew => "重复次数必须至少为1，当前容量{}比特无法容纳{}比特的载荷",
+            Message::SplitCapacityShort => "{}张图片的总容量不足，还差{}字节",
+            Message::SplitTooManyParts => "载荷需要拆分为{}片，最多支持{}片",
+            Message::SplitCountMismatch => "分片声明的总数不一致，可能混入了其他载荷的分片",
+            Message::SplitPartsMissing => "分片不完整: 共{}片，缺少第{}片",
+            Message::SamplesUnsupported => "{} 算法不支持直接处理音频样本",
+            Message::ConfidenceMapUnsupported => "{} 算法不支持块置信度图",
+            Message::StripUnsupported => "{} 算法不支持按条带处理",
+            Message::StrengthOutOfRange => "水印强度必须在0.0-1.0之间，实际为{}",
+            Message::BitsNotByteAligned => "错误: 长度{}不是8的倍数",
+            Message::BitsAnalysis => "比特数据分析:",
+            Message::BitsTotal => "总长度: {} 比特",
+            Message::BitsBytesComplete => "字节数: {} (完整)",
+            Message::BitsBytesIncomplete => "字节数: {} (不完整)",
+            Message::BitsOnes => "1的数量: {} ({}%)",
+            Message::BitsZeros => "0的数量: {} ({}%)",
+            Message::BitsByteValues => "字节值",
+            Message::BitsUtf8 => "UTF-8解码: '{}'",
+            Message::BitsUtf8Lossy => "UTF-8解码失败，使用lossy: '{}'",
+        }
+    }
+
+    fn en(self) -> &'static str {
+        match self {
+            Message::IoError => "I/O error: {}",
+            Message::ImageError => "Image processing error: {}",
+            Message::AudioError => "Audio processing error: {}",
+            Message::UnsupportedFormat => "Unsupported file format: {}",
+            Message::InvalidWatermark => "Invalid watermark data",
+            Message::ExtractionFailed => "Watermark extraction failed",
+            Message::AlgorithmError => "Algorithm error: {}",
+            Message::InvalidArgument => "Invalid argument: {}",
+            Message::ProcessingError => "Processing error: {}",
+            Message::PasswordRequired => "The watermark is encrypted, a password is required",
+            Message::DecryptionFailed => {
+                "Failed to decrypt the watermark: wrong password or damaged watermark"
+            }
+            Message::SignatureInvalid => {
+                "Watermark signature check failed: wrong key or tampered watermark"
+            }
+            Message::OutputNotWritable => "Output directory is not writable: {} ({})",
+            Message::QualityTooLow => {
+                "Embedding quality below the floor: measured {}dB, required at least {}dB"
+            }
+            Message::CapacityExceeded => {
+                "Watermark too long: needs {} bits, at most {} bits can be embedded"
+            }
+            Message::BatchFailed => "{} of {} files failed in the batch",
+
+            Message::ErrorLabel => "Error:",
+            Message::WarningLabel => "Warning:",
+            Message::FfmpegDownloadFailed => "Failed to download FFmpeg: {}",
+            Message::FfmpegInstallHint => {
+                "Make sure FFmpeg is installed, or check your network connection"
+            }
+            Message::FfmpegRetry => "FFmpeg failed temporarily ({}), retrying in {}ms (attempt {})",
+            Message::SplitPartWritten => "Part {}/{} written: {}",
+            Message::SplitHeaderMissing => "No split header found, skipped: {}",
+            Message::WatermarkSaved => "Extracted watermark saved to: {}",
+            Message::BatchSummary => "{} files, {} succeeded, {} failed",
+            Message::DryRun => "🔍  Dry run:",
+            Message::DryRunSummary => "payload {} bits, capacity {} bits, no files written",
+            Message::ProcessingImage => "🖼️  Processing image:",
+            Message::ProcessingAudio => "🎧  Processing audio:",
+            Message::ProcessingVideo => "🎥  Processing video:",
+            Message::AutoStrength => "🎚️  Auto-selected strength:",
+            Message::Snr => "📈  SNR:",
+            Message::ExtractingFrom => "🔍  Extracting watermark from:",
+            Message::UsingAlgorithm => "🔧  Algorithm:",
+            Message::ConfidenceMapSaved => "Block confidence map saved to: {}",
+            Message::BitsCorrected => "🩹  Bits corrected:",
+            Message::RobustWinner => "🩹  Most confident preprocessing:",
+            Message::RobustAttempts => "{} ({} tried)",
+            Message::Detecting => "🔍  Detecting watermark:",
+            Message::Attack => "🛡️  Attack:",
+            Message::AttackExtractionFailed => "extraction failed",
+            Message::DiffSaved => "Difference map saved to: {}",
+            Message::CapacitySummary => {
+                "Up to {} bytes of watermark can be embedded (capacity {} bytes)"
+            }
+            Message::LowConfidence => {
+                "Warning: low confidence ({}%, threshold {}%), the result may be unreliable"
+            }
+            Message::Reassembling => "🎞️  Reassembling video",
+            Message::Frames => "frames",
+
+            Message::AnimatedGifFirstFrameOnly => {
+                "Input is an animated GIF with {} frames but the output format has no animation, only the first frame is watermarked and saved"
+            }
+            Message::PaletteExpanded => {
+                "Palette PNG expanded to {} before embedding, the output no longer uses a palette"
+            }
+            Message::SixteenBitDowngraded => {
+                "The output format does not support 16 bits per channel, saved as 8 bits; PNG or TIFF is recommended"
+            }
+            Message::LowJpegQuality => {
+                "Saving a DCT watermark at a low JPEG quality of {}, lossy compression may destroy it; {} or above is recommended"
+            }
+            Message::LossyImageFormat => {
+                "{} is a lossy format and encoding weakens the watermark: DCT robustness may suffer and an LSB watermark will not survive"
+            }
+            Message::AlreadyWatermarked => {
+                "The input image already carries a self-describing header, so it has been watermarked before; embedding again will corrupt the existing watermark"
+            }
+            Message::ExifTooLarge => {
+                "The EXIF data is {} bytes, over the ~64KB limit of a single JPEG segment; it was not kept in the output JPEG"
+            }
+            Message::IccTooLarge => {
+                "The ICC profile is {} bytes, more than JPEG can store in segments; it was not kept in the output JPEG"
+            }
+            Message::EightBitPcm => {
+                "The source is 8-bit PCM and the output keeps that bit depth; quantization error may exceed the watermark and extraction is likely to fail"
+            }
+            Message::EarlyStop => {
+                "Frame {} passed the frame-index check (confidence {}%), skipping the remaining {} sampled frames"
+            }
+            Message::SceneDetectionFailed => {
+                "Scene change detection failed, falling back to uniform sampling: {}"
+            }
+            Message::KeyframeNotFound => {
+                "No keyframe found after {} seconds, processing all video frames instead"
+            }
+            Message::ProcessAllFrames => "Processing all video frames instead",
+            Message::KeyframeJoinFailed => {
+                "The joined video failed the decode check ({}), re-encoding all video frames instead"
+            }
+            Message::DecodeErrors => "decoding errors",
+            Message::FrameCountMismatch => "decoded {} frames, the source has {}",
+            Message::VideoLowConfidence => {
+                "Warning: low confidence after sample correction ({}%, raw {}%, {} frames); check the video quality or sample more frames"
+            }
+            Message::FusedLowConfidence => {
+                "Warning: low extraction confidence ({}%), check the media quality"
+            }
+            Message::TempFilesCleaned => "Temporary files cleaned up",
+            Message::ResumingFrames => "Resuming: {} frames were watermarked last time ({} frames in total)",
+            Message::VideoEmbedDone => "Video watermark embedded!",
+
+            Message::DryRunEmbedOnly => "--dry-run only applies to embed and cannot be combined with --split",
+            Message::SplitEmbedExtractOnly => "--split only applies to embed and extract",
+            Message::InputNotFound => "Input file does not exist: {}",
+            Message::SplitImagesOnly => "--split only supports images: {}",
+            Message::SplitSingleWatermark => "--split can only embed one watermark",
+            Message::SplitNeedsMoreImages => "The {}-byte payload exceeds the {}-byte capacity of a single image and needs {} images of this size; point -i at a directory or wildcard containing them",
+            Message::SplitSingleExtract => "--split can only extract one watermark",
+            Message::SplitLengthMismatch => "The joined payload is {} bytes, which does not match the {} bytes implied by -l",
+            Message::InvalidInputFile => "Invalid input file: {}",
+            Message::ResumeVideoModeOnly => "--resume only supports the video mode for videos",
+            Message::HeaderSingleWatermark => "--header can only record one watermark",
+            Message::ReembedNeedsForce => "The input image already carries a watermark; use --force if you really want to embed again",
+            Message::HeaderWithDryRun => "--header cannot be combined with --dry-run",
+            Message::RegionWithDryRun => "--region and --normalize-size cannot be combined with --dry-run",
+            Message::MetadataWithDryRun => "--metadata cannot be combined with --dry-run",
+            Message::HeaderSizeMismatch => "The canonical size recorded in the header does not match --normalize-size",
+            Message::SignedNeedsKey => "The watermark is signed; pass the embedding key with --sign",
+            Message::RobustImagesOnly => "--robust only supports images",
+            Message::ConfidenceMapDctOnly => "--confidence-map currently only supports the DCT algorithm on images",
+            Message::MultipleWithOutput => "-o is not supported when extracting several watermarks",
+            Message::LsbCannotDetect => "The LSB algorithm cannot detect whether a watermark is present",
+            Message::MaxBerRange => "--max-ber must be between 0 and 1, got {}",
+            Message::VerifyImagesOnly => "verify only supports images",
+            Message::DiffMediaMismatch => "Both files given to diff must be the same kind of media",
+            Message::DiffImageAudioOnly => "diff only supports images and audio",
+            Message::VideoNoPassword => "Videos do not support --password encrypted watermarks yet",
+            Message::VideoNoSign => "Videos do not support --sign signed watermarks yet",
+            Message::VideoNoEcc => "Videos do not support --ecc error correction yet",
+            Message::VideoNoBinary => "Videos do not support --binary watermarks yet",
+            Message::RepeatImagesOnly => "--repeat only supports images",
+            Message::VideoNoMultiple => "Videos do not support multiple watermarks yet",
+            Message::BlockSizeNoAudio => "--block-size does not support audio yet",
+            Message::TileImagesOnly => "--tile only supports images",
+            Message::TileDctOnly => "--tile only supports the DCT algorithm",
+            Message::DftNoAudio => "The DFT algorithm does not support audio yet",
+            Message::EchoAudioOnly => "The echo hiding algorithm only supports audio",
+            Message::FlagAudioOnly => "{} only supports audio",
+            Message::LengthRequired => "Only images can omit -l and extract from the header; audio and video require -l",
+            Message::FlagWithoutLength => "{} cannot be used when -l is omitted and the header is read",
+            Message::FlagImagesOnly => "{} only supports images",
+            Message::VideoNoBytes => "Videos do not support extracting raw bytes yet",
+
+            Message::InvalidPercentStrength => "Invalid percentage strength: {}",
+            Message::InvalidStrength => "Invalid strength: {}",
+            Message::StrengthRange => "Strength must be between 0.0 and 1.0 (0%-100%): {}",
+            Message::InvalidAudioStrengthScale => "The audio strength scale must be a positive number: {}",
+            Message::InvalidQimStep => "The QIM step must be a positive number: {}",
+            Message::InvalidPerceptualThreshold => "The perceptual threshold must be a non-negative number: {}",
+            Message::InvalidRepeat => "Invalid repeat count: {}, expected auto or a positive integer",
+            Message::InvalidBlockSize => "Invalid block size: {}, expected a multiple of 8 between 8 and {}",
+            Message::InvalidTileSize => "Invalid tile size: {}, expected a multiple of 8 no smaller than 64",
+            Message::InvalidRegion => "Invalid region: {}, expected x,y,w,h",
+            Message::RegionUnaligned => "The region's top-left corner ({},{}) must be aligned to the {}-pixel block grid",
+            Message::RegionTooSmall => "Region {}x{} is too small, width and height must both be at least {} pixels",
+            Message::InvalidVoteChannel => "Invalid vote channel: {}, expected r, g or b",
+            Message::InvalidSize => "Invalid size: {}, expected WxH such as 1024x1024",
+            Message::NormalizeSizeRange => "The canonical size {}x{} must have width and height between 8 and 65535 pixels",
+            Message::InvalidPositionPair => "Invalid coefficient position: {}, expected u,v",
+            Message::InvalidPosition => "Invalid coefficient position: {}",
+            Message::PositionOutsideBlock => "Coefficient position ({},{}) lies outside the {}x{} DCT block",
+            Message::PositionsEmpty => "The coefficient position list cannot be empty",
+
+            Message::AudioStrengthScaleRange => "The audio strength scale must be a positive number, got {}",
+            Message::RawOutputLossless => "Raw mode can only write lossless wav or flac, not {}",
+            Message::RawFloatWavOnly => "In raw mode, floating-point audio can only be written as wav",
+            Message::SyncNotSupported => "The {} algorithm cannot be combined with a sync marker",
+            Message::AudioTooShortForSync => "The audio is too short: the sync marker needs at least {} samples, got {}",
+            Message::AudioFormatsDiffer => "The two audio files differ in format: {} channels {}Hz vs {} channels {}Hz",
+            Message::AudioNormalizeFailed => "Failed to normalize the audio format",
+            Message::UnsupportedBitDepth => "Unsupported bit depth: {} bits",
+            Message::UnsupportedAudioOutput => "Audio output '{}', choose from wav, mp3, aac, m4a, flac",
+            Message::AudioConvertFailed => "Audio conversion failed (target: {})",
+            Message::SyncNotFound => "No sync marker detected; was --sync used when embedding?",
+            Message::InputPathInvalid => "The input path contains invalid characters",
+            Message::FfmpegOutputUnreadable => "Cannot read FFmpeg output: {}",
+            Message::NoAudioStream => "The input file has no audio stream",
+            Message::AudioDurationUnknown => "Cannot determine the audio duration",
+            Message::ConvertingToMono => "Converting audio to mono...",
+            Message::AudioEmbedDone => "✅ Audio watermark embedded",
+            Message::SmoothingPipeline => "🔧 Applying the audio smoothing pipeline...",
+            Message::ProtectiveNormalization => "  📊 Applied protective normalization, factor: {}",
+            Message::SmoothingDone => "✅ Audio smoothing done",
+            Message::GentleLowpass => "  🎛️ Applied a gentle global low-pass filter",
+            Message::AdaptiveCompression => "  🎚️ Applied adaptive dynamic range compression",
+            Message::BoundarySmoothing => "  🎭 Applied boundary smoothing, fade length: {} samples",
+            Message::PerceptualLimiting => "  🔊 Applied perceptual limiting",
+            Message::RegionOutsideImage => "Region {},{},{},{} lies outside the {}x{} image",
+            Message::HeaderDoesNotFit => "A {}x{} image cannot hold the header (it needs a width of at least 8 pixels and more than {} rows)",
+            Message::CannotWriteImage => "Cannot write .{} images, change the output to .avif, .png or .jpg",
+            Message::EmptyImage => "The image is empty",
+            Message::ImageSizesDiffer => "The two images differ in size: {} vs {}",
+            Message::DebugImageInfo => "Image: {}x{} pixels, format: {}",
+            Message::DebugExtracting => "Trying to extract a {}-character watermark ({} bits)...",
+            Message::DebugExtracted => "Watermark extracted:",
+            Message::DebugAlgorithm => "Algorithm: {}",
+            Message::DebugWatermark => "Extracted watermark: {}",
+            Message::DebugStrictUtf8Failed => "Strict UTF-8 conversion failed, trying lossy mode...",
+            Message::DebugExtractedLossy => "Watermark extracted (lossy mode):",
+            Message::DebugStandardFailed => "Standard extraction failed: {}",
+            Message::DebugTryVoting => "Trying extraction by voting...",
+            Message::DebugVotingResult => "Voting result:",
+            Message::DebugExtractedVoting => "Watermark extracted (voting mode):",
+            Message::CannotRebuildImage => "Cannot rebuild an image from {} channels",
+            Message::FfmpegImageConvertFailed => "FFmpeg image conversion failed",
+            Message::AvifEncodeFailed => "FFmpeg AVIF encoding failed, make sure FFmpeg is built with libaom-av1",
+            Message::ImageFormatUnknown => "Cannot determine the image format",
+            Message::StripColorMismatch => "The embedded part has color type {} but the output image has {}",
+            Message::PngMissingIhdr => "The PNG has no IHDR chunk",
+            Message::FileTypeUnknown => "Cannot determine the file type",
+            Message::UnsupportedExtension => "Unsupported file format: {}",
+            Message::WildcardInDirectory => "Wildcards may only appear in the file name, not in directories: {}",
+            Message::NoMatchingFiles => "No files to process: {}",
+            Message::OutputExists => "The output file already exists: {}, use --overwrite to replace it",
+            Message::FfmpegWatcherPanicked => "The FFmpeg watcher thread exited abnormally",
+            Message::KilledBySignal => "the process was killed by a signal",
+            Message::FfmpegTimeout => "ffmpeg timed out",
+            Message::FfmpegInterrupted => "ffmpeg was interrupted",
+            Message::CodecContainerMismatch => "{} cannot be written to a .{} container",
+            Message::CrfRange => "The CRF range for {} is 0-{}, got {}",
+            Message::KeyframeSourceH264Only => "Keyframe mode only supports H.264 videos, the source is {}",
+            Message::UnknownCodec => "unknown",
+            Message::KeyframeOutputH264Only => "Keyframe mode can only output H.264, cannot write {} to {}",
+            Message::KeyframeFrameIndexUnsupported => "Keyframe mode shares one watermark across the leading frames and does not support --frame-index",
+            Message::NoSampledFrames => "Could not read any sampled frame from the video",
+            Message::OutputPathInvalid => "The output path contains invalid characters",
+            Message::VideoPathInvalid => "The video path contains invalid characters",
+            Message::AudioPathInvalid => "The audio path contains invalid characters",
+            Message::NoVideoStream => "The input file has no video stream",
+            Message::FrameExtractionFailed => "Failed to extract video frames",
+            Message::SingleFrameFailed => "Failed to extract a frame",
+            Message::AllSampledFramesFailed => "Watermark extraction failed on every sampled frame",
+            Message::SceneSampling => "Scene sampling: out of {} candidate frames, kept the {} with the best picture quality",
+            Message::SceneDetectionError => "Scene change detection failed",
+            Message::NoFramesExtracted => "No video frames could be extracted",
+            Message::FramesProcessedSoFar => "🎬 {} frames processed",
+            Message::AllFramesFailed => "Watermark extraction failed on every frame",
+            Message::FramesProcessedSummary => "🎬 Processed {} frames, watermark extracted from {}",
+            Message::ImageUnreadable => "Cannot read the image: {}",
+            Message::ImageUndecodable => "Cannot decode the image: {}",
+            Message::ReassembleFailed => "Failed to reassemble the video",
+            Message::ResumeFrameMismatch => "The resume directory {} holds {} frames but its manifest records {}; delete the directory and start over",
+            Message::ResumeParamsMismatch => "The progress in the resume directory {} was made with a different input, watermark, strength or algorithm settings; delete the directory or drop --resume to start over",
+            Message::HeadEncodeFailed => "Failed to encode the leading segment",
+            Message::TailCopyFailed => "Failed to copy the remaining segment",
+            Message::SegmentJoinFailed => "Failed to join the video segments",
+            Message::MuxFailed => "Failed to mux audio and video",
+            Message::NoAudioTrackForEmbed => "The video has no audio track, cannot embed an audio watermark",
+            Message::FrameSizeMismatch => "The video frame data does not match the frame size",
+            Message::NoFramesDecoded => "No frames could be decoded from the video",
+            Message::VideoDecodeFailed => "Video decoding failed",
+            Message::VideoEncodeFailed => "Video encoding failed",
+            Message::FramesProcessed => "{} frames processed",
+            Message::EncoderExited => "The video encoder exited unexpectedly",
+            Message::EncoderStdinUnavailable => "Cannot open the encoder's standard input",
+            Message::AudioExtractFailed => "Audio extraction failed: FFmpeg exited with code {}",
+            Message::VideoStreamExtractFailed => "Failed to extract the video stream",
+            Message::NoAudioTrackForExtract => "The video has no audio track, cannot extract an audio watermark",
+            Message::WritingContainerTag => "Writing the container metadata watermark",
+            Message::ContainerTagFailed => "Failed to write the container metadata",
+            Message::ChoseAudioResult => "Chose the audio watermark (confidence: {}%)",
+            Message::ChoseVideoResult => "Chose the video watermark (confidence: {}%)",
+            Message::VideoResultOnly => "Using the video watermark only",
+            Message::BothBelowThreshold => "❌ Both the audio and video watermarks are below the confidence threshold",
+            Message::BestBelowThreshold => "The best confidence {}% is below the threshold {}%",
+            Message::ContainerTagFound => "Watermark found in the container metadata",
+            Message::ContainerTagMissing => "No watermark in the container metadata",
+            Message::AudioResultConfidence => "Audio watermark confidence: {}%",
+            Message::AudioResultFailed => "Audio watermark extraction failed: {}",
+            Message::VideoResultFailed => "Video frame watermark extraction failed: {}",
+            Message::VideoResultConfidence => "Video frame watermark confidence: {}% ({} frames)",
+            Message::AllCopiesFailed => "No watermark could be extracted from the container metadata, the audio or the video frames",
+            Message::CopiesVoted => "Voted across {} copies, confidence: {}%",
+
+            Message::StageTempDir => "🗂️  Creating a temporary directory",
+            Message::StageExtractFrames => "🎬  Extracting video frames",
+            Message::StageProcessFrames => "🎯  Processing video frames",
+            Message::StageCopyAudio => "🎵  Reusing the original audio track",
+            Message::StageVideoEmbedDone => "🎉 Video watermark embedded!",
+            Message::StageLocateKeyframe => "🔍  Locating the keyframe",
+            Message::StageKeyframeDelta => "🎯  Computing the watermark delta on the first frame",
+            Message::StageEncodeHead => "🎞️  Encoding the leading segment",
+            Message::StageJoin => "🔗  Joining the video",
+            Message::StageExtractAudio => "🎵  Extracting the audio track",
+            Message::StageProcessAudio => "🎯  Processing the audio watermark",
+            Message::StageExtractVideoStream => "🎬  Extracting the video stream",
+            Message::StageMux => "🎞️  Muxing audio and video",
+            Message::StageAudioEmbedDone => "🎉 Audio watermark embedded!",
+            Message::StageSinglePass => "🎯  Decoding once and watermarking video frames",
+            Message::StageAudioWatermark => "🎵  Processing the audio watermark",
+            Message::StageBothEmbedDone => "🎉 Audio and video watermarks embedded!",
+            Message::StageAnalyze => "📊  Analyzing the video",
+            Message::StageExtractAll => "🎬  Extracting all frames",
+            Message::StageExtractSamples => "🎬  Extracting {} sample frames",
+            Message::StageVote => "🗳️  Voting across frames",
+            Message::StageVideoExtractDone => "🎉 Video watermark extracted! Confidence: {}%",
+            Message::StageExtractAudioWatermark => "🎯  Extracting the audio watermark",
+            Message::StageAudioExtractDone => "🎉 Audio watermark extracted!",
+            Message::StageExtractAudioMark => "🎵  Extracting the audio watermark",
+            Message::StageFuse => "🔀  Fusing the audio and video results",
+            Message::StageBothExtractDone => "🎉 Audio and video watermark extracted! Confidence: {}%",
+
+            Message::AttackSpecInvalid => "invalid attack: {}, expected name:value, e.g. jpeg:50",
+            Message::AttackValueInvalid => "invalid attack value: {}",
+            Message::AttackJpegQuality => "JPEG quality must be an integer between 1 and 100: {}",
+            Message::AttackNoiseNegative => "noise standard deviation must not be negative: {}",
+            Message::AttackResizeScale => "resize scale must be greater than 0: {}",
+            Message::AttackCropFraction => "crop fraction must be between 0 and 1: {}",
+            Message::AttackUnknown => "unknown attack: {}, choose from jpeg, noise, resize, rotate, crop",
+            Message::ResizeScaleInvalid => "resize scale must be greater than 0, got {}",
+            Message::ResizeTargetInvalid => "resized dimensions must be greater than 0, got {}x{}",
+            Message::CropFractionInvalid => "crop fraction must be between 0 and 1, got {}",
+            Message::BerLengthInvalid => "bit count must be between 1 and {}, got {}",
+
+            Message::RandomUnavailable => "failed to generate random bytes",
+            Message::EncryptionFailed => "failed to encrypt the watermark",
+            Message::KeyInvalid => "invalid key",
+            Message::DifferentialWithSpread => "differential mode cannot be combined with spread-spectrum mode",
+            Message::DifferentialNeedsTwoPositions => "differential mode needs at least two coefficient positions",
+            Message::QimStepInvalid => "QIM step must be positive, got {}",
+            Message::QimWithOtherModes => "QIM embedding cannot be combined with spread-spectrum or differential mode",
+            Message::PositionOutOfBlock => "coefficient position ({},{}) lies outside the {}x{} DCT block",
+            Message::ConfidenceMapModeUnsupported => "tile, spread-spectrum and global modes do not support block confidence maps",
+            Message::StripModeUnsupported => "tile, spread-spectrum and global modes do not support strip processing",
+            Message::StripPlanBitsMismatch => "the strip plan carries {} bits but the watermark has {} bits",
+            Message::ExpectedExceedsBlocks => "expected length {} exceeds the {} extractable blocks",
+            Message::SpreadChipsZero => "the spread-spectrum chip count must be greater than 0",
+            Message::ExpectedExceedsSpread => "expected length {} exceeds the spread-spectrum capacity of {}",
+            Message::TileSizeInvalid => "tile size {} must be a positive multiple of the DCT block size {}",
+            Message::TilesWithSpread => "tile mode cannot be combined with spread-spectrum mode",
+            Message::ImageSmallerThanTile => "image size {}x{} is smaller than one {}x{} tile",
+            Message::ExpectedExceedsTiles => "expected length {} exceeds the tile-mode capacity of {}",
+            Message::NoValidTile => "no complete tile passed the CRC check; the image may be cropped too small or damaged",
+            Message::GlobalWithOtherModes => "global mode cannot be combined with custom coefficient positions, tiles, spread-spectrum, differential or QIM modes",
+            Message::ExpectedExceedsGlobal => "expected length {} exceeds the global-mode capacity of {}",
+            Message::ExpectedExceedsDftRing => "the expected watermark length exceeds the DFT ring capacity: at most {} bits, {} bits needed",
+            Message::EccPayloadTooShort => "the error-corrected payload has only {} bytes, restoring {} bytes needs {} bytes",
+            Message::HeaderFieldTooLarge => "the self-describing header cannot record a {} larger than 255",
+            Message::HeaderPayloadTooLarge => "the self-describing header records at most {} payload bytes, got {} bytes",
+            Message::HeaderSizeTooLarge => "the self-describing header cannot record a normalized size larger than {} pixels",
+            Message::HeaderBlockSize => "block size",
+            Message::HeaderInterleave => "interleave depth",
+            Message::BitPlanesInvalid => "the number of bit planes must be between 1 and {}, got {}",
+            Message::ExpectedExceedsBits => "expected length {} exceeds the {} extractable bits",
+            Message::SampleReshapeFailed => "failed to reshape samples: {}",
+            Message::RepetitionTooFew => "the repetition count must be at least 1: a capacity of {} bits cannot hold a {}-bit payload",
+            Message::SplitCapacityShort => "the {} images do not have enough total capacity, {} bytes short",
+            Message::SplitTooManyParts => "the payload would need {} parts, at most {} are supported",
+            Message::SplitCountMismatch => "the parts declare different totals; parts from another payload may be mixed in",
+            Message::SplitPartsMissing => "incomplete parts: {} in total, missing part {}",
+            Message::SamplesUnsupported => "the {} algorithm does not support audio samples directly",
+            Message::ConfidenceMapUnsupported => "the {} algorithm does not support block confidence maps",
+            Message::StripUnsupported => "the {} algorithm does not support strip processing",
+            Message::StrengthOutOfRange => "watermark strength must be between 0.0 and 1.0, got {}",
+            Message::BitsNotByteAligned => "error: length {} is not a multiple of 8",
+            Message::BitsAnalysis => "Bit analysis:",
+            Message::BitsTotal => "total length: {} bits",
+            Message::BitsBytesComplete => "bytes: {} (complete)",
+            Message::BitsBytesIncomplete => "bytes: {} (incomplete)",
+            Message::BitsOnes => "ones: {} ({}%)",
+            Message::BitsZeros => "zeros: {} ({}%)",
+            Message::BitsByteValues => "byte values",
+            Message::BitsUtf8 => "UTF-8 decoded: '{}'",
+            Message::BitsUtf8Lossy => "UTF-8 decoding failed, lossy: '{}'",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn english_text_has_no_chinese_and_the_same_placeholders() {
+        for message in [
+            Message::InvalidArgument,
+            Message::ReembedNeedsForce,
+            Message::ResumeFrameMismatch,
+            Message::FfmpegTimeout,
+            Message::SceneSampling,
+            Message::StageVideoExtractDone,
+            Message::AttackSpecInvalid,
+            Message::PositionOutOfBlock,
+            Message::StrengthOutOfRange,
+        ] {
+            let (zh, en) = (message.zh(), message.en());
+            assert!(
+                !en.chars().any(|c| ('\u{4e00}'..='\u{9fff}').contains(&c)),
+                "{message:?}: {en}"
+            );
+            assert_eq!(
+                zh.matches("{}").count(),
+                en.matches("{}").count(),
+                "{message:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn format_fills_placeholders_in_order() {
+        set_lang(Lang::Zh);
+        assert_eq!(
+            Message::PositionOutOfBlock.format(&[&1, &2, &8, &8]),
+            "系数位置(1,2)超出了8x8的DCT块"
+        );
+    }
+}
@@ -1,16 +1,30 @@
 pub mod cli;
 pub mod error;
+/// 面向用户的消息目录，按 `--lang` 或 `LANG` 环境变量选择中文或英文
+pub mod i18n;
 pub mod media;
+/// 攻击模拟：对嵌入后的单通道数据（像素归一化到 [0.0, 1.0]）施加常见处理，
+/// 配合 `measure_ber` 评估水印的存活情况；`Recovery` 为提取前抵消这些处理的预处理
+pub mod robustness;
 pub mod watermark;
 
 /// 便于使用的预导入模块
 pub mod prelude {
-    pub use crate::cli::{Algorithm, Cli, Commands, VideoWatermarkMode};
+    pub use crate::cli::{
+        Algorithm, Cli, Commands, DctMode, EccScheme, FrameSampling, FrequencyBand, ImageChannel,
+        ImageRegion, Lang, OutputFormat, VideoCodec, VideoWatermarkMode, VoteStrategy,
+    };
     pub use crate::error::{Result, WatermarkError};
     pub use crate::media::{
-        AudioWatermarker, ImageWatermarker, MediaType, MediaUtils, VideoWatermarker,
+        AudioEmbedOptions, AudioWatermarker, ChannelVote, EmbedOptions, ExtractionResult,
+        FrameIndexRange, ImageEmbedOptions, ImageWatermarker, Integrity, MediaType, MediaUtils,
+        Progress, ProgressEvent, RobustExtraction, VideoEncodeOptions, VideoExtractOptions,
+        VideoProperties, VideoWatermarker,
     };
+    pub use crate::robustness::{measure_ber, Attack, Recovery};
     pub use crate::watermark::{
-        DctWatermark, WatermarkAlgorithm, WatermarkFactory, WatermarkUtils,
+        AlgorithmOptions, DctWatermark, DftWatermark, EchoWatermark, LsbWatermark,
+        PsychoacousticWatermark, Repetition, RepetitionWatermark, WatermarkAlgorithm,
+        WatermarkFactory, WatermarkHeader, WatermarkUtils,
     };
 }
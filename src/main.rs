@@ -1,46 +1,886 @@
-use clap::Parser;
+use base64::prelude::*;
+use clap::{Parser, ValueEnum};
 use colored::*;
+use indicatif::{ProgressBar, ProgressStyle};
+use seal::i18n::{self, Message};
+use seal::media::ffmpeg;
+use seal::media::video::DEFAULT_CONFIDENCE_THRESHOLD;
 use seal::prelude::*;
+use seal::robustness;
+use seal::watermark::ecc::EccOptions;
+use seal::watermark::{crypto, split};
 use serde_json::json;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
 use std::process;
+use std::time::{Duration, Instant};
 
 fn main() -> Result<()> {
+    // 先按 LANG 环境变量设置语言，命令行参数解析失败时的提示也能使用对应语言
+    i18n::set_lang(i18n::resolve_lang(None));
+    let cli = Cli::parse();
+    i18n::set_lang(i18n::resolve_lang(cli.lang));
+
     // 确保 FFmpeg 可用
     if let Err(e) = ffmpeg_sidecar::download::auto_download() {
         eprintln!(
             "{} {}",
-            "警告:".yellow().bold(),
-            format!("无法下载 FFmpeg: {e}").red()
+            Message::WarningLabel.text().yellow().bold(),
+            Message::FfmpegDownloadFailed.format(&[&e]).red()
         );
-        eprintln!("{}", "请确保系统中已安装 FFmpeg，或者检查网络连接".yellow());
+        eprintln!("{}", Message::FfmpegInstallHint.text().yellow());
     }
 
-    let cli = Cli::parse();
+    ffmpeg::set_timeout((cli.timeout > 0).then(|| Duration::from_secs(cli.timeout)));
+    ffmpeg::set_retries(cli.ffmpeg_retries);
+    #[cfg(unix)]
+    install_interrupt_handler();
 
     // 记录本次动作类型，便于错误时输出JSON
     let action_for_error = match &cli.command {
         Commands::Embed { .. } => "embed",
         Commands::Extract { .. } => "extract",
+        Commands::Detect { .. } => "detect",
+        Commands::Info { .. } => "info",
+        Commands::Verify { .. } => "verify",
+        Commands::Diff { .. } => "diff",
+        Commands::ListAlgorithms => "list-algorithms",
+        Commands::Bench { .. } => "bench",
     };
 
+    let format = cli.format;
     if let Err(e) = run(cli) {
         // 错误信息：stderr 打印人类可读，stdout 打印单行 JSON 便于机器解析
         let err_msg = e.to_string();
-        eprintln!("{} {}", "错误:".red().bold(), err_msg.red());
-        println!(
-            "{}",
-            json!({
-                "status": "error",
-                "action": action_for_error,
-                "message": err_msg,
-            })
+        eprintln!(
+            "{} {}",
+            Message::ErrorLabel.text().red().bold(),
+            err_msg.red()
         );
-        process::exit(1);
+        let mut json_output = json!({
+            "status": "error",
+            "action": action_for_error,
+            "message": err_msg,
+        });
+        mark_signature_error(&mut json_output, &e);
+        // 文本格式下错误只在 stderr 给出，stdout 保持为空；批处理已经输出了逐项结果的数组
+        if format != OutputFormat::Text && !matches!(e, WatermarkError::BatchFailed { .. }) {
+            print_result(format, &json_output);
+        }
+        // 与被 Ctrl-C 直接终止时的退出码一致
+        process::exit(if ffmpeg::is_cancelled() { 130 } else { 1 });
     }
     Ok(())
 }
 
+/// Ctrl-C 时若有FFmpeg进程在运行则请求中止：进程被终止、临时文件清理后以错误退出；
+/// 否则与默认行为相同立即退出。处理一次后恢复默认，再按一次 Ctrl-C 即强制退出。
+#[cfg(unix)]
+fn install_interrupt_handler() {
+    extern "C" fn on_interrupt(_signal: libc::c_int) {
+        if ffmpeg::is_running() {
+            ffmpeg::cancel();
+        } else {
+            // SAFETY: _exit 是异步信号安全的
+            unsafe { libc::_exit(130) };
+        }
+    }
+
+    // SAFETY: sigaction 结构按零值初始化后只设置处理函数和标志，处理函数只访问原子变量
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = on_interrupt as *const () as libc::sighandler_t;
+        action.sa_flags = libc::SA_RESETHAND;
+        libc::sigemptyset(&mut action.sa_mask);
+        libc::sigaction(libc::SIGINT, &action, std::ptr::null_mut());
+    }
+}
+
 fn run(cli: Cli) -> Result<()> {
+    if cli.dry_run && !matches!(&cli.command, Commands::Embed { split: false, .. }) {
+        return Err(WatermarkError::InvalidArgument(
+            Message::DryRunEmbedOnly.text().to_string(),
+        ));
+    }
+    let input = match &cli.command {
+        Commands::Embed { input, .. }
+        | Commands::Extract { input, .. }
+        | Commands::Detect { input, .. }
+        | Commands::Info { input, .. }
+        | Commands::Verify { input, .. } => input,
+        // diff 总是比较一对文件，list-algorithms 没有输入文件，bench 只测单个文件，都不做批处理
+        Commands::Diff { .. } | Commands::ListAlgorithms | Commands::Bench { .. } => {
+            print_result(cli.format, &execute(&cli)?);
+            return Ok(());
+        }
+    };
+    let split = matches!(
+        &cli.command,
+        Commands::Embed { split: true, .. } | Commands::Extract { split: true, .. }
+    );
+    match MediaUtils::batch_inputs(input)? {
+        // 分片模式把所有文件当作一个整体处理，只输出一个结果
+        Some(inputs) if split => {
+            let files = inputs
+                .iter()
+                .map(|file| batch_file_cli(&cli, file))
+                .collect::<Result<Vec<_>>>()?;
+            print_result(cli.format, &execute_split(&cli, &files)?);
+            Ok(())
+        }
+        None if split => {
+            print_result(
+                cli.format,
+                &execute_split(&cli, std::slice::from_ref(&cli))?,
+            );
+            Ok(())
+        }
+        Some(inputs) => run_batch(&cli, &inputs),
+        None => {
+            print_result(cli.format, &execute(&cli)?);
+            Ok(())
+        }
+    }
+}
+
+/// `--format csv` 的列，顺序固定，结果中没有的字段留空
+const CSV_COLUMNS: [&str; 10] = [
+    "status",
+    "action",
+    "media_type",
+    "input",
+    "output",
+    "algorithm",
+    "watermark",
+    "confidence",
+    "integrity",
+    "message",
+];
+
+/// 按 `--format` 把结果写到 stdout
+fn print_result(format: OutputFormat, result: &serde_json::Value) {
+    for line in render_result(format, result, csv_header_needed()) {
+        println!("{line}");
+    }
+}
+
+/// 结果按 `--format` 输出的各行，批处理的结果数组在文本和CSV格式下逐项输出；
+/// `csv_header` 为假时CSV格式不输出表头
+fn render_result(
+    format: OutputFormat,
+    result: &serde_json::Value,
+    csv_header: bool,
+) -> Vec<String> {
+    let items = match result {
+        serde_json::Value::Array(items) => items.as_slice(),
+        single => std::slice::from_ref(single),
+    };
+    match format {
+        OutputFormat::Json => vec![result.to_string()],
+        OutputFormat::Text => {
+            let batch = result.is_array();
+            items
+                .iter()
+                .flat_map(|item| {
+                    // 批处理时每行前加上文件名，便于区分
+                    let prefix = match item["input"].as_str() {
+                        Some(input) if batch => format!("{input}: "),
+                        _ => String::new(),
+                    };
+                    text_lines(item)
+                        .into_iter()
+                        .map(move |line| format!("{prefix}{line}"))
+                })
+                .collect()
+        }
+        OutputFormat::Csv => {
+            let header = csv_header.then(|| CSV_COLUMNS.join(","));
+            let rows = items.iter().map(|item| {
+                CSV_COLUMNS
+                    .iter()
+                    .map(|column| csv_field(&item[*column]))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            });
+            header.into_iter().chain(rows).collect()
+        }
+    }
+}
+
+/// 单个结果的文本形式：成功的提取只给出水印（多个水印时每行一个），
+/// 失败给出错误信息，其他结果逐行给出 `字段: 值`
+fn text_lines(item: &serde_json::Value) -> Vec<String> {
+    if item["status"] == "error" {
+        return vec![format!(
+            "{} {}",
+            Message::ErrorLabel.text(),
+            text_value(&item["message"])
+        )];
+    }
+    if item["action"] == "extract" {
+        return match item["watermarks"].as_array() {
+            Some(watermarks) => watermarks
+                .iter()
+                .map(|entry| text_value(&entry["watermark"]))
+                .collect(),
+            None => vec![text_value(&item["watermark"])],
+        };
+    }
+    item.as_object()
+        .into_iter()
+        .flatten()
+        .filter(|(key, _)| !matches!(key.as_str(), "status" | "action"))
+        .map(|(key, value)| format!("{key}: {}", text_value(value)))
+        .collect()
+}
+
+/// 字符串按原样输出，其他值输出为紧凑的JSON，缺失的字段为空
+fn text_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// 按CSV规则转义一个字段：含逗号、引号或换行时加引号，引号写作两个
+fn csv_field(value: &serde_json::Value) -> String {
+    let text = text_value(value);
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+/// stdout 是已有内容的普通文件时不再输出表头，`>> results.csv` 多次追加时表头只出现一次
+fn csv_header_needed() -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::fd::AsFd;
+        let metadata = std::io::stdout()
+            .as_fd()
+            .try_clone_to_owned()
+            .and_then(|fd| std::fs::File::from(fd).metadata());
+        if let Ok(metadata) = metadata {
+            return !(metadata.is_file() && metadata.len() > 0);
+        }
+    }
+    true
+}
+
+/// 执行 `--split` 命令，`files` 为改写成单文件的各个命令，按文件名顺序排列
+fn execute_split(cli: &Cli, files: &[Cli]) -> Result<serde_json::Value> {
+    match &cli.command {
+        Commands::Embed { .. } => embed_split(cli, files),
+        Commands::Extract { .. } => extract_split(cli, files),
+        _ => Err(WatermarkError::InvalidArgument(
+            Message::SplitEmbedExtractOnly.text().to_string(),
+        )),
+    }
+}
+
+/// 分片模式下的单个文件必须存在且是图片
+fn split_file_input(file_cli: &Cli) -> Result<&Path> {
+    let input = match &file_cli.command {
+        Commands::Embed { input, .. } | Commands::Extract { input, .. } => input,
+        _ => unreachable!("分片模式只处理 embed 和 extract"),
+    };
+    if !MediaUtils::file_exists(input) {
+        return Err(WatermarkError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            Message::InputNotFound.format(&[&format!("{input:?}")]),
+        )));
+    }
+    if !matches!(MediaUtils::detect_media_type(input)?, MediaType::Image) {
+        return Err(WatermarkError::InvalidArgument(
+            Message::SplitImagesOnly.format(&[&format!("{input:?}")]),
+        ));
+    }
+    Ok(input)
+}
+
+/// `--split` 嵌入：把载荷拆成带分片头部的分片，按文件名顺序依次写入各图片
+///
+/// 只使用放完载荷所需的前若干张图片；所有容量在写出任何文件前检查，
+/// 单张图片容量不足时报告同尺寸下需要的图片数。
+fn embed_split(cli: &Cli, files: &[Cli]) -> Result<serde_json::Value> {
+    let Commands::Embed {
+        output,
+        watermark,
+        algorithm,
+        strength,
+        seed,
+        block_size,
+        positions,
+        band,
+        bit_planes,
+        spread_chips,
+        differential,
+        qim_step,
+        dct_mode,
+        perceptual_threshold,
+        channel,
+        strip_metadata,
+        jpeg_quality,
+        min_psnr,
+        password,
+        sign,
+        ecc,
+        interleave,
+        binary,
+        report,
+        ..
+    } = &cli.command
+    else {
+        unreachable!("embed_split 只处理 embed 命令");
+    };
+
+    let watermark_algorithm = WatermarkFactory::create_algorithm_with_options(
+        algorithm.clone(),
+        &AlgorithmOptions {
+            seed: *seed,
+            block_size: *block_size,
+            positions: positions.as_ref().map(|p| p.0.clone()),
+            band: *band,
+            bit_planes: *bit_planes,
+            spread_chips: *spread_chips,
+            differential: *differential,
+            qim_step: *qim_step,
+            dct_mode: *dct_mode,
+            perceptual_threshold: perceptual_threshold.map(pixel_variance),
+            ..Default::default()
+        },
+    );
+
+    let mut targets = Vec::with_capacity(files.len());
+    for file_cli in files {
+        let input = split_file_input(file_cli)?;
+        let Commands::Embed { output, .. } = &file_cli.command else {
+            unreachable!("embed_split 只处理 embed 命令");
+        };
+        let capacity = ImageWatermarker::payload_capacity(input, watermark_algorithm.as_ref())? / 8;
+        targets.push((input, output, capacity));
+    }
+
+    let [watermark] = watermark.as_slice() else {
+        return Err(WatermarkError::InvalidArgument(
+            Message::SplitSingleWatermark.text().to_string(),
+        ));
+    };
+    let ecc = ecc_options(*ecc, *interleave);
+    let payload = prepare_payload(
+        watermark,
+        *binary,
+        password.as_deref(),
+        sign.as_deref(),
+        ecc,
+    )?;
+    let capacities: Vec<usize> = targets.iter().map(|&(_, _, capacity)| capacity).collect();
+    let parts = split::split_payload(&payload, &capacities).map_err(|e| {
+        match (
+            capacities.as_slice(),
+            split::parts_needed(payload.len(), capacities[0]),
+        ) {
+            ([capacity], Some(needed)) => {
+                WatermarkError::InvalidArgument(Message::SplitNeedsMoreImages.format(&[
+                    &payload.len(),
+                    &capacity,
+                    &needed,
+                ]))
+            }
+            _ => e,
+        }
+    })?;
+
+    for &(target, _) in &parts {
+        MediaUtils::check_output(targets[target].1, cli.overwrite)?;
+    }
+    if let Some(path) = report_path(report.as_ref(), output, "embed") {
+        MediaUtils::check_output(path, cli.overwrite)?;
+    }
+
+    let options = ImageEmbedOptions {
+        min_psnr: *min_psnr,
+        strip_metadata: *strip_metadata,
+        jpeg_quality: *jpeg_quality,
+        channel: *channel,
+        strip_rows: None,
+        header: None,
+        region: None,
+        normalize_size: None,
+    };
+    let mut results = Vec::with_capacity(parts.len());
+    for (index, (target, part)) in parts.iter().enumerate() {
+        let (input, part_output, _) = targets[*target];
+        ImageWatermarker::embed_bytes_with_options(
+            input,
+            part_output,
+            part,
+            watermark_algorithm.as_ref(),
+            *strength,
+            &options,
+        )?;
+        eprintln!(
+            "{} {}",
+            "🧩".green(),
+            Message::SplitPartWritten
+                .format(&[&(index + 1), &parts.len(), &format!("{part_output:?}")])
+                .green()
+        );
+        results.push(json!({
+            "input": input.display().to_string(),
+            "output": part_output.display().to_string(),
+            "part": index + 1,
+            "bytes": part.len() - split::PART_HEADER_LEN,
+        }));
+    }
+
+    let unused: Vec<String> = targets
+        .iter()
+        .enumerate()
+        .filter(|(target, _)| !parts.iter().any(|(used, _)| used == target))
+        .map(|(_, (input, _, _))| input.display().to_string())
+        .collect();
+
+    let json_output = json!({
+        "status": "success",
+        "action": "embed",
+        "algorithm": format!("{:?}", algorithm),
+        "media_type": format!("{:?}", MediaType::Image),
+        "strength": strength,
+        "split": true,
+        "payload_bytes": payload.len(),
+        "parts": parts.len(),
+        "files": results,
+        "unused": unused,
+    });
+    write_report(
+        report.as_ref(),
+        output,
+        "embed",
+        &json_output,
+        cli.overwrite,
+    )?;
+    Ok(json_output)
+}
+
+/// `--split` 提取：从每张图片读取分片头部和分片数据，按序号拼接后再解密和校验长度
+///
+/// 没有分片头部的图片被跳过并记入 `skipped`，缺少分片时报错并列出缺少的序号。
+fn extract_split(cli: &Cli, files: &[Cli]) -> Result<serde_json::Value> {
+    let Commands::Extract {
+        input,
+        algorithm,
+        length,
+        output,
+        seed,
+        block_size,
+        positions,
+        band,
+        bit_planes,
+        spread_chips,
+        differential,
+        qim_step,
+        dct_mode,
+        channel,
+        password,
+        sign,
+        ecc,
+        interleave,
+        binary,
+        confidence_threshold,
+        report,
+        ..
+    } = &cli.command
+    else {
+        unreachable!("extract_split 只处理 extract 命令");
+    };
+
+    let base = match output {
+        Some(output_path) => output_path.clone(),
+        None if input.is_file() => input.clone(),
+        None => MediaUtils::batch_root(input),
+    };
+    for path in output
+        .iter()
+        .cloned()
+        .chain(report_path(report.as_ref(), &base, "extract"))
+    {
+        MediaUtils::check_output(path, cli.overwrite)?;
+    }
+
+    let watermark_algorithm = WatermarkFactory::create_algorithm_with_options(
+        algorithm.clone(),
+        &AlgorithmOptions {
+            seed: *seed,
+            block_size: *block_size,
+            positions: positions.as_ref().map(|p| p.0.clone()),
+            band: *band,
+            bit_planes: *bit_planes,
+            spread_chips: *spread_chips,
+            differential: *differential,
+            qim_step: *qim_step,
+            dct_mode: *dct_mode,
+            ..Default::default()
+        },
+    );
+
+    let mut parts = Vec::new();
+    let mut results = Vec::new();
+    let mut skipped = Vec::new();
+    let mut confidence_sum = 0.0;
+    for file_cli in files {
+        let file = split_file_input(file_cli)?;
+        let header = ImageWatermarker::extract_bytes_from_channel(
+            file,
+            watermark_algorithm.as_ref(),
+            split::PART_HEADER_LEN,
+            *channel,
+        )
+        .ok()
+        .and_then(|(bytes, _)| split::PartHeader::parse(&bytes));
+        let Some(header) = header else {
+            eprintln!(
+                "{} {}",
+                "⚠️".yellow(),
+                Message::SplitHeaderMissing
+                    .format(&[&format!("{file:?}")])
+                    .yellow()
+            );
+            skipped.push(file.display().to_string());
+            continue;
+        };
+
+        let (bytes, confidence) = ImageWatermarker::extract_bytes_from_channel(
+            file,
+            watermark_algorithm.as_ref(),
+            split::PART_HEADER_LEN + header.chunk_len,
+            *channel,
+        )?;
+        confidence_sum += confidence;
+        results.push(json!({
+            "input": file.display().to_string(),
+            "part": header.index + 1,
+            "bytes": header.chunk_len,
+            "confidence": confidence,
+        }));
+        parts.push((header, bytes[split::PART_HEADER_LEN..].to_vec()));
+    }
+
+    let part_count = parts.len();
+    let raw = split::join_parts(parts)?;
+    let &[length] = length.as_slice() else {
+        return Err(WatermarkError::InvalidArgument(
+            Message::SplitSingleExtract.text().to_string(),
+        ));
+    };
+    let ecc = ecc_options(*ecc, *interleave);
+    let expected = encoded_payload_length(length, password.is_some(), sign.is_some(), ecc);
+    if raw.len() != expected {
+        return Err(WatermarkError::InvalidArgument(
+            Message::SplitLengthMismatch.format(&[&raw.len(), &expected]),
+        ));
+    }
+    let (extracted, bits_corrected) =
+        open_payload(raw, password.as_deref(), sign.as_deref(), ecc, *binary)?;
+    let views = payload_views(&extracted, *binary)?;
+
+    let mut saved_to: Option<String> = None;
+    if let Some(output_path) = output {
+        std::fs::write(output_path, saved_contents(&extracted, *binary)?)?;
+        saved_to = Some(output_path.display().to_string());
+        eprintln!(
+            "{} {}",
+            "💾".green(),
+            Message::WatermarkSaved
+                .format(&[&format!("{output_path:?}")])
+                .green()
+        );
+    }
+
+    let confidence = confidence_sum / part_count as f64;
+    let integrity = if sign.is_some() {
+        Integrity::Verified
+    } else {
+        Integrity::Unchecked
+    };
+    let mut json_output = json!({
+        "status": "success",
+        "action": "extract",
+        "input": input.display().to_string(),
+        "algorithm": format!("{:?}", algorithm),
+        "media_type": format!("{:?}", MediaType::Image),
+        "length": extracted.len(),
+        "confidence": confidence,
+        "integrity": format!("{:?}", integrity),
+        "output": saved_to,
+        "split": true,
+        "parts": part_count,
+        "files": results,
+        "skipped": skipped,
+    });
+    merge_json(&mut json_output, views);
+    mark_low_confidence(&mut json_output, confidence, *confidence_threshold, true);
+    if sign.is_some() {
+        json_output["authentic"] = json!(true);
+    }
+    if ecc.is_some() {
+        json_output["bits_corrected"] = json!(bits_corrected);
+    }
+
+    write_report(
+        report.as_ref(),
+        &base,
+        "extract",
+        &json_output,
+        cli.overwrite,
+    )?;
+    Ok(json_output)
+}
+
+/// 对目录或通配符匹配到的每个文件分别执行命令，stdout 打印每个文件一项的 JSON 数组
+///
+/// 单个文件失败不会中止批处理，而是在数组中记录为错误项；只要有文件失败，
+/// 输出数组之后返回 `BatchFailed`，退出码即为1。
+fn run_batch(cli: &Cli, inputs: &[PathBuf]) -> Result<()> {
+    let (action, report) = match &cli.command {
+        Commands::Embed { report, .. } => ("embed", report.clone()),
+        Commands::Extract { report, .. } => ("extract", report.clone()),
+        Commands::Detect { .. } => ("detect", None),
+        Commands::Info { .. } => ("info", None),
+        Commands::Verify { .. } => ("verify", None),
+        Commands::Diff { .. } | Commands::ListAlgorithms | Commands::Bench { .. } => {
+            unreachable!("diff、list-algorithms 和 bench 不做批处理")
+        }
+    };
+
+    // 批处理时报告文件记录整个数组，而不是每个文件各写一份；在处理任何文件之前确认可以写出
+    let base = match &cli.command {
+        Commands::Embed { output, .. } => output.clone(),
+        Commands::Extract {
+            output: Some(output),
+            ..
+        } => output.clone(),
+        _ => MediaUtils::batch_root(&inputs[0]),
+    };
+    if !cli.dry_run {
+        if let Some(path) = report_path(report.as_ref(), &base, action) {
+            MediaUtils::check_output(path, cli.overwrite)?;
+        }
+    }
+
+    let mut results = Vec::with_capacity(inputs.len());
+    let mut failures = 0;
+    for file in inputs {
+        // Ctrl-C 中止了FFmpeg之后不再处理剩余的文件
+        if ffmpeg::is_cancelled() {
+            break;
+        }
+        let file_cli = batch_file_cli(cli, file)?;
+        match execute(&file_cli) {
+            Ok(json_output) => results.push(json_output),
+            Err(e) => {
+                failures += 1;
+                eprintln!(
+                    "{} {:?}: {}",
+                    Message::ErrorLabel.text().red().bold(),
+                    file,
+                    e.to_string().red()
+                );
+                let mut json_output = json!({
+                    "status": "error",
+                    "action": action,
+                    "input": file.display().to_string(),
+                    "message": e.to_string(),
+                });
+                mark_signature_error(&mut json_output, &e);
+                results.push(json_output);
+            }
+        }
+    }
+
+    eprintln!(
+        "{} {}",
+        "📋".blue(),
+        Message::BatchSummary.format(&[&inputs.len(), &(inputs.len() - failures), &failures])
+    );
+
+    let summary = json!(results);
+    if !cli.dry_run {
+        write_report(report.as_ref(), &base, action, &summary, cli.overwrite)?;
+    }
+    print_result(cli.format, &summary);
+
+    if failures > 0 {
+        return Err(WatermarkError::BatchFailed {
+            failed: failures,
+            total: inputs.len(),
+        });
+    }
+    Ok(())
+}
+
+/// 把批处理命令改写为只处理 `file` 的单文件命令，输出路径放到输出目录下并保留原文件名
+fn batch_file_cli(cli: &Cli, file: &Path) -> Result<Cli> {
+    let file_name = file.file_name().ok_or_else(|| {
+        WatermarkError::InvalidArgument(Message::InvalidInputFile.format(&[&format!("{file:?}")]))
+    })?;
+
+    let mut file_cli = cli.clone();
+    match &mut file_cli.command {
+        Commands::Embed {
+            input,
+            output,
+            report,
+            metadata,
+            ..
+        } => {
+            *input = file.to_path_buf();
+            *output = output.join(file_name);
+            *report = None;
+            // 批处理时 --metadata 给出的路径视为目录，每个文件写入 `<文件名>.metadata.json`
+            if let Some(Some(dir)) = metadata {
+                let mut name = file_name.to_owned();
+                name.push(".metadata.json");
+                *dir = dir.join(name);
+            }
+        }
+        Commands::Extract {
+            input,
+            output,
+            binary,
+            report,
+            confidence_map,
+            ..
+        } => {
+            *input = file.to_path_buf();
+            // 批处理时 --confidence-map 视为目录，每个文件写入 `<文件名>.confidence.png`
+            *confidence_map = confidence_map.as_ref().map(|dir| {
+                let mut name = file_name.to_owned();
+                name.push(".confidence.png");
+                dir.join(name)
+            });
+            let extension = if *binary { "bin" } else { "txt" };
+            *output = output.as_ref().map(|dir| {
+                let mut name = file_name.to_owned();
+                name.push(format!(".{extension}"));
+                dir.join(name)
+            });
+            *report = None;
+        }
+        Commands::Detect { input, .. }
+        | Commands::Info { input, .. }
+        | Commands::Verify { input, .. } => *input = file.to_path_buf(),
+        Commands::Diff { .. } | Commands::ListAlgorithms | Commands::Bench { .. } => {
+            unreachable!("diff、list-algorithms 和 bench 不做批处理")
+        }
+    }
+    Ok(file_cli)
+}
+
+/// `--dry-run` 嵌入：检查容量并预估结果，不嵌入也不写出任何文件
+///
+/// 图片在内存中完成一次嵌入以计算PSNR，音频按样本数计算容量，
+/// 视频只探测流信息，按时长和帧率估算帧数，不抽取任何帧。
+fn embed_dry_run(
+    cli: &Cli,
+    media_type: &MediaType,
+    payload: &[u8],
+    algorithm: &(dyn WatermarkAlgorithm + Sync),
+) -> Result<serde_json::Value> {
+    let Commands::Embed {
+        input,
+        output,
+        algorithm: algorithm_kind,
+        strength,
+        video_mode,
+        channel,
+        min_psnr,
+        strip_rows,
+        target_psnr,
+        ..
+    } = &cli.command
+    else {
+        unreachable!("embed_dry_run 只处理 embed 命令");
+    };
+
+    let payload_bits = payload.len() * 8;
+    let mut json_output = json!({
+        "status": "success",
+        "action": "embed",
+        "dry_run": true,
+        "input": input.display().to_string(),
+        "output": output.display().to_string(),
+        "algorithm": format!("{:?}", algorithm_kind),
+        "media_type": format!("{:?}", media_type),
+        "strength": strength,
+        "payload_bits": payload_bits,
+    });
+
+    let capacity_bits = match media_type {
+        MediaType::Image => {
+            let capacity_bits = ImageWatermarker::payload_capacity(input, algorithm)?;
+            let psnr = match target_psnr {
+                Some(target) => {
+                    let options = ImageEmbedOptions {
+                        channel: *channel,
+                        strip_rows: *strip_rows,
+                        ..Default::default()
+                    };
+                    let (chosen, psnr) = ImageWatermarker::auto_strength(
+                        input, payload, algorithm, *target, &options,
+                    )?;
+                    json_output["strength"] = json!(chosen);
+                    psnr
+                }
+                None => {
+                    ImageWatermarker::estimate_psnr(input, payload, algorithm, *strength, *channel)?
+                }
+            };
+            if let Some(floor) = min_psnr {
+                if psnr < *floor {
+                    return Err(WatermarkError::QualityTooLow {
+                        measured: psnr,
+                        floor: *floor,
+                    });
+                }
+            }
+            insert_quality_db(&mut json_output, "estimated_psnr_db", psnr);
+            capacity_bits
+        }
+        MediaType::Audio => {
+            let (frames, sample_rate) = AudioWatermarker::probe_frames(input)?;
+            json_output["duration_secs"] = json!(frames as f64 / sample_rate as f64);
+            AudioWatermarker::payload_capacity(input, algorithm)?
+        }
+        MediaType::Video => {
+            let properties = VideoWatermarker::probe(input)?;
+            json_output["video_mode"] = json!(format!("{:?}", video_mode));
+            json_output["duration_secs"] = json!(properties.duration);
+            json_output["fps"] = json!(properties.fps);
+            json_output["estimated_frames"] = json!(properties
+                .duration
+                .map(|duration| (duration * properties.fps as f64).round() as u64));
+            // 与嵌入时一样按单帧计算容量，直接使用探测到的分辨率
+            algorithm.capacity(properties.height as usize, properties.width as usize)
+        }
+    };
+    WatermarkUtils::ensure_capacity(payload_bits, capacity_bits)?;
+    json_output["capacity_bits"] = json!(capacity_bits);
+
+    if cli.verbose {
+        eprintln!(
+            "{} {}",
+            Message::DryRun.text().blue().bold(),
+            Message::DryRunSummary.format(&[&payload_bits, &capacity_bits])
+        );
+    }
+    Ok(json_output)
+}
+
+/// 执行单个文件的命令，返回成功时的结果 JSON
+fn execute(cli: &Cli) -> Result<serde_json::Value> {
     match &cli.command {
         Commands::Embed {
             input,
@@ -50,115 +890,317 @@ fn run(cli: Cli) -> Result<()> {
             strength,
             lossless,
             video_mode,
+            ffmpeg_threads,
+            disk_frames,
+            resume,
+            frame_index,
+            embed_every,
+            codec,
+            crf,
+            seed,
+            block_size,
+            positions,
+            band,
+            bit_planes,
+            spread_chips,
+            differential,
+            qim_step,
+            dct_mode,
+            perceptual_threshold,
+            repeat,
+            tile,
+            psychoacoustic,
+            raw_embed,
+            sync,
+            channel,
+            strip_metadata,
+            jpeg_quality,
+            min_psnr,
+            min_snr,
+            audio_strength_scale,
+            password,
+            sign,
+            ecc,
+            interleave,
+            binary,
+            show_capacity,
+            report,
+            metadata,
+            strip_rows,
+            target_psnr,
+            header,
+            region,
+            normalize_size,
+            force,
+            split: _, // 分片模式已在 run 中单独处理
         } => {
             if !MediaUtils::file_exists(input) {
                 return Err(WatermarkError::Io(std::io::Error::new(
                     std::io::ErrorKind::NotFound,
-                    format!("输入文件不存在: {input:?}"),
+                    Message::InputNotFound.format(&[&format!("{input:?}")]),
                 )));
             }
 
-            MediaUtils::ensure_output_dir(output)?;
+            // 报告和元数据文件同样不能覆盖已有文件，在嵌入之前一并检查
+            if !cli.dry_run {
+                MediaUtils::check_output(output, cli.overwrite)?;
+                let sidecars = report_path(report.as_ref(), output, "embed")
+                    .into_iter()
+                    .chain(
+                        metadata
+                            .as_ref()
+                            .map(|path| metadata_path(path.as_deref(), output)),
+                    );
+                for path in sidecars {
+                    MediaUtils::check_output(path, cli.overwrite)?;
+                }
+            }
 
             // 检测媒体类型
             let media_type = MediaUtils::detect_media_type(input)?;
 
             // 创建水印算法
-            let watermark_algorithm = WatermarkFactory::create_algorithm(algorithm.clone());
+            let algorithm_options = AlgorithmOptions {
+                seed: *seed,
+                block_size: *block_size,
+                positions: positions.as_ref().map(|p| p.0.clone()),
+                band: *band,
+                bit_planes: *bit_planes,
+                spread_chips: *spread_chips,
+                differential: *differential,
+                qim_step: *qim_step,
+                dct_mode: *dct_mode,
+                repeat: *repeat,
+                tile_size: *tile,
+                psychoacoustic: *psychoacoustic,
+                perceptual_threshold: perceptual_threshold.map(pixel_variance),
+            };
+            let watermark_algorithm = WatermarkFactory::create_algorithm_with_options(
+                algorithm.clone(),
+                &algorithm_options,
+            );
+
+            reject_video_payload_options(
+                &media_type,
+                password.is_some(),
+                sign.is_some(),
+                ecc.is_some(),
+                *binary,
+            )?;
+            reject_non_image_repeat(&media_type, repeat.is_some())?;
+            reject_audio_block_size(&media_type, block_size.is_some())?;
+            reject_unsupported_tile(&media_type, algorithm, tile.is_some())?;
+            reject_unsupported_algorithm(&media_type, algorithm)?;
+            reject_non_audio_option(&media_type, "--psychoacoustic", *psychoacoustic)?;
+            reject_non_audio_option(&media_type, "--raw-embed", *raw_embed)?;
+            reject_non_audio_option(&media_type, "--sync", *sync)?;
+            reject_non_image_option(&media_type, "--strip-rows", strip_rows.is_some())?;
+            reject_non_image_option(&media_type, "--target-psnr", target_psnr.is_some())?;
+            reject_non_image_option(&media_type, "--header", *header)?;
+            reject_non_image_option(&media_type, "--region", region.is_some())?;
+            reject_non_image_option(&media_type, "--normalize-size", normalize_size.is_some())?;
+            reject_multiple_video_watermarks(&media_type, watermark.len())?;
+            if *resume
+                && !(matches!(media_type, MediaType::Video)
+                    && matches!(video_mode, VideoWatermarkMode::Video))
+            {
+                return Err(WatermarkError::InvalidArgument(
+                    Message::ResumeVideoModeOnly.text().to_string(),
+                ));
+            }
+            if *header && watermark.len() > 1 {
+                return Err(WatermarkError::InvalidArgument(
+                    Message::HeaderSingleWatermark.text().to_string(),
+                ));
+            }
+            // 带有自描述头部的图片已经嵌入过水印，叠加嵌入会不可预料地破坏原有的水印
+            if matches!(media_type, MediaType::Image)
+                && ImageWatermarker::has_header(input, *normalize_size)
+            {
+                eprintln!("{} {}", "⚠️".yellow(), Message::AlreadyWatermarked.text());
+                if !*force {
+                    return Err(WatermarkError::InvalidArgument(
+                        Message::ReembedNeedsForce.text().to_string(),
+                    ));
+                }
+            }
+
+            let ecc = ecc_options(*ecc, *interleave);
+            let (payload, watermark_bytes) = prepare_payloads(
+                watermark,
+                *binary,
+                password.as_deref(),
+                sign.as_deref(),
+                ecc,
+            )?;
+            // 多个水印时给出各自占用的载荷字节数（含签名和加密开销），按顺序排列在容量中
+            let watermarks_json = if watermark_bytes.len() > 1 {
+                json!({ "watermark_bytes": watermark_bytes })
+            } else {
+                serde_json::Value::Null
+            };
+
+            // 在嵌入之前给出容量，载荷过长而报错时也能看到上限
+            let capacity_json = if *show_capacity {
+                embed_capacity_json(
+                    &media_type,
+                    input,
+                    watermark_algorithm.as_ref(),
+                    password.is_some(),
+                    sign.is_some(),
+                    ecc,
+                )?
+            } else {
+                serde_json::Value::Null
+            };
+
+            if cli.dry_run {
+                if *header {
+                    return Err(WatermarkError::InvalidArgument(
+                        Message::HeaderWithDryRun.text().to_string(),
+                    ));
+                }
+                if region.is_some() || normalize_size.is_some() {
+                    return Err(WatermarkError::InvalidArgument(
+                        Message::RegionWithDryRun.text().to_string(),
+                    ));
+                }
+                if metadata.is_some() {
+                    return Err(WatermarkError::InvalidArgument(
+                        Message::MetadataWithDryRun.text().to_string(),
+                    ));
+                }
+                let mut json_output =
+                    embed_dry_run(cli, &media_type, &payload, watermark_algorithm.as_ref())?;
+                merge_json(&mut json_output, capacity_json);
+                merge_json(&mut json_output, watermarks_json);
+                return Ok(json_output);
+            }
 
             // 根据媒体类型选择处理方式
             let mut processed_frames_opt: Option<usize> = None;
+            let mut quality_db_opt: Option<(&str, f64)> = None;
+            let mut strength = *strength;
             match media_type {
                 MediaType::Image => {
                     if cli.verbose {
                         eprintln!(
                             "{} {}",
-                            "🖼️  处理图片文件:".blue().bold(),
+                            Message::ProcessingImage.text().blue().bold(),
                             format!("{input:?}").cyan()
                         );
+                    }
 
-                        // 检查水印容量
-                        if !ImageWatermarker::check_watermark_capacity(
+                    let options = ImageEmbedOptions {
+                        min_psnr: *min_psnr,
+                        strip_metadata: *strip_metadata,
+                        jpeg_quality: *jpeg_quality,
+                        channel: *channel,
+                        strip_rows: *strip_rows,
+                        header: header.then(|| WatermarkHeader {
+                            algorithm: algorithm.clone(),
+                            block_size: *block_size,
+                            ecc: ecc.map(|options| options.scheme),
+                            interleave: *interleave,
+                            channel: *channel,
+                            binary: *binary,
+                            encrypted: password.is_some(),
+                            signed: sign.is_some(),
+                            payload_len: payload.len(),
+                            normalize_size: *normalize_size,
+                        }),
+                        region: *region,
+                        normalize_size: *normalize_size,
+                    };
+                    if let Some(target) = target_psnr {
+                        let (chosen, psnr) = ImageWatermarker::auto_strength(
                             input,
-                            watermark,
+                            &payload,
                             watermark_algorithm.as_ref(),
-                        )? {
+                            *target,
+                            &options,
+                        )?;
+                        if cli.verbose {
                             eprintln!(
-                                "{} {}",
-                                "⚠️".yellow(),
-                                "警告: 水印可能太长，可能影响嵌入效果".yellow()
+                                "{} {:.4}（PSNR {:.2} dB）",
+                                Message::AutoStrength.text().blue().bold(),
+                                chosen,
+                                psnr
                             );
                         }
+                        strength = chosen;
                     }
-
-                    ImageWatermarker::embed_watermark(
+                    let psnr = ImageWatermarker::embed_bytes_with_options(
                         input,
                         output,
-                        watermark,
+                        &payload,
                         watermark_algorithm.as_ref(),
-                        *strength,
+                        strength,
+                        &options,
                     )?;
+                    if min_psnr.is_some() || target_psnr.is_some() {
+                        quality_db_opt = Some(("psnr_db", psnr));
+                    }
                 }
                 MediaType::Audio => {
                     if cli.verbose {
                         eprintln!(
                             "{} {}",
-                            "🎧  处理音频文件:".blue().bold(),
+                            Message::ProcessingAudio.text().blue().bold(),
                             format!("{input:?}").cyan()
                         );
-
-                        // 检查水印容量
-                        if !AudioWatermarker::check_watermark_capacity(
-                            input,
-                            watermark,
-                            watermark_algorithm.as_ref(),
-                        )? {
-                            eprintln!(
-                                "{} {}",
-                                "⚠️".yellow(),
-                                "警告: 水印可能太长，可能影响嵌入效果".yellow()
-                            );
-                        }
                     }
 
-                    AudioWatermarker::embed_watermark(
+                    let snr = AudioWatermarker::embed_bytes_with_options(
                         input,
                         output,
-                        watermark,
+                        &payload,
                         watermark_algorithm.as_ref(),
-                        *strength,
+                        strength,
+                        &AudioEmbedOptions {
+                            min_snr: *min_snr,
+                            raw: *raw_embed,
+                            strength_scale: *audio_strength_scale,
+                            sync: *sync,
+                        },
                     )?;
+                    // SNR在重新编码之前计算，反映的是水印本身而不是编码器带来的失真
+                    if cli.verbose {
+                        eprintln!("{} {:.2} dB", Message::Snr.text().blue().bold(), snr);
+                    }
+                    quality_db_opt = Some(("snr_db", snr));
                 }
                 MediaType::Video => {
                     if cli.verbose {
                         eprintln!(
                             "{} {}",
-                            "🎥  处理视频文件:".blue().bold(),
+                            Message::ProcessingVideo.text().blue().bold(),
                             format!("{input:?}").cyan()
                         );
-
-                        // 检查水印容量
-                        if !VideoWatermarker::check_watermark_capacity(
-                            input,
-                            watermark,
-                            watermark_algorithm.as_ref(),
-                        )? {
-                            eprintln!(
-                                "{} {}",
-                                "⚠️".yellow(),
-                                "警告: 水印可能太长，可能影响嵌入效果".yellow()
-                            );
-                        }
                     }
 
-                    let processed_frames = VideoWatermarker::embed_watermark(
+                    let terminal_progress = TerminalProgress::default();
+                    let report_progress = |event| terminal_progress.handle(event);
+                    let processed_frames = VideoWatermarker::embed_watermark_with_options(
                         input,
                         output,
-                        watermark,
+                        &watermark[0],
                         watermark_algorithm.as_ref(),
-                        *strength,
-                        *lossless,
+                        strength,
                         video_mode.clone(),
+                        VideoEncodeOptions {
+                            lossless: *lossless,
+                            ffmpeg_threads: *ffmpeg_threads,
+                            disk_frames: *disk_frames,
+                            resume: *resume,
+                            algorithm_options: Some(&algorithm_options),
+                            channel: *channel,
+                            frame_index: *frame_index,
+                            embed_every: *embed_every,
+                            codec: *codec,
+                            crf: *crf,
+                            progress: Progress::new(&report_progress),
+                        },
                     )?;
                     processed_frames_opt = Some(processed_frames);
                 }
@@ -185,7 +1227,23 @@ fn run(cli: Cli) -> Result<()> {
                 json_output["processed_frames"] = json!(n);
             }
 
-            println!("{}", json_output);
+            if let Some((key, db)) = quality_db_opt {
+                insert_quality_db(&mut json_output, key, db);
+            }
+            merge_json(&mut json_output, capacity_json);
+            merge_json(&mut json_output, watermarks_json);
+
+            write_report(
+                report.as_ref(),
+                output,
+                "embed",
+                &json_output,
+                cli.overwrite,
+            )?;
+            if let Some(path) = metadata {
+                write_embed_metadata(cli, path.as_deref(), &media_type, strength)?;
+            }
+            Ok(json_output)
         }
 
         Commands::Extract {
@@ -194,76 +1252,376 @@ fn run(cli: Cli) -> Result<()> {
             length,
             output,
             sample_frames,
+            sampling,
+            frame_index,
+            embed_every,
             confidence_threshold,
             video_mode,
+            seed,
+            block_size,
+            positions,
+            band,
+            bit_planes,
+            spread_chips,
+            differential,
+            qim_step,
+            dct_mode,
+            repeat,
+            tile,
+            psychoacoustic,
+            sync,
+            password,
+            sign,
+            ecc,
+            interleave,
+            binary,
+            channel,
+            channels_vote_threshold,
+            extract_channels,
+            vote,
+            report,
+            confidence_map,
+            robust,
+            strip_rows,
+            region,
+            normalize_size,
+            split: _,
         } => {
             // 检查输入文件是否存在
             if !MediaUtils::file_exists(input) {
                 return Err(WatermarkError::Io(std::io::Error::new(
                     std::io::ErrorKind::NotFound,
-                    format!("输入文件不存在: {input:?}"),
+                    Message::InputNotFound.format(&[&format!("{input:?}")]),
                 )));
             }
 
             // 检测媒体类型
             let media_type = MediaUtils::detect_media_type(input)?;
 
+            // 省略 -l 时从图片顶部的自描述头部读取嵌入参数，头部记录的参数优先于命令行
+            let header = if length.is_empty() {
+                reject_header_conflicts(
+                    &media_type,
+                    &[
+                        ("--seed", seed.is_some()),
+                        ("--positions", positions.is_some()),
+                        ("--band", *band != FrequencyBand::Mid),
+                        ("--spread-chips", spread_chips.is_some()),
+                        ("--differential", *differential),
+                        ("--qim-step", qim_step.is_some()),
+                        ("--dct-mode", *dct_mode != DctMode::Block),
+                        ("--bit-planes", bit_planes.is_some()),
+                        ("--repeat", repeat.is_some()),
+                        ("--tile", tile.is_some()),
+                        (
+                            "--channels-vote-threshold",
+                            channels_vote_threshold.is_some(),
+                        ),
+                        ("--extract-channels", !extract_channels.is_empty()),
+                        ("--vote", vote.is_some()),
+                        ("--confidence-map", confidence_map.is_some()),
+                        ("--robust", *robust),
+                        ("--strip-rows", strip_rows.is_some()),
+                        ("--region", region.is_some()),
+                    ],
+                )?;
+                let header = ImageWatermarker::read_header(input, *normalize_size)?;
+                if header.normalize_size != *normalize_size {
+                    return Err(WatermarkError::InvalidArgument(
+                        Message::HeaderSizeMismatch.text().to_string(),
+                    ));
+                }
+                if header.encrypted && password.is_none() {
+                    return Err(WatermarkError::PasswordRequired);
+                }
+                if header.signed && sign.is_none() {
+                    return Err(WatermarkError::InvalidArgument(
+                        Message::SignedNeedsKey.text().to_string(),
+                    ));
+                }
+                Some(header)
+            } else {
+                None
+            };
+            let algorithm = &header
+                .as_ref()
+                .map_or_else(|| algorithm.clone(), |header| header.algorithm.clone());
+            let block_size = &header
+                .as_ref()
+                .map_or(*block_size, |header| header.block_size);
+            let ecc = &header.as_ref().map_or(*ecc, |header| header.ecc);
+            let interleave = &header
+                .as_ref()
+                .map_or(*interleave, |header| header.interleave);
+            let channel = &header.as_ref().map_or(*channel, |header| header.channel);
+            let binary = &header.as_ref().map_or(*binary, |header| header.binary);
+
+            reject_video_payload_options(
+                &media_type,
+                password.is_some(),
+                sign.is_some(),
+                ecc.is_some(),
+                *binary,
+            )?;
+            reject_non_image_repeat(&media_type, repeat.is_some())?;
+            reject_audio_block_size(&media_type, block_size.is_some())?;
+            reject_unsupported_tile(&media_type, algorithm, tile.is_some())?;
+            reject_unsupported_algorithm(&media_type, algorithm)?;
+            reject_non_audio_option(&media_type, "--psychoacoustic", *psychoacoustic)?;
+            reject_non_audio_option(&media_type, "--sync", *sync)?;
+            reject_non_image_option(&media_type, "--strip-rows", strip_rows.is_some())?;
+            reject_non_image_option(&media_type, "--region", region.is_some())?;
+            reject_non_image_option(&media_type, "--normalize-size", normalize_size.is_some())?;
+            reject_non_image_option(
+                &media_type,
+                "--extract-channels",
+                !extract_channels.is_empty(),
+            )?;
+            reject_non_image_option(&media_type, "--vote", vote.is_some())?;
+            if *robust && !matches!(media_type, MediaType::Image) {
+                return Err(WatermarkError::InvalidArgument(
+                    Message::RobustImagesOnly.text().to_string(),
+                ));
+            }
+            if confidence_map.is_some()
+                && !(matches!(media_type, MediaType::Image) && matches!(algorithm, Algorithm::Dct))
+            {
+                return Err(WatermarkError::InvalidArgument(
+                    Message::ConfidenceMapDctOnly.text().to_string(),
+                ));
+            }
+
+            // 在提取之前确认输出位置可写，报告文件同样不能覆盖已有文件
+            let report_file = report_path(
+                report.as_ref(),
+                output.as_deref().unwrap_or(input),
+                "extract",
+            );
+            for path in output.iter().chain(confidence_map).chain(&report_file) {
+                MediaUtils::check_output(path, cli.overwrite)?;
+            }
+
             // 创建水印算法
-            let watermark_algorithm = WatermarkFactory::create_algorithm(algorithm.clone());
+            let watermark_algorithm = WatermarkFactory::create_algorithm_with_options(
+                algorithm.clone(),
+                &AlgorithmOptions {
+                    seed: *seed,
+                    block_size: *block_size,
+                    positions: positions.as_ref().map(|p| p.0.clone()),
+                    band: *band,
+                    bit_planes: *bit_planes,
+                    spread_chips: *spread_chips,
+                    differential: *differential,
+                    qim_step: *qim_step,
+                    dct_mode: *dct_mode,
+                    repeat: *repeat,
+                    tile_size: *tile,
+                    psychoacoustic: *psychoacoustic,
+                    ..Default::default()
+                },
+            );
 
             if cli.verbose {
                 eprintln!(
                     "{} {}",
-                    "🔍  从文件提取水印:".blue().bold(),
+                    Message::ExtractingFrom.text().blue().bold(),
                     format!("{input:?}").cyan()
                 );
                 eprintln!(
                     "{} {}",
-                    "🔧  使用算法:".blue().bold(),
+                    Message::UsingAlgorithm.text().blue().bold(),
                     format!("{algorithm:?}").cyan()
                 );
             }
 
-            let watermark_length = *length;
+            reject_multiple_video_watermarks(&media_type, length.len())?;
+            if length.len() > 1 && output.is_some() {
+                return Err(WatermarkError::InvalidArgument(
+                    Message::MultipleWithOutput.text().to_string(),
+                ));
+            }
+            let watermark_length: usize = length.iter().sum();
+            let ecc = ecc_options(*ecc, *interleave);
+            let payload_lengths: Vec<usize> = match &header {
+                Some(header) => vec![header.payload_len],
+                None => length
+                    .iter()
+                    .map(|&length| {
+                        encoded_payload_length(length, password.is_some(), sign.is_some(), ecc)
+                    })
+                    .collect(),
+            };
+            let payload_length = payload_lengths.iter().sum();
 
             // 根据媒体类型选择处理方式
-            let (extracted_watermark, confidence, actual_frames_used) = match media_type {
-                MediaType::Image => {
-                    let watermark = ImageWatermarker::extract_watermark(
-                        input,
-                        watermark_algorithm.as_ref(),
-                        watermark_length,
+            let mut channel_vote: Option<ChannelVote> = None;
+            let vote_strategy = vote.unwrap_or_default();
+            let mut recovery: Option<(Recovery, usize)> = None;
+            let mut bits_corrected: Option<usize> = None;
+            let mut sync_offset: Option<i64> = None;
+            let (payloads, result) = match media_type {
+                MediaType::Image | MediaType::Audio => {
+                    // 指定阈值、投票通道或投票方式时对图片各颜色通道分别提取并投票，阈值默认为1
+                    let vote_threshold = channels_vote_threshold
+                        .or((!extract_channels.is_empty() || vote.is_some()).then_some(1));
+                    let (raw, confidence) = match (&media_type, vote_threshold, strip_rows) {
+                        (MediaType::Image, _, _) if header.is_some() => {
+                            ImageWatermarker::extract_bytes_after_header(
+                                input,
+                                watermark_algorithm.as_ref(),
+                                payload_length,
+                                *channel,
+                                *normalize_size,
+                            )?
+                        }
+                        (MediaType::Audio, _, _) if *sync => {
+                            let (raw, confidence, offset) =
+                                AudioWatermarker::extract_bytes_with_sync(
+                                    input,
+                                    watermark_algorithm.as_ref(),
+                                    payload_length,
+                                )?;
+                            sync_offset = Some(offset);
+                            (raw, confidence)
+                        }
+                        (MediaType::Image, Some(threshold), _) => {
+                            let (raw, vote) = ImageWatermarker::extract_bytes_with_channel_vote(
+                                input,
+                                watermark_algorithm.as_ref(),
+                                payload_length,
+                                extract_channels,
+                                vote_strategy,
+                                threshold,
+                            )?;
+                            let confidence = vote.confidence();
+                            channel_vote = Some(vote);
+                            (raw, confidence)
+                        }
+                        (MediaType::Image, None, _) if *robust => {
+                            let extraction = ImageWatermarker::extract_bytes_robust(
+                                input,
+                                watermark_algorithm.as_ref(),
+                                payload_length,
+                                *channel,
+                            )?;
+                            recovery = Some((extraction.recovery, extraction.attempts));
+                            (extraction.bytes, extraction.confidence)
+                        }
+                        (MediaType::Image, None, Some(strip_rows)) => {
+                            ImageWatermarker::extract_bytes_in_strips(
+                                input,
+                                watermark_algorithm.as_ref(),
+                                payload_length,
+                                *channel,
+                                *strip_rows,
+                            )?
+                        }
+                        (MediaType::Image, None, None) => match (region, normalize_size) {
+                            (Some(region), _) => ImageWatermarker::extract_bytes_from_region(
+                                input,
+                                watermark_algorithm.as_ref(),
+                                payload_length,
+                                *channel,
+                                *region,
+                            )?,
+                            (None, Some(size)) => ImageWatermarker::extract_bytes_normalized(
+                                input,
+                                watermark_algorithm.as_ref(),
+                                payload_length,
+                                *channel,
+                                *size,
+                            )?,
+                            (None, None) => ImageWatermarker::extract_bytes_from_channel(
+                                input,
+                                watermark_algorithm.as_ref(),
+                                payload_length,
+                                *channel,
+                            )?,
+                        },
+                        _ => extract_media_bytes(
+                            &media_type,
+                            input,
+                            watermark_algorithm.as_ref(),
+                            payload_length,
+                        )?,
+                    };
+
+                    let (payloads, corrected) = open_payloads(
+                        raw,
+                        &payload_lengths,
+                        password.as_deref(),
+                        sign.as_deref(),
+                        ecc,
+                        *binary,
                     )?;
-                    (watermark, 1.0, 1) // 图片始终置信度100%，使用1帧
+                    bits_corrected = ecc.map(|_| corrected);
+                    // 图片和音频使用1帧，签名校验失败时 open_payload 已经返回错误
+                    let mut result = ExtractionResult::single(
+                        String::from_utf8_lossy(&payloads[0]).into_owned(),
+                        confidence,
+                    );
+                    if sign.is_some() {
+                        result.integrity = Integrity::Verified;
+                    }
+                    (payloads, result)
                 }
-                MediaType::Audio => {
-                    let watermark = AudioWatermarker::extract_watermark(
+                MediaType::Video => {
+                    let terminal_progress = TerminalProgress::default();
+                    let report_progress = |event| terminal_progress.handle(event);
+                    let result = VideoWatermarker::extract_watermark_with_options(
                         input,
                         watermark_algorithm.as_ref(),
                         watermark_length,
+                        Some(*sample_frames),
+                        *confidence_threshold,
+                        video_mode.clone(),
+                        VideoExtractOptions {
+                            sampling: *sampling,
+                            frame_index: *frame_index,
+                            embed_every: *embed_every,
+                            early_stop_confidence: Some(
+                                confidence_threshold.unwrap_or(DEFAULT_CONFIDENCE_THRESHOLD),
+                            ),
+                            progress: Progress::new(&report_progress),
+                        },
                     )?;
-                    (watermark, 1.0, 1) // 音频始终置信度100%，使用1帧
+                    (vec![result.text.clone().into_bytes()], result)
                 }
-                MediaType::Video => VideoWatermarker::extract_watermark(
-                    input,
-                    watermark_algorithm.as_ref(),
-                    watermark_length,
-                    Some(*sample_frames),
-                    Some(*confidence_threshold),
-                    video_mode.clone(),
-                )?,
             };
 
+            let extracted = &payloads[0];
+            let views = payload_views(extracted, *binary)?;
+
             // 输出到文件（如果指定）
             let mut saved_to: Option<String> = None;
             if let Some(output_path) = output {
-                MediaUtils::ensure_output_dir(output_path)?;
-                std::fs::write(output_path, &extracted_watermark)?;
+                std::fs::write(output_path, saved_contents(extracted, *binary)?)?;
                 saved_to = Some(output_path.display().to_string());
                 eprintln!(
                     "{} {}",
                     "💾".green(),
-                    format!("提取的水印已保存到: {output_path:?}").green()
+                    Message::WatermarkSaved
+                        .format(&[&format!("{output_path:?}")])
+                        .green()
+                );
+            }
+
+            // 置信度图按实际提取的载荷长度（含加密开销）计算
+            let mut confidence_map_size: Option<(u32, u32)> = None;
+            if let Some(map_path) = confidence_map {
+                confidence_map_size = Some(ImageWatermarker::save_confidence_map(
+                    input,
+                    map_path,
+                    watermark_algorithm.as_ref(),
+                    payload_length,
+                    *channel,
+                )?);
+                eprintln!(
+                    "{} {}",
+                    "🗺️".green(),
+                    Message::ConfidenceMapSaved
+                        .format(&[&format!("{map_path:?}")])
+                        .green()
                 );
             }
 
@@ -274,23 +1632,1754 @@ fn run(cli: Cli) -> Result<()> {
                 "input": input.display().to_string(),
                 "algorithm": format!("{:?}", algorithm),
                 "media_type": format!("{:?}", media_type),
-                "length": extracted_watermark.len(),
-                "watermark": extracted_watermark,
+                "length": extracted.len(),
+                "confidence": result.confidence,
+                "integrity": format!("{:?}", result.integrity),
                 "output": saved_to,
             });
+            merge_json(&mut json_output, views);
+            if let Some(header) = &header {
+                json_output["header"] = json!({
+                    "block_size": header.block_size,
+                    "ecc": header.ecc.map(|scheme| format!("{scheme:?}")),
+                    "interleave": header.interleave,
+                    "channel": format!("{:?}", header.channel),
+                    "binary": header.binary,
+                    "payload_bytes": header.payload_len,
+                    "normalize_size": header.normalize_size,
+                });
+            }
 
-            // 对于视频类型，添加额外的质量信息和 video_mode
-            if matches!(media_type, MediaType::Video) {
-                json_output["confidence"] = json!(confidence);
-                json_output["sample_frames_requested"] = json!(sample_frames);
-                json_output["actual_frames_used"] = json!(actual_frames_used);
-                json_output["confidence_threshold"] = json!(confidence_threshold);
-                json_output["video_mode"] = json!(format!("{:?}", video_mode));
+            // 多个水印时顶层字段为第一个水印，`watermarks` 按嵌入顺序给出全部水印
+            if payloads.len() > 1 {
+                let watermarks = payloads
+                    .iter()
+                    .map(|payload| {
+                        let mut entry = json!({ "length": payload.len() });
+                        merge_json(&mut entry, payload_views(payload, *binary)?);
+                        Ok(entry)
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                json_output["watermarks"] = json!(watermarks);
             }
 
-            println!("{}", json_output);
-        }
-    }
+            if let (Some(map_path), Some((width, height))) = (confidence_map, confidence_map_size) {
+                json_output["confidence_map"] = json!({
+                    "path": map_path.display().to_string(),
+                    "width": width,
+                    "height": height,
+                });
+            }
 
-    Ok(())
+            if sign.is_some() {
+                json_output["authentic"] = json!(true);
+            }
+
+            // 纠错编码在所有水印中纠正的比特总数
+            if let Some(corrected) = bits_corrected {
+                if cli.verbose {
+                    eprintln!(
+                        "{} {}",
+                        Message::BitsCorrected.text().blue().bold(),
+                        corrected
+                    );
+                }
+                json_output["bits_corrected"] = json!(corrected);
+            }
+
+            // 报告尝试过的预处理中胜出的一个
+            if let Some((recovery, attempts)) = recovery {
+                if cli.verbose {
+                    eprintln!(
+                        "{} {}",
+                        Message::RobustWinner.text().blue().bold(),
+                        Message::RobustAttempts
+                            .format(&[&recovery, &attempts])
+                            .cyan()
+                    );
+                }
+                json_output["robust"] = json!({
+                    "transform": recovery.to_string(),
+                    "attempts": attempts,
+                });
+            }
+
+            // 同步对齐时报告原始起点在文件中的样本位置，负数为开头被裁掉的样本数
+            if let Some(offset) = sync_offset {
+                json_output["sync_offset"] = json!(offset);
+            }
+
+            // 多通道投票时报告未达到阈值的比特位置（按提取到的原始载荷计）
+            if let Some(vote) = &channel_vote {
+                json_output["uncertain_bits"] = json!(vote.uncertain_bits);
+                json_output["vote"] = json!(format!("{:?}", vote_strategy));
+                if !extract_channels.is_empty() {
+                    json_output["extract_channels"] = json!(extract_channels
+                        .iter()
+                        .map(|channel| format!("{channel:?}"))
+                        .collect::<Vec<_>>());
+                }
+            }
+
+            // 对于视频类型，添加额外的质量信息和 video_mode
+            if matches!(media_type, MediaType::Video) {
+                json_output["sample_frames_requested"] = json!(sample_frames);
+                json_output["sampling"] = json!(format!("{:?}", sampling));
+
+                // 帧序号给出片段对应的原始帧范围，不连续说明经过拼接或重排
+                if *frame_index {
+                    json_output["frame_indices"] = match result.frame_indices {
+                        Some(range) => json!({
+                            "first": range.first,
+                            "last": range.last,
+                            "frames": range.frames,
+                            "contiguous": range.contiguous,
+                        }),
+                        None => serde_json::Value::Null,
+                    };
+                }
+                json_output["actual_frames_used"] = json!(result.frames_used);
+                json_output["video_mode"] = json!(format!("{:?}", video_mode));
+
+                // 多帧投票的结果同时给出按样本数修正后的置信度
+                if matches!(
+                    video_mode,
+                    VideoWatermarkMode::Video | VideoWatermarkMode::Keyframe
+                ) {
+                    json_output["adjusted_confidence"] =
+                        json!(VideoWatermarker::sample_adjusted_confidence(
+                            result.confidence,
+                            result.frames_used
+                        ));
+                }
+            }
+
+            // 视频未指定阈值时使用默认值，帧投票的结果以修正后的置信度比较，低置信度警告已在提取时给出
+            let threshold = match media_type {
+                MediaType::Video => {
+                    Some(confidence_threshold.unwrap_or(DEFAULT_CONFIDENCE_THRESHOLD))
+                }
+                _ => *confidence_threshold,
+            };
+            let checked_confidence = json_output["adjusted_confidence"]
+                .as_f64()
+                .unwrap_or(result.confidence);
+            mark_low_confidence(
+                &mut json_output,
+                checked_confidence,
+                threshold,
+                !matches!(media_type, MediaType::Video),
+            );
+
+            write_report(
+                report.as_ref(),
+                output.as_deref().unwrap_or(input),
+                "extract",
+                &json_output,
+                cli.overwrite,
+            )?;
+            Ok(json_output)
+        }
+
+        Commands::Detect {
+            input,
+            algorithm,
+            seed,
+            block_size,
+            sample_frames,
+        } => {
+            if !MediaUtils::file_exists(input) {
+                return Err(WatermarkError::Io(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    Message::InputNotFound.format(&[&format!("{input:?}")]),
+                )));
+            }
+
+            // LSB 没有软判决信息，无法区分有无水印
+            if matches!(algorithm, Algorithm::Lsb) {
+                return Err(WatermarkError::InvalidArgument(
+                    Message::LsbCannotDetect.text().to_string(),
+                ));
+            }
+
+            let media_type = MediaUtils::detect_media_type(input)?;
+            reject_audio_block_size(&media_type, block_size.is_some())?;
+            reject_unsupported_algorithm(&media_type, algorithm)?;
+            let watermark_algorithm = WatermarkFactory::create_algorithm_with_options(
+                algorithm.clone(),
+                &AlgorithmOptions {
+                    seed: *seed,
+                    block_size: *block_size,
+                    ..Default::default()
+                },
+            );
+
+            if cli.verbose {
+                eprintln!(
+                    "{} {}",
+                    Message::Detecting.text().blue().bold(),
+                    format!("{input:?}").cyan()
+                );
+            }
+
+            let (score, frames_used) = match media_type {
+                MediaType::Image => (
+                    ImageWatermarker::detect_watermark(input, watermark_algorithm.as_ref())?,
+                    None,
+                ),
+                MediaType::Audio => (
+                    AudioWatermarker::detect_watermark(input, watermark_algorithm.as_ref())?,
+                    None,
+                ),
+                MediaType::Video => {
+                    let (score, frames) = VideoWatermarker::detect_watermark(
+                        input,
+                        watermark_algorithm.as_ref(),
+                        *sample_frames,
+                    )?;
+                    (score, Some(frames))
+                }
+            };
+
+            let mut json_output = json!({
+                "status": "success",
+                "action": "detect",
+                "input": input.display().to_string(),
+                "algorithm": format!("{:?}", algorithm),
+                "media_type": format!("{:?}", media_type),
+                "watermark_present": score >= WatermarkUtils::DETECTION_THRESHOLD,
+                "score": score,
+                "threshold": WatermarkUtils::DETECTION_THRESHOLD,
+            });
+
+            if let Some(n) = frames_used {
+                json_output["actual_frames_used"] = json!(n);
+            }
+
+            Ok(json_output)
+        }
+
+        Commands::Info {
+            input,
+            algorithm,
+            dct_mode,
+        } => {
+            if !MediaUtils::file_exists(input) {
+                return Err(WatermarkError::Io(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    Message::InputNotFound.format(&[&format!("{input:?}")]),
+                )));
+            }
+
+            let media_type = MediaUtils::detect_media_type(input)?;
+            reject_unsupported_algorithm(&media_type, algorithm)?;
+            let watermark_algorithm = WatermarkFactory::create_algorithm_with_options(
+                algorithm.clone(),
+                &AlgorithmOptions {
+                    dct_mode: *dct_mode,
+                    ..Default::default()
+                },
+            );
+
+            let mut json_output = json!({
+                "status": "success",
+                "action": "info",
+                "input": input.display().to_string(),
+                "algorithm": format!("{:?}", algorithm),
+                "media_type": format!("{:?}", media_type),
+            });
+
+            // 视频按单帧计算容量，与嵌入时的检查一致
+            let capacity_bits = match media_type {
+                MediaType::Image => {
+                    let (width, height, format) = ImageWatermarker::get_image_info(input)?;
+                    json_output["width"] = json!(width);
+                    json_output["height"] = json!(height);
+                    json_output["format"] = json!(format!("{:?}", format));
+                    ImageWatermarker::payload_capacity(input, watermark_algorithm.as_ref())?
+                }
+                MediaType::Audio => {
+                    let spec = AudioWatermarker::get_audio_info(input)?;
+                    json_output["sample_rate"] = json!(spec.sample_rate);
+                    json_output["channels"] = json!(spec.channels);
+                    json_output["bits_per_sample"] = json!(spec.bits_per_sample);
+                    json_output["duration_secs"] =
+                        json!(AudioWatermarker::get_audio_duration(input)?);
+                    AudioWatermarker::payload_capacity(input, watermark_algorithm.as_ref())?
+                }
+                MediaType::Video => {
+                    let properties = VideoWatermarker::probe(input)?;
+                    json_output["codec"] = json!(properties.codec);
+                    json_output["width"] = json!(properties.width);
+                    json_output["height"] = json!(properties.height);
+                    json_output["fps"] = json!(properties.fps);
+                    json_output["duration_secs"] = json!(properties.duration);
+                    json_output["has_audio"] = json!(properties.has_audio);
+                    VideoWatermarker::frame_capacity(input, watermark_algorithm.as_ref())?
+                }
+            };
+            json_output["capacity_bits"] = json!(capacity_bits);
+            json_output["capacity_bytes"] = json!(capacity_bits / 8);
+
+            Ok(json_output)
+        }
+
+        Commands::Verify {
+            input,
+            watermark,
+            algorithm,
+            seed,
+            block_size,
+            qim_step,
+            attacks,
+            max_ber,
+        } => {
+            if !MediaUtils::file_exists(input) {
+                return Err(WatermarkError::Io(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    Message::InputNotFound.format(&[&format!("{input:?}")]),
+                )));
+            }
+
+            if !(0.0..=1.0).contains(max_ber) {
+                return Err(WatermarkError::InvalidArgument(
+                    Message::MaxBerRange.format(&[&max_ber]),
+                ));
+            }
+
+            let media_type = MediaUtils::detect_media_type(input)?;
+            if !matches!(media_type, MediaType::Image) {
+                return Err(WatermarkError::InvalidArgument(
+                    Message::VerifyImagesOnly.text().to_string(),
+                ));
+            }
+            reject_unsupported_algorithm(&media_type, algorithm)?;
+            let watermark_algorithm = WatermarkFactory::create_algorithm_with_options(
+                algorithm.clone(),
+                &AlgorithmOptions {
+                    seed: *seed,
+                    block_size: *block_size,
+                    qim_step: *qim_step,
+                    ..Default::default()
+                },
+            );
+
+            let bits = WatermarkUtils::string_to_bits(watermark);
+            let data = ImageWatermarker::load_channel(input)?;
+            let baseline_ber =
+                robustness::measure_ber(watermark_algorithm.as_ref(), &bits, &data, bits.len())?;
+
+            let mut results = Vec::with_capacity(attacks.len());
+            for attack in attacks {
+                // 裁剪等攻击可能使容量不足以提取全部比特，此时记为失败而不中止其余攻击
+                let outcome = attack.apply(&data, seed.unwrap_or(0)).and_then(|attacked| {
+                    robustness::measure_ber(
+                        watermark_algorithm.as_ref(),
+                        &bits,
+                        &attacked,
+                        bits.len(),
+                    )
+                });
+                let result = match outcome {
+                    Ok(ber) => json!({
+                        "attack": attack.to_string(),
+                        "ber": ber,
+                        "survived": ber <= *max_ber,
+                    }),
+                    Err(e) => json!({
+                        "attack": attack.to_string(),
+                        "ber": null,
+                        "survived": false,
+                        "message": e.to_string(),
+                    }),
+                };
+
+                if cli.verbose {
+                    eprintln!(
+                        "{} {} BER: {}",
+                        Message::Attack.text().blue().bold(),
+                        attack,
+                        result["ber"]
+                            .as_f64()
+                            .map_or(Message::AttackExtractionFailed.text().to_string(), |ber| {
+                                format!("{ber:.4}")
+                            })
+                    );
+                }
+                results.push(result);
+            }
+
+            Ok(json!({
+                "status": "success",
+                "action": "verify",
+                "input": input.display().to_string(),
+                "algorithm": format!("{:?}", algorithm),
+                "bits": bits.len(),
+                "baseline_ber": baseline_ber,
+                "max_ber": max_ber,
+                "results": results,
+            }))
+        }
+
+        Commands::Diff {
+            original,
+            watermarked,
+            output,
+            block_size,
+            channel,
+        } => {
+            for path in [original, watermarked] {
+                if !MediaUtils::file_exists(path) {
+                    return Err(WatermarkError::Io(std::io::Error::new(
+                        std::io::ErrorKind::NotFound,
+                        Message::InputNotFound.format(&[&format!("{path:?}")]),
+                    )));
+                }
+            }
+            let media_type = MediaUtils::detect_media_type(original)?;
+            if MediaUtils::detect_media_type(watermarked)? != media_type {
+                return Err(WatermarkError::InvalidArgument(
+                    Message::DiffMediaMismatch.text().to_string(),
+                ));
+            }
+            if matches!(media_type, MediaType::Video) {
+                return Err(WatermarkError::InvalidArgument(
+                    Message::DiffImageAudioOnly.text().to_string(),
+                ));
+            }
+            MediaUtils::check_output(output, cli.overwrite)?;
+
+            let mut json_output = json!({
+                "status": "success",
+                "action": "diff",
+                "original": original.display().to_string(),
+                "watermarked": watermarked.display().to_string(),
+                "media_type": format!("{:?}", media_type),
+                "output": output.display().to_string(),
+            });
+
+            match media_type {
+                MediaType::Image => {
+                    let diff = ImageWatermarker::diff_images(
+                        original,
+                        watermarked,
+                        *block_size,
+                        *channel,
+                    )?;
+                    let (width, height) = diff.save_heatmap(output)?;
+                    let (blocks_h, blocks_w) = diff.block_energy.dim();
+                    let energies = diff.block_energy.iter().copied();
+                    json_output["identical"] = json!(diff.is_identical());
+                    json_output["psnr_db"] = json!(diff.psnr);
+                    json_output["block_size"] = json!(block_size);
+                    json_output["blocks"] = json!(blocks_h * blocks_w);
+                    json_output["changed_blocks"] = json!(diff.changed_blocks());
+                    json_output["max_block_energy"] = json!(energies.clone().fold(0.0, f64::max));
+                    json_output["mean_block_energy"] =
+                        json!(energies.sum::<f64>() / (blocks_h * blocks_w).max(1) as f64);
+                    // 修改量最大的系数位置，嵌入时使用的中频位置应当排在前面
+                    json_output["peak_positions"] = json!(diff.peak_positions(8));
+                    json_output["heatmap_width"] = json!(width);
+                    json_output["heatmap_height"] = json!(height);
+                }
+                MediaType::Audio => {
+                    let diff = AudioWatermarker::diff_audio(original, watermarked)?;
+                    let (width, height) = diff.save_envelope_plot(output)?;
+                    json_output["identical"] = json!(diff.is_identical());
+                    json_output["snr_db"] = json!(diff.snr);
+                    json_output["sample_rate"] = json!(diff.sample_rate);
+                    json_output["window_samples"] = json!(diff.window);
+                    json_output["envelope_points"] = json!(diff.envelope.len());
+                    json_output["max_difference"] = json!(diff.max_difference);
+                    json_output["plot_width"] = json!(width);
+                    json_output["plot_height"] = json!(height);
+                }
+                MediaType::Video => unreachable!("视频已在前面拒绝"),
+            }
+
+            eprintln!(
+                "{} {}",
+                "🗺️".green(),
+                Message::DiffSaved.format(&[&format!("{output:?}")]).green()
+            );
+            Ok(json_output)
+        }
+
+        Commands::ListAlgorithms => {
+            let algorithms: Vec<serde_json::Value> = WatermarkFactory::all_algorithms()
+                .iter()
+                .map(|(algorithm, instance)| {
+                    let value = algorithm.to_possible_value();
+                    let media: Vec<String> = [MediaType::Image, MediaType::Audio, MediaType::Video]
+                        .into_iter()
+                        .filter(|media_type| instance.supports(*media_type))
+                        .map(|media_type| format!("{media_type:?}"))
+                        .collect();
+                    json!({
+                        "algorithm": format!("{:?}", algorithm),
+                        "value": value.as_ref().map(|value| value.get_name()),
+                        "description": value
+                            .as_ref()
+                            .and_then(|value| value.get_help())
+                            .map(|help| help.to_string()),
+                        "name": instance.name(),
+                        "media_types": media,
+                        "robust": instance.robust(),
+                        "capacity_class": capacity_class(instance.as_ref()),
+                    })
+                })
+                .collect();
+            Ok(json!({
+                "status": "success",
+                "action": "list-algorithms",
+                "algorithms": algorithms,
+            }))
+        }
+
+        Commands::Bench {
+            input,
+            iterations,
+            algorithm,
+            watermark,
+            strength,
+        } => {
+            if !MediaUtils::file_exists(input) {
+                return Err(WatermarkError::Io(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    Message::InputNotFound.format(&[&format!("{input:?}")]),
+                )));
+            }
+            let media_type = MediaUtils::detect_media_type(input)?;
+            reject_unsupported_algorithm(&media_type, algorithm)?;
+            let watermark_algorithm = WatermarkFactory::create_algorithm(algorithm.clone());
+
+            // 输出与输入同格式，写到临时目录，每次迭代覆盖，结束后删除
+            let extension = input
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .unwrap_or("bin");
+            let output =
+                std::env::temp_dir().join(format!("seal_bench_{}.{extension}", process::id()));
+            let mut samples = Vec::with_capacity(*iterations as usize);
+            let outcome = (0..*iterations).try_for_each(|_| {
+                samples.push(bench_iteration(
+                    &media_type,
+                    input,
+                    &output,
+                    watermark_algorithm.as_ref(),
+                    watermark,
+                    *strength,
+                )?);
+                Ok::<_, WatermarkError>(())
+            });
+            let _ = std::fs::remove_file(&output);
+            outcome?;
+
+            let embed: Vec<Duration> = samples.iter().map(|&(embed, _, _)| embed).collect();
+            let extract: Vec<Duration> = samples.iter().map(|&(_, extract, _)| extract).collect();
+            let total: Vec<Duration> = samples
+                .iter()
+                .map(|&(embed, extract, _)| embed + extract)
+                .collect();
+            let input_bytes = std::fs::metadata(input)?.len();
+            let (median_millis, _) = timing_millis(&total);
+
+            Ok(json!({
+                "status": "success",
+                "action": "bench",
+                "input": input.display().to_string(),
+                "media_type": format!("{:?}", media_type),
+                "algorithm": format!("{:?}", algorithm),
+                "iterations": iterations,
+                "input_bytes": input_bytes,
+                "embed": timing_json(&embed),
+                "extract": timing_json(&extract),
+                "total": timing_json(&total),
+                // 按总耗时的中位数计算
+                "mb_per_sec": (median_millis > 0.0)
+                    .then(|| input_bytes as f64 / 1000.0 / median_millis),
+                "recovered": samples.iter().all(|&(_, _, recovered)| recovered),
+            }))
+        }
+    }
+}
+
+/// bench 的一次迭代：把水印嵌入到 `output` 再从中提取，返回（嵌入耗时, 提取耗时, 是否提取到原文）
+fn bench_iteration(
+    media_type: &MediaType,
+    input: &Path,
+    output: &Path,
+    algorithm: &(dyn WatermarkAlgorithm + Sync),
+    watermark: &str,
+    strength: f64,
+) -> Result<(Duration, Duration, bool)> {
+    let started = Instant::now();
+    match media_type {
+        MediaType::Image => {
+            ImageWatermarker::embed_watermark(input, output, watermark, algorithm, strength)?;
+        }
+        MediaType::Audio => {
+            AudioWatermarker::embed_watermark(input, output, watermark, algorithm, strength)?;
+        }
+        MediaType::Video => {
+            VideoWatermarker::embed_watermark(
+                input,
+                output,
+                watermark,
+                algorithm,
+                strength,
+                false,
+                VideoWatermarkMode::Video,
+            )?;
+        }
+    }
+    let embed = started.elapsed();
+
+    let started = Instant::now();
+    let extraction = match media_type {
+        MediaType::Image => ImageWatermarker::extract_watermark(output, algorithm, watermark.len()),
+        MediaType::Audio => AudioWatermarker::extract_watermark(output, algorithm, watermark.len()),
+        MediaType::Video => VideoWatermarker::extract_watermark(
+            output,
+            algorithm,
+            watermark.len(),
+            None,
+            None,
+            VideoWatermarkMode::Video,
+        ),
+    };
+    let extract = started.elapsed();
+
+    // 提取出乱码时也计入耗时，只把结果记为未恢复
+    let recovered = extraction.is_ok_and(|result| result.text == watermark);
+    Ok((embed, extract, recovered))
+}
+
+/// 耗时的中位数和 p95（毫秒），按最近秩法取样本中的值
+fn timing_millis(samples: &[Duration]) -> (f64, f64) {
+    let mut millis: Vec<f64> = samples
+        .iter()
+        .map(|duration| duration.as_secs_f64() * 1000.0)
+        .collect();
+    millis.sort_by(f64::total_cmp);
+    let rank = |percentile: f64| {
+        let index = (percentile * millis.len() as f64).ceil() as usize;
+        millis[index.clamp(1, millis.len()) - 1]
+    };
+    (rank(0.5), rank(0.95))
+}
+
+/// `timing_millis` 的JSON形式
+fn timing_json(samples: &[Duration]) -> serde_json::Value {
+    let (median, p95) = timing_millis(samples);
+    json!({
+        "median_ms": median,
+        "p95_ms": p95,
+    })
+}
+
+/// 按算法在 512x512 数据上的容量估计每个像素（或样本）承载的比特数，粗分为高、中、低三档
+fn capacity_class(algorithm: &dyn WatermarkAlgorithm) -> &'static str {
+    const REFERENCE_SIZE: usize = 512;
+    let density = algorithm.capacity(REFERENCE_SIZE, REFERENCE_SIZE) as f64
+        / (REFERENCE_SIZE * REFERENCE_SIZE) as f64;
+    if density >= 1.0 / 8.0 {
+        "high"
+    } else if density >= 1.0 / 1024.0 {
+        "medium"
+    } else {
+        "low"
+    }
+}
+
+/// 视频暂不支持加密、纠错编码和二进制载荷
+fn reject_video_payload_options(
+    media_type: &MediaType,
+    password: bool,
+    sign: bool,
+    ecc: bool,
+    binary: bool,
+) -> Result<()> {
+    if !matches!(media_type, MediaType::Video) {
+        return Ok(());
+    }
+    if password {
+        return Err(WatermarkError::InvalidArgument(
+            Message::VideoNoPassword.text().to_string(),
+        ));
+    }
+    if sign {
+        return Err(WatermarkError::InvalidArgument(
+            Message::VideoNoSign.text().to_string(),
+        ));
+    }
+    if ecc {
+        return Err(WatermarkError::InvalidArgument(
+            Message::VideoNoEcc.text().to_string(),
+        ));
+    }
+    if binary {
+        return Err(WatermarkError::InvalidArgument(
+            Message::VideoNoBinary.text().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// `--report` 的写出路径，未给路径时为 `<base>.<action>.json`
+fn report_path(report: Option<&Option<PathBuf>>, base: &Path, action: &str) -> Option<PathBuf> {
+    report.map(|path| match path {
+        Some(path) => path.clone(),
+        None => {
+            let mut name = base.as_os_str().to_owned();
+            name.push(format!(".{action}.json"));
+            PathBuf::from(name)
+        }
+    })
+}
+
+/// 按 `--report` 把与 stdout 相同的结果 JSON 写入文件，未给路径时写到 `<base>.<action>.json`
+///
+/// 与其他输出文件一样，已存在时只有 `--overwrite` 才会覆盖。
+fn write_report(
+    report: Option<&Option<PathBuf>>,
+    base: &Path,
+    action: &str,
+    json_output: &serde_json::Value,
+    overwrite: bool,
+) -> Result<()> {
+    let Some(path) = report_path(report, base, action) else {
+        return Ok(());
+    };
+    MediaUtils::check_output(&path, overwrite)?;
+    std::fs::write(&path, format!("{json_output}\n"))?;
+    Ok(())
+}
+
+/// `--metadata` 的写出路径，未给路径时为 `<输出>.metadata.json`
+fn metadata_path(path: Option<&Path>, output: &Path) -> PathBuf {
+    match path {
+        Some(path) => path.to_path_buf(),
+        None => {
+            let mut name = output.as_os_str().to_owned();
+            name.push(".metadata.json");
+            PathBuf::from(name)
+        }
+    }
+}
+
+/// 按 `--metadata` 写出描述本次嵌入的元数据文件，未给路径时写到 `<输出>.metadata.json`
+///
+/// 记录提取时需要一致的算法参数、嵌入时间（Unix秒）、每个水印的SHA-256以及输入和输出文件的SHA-256。
+/// 未加密时同时记录水印原文，使用口令加密时只记录摘要。
+fn write_embed_metadata(
+    cli: &Cli,
+    path: Option<&Path>,
+    media_type: &MediaType,
+    strength: f64,
+) -> Result<()> {
+    let Commands::Embed {
+        input,
+        output,
+        watermark,
+        algorithm,
+        video_mode,
+        embed_every,
+        seed,
+        block_size,
+        positions,
+        band,
+        bit_planes,
+        spread_chips,
+        differential,
+        qim_step,
+        dct_mode,
+        perceptual_threshold,
+        repeat,
+        tile,
+        psychoacoustic,
+        raw_embed,
+        sync,
+        channel,
+        audio_strength_scale,
+        password,
+        sign,
+        ecc,
+        interleave,
+        binary,
+        header,
+        region,
+        normalize_size,
+        ..
+    } = &cli.command
+    else {
+        unreachable!("write_embed_metadata 只处理 embed 命令");
+    };
+
+    let mut watermarks = Vec::with_capacity(watermark.len());
+    for watermark in watermark {
+        let contents = if *binary {
+            std::fs::read(watermark)?
+        } else {
+            watermark.as_bytes().to_vec()
+        };
+        let mut entry = json!({
+            "bytes": contents.len(),
+            "sha256": crypto::sha256_hex(&contents),
+        });
+        if password.is_none() && !*binary {
+            entry["text"] = json!(watermark);
+        }
+        watermarks.push(entry);
+    }
+
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+    let repeat = repeat.map(|repeat| match repeat {
+        Repetition::Auto => "auto".to_string(),
+        Repetition::Times(times) => times.to_string(),
+    });
+
+    let mut metadata = json!({
+        "tool": "seal",
+        "version": env!("CARGO_PKG_VERSION"),
+        "timestamp": timestamp,
+        "media_type": format!("{:?}", media_type),
+        "input": input.display().to_string(),
+        "input_sha256": crypto::file_sha256_hex(input)?,
+        "output": output.display().to_string(),
+        "output_sha256": crypto::file_sha256_hex(output)?,
+        "algorithm": format!("{:?}", algorithm),
+        "strength": strength,
+        "seed": seed,
+        "block_size": block_size,
+        "positions": positions.as_ref().map(|p| p.0.clone()),
+        "band": format!("{:?}", band),
+        "bit_planes": bit_planes,
+        "spread_chips": spread_chips,
+        "differential": differential,
+        "qim_step": qim_step,
+        "dct_mode": format!("{:?}", dct_mode),
+        "perceptual_threshold": perceptual_threshold,
+        "repeat": repeat,
+        "tile": tile,
+        "encrypted": password.is_some(),
+        "signed": sign.is_some(),
+        "ecc": ecc.map(|scheme| format!("{scheme:?}")),
+        "interleave": interleave,
+        "binary": binary,
+        "watermarks": watermarks,
+    });
+    match media_type {
+        MediaType::Image => {
+            metadata["channel"] = json!(format!("{:?}", channel));
+            metadata["header"] = json!(header);
+            metadata["region"] =
+                json!(region.map(|region| [region.x, region.y, region.width, region.height]));
+            metadata["normalize_size"] = json!(normalize_size);
+        }
+        MediaType::Audio => {
+            metadata["psychoacoustic"] = json!(psychoacoustic);
+            metadata["raw_embed"] = json!(raw_embed);
+            metadata["sync"] = json!(sync);
+            metadata["audio_strength_scale"] = json!(audio_strength_scale);
+        }
+        MediaType::Video => {
+            metadata["video_mode"] = json!(format!("{:?}", video_mode));
+            metadata["embed_every"] = json!(embed_every);
+        }
+    }
+
+    let path = metadata_path(path, output);
+    MediaUtils::check_output(&path, cli.overwrite)?;
+    std::fs::write(&path, format!("{metadata:#}\n"))?;
+    Ok(())
+}
+
+/// 重复编码只针对单张图片
+fn reject_non_image_repeat(media_type: &MediaType, repeat: bool) -> Result<()> {
+    if repeat && !matches!(media_type, MediaType::Image) {
+        return Err(WatermarkError::InvalidArgument(
+            Message::RepeatImagesOnly.text().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// 视频的每一帧只携带一个水印文本
+fn reject_multiple_video_watermarks(media_type: &MediaType, count: usize) -> Result<()> {
+    if count > 1 && matches!(media_type, MediaType::Video) {
+        return Err(WatermarkError::InvalidArgument(
+            Message::VideoNoMultiple.text().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// 音频的容量计算和折叠矩阵按8x8块对齐，暂不支持其他块边长
+fn reject_audio_block_size(media_type: &MediaType, block_size: bool) -> Result<()> {
+    if block_size && matches!(media_type, MediaType::Audio) {
+        return Err(WatermarkError::InvalidArgument(
+            Message::BlockSizeNoAudio.text().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// 分块模式只针对使用DCT算法的单张图片
+fn reject_unsupported_tile(
+    media_type: &MediaType,
+    algorithm: &Algorithm,
+    tile: bool,
+) -> Result<()> {
+    if tile && !matches!(media_type, MediaType::Image) {
+        return Err(WatermarkError::InvalidArgument(
+            Message::TileImagesOnly.text().to_string(),
+        ));
+    }
+    if tile && !matches!(algorithm, Algorithm::Dct) {
+        return Err(WatermarkError::InvalidArgument(
+            Message::TileDctOnly.text().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// DFT算法对整幅数据做变换，音频按矩阵排列后没有对应的频率意义；
+/// 回声隐藏只能处理一维音频样本
+fn reject_unsupported_algorithm(media_type: &MediaType, algorithm: &Algorithm) -> Result<()> {
+    if matches!(media_type, MediaType::Audio) && matches!(algorithm, Algorithm::Dft) {
+        return Err(WatermarkError::InvalidArgument(
+            Message::DftNoAudio.text().to_string(),
+        ));
+    }
+    if !matches!(media_type, MediaType::Audio) && matches!(algorithm, Algorithm::Echo) {
+        return Err(WatermarkError::InvalidArgument(
+            Message::EchoAudioOnly.text().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// 把按8位灰度计的方差换算到归一化到 [0, 1] 的像素上
+fn pixel_variance(variance: f64) -> f64 {
+    variance / (255.0 * 255.0)
+}
+
+/// 心理声学模式、原始嵌入模式等只针对音频文件的选项
+fn reject_non_audio_option(media_type: &MediaType, flag: &str, present: bool) -> Result<()> {
+    if present && !matches!(media_type, MediaType::Audio) {
+        return Err(WatermarkError::InvalidArgument(
+            Message::FlagAudioOnly.format(&[&flag]),
+        ));
+    }
+    Ok(())
+}
+
+/// 省略 -l 按自描述头部提取时只支持图片，且不能同时指定头部无法记录的嵌入参数
+fn reject_header_conflicts(media_type: &MediaType, flags: &[(&str, bool)]) -> Result<()> {
+    if !matches!(media_type, MediaType::Image) {
+        return Err(WatermarkError::InvalidArgument(
+            Message::LengthRequired.text().to_string(),
+        ));
+    }
+    if let Some((flag, _)) = flags.iter().find(|(_, present)| *present) {
+        return Err(WatermarkError::InvalidArgument(
+            Message::FlagWithoutLength.format(&[&flag]),
+        ));
+    }
+    Ok(())
+}
+
+/// 按条带分段处理等只针对图片文件的选项
+fn reject_non_image_option(media_type: &MediaType, flag: &str, present: bool) -> Result<()> {
+    if present && !matches!(media_type, MediaType::Image) {
+        return Err(WatermarkError::InvalidArgument(
+            Message::FlagImagesOnly.format(&[&flag]),
+        ));
+    }
+    Ok(())
+}
+
+/// 准备嵌入的水印载荷：二进制模式读取文件原始内容，提供签名密钥时附加签名，提供口令时再加密，
+/// 最后按 `--ecc` 做纠错编码和比特交织
+fn prepare_payload(
+    watermark: &str,
+    binary: bool,
+    password: Option<&str>,
+    sign: Option<&str>,
+    ecc: Option<EccOptions>,
+) -> Result<Vec<u8>> {
+    let mut plaintext = if binary {
+        std::fs::read(watermark)?
+    } else {
+        watermark.as_bytes().to_vec()
+    };
+    if let Some(key) = sign {
+        plaintext = crypto::sign_payload(&plaintext, key);
+    }
+    let sealed = match password {
+        Some(password) => crypto::encrypt_payload(&plaintext, password)?,
+        None => plaintext,
+    };
+    Ok(match ecc {
+        Some(options) => options.encode(&sealed),
+        None => sealed,
+    })
+}
+
+/// `--ecc` 和 `--interleave` 组合成的纠错编码参数
+fn ecc_options(scheme: Option<EccScheme>, interleave: Option<usize>) -> Option<EccOptions> {
+    scheme.map(|scheme| EccOptions { scheme, interleave })
+}
+
+/// 纠错编码之前的载荷字节数：明文长度为 `length` 字节时含签名和加密的额外开销
+fn sealed_payload_length(length: usize, encrypted: bool, signed: bool) -> usize {
+    let signed_length = if signed {
+        length + crypto::SIGNATURE_LEN
+    } else {
+        length
+    };
+    if encrypted {
+        signed_length + crypto::ENCRYPTION_OVERHEAD
+    } else {
+        signed_length
+    }
+}
+
+/// 明文长度为 `length` 字节时实际嵌入的载荷字节数（含签名、加密和纠错编码的额外开销）
+fn encoded_payload_length(
+    length: usize,
+    encrypted: bool,
+    signed: bool,
+    ecc: Option<EccOptions>,
+) -> usize {
+    let sealed_length = sealed_payload_length(length, encrypted, signed);
+    match ecc {
+        Some(options) => options.encoded_len(sealed_length),
+        None => sealed_length,
+    }
+}
+
+/// 依次准备每个水印的载荷并首尾相接，返回拼接后的载荷和每个水印占用的字节数
+///
+/// 每个水印分别签名、加密和纠错编码，嵌入后按顺序占用容量中连续的一段。
+fn prepare_payloads(
+    watermarks: &[String],
+    binary: bool,
+    password: Option<&str>,
+    sign: Option<&str>,
+    ecc: Option<EccOptions>,
+) -> Result<(Vec<u8>, Vec<usize>)> {
+    let mut payload = Vec::new();
+    let mut lengths = Vec::with_capacity(watermarks.len());
+    for watermark in watermarks {
+        let part = prepare_payload(watermark, binary, password, sign, ecc)?;
+        lengths.push(part.len());
+        payload.extend(part);
+    }
+    Ok((payload, lengths))
+}
+
+/// 把提取到的原始载荷按每个水印的字节数切开，分别还原，同时返回纠错编码纠正的比特总数
+fn open_payloads(
+    raw: Vec<u8>,
+    lengths: &[usize],
+    password: Option<&str>,
+    sign: Option<&str>,
+    ecc: Option<EccOptions>,
+    binary: bool,
+) -> Result<(Vec<Vec<u8>>, usize)> {
+    let mut rest = raw.as_slice();
+    let mut bits_corrected = 0;
+    let payloads = lengths
+        .iter()
+        .map(|&length| {
+            let (part, tail) = rest.split_at(length.min(rest.len()));
+            rest = tail;
+            let (payload, corrected) = open_payload(part.to_vec(), password, sign, ecc, binary)?;
+            bits_corrected += corrected;
+            Ok(payload)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok((payloads, bits_corrected))
+}
+
+/// 还原提取到的原始载荷：按 `--ecc` 解交织并纠错解码，提供口令时解密，提供签名密钥时校验并去掉签名，
+/// 未提供口令的文本提取遇到加密载荷时报错；同时返回纠错编码纠正的比特数
+fn open_payload(
+    raw: Vec<u8>,
+    password: Option<&str>,
+    sign: Option<&str>,
+    ecc: Option<EccOptions>,
+    binary: bool,
+) -> Result<(Vec<u8>, usize)> {
+    let (raw, bits_corrected) = match ecc {
+        Some(options) => options.decode(&raw)?,
+        None => (raw, 0),
+    };
+    let opened = match password {
+        Some(password) => crypto::decrypt_payload(&raw, password)?,
+        // 加密载荷无法直接作为文本读取
+        None if !binary && crypto::is_encrypted(&raw) => {
+            return Err(WatermarkError::PasswordRequired)
+        }
+        None => raw,
+    };
+    let verified = match sign {
+        Some(key) => crypto::verify_payload(&opened, key)?,
+        None => opened,
+    };
+    Ok((verified, bits_corrected))
+}
+
+/// 提取结果的文本、十六进制和 base64 三种视图，由调用方按载荷类型选用
+///
+/// 二进制载荷的 `watermark` 为 base64 并附带 `encoding`，文本载荷必须是合法UTF-8，
+/// 在第一个空字节处截断；十六进制和 base64 视图保留全部字节。
+fn payload_views(extracted: &[u8], binary: bool) -> Result<serde_json::Value> {
+    let watermark_text = WatermarkUtils::bytes_to_string(extracted.to_vec()).ok();
+    let watermark_hex: String = extracted.iter().map(|b| format!("{b:02x}")).collect();
+    let watermark_base64 = BASE64_STANDARD.encode(extracted);
+
+    let mut views = if binary {
+        json!({ "watermark": watermark_base64, "encoding": "base64" })
+    } else {
+        json!({
+            "watermark": watermark_text
+                .clone()
+                .ok_or(WatermarkError::InvalidWatermark)?,
+        })
+    };
+    views["watermark_text"] = json!(watermark_text);
+    views["watermark_hex"] = json!(watermark_hex);
+    views["watermark_base64"] = json!(watermark_base64);
+    Ok(views)
+}
+
+/// `--output` 写出的内容：二进制载荷原样写出，文本载荷写出在第一个空字节处截断的文本
+fn saved_contents(extracted: &[u8], binary: bool) -> Result<Vec<u8>> {
+    if binary {
+        Ok(extracted.to_vec())
+    } else {
+        Ok(WatermarkUtils::bytes_to_string(extracted.to_vec())?.into_bytes())
+    }
+}
+
+/// 写入PSNR/SNR (dB)；水印没有改变任何样本时为无穷大，JSON 中写为 `null` 并附带 `"identical": true`
+fn insert_quality_db(target: &mut serde_json::Value, key: &str, db: f64) {
+    target[key] = json!(db);
+    if db.is_infinite() {
+        target["identical"] = json!(true);
+    }
+}
+
+/// 把 `extra` 对象的字段并入 `target` 对象
+fn merge_json(target: &mut serde_json::Value, extra: serde_json::Value) {
+    if let (Some(target), serde_json::Value::Object(extra)) = (target.as_object_mut(), extra) {
+        target.extend(extra);
+    }
+}
+
+/// `--show-capacity`：按媒体类型计算容量，扣除签名、加密和纠错编码开销后得到最多可嵌入的水印字节数
+fn embed_capacity_json(
+    media_type: &MediaType,
+    input: &Path,
+    algorithm: &(dyn WatermarkAlgorithm + Sync),
+    encrypted: bool,
+    signed: bool,
+    ecc: Option<EccOptions>,
+) -> Result<serde_json::Value> {
+    let capacity_bytes = match media_type {
+        MediaType::Image => ImageWatermarker::max_capacity_bytes(input, algorithm)?,
+        MediaType::Audio => AudioWatermarker::max_capacity_bytes(input, algorithm)?,
+        MediaType::Video => VideoWatermarker::max_capacity_bytes(input, algorithm)?,
+    };
+    let sealed_capacity = match ecc {
+        Some(options) => options.max_data_len(capacity_bytes),
+        None => capacity_bytes,
+    };
+    let max_watermark_bytes =
+        sealed_capacity.saturating_sub(sealed_payload_length(0, encrypted, signed));
+    eprintln!(
+        "{} {}",
+        "📏".blue(),
+        Message::CapacitySummary.format(&[&max_watermark_bytes, &capacity_bytes])
+    );
+    Ok(json!({
+        "capacity_bytes": capacity_bytes,
+        "max_watermark_bytes": max_watermark_bytes,
+    }))
+}
+
+/// 签名校验失败时在错误结果中写入 `"authentic": false`
+fn mark_signature_error(json_output: &mut serde_json::Value, error: &WatermarkError) {
+    if matches!(error, WatermarkError::SignatureInvalid) {
+        json_output["authentic"] = json!(false);
+    }
+}
+
+/// 指定了阈值时在结果中写入 `confidence_threshold` 和 `low_confidence`，
+/// 置信度低于阈值且 `warn` 为真时在 stderr 给出警告
+fn mark_low_confidence(
+    json_output: &mut serde_json::Value,
+    confidence: f64,
+    threshold: Option<f64>,
+    warn: bool,
+) {
+    let Some(threshold) = threshold else {
+        return;
+    };
+    let low = confidence < threshold;
+    json_output["confidence_threshold"] = json!(threshold);
+    json_output["low_confidence"] = json!(low);
+    if low && warn {
+        eprintln!(
+            "{} {}",
+            "⚠️".yellow(),
+            Message::LowConfidence.format(&[
+                &format!("{:.1}", confidence * 100.0),
+                &format!("{:.1}", threshold * 100.0)
+            ])
+        );
+    }
+}
+
+/// 把视频处理的进度事件画成终端进度条：总体步骤一条，逐帧处理时另起一条
+#[derive(Default)]
+struct TerminalProgress {
+    steps: RefCell<Option<ProgressBar>>,
+    frames: RefCell<Option<ProgressBar>>,
+}
+
+impl TerminalProgress {
+    fn handle(&self, event: ProgressEvent) {
+        match event {
+            ProgressEvent::Started { steps } => {
+                let bar = ProgressBar::new(steps as u64);
+                bar.set_style(
+                    ProgressStyle::default_bar()
+                        .template(
+                            "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}",
+                        )
+                        .unwrap()
+                        .progress_chars("█▉▊▋▌▍▎▏  "),
+                );
+                *self.steps.borrow_mut() = Some(bar);
+            }
+            ProgressEvent::Stage(message) => self.set_message(message),
+            ProgressEvent::Reassembling => {
+                self.set_message(Message::Reassembling.text().to_string())
+            }
+            ProgressEvent::StepCompleted => {
+                self.finish_frames();
+                if let Some(bar) = &*self.steps.borrow() {
+                    bar.inc(1);
+                }
+            }
+            ProgressEvent::FramesExtracted(_) => {}
+            ProgressEvent::FrameProcessed { total, .. } => {
+                let mut frames = self.frames.borrow_mut();
+                let bar = frames.get_or_insert_with(|| match total {
+                    Some(total) => {
+                        let bar = ProgressBar::new(total as u64);
+                        bar.set_style(
+                            ProgressStyle::default_bar()
+                                .template(&format!(
+                                    "{{spinner:.green}} [{{elapsed_precise}}] [{{bar:30.yellow/red}}] {{pos}}/{{len}} {}",
+                                    Message::Frames.text()
+                                ))
+                                .unwrap()
+                                .progress_chars("█▉▊▋▌▍▎▏  "),
+                        );
+                        bar
+                    }
+                    None => {
+                        let bar = ProgressBar::new_spinner();
+                        bar.set_style(
+                            ProgressStyle::default_spinner()
+                                .template(&format!(
+                                    "{{spinner:.green}} [{{elapsed_precise}}] {{pos}} {}",
+                                    Message::Frames.text()
+                                ))
+                                .unwrap(),
+                        );
+                        bar
+                    }
+                });
+                bar.inc(1);
+            }
+            ProgressEvent::Finished(message) => {
+                self.finish_frames();
+                if let Some(bar) = self.steps.borrow_mut().take() {
+                    bar.finish_with_message(message.green().bold().to_string());
+                }
+            }
+            ProgressEvent::Abandoned(message) => {
+                self.finish_frames();
+                if let Some(bar) = self.steps.borrow_mut().take() {
+                    bar.abandon_with_message(message);
+                }
+            }
+        }
+    }
+
+    fn set_message(&self, message: String) {
+        self.finish_frames();
+        if let Some(bar) = &*self.steps.borrow() {
+            bar.set_message(message);
+        }
+    }
+
+    /// 逐帧处理结束，收起帧进度条
+    fn finish_frames(&self) {
+        if let Some(bar) = self.frames.borrow_mut().take() {
+            bar.finish();
+        }
+    }
+}
+
+/// 从图片或音频中提取指定字节数的原始载荷及其平均置信度
+fn extract_media_bytes(
+    media_type: &MediaType,
+    input: &std::path::Path,
+    algorithm: &(dyn WatermarkAlgorithm + Sync),
+    byte_length: usize,
+) -> Result<(Vec<u8>, f64)> {
+    match media_type {
+        MediaType::Image => {
+            ImageWatermarker::extract_bytes_with_confidence(input, algorithm, byte_length)
+        }
+        MediaType::Audio => {
+            AudioWatermarker::extract_bytes_with_confidence(input, algorithm, byte_length)
+        }
+        MediaType::Video => Err(WatermarkError::InvalidArgument(
+            Message::VideoNoBytes.text().to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("seal_main_{}_{name}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// 带纹理的测试图片，DCT各块都有足够的中频能量
+    fn write_test_image(path: &Path) {
+        RgbImage::from_fn(256, 256, |x, y| {
+            let v = ((x * 7 + y * 13) % 64 + (x ^ y) % 32) as u8;
+            Rgb([60 + v, 90 + v / 2, 120 + v])
+        })
+        .save(path)
+        .unwrap();
+    }
+
+    fn embed(input: &Path, output: &Path, extra: &[&str]) -> Result<()> {
+        let args = [
+            "seal",
+            "embed",
+            "-i",
+            input.to_str().unwrap(),
+            "-o",
+            output.to_str().unwrap(),
+            "-w",
+            "hello",
+        ];
+        run(Cli::try_parse_from(args.iter().chain(extra)).unwrap())
+    }
+
+    #[test]
+    fn infinite_quality_is_reported_as_identical() {
+        let mut output = json!({});
+        insert_quality_db(&mut output, "psnr_db", 42.5);
+        assert_eq!(output, json!({ "psnr_db": 42.5 }));
+
+        let mut output = json!({});
+        insert_quality_db(&mut output, "snr_db", f64::INFINITY);
+        assert_eq!(output, json!({ "snr_db": null, "identical": true }));
+    }
+
+    #[test]
+    fn second_embed_requires_force() {
+        let dir = scratch_dir("reembed");
+        let (original, first, second) = (
+            dir.join("original.png"),
+            dir.join("first.png"),
+            dir.join("second.png"),
+        );
+        write_test_image(&original);
+
+        embed(&original, &first, &["--header"]).unwrap();
+        let err = embed(&first, &second, &[]).unwrap_err();
+        assert!(matches!(err, WatermarkError::InvalidArgument(_)));
+        assert!(!second.exists());
+
+        // 强制重新嵌入后新的头部和水印都能读出，不用任何参数即可提取
+        let args = [
+            "seal",
+            "embed",
+            "-i",
+            first.to_str().unwrap(),
+            "-o",
+            second.to_str().unwrap(),
+            "-w",
+            "world",
+            "--header",
+            "--force",
+        ];
+        run(Cli::try_parse_from(args).unwrap()).unwrap();
+        let cli = Cli::try_parse_from(["seal", "extract", "-i", second.to_str().unwrap()]).unwrap();
+        assert_eq!(execute(&cli).unwrap()["watermark"], "world");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn header_configures_extraction_without_any_flags() {
+        let dir = scratch_dir("header");
+        let (original, marked) = (dir.join("original.png"), dir.join("marked.png"));
+        write_test_image(&original);
+        embed(
+            &original,
+            &marked,
+            &[
+                "--header",
+                "--block-size",
+                "16",
+                "--ecc",
+                "hamming",
+                "--interleave",
+                "4",
+                "--channel",
+                "g",
+            ],
+        )
+        .unwrap();
+
+        let cli = Cli::try_parse_from(["seal", "extract", "-i", marked.to_str().unwrap()]).unwrap();
+        let result = execute(&cli).unwrap();
+        assert_eq!(result["watermark"], "hello");
+        assert_eq!(result["header"]["block_size"], 16);
+        assert_eq!(result["header"]["ecc"], "Hamming");
+        assert_eq!(result["header"]["interleave"], 4);
+        assert_eq!(result["header"]["channel"], "G");
+
+        // 没有头部时同样不带参数的提取无法确定长度，直接报错而不是输出乱码
+        let plain = dir.join("plain.png");
+        embed(&original, &plain, &["--block-size", "16"]).unwrap();
+        let cli = Cli::try_parse_from(["seal", "extract", "-i", plain.to_str().unwrap()]).unwrap();
+        assert!(execute(&cli).is_err());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn list_algorithms_reports_media_support_for_every_algorithm() {
+        let cli = Cli::try_parse_from(["seal", "list-algorithms"]).unwrap();
+        let result = execute(&cli).unwrap();
+        let algorithms = result["algorithms"].as_array().unwrap();
+        assert_eq!(algorithms.len(), Algorithm::value_variants().len());
+
+        let entry = |value: &str| {
+            algorithms
+                .iter()
+                .find(|entry| entry["value"] == value)
+                .unwrap_or_else(|| panic!("{value} missing from {result}"))
+        };
+        let all = json!(["Image", "Audio", "Video"]);
+        assert_eq!(entry("dct")["media_types"], all);
+        assert_eq!(entry("dct")["robust"], true);
+        assert_eq!(entry("dft")["media_types"], json!(["Image", "Video"]));
+        assert_eq!(entry("echo")["media_types"], json!(["Audio"]));
+        assert_eq!(entry("lsb")["media_types"], all);
+        assert_eq!(entry("lsb")["robust"], false);
+        assert_eq!(entry("lsb")["capacity_class"], "high");
+    }
+
+    #[test]
+    fn region_watermark_is_only_found_in_that_region() {
+        let dir = scratch_dir("region");
+        let (original, marked) = (dir.join("original.png"), dir.join("marked.png"));
+        write_test_image(&original);
+        embed(&original, &marked, &["--region", "64,64,128,128"]).unwrap();
+
+        let extract = |region: &str| {
+            let args = [
+                "seal",
+                "extract",
+                "-i",
+                marked.to_str().unwrap(),
+                "-l",
+                "5",
+                "--region",
+                region,
+            ];
+            execute(&Cli::try_parse_from(args).unwrap())
+                .ok()
+                .map(|result| result["watermark"].clone())
+        };
+        assert_eq!(extract("64,64,128,128"), Some(json!("hello")));
+        // 角落里同样大小的区域与嵌入区域只有四分之一重叠，各比特对应的块都换了位置，读不出水印
+        assert_ne!(extract("0,0,128,128"), Some(json!("hello")));
+        assert_ne!(extract("128,128,128,128"), Some(json!("hello")));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn percent_strength_embeds_the_same_bytes_as_a_fraction() {
+        assert_eq!(
+            seal::cli::parse_strength("10%"),
+            seal::cli::parse_strength("0.1")
+        );
+
+        let dir = scratch_dir("percent");
+        let (original, percent, fraction) = (
+            dir.join("original.png"),
+            dir.join("percent.png"),
+            dir.join("fraction.png"),
+        );
+        write_test_image(&original);
+        embed(&original, &percent, &["-s", "10%"]).unwrap();
+        embed(&original, &fraction, &["-s", "0.1"]).unwrap();
+        assert_eq!(
+            std::fs::read(&percent).unwrap(),
+            std::fs::read(&fraction).unwrap()
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn min_psnr_rejects_a_strong_embed_on_a_flat_image_without_writing() {
+        let dir = scratch_dir("min_psnr");
+        let (flat, output) = (dir.join("flat.png"), dir.join("output.png"));
+        RgbImage::from_pixel(256, 256, Rgb([128, 128, 128]))
+            .save(&flat)
+            .unwrap();
+
+        let err = embed(&flat, &output, &["-s", "1.0", "--min-psnr", "45"]).unwrap_err();
+        assert!(matches!(
+            err,
+            WatermarkError::QualityTooLow { measured, floor } if measured < floor && floor == 45.0
+        ));
+        assert!(!output.exists());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn binary_payload_round_trips_with_matching_base64_view() {
+        let dir = scratch_dir("binary");
+        let (original, marked) = (dir.join("original.png"), dir.join("marked.png"));
+        write_test_image(&original);
+        let mut blob: Vec<u8> = (0..32u32).map(|i| (i * 97 + 13) as u8).collect();
+        blob[3] = 0x00;
+        blob[10] = 0xff;
+        assert!(String::from_utf8(blob.clone()).is_err());
+
+        let algorithm = DctWatermark::new();
+        ImageWatermarker::embed_bytes(&original, &marked, &blob, &algorithm, 0.1).unwrap();
+        let extracted = ImageWatermarker::extract_bytes(&marked, &algorithm, blob.len()).unwrap();
+        assert_eq!(extracted, blob);
+
+        let views = payload_views(&extracted, true).unwrap();
+        let encoded = BASE64_STANDARD.encode(&blob);
+        assert_eq!(views["watermark"], encoded);
+        assert_eq!(views["encoding"], "base64");
+        assert_eq!(views["watermark_base64"], encoded);
+        assert_eq!(views["watermark_text"], serde_json::Value::Null);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn metadata_and_report_respect_no_clobber() {
+        let dir = scratch_dir("sidecars");
+        let (original, marked) = (dir.join("original.png"), dir.join("marked.png"));
+        let (metadata, report) = (
+            dir.join("marked.png.metadata.json"),
+            dir.join("marked.png.embed.json"),
+        );
+        write_test_image(&original);
+
+        // 已存在的元数据文件在嵌入之前就被拒绝，不会写出任何结果
+        std::fs::write(&metadata, "keep").unwrap();
+        let err = embed(&original, &marked, &["--metadata", "--report"]).unwrap_err();
+        assert!(
+            matches!(&err, WatermarkError::Io(e) if e.kind() == std::io::ErrorKind::AlreadyExists)
+        );
+        assert!(!marked.exists());
+        assert!(!report.exists());
+        assert_eq!(std::fs::read_to_string(&metadata).unwrap(), "keep");
+
+        embed(
+            &original,
+            &marked,
+            &["--metadata", "--report", "--overwrite"],
+        )
+        .unwrap();
+        let sidecar: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&metadata).unwrap()).unwrap();
+        for key in [
+            "tool",
+            "timestamp",
+            "input_sha256",
+            "algorithm",
+            "strength",
+            "channel",
+        ] {
+            assert!(sidecar.get(key).is_some(), "missing {key}");
+        }
+        assert_eq!(
+            sidecar["output_sha256"],
+            crypto::file_sha256_hex(&marked).unwrap()
+        );
+        assert_eq!(sidecar["watermarks"][0]["text"], "hello");
+        assert!(report.exists());
+
+        // 报告文件同样受保护
+        let again = dir.join("again.png");
+        std::fs::write(dir.join("again.png.embed.json"), "keep").unwrap();
+        let err = embed(&original, &again, &["--report"]).unwrap_err();
+        assert!(
+            matches!(&err, WatermarkError::Io(e) if e.kind() == std::io::ErrorKind::AlreadyExists)
+        );
+        assert!(!again.exists());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn block_size_round_trips_at_8_and_16() {
+        let dir = scratch_dir("block_size");
+        let original = dir.join("original.png");
+        write_test_image(&original);
+
+        let extract = |input: &Path, block_size: &str| {
+            let cli = Cli::try_parse_from([
+                "seal",
+                "extract",
+                "-i",
+                input.to_str().unwrap(),
+                "-l",
+                "5",
+                "--block-size",
+                block_size,
+            ])
+            .unwrap();
+            execute(&cli).ok().map(|result| result["watermark"].clone())
+        };
+        for block_size in ["8", "16"] {
+            let marked = dir.join(format!("marked_{block_size}.png"));
+            embed(&original, &marked, &["--block-size", block_size]).unwrap();
+            assert_eq!(extract(&marked, block_size), Some(json!("hello")));
+        }
+
+        // 按16x16嵌入的水印用8x8读取时块和系数位置都对不上
+        assert_ne!(
+            extract(&dir.join("marked_16.png"), "8"),
+            Some(json!("hello"))
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn batch_embeds_every_png_and_reports_failures() {
+        let dir = scratch_dir("batch");
+        let (inputs, outputs) = (dir.join("in"), dir.join("out"));
+        std::fs::create_dir_all(&inputs).unwrap();
+        for name in ["a.png", "b.png", "c.png"] {
+            write_test_image(&inputs.join(name));
+        }
+        std::fs::write(inputs.join("notes.txt"), "not an image").unwrap();
+
+        embed(&inputs.join("*.png"), &outputs, &[]).unwrap();
+        for name in ["a.png", "b.png", "c.png"] {
+            let cli = Cli::try_parse_from([
+                "seal",
+                "extract",
+                "-i",
+                outputs.join(name).to_str().unwrap(),
+                "-l",
+                "5",
+            ])
+            .unwrap();
+            assert_eq!(execute(&cli).unwrap()["watermark"], "hello");
+        }
+        assert!(!outputs.join("notes.txt").exists());
+
+        // 一个文件失败时其余文件照常处理，整个批处理以 BatchFailed 结束
+        std::fs::write(inputs.join("b.png"), "corrupt").unwrap();
+        std::fs::remove_dir_all(&outputs).unwrap();
+        let err = embed(&inputs.join("*.png"), &outputs, &[]).unwrap_err();
+        assert!(matches!(
+            err,
+            WatermarkError::BatchFailed {
+                failed: 1,
+                total: 3
+            }
+        ));
+        assert!(outputs.join("a.png").exists());
+        assert!(outputs.join("c.png").exists());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn extract_result_renders_in_every_format() {
+        let dir = scratch_dir("format");
+        let (original, marked) = (dir.join("original.png"), dir.join("marked.png"));
+        write_test_image(&original);
+        embed(&original, &marked, &[]).unwrap();
+        let cli =
+            Cli::try_parse_from(["seal", "extract", "-i", marked.to_str().unwrap(), "-l", "5"])
+                .unwrap();
+        let result = execute(&cli).unwrap();
+
+        // JSON：单行，可以原样解析回结果
+        let json = render_result(OutputFormat::Json, &result, true);
+        assert_eq!(json.len(), 1);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json[0]).unwrap(),
+            result
+        );
+
+        // 文本：只有水印本身
+        assert_eq!(render_result(OutputFormat::Text, &result, true), ["hello"]);
+
+        // CSV：固定的表头加一行，追加到已有文件时不带表头
+        let csv = render_result(OutputFormat::Csv, &result, true);
+        assert_eq!(csv.len(), 2);
+        assert_eq!(
+            csv[0],
+            "status,action,media_type,input,output,algorithm,watermark,confidence,integrity,message"
+        );
+        let row: Vec<&str> = csv[1].split(',').collect();
+        assert_eq!(row.len(), CSV_COLUMNS.len());
+        assert_eq!(&row[..3], ["success", "extract", "Image"]);
+        assert_eq!(row[6], "hello");
+        assert_eq!(row[9], "");
+        assert_eq!(render_result(OutputFormat::Csv, &result, false), &csv[1..]);
+
+        // 批处理的结果数组：文本逐行带文件名，CSV每个文件一行
+        let batch = json!([result, { "status": "error", "action": "extract", "input": "b.png", "message": "x, y" }]);
+        let text = render_result(OutputFormat::Text, &batch, false);
+        assert_eq!(text[0], format!("{}: hello", marked.display()));
+        assert_eq!(text[1], "b.png: 错误: x, y");
+        let csv = render_result(OutputFormat::Csv, &batch, false);
+        assert_eq!(csv.len(), 2);
+        assert!(csv[1].ends_with(",\"x, y\""));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
 }
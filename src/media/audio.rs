@@ -1,11 +1,53 @@
+use crate::cli::Algorithm;
 use crate::error::{Result, WatermarkError};
-use crate::watermark::dct::DctWatermark;
-use crate::watermark::{WatermarkAlgorithm, WatermarkUtils};
+use crate::i18n::Message;
+use crate::media::diff::{AudioDiff, ENVELOPE_POINTS};
+use crate::media::ffmpeg::{self, TempDir};
+use crate::media::sync;
+use crate::media::{EmbedOptions, ExtractionResult, ImageWatermarker};
+use crate::watermark::{crypto, WatermarkAlgorithm, WatermarkUtils};
+use colored::Colorize;
 use ffmpeg_sidecar::command::FfmpegCommand;
+use ffmpeg_sidecar::event::FfmpegEvent;
 use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
 use ndarray::Array2;
 use std::path::Path;
 
+/// DCT、LSB等通用算法嵌入音频时的默认强度系数，实际强度为 `strength * 系数`
+pub const DEFAULT_AUDIO_STRENGTH_SCALE: f64 = 0.05;
+
+/// 按系数折算后的有效强度上限，更高的强度会带来明显可闻的失真
+const MAX_EFFECTIVE_AUDIO_STRENGTH: f64 = 0.25;
+
+/// ffmpeg输出水印处理所用WAV的参数：WAV容器中的16位PCM，保留源采样率和声道数
+///
+/// 视频提取音轨时使用同一组参数，提取结果由 `read_audio` 直接读取，不会再转换一次。
+pub(crate) const CANONICAL_WAV_ARGS: [&str; 4] = ["-acodec", "pcm_s16le", "-f", "wav"];
+
+/// 音频嵌入的可选参数
+#[derive(Debug, Clone)]
+pub struct AudioEmbedOptions {
+    /// SNR下限 (dB)，嵌入后低于此值则中止且不写出文件
+    pub min_snr: Option<f64>,
+    /// 原始模式：跳过限幅、滤波和淡入淡出等所有后处理
+    pub raw: bool,
+    /// 通用算法的强度系数，嘈杂的音乐可调高，安静的人声可调低；STFT和回声隐藏不使用
+    pub strength_scale: f64,
+    /// 叠加周期性的同步标记，提取时可在开头被裁剪或插入样本后重新对齐
+    pub sync: bool,
+}
+
+impl Default for AudioEmbedOptions {
+    fn default() -> Self {
+        Self {
+            min_snr: None,
+            raw: false,
+            strength_scale: DEFAULT_AUDIO_STRENGTH_SCALE,
+            sync: false,
+        }
+    }
+}
+
 /// 音频水印处理器
 pub struct AudioWatermarker;
 
@@ -20,7 +62,7 @@ impl AudioWatermarker {
     /// * `strength` - 水印强度
     ///
     /// # 返回
-    /// * `Ok(())` - 成功嵌入水印
+    /// * `Ok(f64)` - 成功嵌入水印，返回水印音频相对原始音频的SNR (dB)，在重新编码之前计算
     /// * `Err(WatermarkError)` - 嵌入水印失败
     pub fn embed_watermark<P: AsRef<Path>>(
         input_path: P,
@@ -28,79 +70,392 @@ impl AudioWatermarker {
         watermark_text: &str,
         algorithm: &dyn WatermarkAlgorithm,
         strength: f64,
-    ) -> Result<()> {
+    ) -> Result<f64> {
+        Self::embed_bytes(
+            input_path,
+            output_path,
+            watermark_text.as_bytes(),
+            algorithm,
+            strength,
+        )
+    }
+
+    /// # 按嵌入选项创建算法并嵌入水印文本到音频中，图片和视频的选项被忽略
+    ///
+    /// # 返回
+    /// * `Ok(f64)` - 水印音频相对原始音频的SNR (dB)
+    pub fn embed_with_options<P: AsRef<Path>>(
+        input_path: P,
+        output_path: P,
+        watermark_text: &str,
+        algorithm: Algorithm,
+        options: &EmbedOptions,
+    ) -> Result<f64> {
+        let algorithm = options.create_algorithm(algorithm);
+        Self::embed_bytes_with_options(
+            input_path,
+            output_path,
+            watermark_text.as_bytes(),
+            algorithm.as_ref(),
+            options.strength,
+            &options.audio,
+        )
+    }
+
+    /// # 嵌入任意字节载荷到音频中
+    ///
+    /// # 返回
+    /// * `Ok(f64)` - 水印音频相对原始音频的SNR (dB)
+    pub fn embed_bytes<P: AsRef<Path>>(
+        input_path: P,
+        output_path: P,
+        payload: &[u8],
+        algorithm: &dyn WatermarkAlgorithm,
+        strength: f64,
+    ) -> Result<f64> {
+        Self::embed_bytes_with_options(
+            input_path,
+            output_path,
+            payload,
+            algorithm,
+            strength,
+            &AudioEmbedOptions::default(),
+        )
+    }
+
+    /// # 嵌入字节载荷到音频中，若SNR低于下限则中止且不写出文件
+    ///
+    /// # 返回
+    /// * `Ok(f64)` - 水印音频相对原始音频的SNR (dB)
+    /// * `Err(WatermarkError::QualityTooLow)` - SNR低于下限
+    pub fn embed_bytes_with_min_snr<P: AsRef<Path>>(
+        input_path: P,
+        output_path: P,
+        payload: &[u8],
+        algorithm: &dyn WatermarkAlgorithm,
+        strength: f64,
+        min_snr: f64,
+    ) -> Result<f64> {
+        Self::embed_bytes_with_options(
+            input_path,
+            output_path,
+            payload,
+            algorithm,
+            strength,
+            &AudioEmbedOptions {
+                min_snr: Some(min_snr),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// # 原始模式嵌入字节载荷，跳过所有限幅、滤波和淡入淡出等后处理
+    ///
+    /// 输出只包含算法本身对样本的修改，相同输入和参数总是得到相同的结果，
+    /// 便于做可复现的往返验证。设置了 `min_snr` 时同样执行质量门限检查。
+    ///
+    /// # 返回
+    /// * `Ok(f64)` - 水印音频相对原始音频的SNR (dB)
+    pub fn embed_bytes_raw<P: AsRef<Path>>(
+        input_path: P,
+        output_path: P,
+        payload: &[u8],
+        algorithm: &dyn WatermarkAlgorithm,
+        strength: f64,
+        min_snr: Option<f64>,
+    ) -> Result<f64> {
+        Self::embed_bytes_with_options(
+            input_path,
+            output_path,
+            payload,
+            algorithm,
+            strength,
+            &AudioEmbedOptions {
+                min_snr,
+                raw: true,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// # 按给定的可选参数嵌入字节载荷到音频中
+    ///
+    /// # 返回
+    /// * `Ok(f64)` - 水印音频相对原始音频的SNR (dB)
+    pub fn embed_bytes_with_options<P: AsRef<Path>>(
+        input_path: P,
+        output_path: P,
+        payload: &[u8],
+        algorithm: &dyn WatermarkAlgorithm,
+        strength: f64,
+        options: &AudioEmbedOptions,
+    ) -> Result<f64> {
+        WatermarkUtils::validate_strength(strength)?;
+        let scale = options.strength_scale;
+        if !scale.is_finite() || scale <= 0.0 {
+            return Err(WatermarkError::InvalidArgument(
+                Message::AudioStrengthScaleRange.format(&[&scale]),
+            ));
+        }
+        let (min_snr, raw) = (options.min_snr, options.raw);
+
         let input_path = input_path.as_ref();
         let output_path = output_path.as_ref();
+        // 在耗时的处理之前确认输出格式受支持
+        let codec_args = Self::output_codec_args(output_path)?;
+        // 原始模式承诺输出只含算法本身的修改，有损编码会把这些修改改得面目全非
+        if raw && codec_args.is_some_and(|args| !args.contains(&"flac")) {
+            return Err(WatermarkError::InvalidArgument(
+                Message::RawOutputLossless.format(&[&output_path.display()]),
+            ));
+        }
 
         // 创建临时目录
-        let temp_dir = std::env::temp_dir().join(format!("audio_watermark_{}", std::process::id()));
-        std::fs::create_dir_all(&temp_dir)?;
+        let temp_dir = TempDir::new("audio_watermark")?;
 
-        // 使用ffmpeg转换为统一格式（16bit 44.1kHz 单声道 WAV）
-        let normalized_audio = temp_dir.join("normalized.wav");
-        Self::normalize_audio_format(input_path, &normalized_audio)?;
+        // WAV输出沿用源文件的声道数、采样率、位深度和样本格式
+        let (spec, interleaved) = Self::read_audio(input_path, &temp_dir.join("normalized.wav"))?;
 
-        // 读取标准化后的音频
-        let mut reader = WavReader::open(&normalized_audio)?;
-        let spec = reader.spec();
+        if spec.sample_format == SampleFormat::Int && spec.bits_per_sample == 8 {
+            eprintln!("{} {}", "⚠️".yellow(), Message::EightBitPcm.text());
+        }
+        // FLAC只能存整数样本，浮点源文件写成FLAC时会被量化
+        if raw && codec_args.is_some() && spec.sample_format == SampleFormat::Float {
+            return Err(WatermarkError::InvalidArgument(
+                Message::RawFloatWavOnly.text().to_string(),
+            ));
+        }
 
-        // 读取音频样本
-        let samples: Vec<f64> = reader
-            .samples::<i16>()
-            .collect::<std::result::Result<Vec<_>, _>>()?
-            .into_iter()
-            .map(|s| s as f64 / i16::MAX as f64)
-            .collect();
+        // 多声道时每个声道各自嵌入一份完整的水印，提取时在声道间投票
+        let channels = spec.channels.max(1) as usize;
+        let frames = interleaved.len() / channels;
+
+        if options.sync {
+            // 同步标记相当于一层低电平噪声，逐样本精确保留的算法经不起叠加
+            if !algorithm.robust() {
+                return Err(WatermarkError::InvalidArgument(
+                    Message::SyncNotSupported.format(&[&algorithm.name()]),
+                ));
+            }
+            if frames < 2 * sync::PERIOD {
+                return Err(WatermarkError::InvalidArgument(
+                    Message::AudioTooShortForSync.format(&[&(2 * sync::PERIOD), &frames]),
+                ));
+            }
+        }
+
+        // 将水印载荷转换为比特
+        let watermark_bits = WatermarkUtils::bytes_to_bits(payload);
+        WatermarkUtils::ensure_capacity(
+            watermark_bits.len(),
+            Self::samples_capacity(frames, algorithm)?,
+        )?;
+
+        // 使用音频专用DCT算法，确保无噪声；STFT算法的修改量由频带能量决定，
+        // 回声隐藏的强度直接对应回声幅度，两者都使用原始强度
+        let ultra_low_strength = if matches!(algorithm.name(), "STFT" | "ECHO") {
+            strength
+        } else {
+            (strength * scale).min(MAX_EFFECTIVE_AUDIO_STRENGTH)
+        };
 
-        // 将水印文本转换为比特
-        let watermark_bits = WatermarkUtils::string_to_bits(watermark_text);
+        let mut output_samples = interleaved.clone();
+        for channel in 0..channels {
+            let samples = Self::channel_samples(&interleaved, channels, channel);
+            let watermarked_samples = Self::ultra_gentle_embed(
+                &samples,
+                &watermark_bits,
+                algorithm,
+                ultra_low_strength,
+                raw,
+            )?;
+            for (frame, sample) in watermarked_samples.into_iter().take(frames).enumerate() {
+                output_samples[frame * channels + channel] = sample;
+            }
+        }
 
-        // 使用音频专用DCT算法，确保无噪声
-        let ultra_low_strength = strength * 0.05; // 5%的强度，配合音频专用算法
+        // 同步标记在水印和后处理之后叠加，各声道相同，声道平均后仍然完整
+        if options.sync {
+            for (frame, marker) in sync::pattern(frames).into_iter().enumerate() {
+                for sample in &mut output_samples[frame * channels..(frame + 1) * channels] {
+                    *sample += marker;
+                }
+            }
+        }
 
-        let watermarked_samples =
-            Self::ultra_gentle_embed(&samples, &watermark_bits, algorithm, ultra_low_strength)?;
+        // 质量门限：在重新编码之前检查，反映的是水印本身带来的失真
+        let snr = Self::calculate_snr(&interleaved, &output_samples);
+        if let Some(floor) = min_snr {
+            if snr < floor {
+                return Err(WatermarkError::QualityTooLow {
+                    measured: snr,
+                    floor,
+                });
+            }
+        }
 
         // 创建临时水印音频文件
         let watermarked_temp = temp_dir.join("watermarked.wav");
-        Self::write_wav(&watermarked_temp, &watermarked_samples, spec)?;
+        Self::write_wav(&watermarked_temp, &output_samples, spec)?;
 
-        // 使用ffmpeg转换回原始格式
-        Self::convert_to_original_format(
-            &watermarked_temp,
-            &input_path.to_path_buf(),
-            &output_path.to_path_buf(),
-        )?;
+        // 按输出扩展名编码，WAV直接复制
+        Self::convert_to_output_format(&watermarked_temp, output_path)?;
 
-        // 清理临时文件
-        std::fs::remove_dir_all(&temp_dir)?;
+        Ok(snr)
+    }
 
-        Ok(())
+    /// 比较原始音频和水印音频，给出按窗口计算的样本差包络和SNR
+    ///
+    /// 两者的声道数和采样率必须相同，多声道时比较声道平均后的样本；
+    /// 长度不同时按较短的一段比较，包络最多 `ENVELOPE_POINTS` 个窗口。
+    pub fn diff_audio<P: AsRef<Path>>(original_path: P, watermarked_path: P) -> Result<AudioDiff> {
+        let temp_dir = TempDir::new("audio_diff")?;
+        let (original_spec, original) =
+            Self::read_audio(original_path.as_ref(), &temp_dir.join("original.wav"))?;
+        let (watermarked_spec, watermarked) =
+            Self::read_audio(watermarked_path.as_ref(), &temp_dir.join("watermarked.wav"))?;
+        if (original_spec.channels, original_spec.sample_rate)
+            != (watermarked_spec.channels, watermarked_spec.sample_rate)
+        {
+            return Err(WatermarkError::InvalidArgument(
+                Message::AudioFormatsDiffer.format(&[
+                    &original_spec.channels,
+                    &original_spec.sample_rate,
+                    &watermarked_spec.channels,
+                    &watermarked_spec.sample_rate,
+                ]),
+            ));
+        }
+
+        let original = Self::downmix(&original, original_spec.channels);
+        let watermarked = Self::downmix(&watermarked, watermarked_spec.channels);
+        let frames = original.len().min(watermarked.len());
+        let (original, watermarked) = (&original[..frames], &watermarked[..frames]);
+        let difference: Vec<f64> = original
+            .iter()
+            .zip(watermarked)
+            .map(|(&o, &w)| w - o)
+            .collect();
+
+        let window = frames.div_ceil(ENVELOPE_POINTS).max(1);
+        let envelope = difference
+            .chunks(window)
+            .map(|chunk| (chunk.iter().map(|d| d * d).sum::<f64>() / chunk.len() as f64).sqrt())
+            .collect();
+
+        Ok(AudioDiff {
+            snr: Self::calculate_snr(original, watermarked),
+            envelope,
+            window,
+            sample_rate: original_spec.sample_rate,
+            max_difference: difference.iter().fold(0.0, |max, d| d.abs().max(max)),
+        })
+    }
+
+    /// 计算原始样本与水印样本之间的信噪比 (dB)，两者完全相同时返回正无穷
+    pub fn calculate_snr(original: &[f64], watermarked: &[f64]) -> f64 {
+        let (signal_power, noise_power) = original
+            .iter()
+            .zip(watermarked.iter())
+            .fold((0.0, 0.0), |(signal, noise), (&o, &w)| {
+                (signal + o * o, noise + (o - w) * (o - w))
+            });
+
+        if noise_power == 0.0 {
+            f64::INFINITY
+        } else {
+            10.0 * (signal_power / noise_power).log10()
+        }
     }
 
-    /// 将音频标准化为统一格式
+    /// 将音频标准化为16位PCM WAV，不改变采样率和声道数
     fn normalize_audio_format<P: AsRef<Path>>(input_path: P, output_path: P) -> Result<()> {
         let mut command = FfmpegCommand::new();
         command
             .input(input_path.as_ref().to_str().unwrap())
-            .args(["-ac", "1"]) // 转换为单声道
-            .args(["-ar", "44100"]) // 采样率44.1kHz
-            .args(["-acodec", "pcm_s16le"]) // 16位PCM
+            .args(CANONICAL_WAV_ARGS)
             .args(["-y"]) // 覆盖输出文件
             .output(output_path.as_ref().to_str().unwrap());
 
-        let mut child = command.spawn().map_err(WatermarkError::Io)?;
-        let status = child.wait().map_err(WatermarkError::Io)?;
+        let status = ffmpeg::run(&mut command)?;
 
         if !status.success() {
             return Err(WatermarkError::ProcessingError(
-                "音频格式标准化失败".to_string(),
+                Message::AudioNormalizeFailed.text().to_string(),
             ));
         }
 
         Ok(())
     }
 
+    /// 读取音频的格式和样本：WAV直接读取，其他格式先用ffmpeg转换为16bit PCM WAV，
+    /// 保存在 `normalized_path`，保留源采样率和声道数
+    ///
+    /// 视频音轨按 `CANONICAL_WAV_ARGS` 提取，总是走直接读取的分支，整个流程只经过一次ffmpeg。
+    fn read_audio(input_path: &Path, normalized_path: &Path) -> Result<(WavSpec, Vec<f64>)> {
+        if Self::get_audio_info(input_path).is_ok() {
+            return Self::read_wav(input_path);
+        }
+        Self::normalize_audio_format(input_path, normalized_path)?;
+        Self::read_wav(normalized_path)
+    }
+
+    /// 读取WAV文件，返回其格式和交错排列、归一化到 [-1.0, 1.0] 的样本
+    ///
+    /// 整数样本按位深度的最大值归一化，与 `write_wav` 的换算一致。
+    fn read_wav(path: &Path) -> Result<(WavSpec, Vec<f64>)> {
+        let mut reader = WavReader::open(path)?;
+        let spec = reader.spec();
+        let samples = match spec.sample_format {
+            SampleFormat::Float => reader
+                .samples::<f32>()
+                .map(|s| s.map(f64::from))
+                .collect::<std::result::Result<Vec<_>, _>>()?,
+            SampleFormat::Int => {
+                let max = Self::int_sample_max(spec.bits_per_sample)?;
+                reader
+                    .samples::<i32>()
+                    .map(|s| s.map(|s| s as f64 / max))
+                    .collect::<std::result::Result<Vec<_>, _>>()?
+            }
+        };
+        Ok((spec, samples))
+    }
+
+    /// 整数样本在给定位深度下的最大值
+    fn int_sample_max(bits_per_sample: u16) -> Result<f64> {
+        match bits_per_sample {
+            8 => Ok(i8::MAX as f64),
+            16 => Ok(i16::MAX as f64),
+            24 => Ok(((1 << 23) - 1) as f64),
+            32 => Ok(i32::MAX as f64),
+            _ => Err(WatermarkError::UnsupportedFormat(
+                Message::UnsupportedBitDepth.format(&[&bits_per_sample]),
+            )),
+        }
+    }
+
+    /// 把交错排列的多声道样本平均为单声道，单声道时原样返回
+    fn downmix(interleaved: &[f64], channels: u16) -> Vec<f64> {
+        if channels <= 1 {
+            return interleaved.to_vec();
+        }
+        interleaved
+            .chunks_exact(channels as usize)
+            .map(|frame| frame.iter().sum::<f64>() / channels as f64)
+            .collect()
+    }
+
+    /// 从交错排列的样本中取出第 `channel` 个声道
+    fn channel_samples(interleaved: &[f64], channels: usize, channel: usize) -> Vec<f64> {
+        interleaved
+            .iter()
+            .skip(channel)
+            .step_by(channels)
+            .copied()
+            .collect()
+    }
+
     /// 准备样本以适应水印算法
     fn prepare_samples_for_watermarking(
         samples: &[f64],
@@ -109,17 +464,8 @@ impl AudioWatermarker {
         let len = samples.len();
         let matrix_size = (len as f64).sqrt().ceil() as usize;
 
-        let required_size = match algorithm.name() {
-            name if name.contains("DCT") => {
-                let adjusted_size = matrix_size.div_ceil(8) * 8;
-                adjusted_size * adjusted_size
-            }
-            name if name.contains("DWT") => {
-                let adjusted_size = matrix_size.next_power_of_two();
-                adjusted_size * adjusted_size
-            }
-            _ => return Err(WatermarkError::Algorithm("未知算法".to_string())),
-        };
+        let adjusted_size = Self::aligned_matrix_size(matrix_size, algorithm);
+        let required_size = adjusted_size * adjusted_size;
 
         let mut prepared_samples = samples.to_vec();
 
@@ -138,66 +484,49 @@ impl AudioWatermarker {
         Ok(prepared_samples)
     }
 
-    /// 转换回目标格式
-    fn convert_to_original_format<P: AsRef<Path>>(
-        watermarked_path: P,
-        _original_path: P,
-        output_path: P,
-    ) -> Result<()> {
+    /// 输出扩展名对应的ffmpeg编码参数，WAV返回 `None` 表示直接复制，不支持的扩展名报错
+    ///
+    /// 编码时沿用临时WAV的采样率和声道数（即源文件的），有损格式用较高的比特率减少水印损失。
+    fn output_codec_args(output_path: &Path) -> Result<Option<&'static [&'static str]>> {
         let output_ext = output_path
-            .as_ref()
             .extension()
             .and_then(|s| s.to_str())
             .map(|s| s.to_ascii_lowercase())
             .unwrap_or_default();
 
-        let mut command = FfmpegCommand::new();
-        command.input(watermarked_path.as_ref().to_str().unwrap());
-
-        // 根据输出文件扩展名选择合适的编码参数
         match output_ext.as_str() {
-            "wav" | "wave" => {
-                // WAV: 保持无损PCM格式
-                command.args(["-acodec", "pcm_s16le"]);
-            }
-            "mp3" => {
-                // MP3: 使用较高质量设置以减少水印损失
-                command
-                    .args(["-acodec", "libmp3lame"])
-                    .args(["-b:a", "320k"]) // 最高常用比特率
-                    .args(["-ac", "1"]) // 单声道（与嵌入时一致）
-                    .args(["-ar", "44100"]); // 44.1kHz（与嵌入时一致）
-            }
-            "aac" | "m4a" => {
-                // AAC: 使用较高质量设置
-                command
-                    .args(["-acodec", "aac"])
-                    .args(["-b:a", "320k"]) // 高比特率
-                    .args(["-ac", "1"]) // 单声道
-                    .args(["-ar", "44100"]); // 44.1kHz
-            }
-            "flac" => {
-                // FLAC: 无损压缩
-                command.args(["-acodec", "flac"]);
-            }
-            _ => {
-                // 其他格式: 让ffmpeg自动选择合适的编码器
-                eprintln!("⚠️ 未知音频格式 '{}', 使用默认编码", output_ext);
-            }
+            // 临时文件已是与源文件采样率、声道数相同的PCM（WAV源文件的位深度也相同），无需重新编码
+            "wav" | "wave" => Ok(None),
+            "mp3" => Ok(Some(&["-acodec", "libmp3lame", "-b:a", "320k"])),
+            "aac" | "m4a" => Ok(Some(&["-acodec", "aac", "-b:a", "320k"])),
+            // FLAC: 无损压缩
+            "flac" => Ok(Some(&["-acodec", "flac"])),
+            _ => Err(WatermarkError::UnsupportedFormat(
+                Message::UnsupportedAudioOutput.format(&[&output_ext]),
+            )),
         }
+    }
+
+    /// 把水印后的WAV转换为输出扩展名对应的格式
+    fn convert_to_output_format(watermarked_path: &Path, output_path: &Path) -> Result<()> {
+        let Some(codec_args) = Self::output_codec_args(output_path)? else {
+            std::fs::copy(watermarked_path, output_path)?;
+            return Ok(());
+        };
 
+        let mut command = FfmpegCommand::new();
         command
+            .input(watermarked_path.to_str().unwrap())
+            .args(codec_args)
             .args(["-y"]) // 覆盖输出文件
-            .output(output_path.as_ref().to_str().unwrap());
+            .output(output_path.to_str().unwrap());
 
-        let mut child = command.spawn().map_err(WatermarkError::Io)?;
-        let status = child.wait().map_err(WatermarkError::Io)?;
+        let status = ffmpeg::run(&mut command)?;
 
         if !status.success() {
-            return Err(WatermarkError::ProcessingError(format!(
-                "音频格式转换失败 (目标格式: {})",
-                output_ext
-            )));
+            return Err(WatermarkError::ProcessingError(
+                Message::AudioConvertFailed.format(&[&output_path.display()]),
+            ));
         }
 
         Ok(())
@@ -211,45 +540,169 @@ impl AudioWatermarker {
     /// * `watermark_length` - 期望的水印长度
     ///
     /// # 返回
-    /// * `Ok(String)` - 提取的水印文本
+    /// * `Ok(ExtractionResult)` - 提取的水印文本及其置信度
     /// * `Err(WatermarkError)` - 提取水印失败
     pub fn extract_watermark<P: AsRef<Path>>(
         input_path: P,
         algorithm: &dyn WatermarkAlgorithm,
         watermark_length: usize,
-    ) -> Result<String> {
+    ) -> Result<ExtractionResult> {
+        let (bytes, confidence) =
+            Self::extract_bytes_with_confidence(input_path, algorithm, watermark_length)?;
+
+        // 加密载荷无法直接作为文本读取
+        if crypto::is_encrypted(&bytes) {
+            return Err(WatermarkError::PasswordRequired);
+        }
+
+        // 转换为字符串
+        let text = WatermarkUtils::bytes_to_string(bytes)?;
+        Ok(ExtractionResult::single(text, confidence))
+    }
+
+    /// # 从音频中提取指定字节数的原始载荷
+    pub fn extract_bytes<P: AsRef<Path>>(
+        input_path: P,
+        algorithm: &dyn WatermarkAlgorithm,
+        byte_length: usize,
+    ) -> Result<Vec<u8>> {
+        Self::extract_bytes_with_confidence(input_path, algorithm, byte_length)
+            .map(|(payload, _)| payload)
+    }
+
+    /// # 计算音频的水印存在性得分 (0.0-1.0)
+    ///
+    /// DCT等算法在音频上只强制系数的符号，修改量远小于音乐本身的系数，软判决置信度
+    /// 在有无水印时都很低。嵌入时加了同步标记的音频另按同步相关峰的显著性计分，
+    /// 刚好能定位到同步标记时对应 `DETECTION_THRESHOLD`，取两者中的较大值。
+    pub fn detect_watermark<P: AsRef<Path>>(
+        input_path: P,
+        algorithm: &dyn WatermarkAlgorithm,
+    ) -> Result<f64> {
+        let temp_dir = TempDir::new("audio_detect")?;
+        let (spec, interleaved) =
+            Self::read_audio(input_path.as_ref(), &temp_dir.join("normalized.wav"))?;
+
+        let decisions = Self::extract_samples(
+            &interleaved,
+            spec.channels,
+            algorithm,
+            WatermarkUtils::DETECTION_PROBE_BITS,
+        )?;
+        let sync_score = sync::significance(&Self::downmix(&interleaved, spec.channels))
+            * WatermarkUtils::DETECTION_THRESHOLD;
+        Ok(WatermarkUtils::mean_confidence(&decisions).max(sync_score.min(1.0)))
+    }
+
+    /// # 从音频中提取原始载荷，同时返回所有比特的平均软判决置信度
+    pub fn extract_bytes_with_confidence<P: AsRef<Path>>(
+        input_path: P,
+        algorithm: &dyn WatermarkAlgorithm,
+        byte_length: usize,
+    ) -> Result<(Vec<u8>, f64)> {
         let input_path = input_path.as_ref();
 
         // 创建临时目录
-        let temp_dir = std::env::temp_dir().join(format!("audio_extract_{}", std::process::id()));
-        std::fs::create_dir_all(&temp_dir)?;
-
-        // 使用ffmpeg标准化音频格式
-        let normalized_audio = temp_dir.join("normalized.wav");
-        Self::normalize_audio_format(input_path, &normalized_audio)?;
-
-        // 读取标准化后的音频文件
-        let mut reader = WavReader::open(&normalized_audio)?;
-        let _spec = reader.spec();
-
-        // 读取音频样本
-        let samples: Vec<f64> = reader
-            .samples::<i16>()
-            .collect::<std::result::Result<Vec<_>, _>>()?
-            .into_iter()
-            .map(|s| s as f64 / i16::MAX as f64)
-            .collect();
+        let temp_dir = TempDir::new("audio_extract")?;
+
+        // WAV直接读取，不经过ffmpeg转换，样本与文件中的完全一致；其他格式先标准化
+        let (spec, interleaved) = Self::read_audio(input_path, &temp_dir.join("normalized.wav"))?;
+
+        // 多声道时在各声道和声道平均上分别提取后投票
+        let decisions =
+            Self::extract_samples(&interleaved, spec.channels, algorithm, byte_length * 8)?;
+        let bits: Vec<u8> = decisions.iter().map(|&(bit, _)| bit).collect();
+        Ok((
+            WatermarkUtils::bits_to_bytes(&bits),
+            WatermarkUtils::mean_confidence(&decisions),
+        ))
+    }
+
+    /// # 按同步标记对齐后从音频中提取原始载荷
+    ///
+    /// 嵌入时须使用了同步标记。先在声道平均上定位原始的第0个样本，丢弃前面插入的样本，
+    /// 或用静音补齐开头被裁掉的样本，再按正常流程提取；开头被裁掉部分的比特按静音判决。
+    ///
+    /// # 返回
+    /// * `Ok((载荷, 置信度, 偏移))` - 偏移为原始第0个样本在文件中的位置，负数表示开头被裁掉的样本数
+    pub fn extract_bytes_with_sync<P: AsRef<Path>>(
+        input_path: P,
+        algorithm: &dyn WatermarkAlgorithm,
+        byte_length: usize,
+    ) -> Result<(Vec<u8>, f64, i64)> {
+        let temp_dir = TempDir::new("audio_extract")?;
+        let (spec, interleaved) =
+            Self::read_audio(input_path.as_ref(), &temp_dir.join("normalized.wav"))?;
+
+        let offset =
+            sync::locate(&Self::downmix(&interleaved, spec.channels)).ok_or_else(|| {
+                WatermarkError::ProcessingError(Message::SyncNotFound.text().to_string())
+            })?;
+
+        let channels = spec.channels.max(1) as usize;
+        let shift = offset.unsigned_abs() as usize * channels;
+        let aligned = if offset >= 0 {
+            interleaved[shift.min(interleaved.len())..].to_vec()
+        } else {
+            let mut padded = vec![0.0; shift];
+            padded.extend_from_slice(&interleaved);
+            padded
+        };
 
-        // 使用相同的音频专用DCT提取
-        let extracted_bits = Self::ultra_gentle_extract(&samples, algorithm, watermark_length * 8)?;
+        let decisions = Self::extract_samples(&aligned, spec.channels, algorithm, byte_length * 8)?;
+        let bits: Vec<u8> = decisions.iter().map(|&(bit, _)| bit).collect();
+        Ok((
+            WatermarkUtils::bits_to_bytes(&bits),
+            WatermarkUtils::mean_confidence(&decisions),
+            offset,
+        ))
+    }
 
-        // 直接转换比特数组为字符串
-        let watermark_text = WatermarkUtils::bits_to_string(&extracted_bits)?;
+    /// 从交错排列的样本中提取水印，返回每个比特的软判决
+    ///
+    /// 单声道直接提取。嵌入时每个声道各有一份完整的水印，多声道时分别从各声道和声道平均中提取，
+    /// 逐比特多数投票，某个声道被噪声或静音破坏时其余声道仍能给出正确结果；
+    /// 每个比特的置信度为同意票的比例乘以这些票的平均置信度。
+    pub fn extract_samples(
+        interleaved: &[f64],
+        channels: u16,
+        algorithm: &dyn WatermarkAlgorithm,
+        bit_count: usize,
+    ) -> Result<Vec<(u8, f64)>> {
+        let mixed = Self::downmix(interleaved, channels);
+        if channels <= 1 {
+            return Self::ultra_gentle_extract(&mixed, algorithm, bit_count);
+        }
 
-        // 清理临时文件
-        let _ = std::fs::remove_dir_all(&temp_dir); // 使用 let _ 避免清理失败影响结果
+        let channels = channels as usize;
+        let mut voters = (0..channels)
+            .map(|channel| {
+                let samples = Self::channel_samples(interleaved, channels, channel);
+                Self::ultra_gentle_extract(&samples, algorithm, bit_count)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        voters.push(Self::ultra_gentle_extract(&mixed, algorithm, bit_count)?);
 
-        Ok(watermark_text)
+        let voter_bits: Vec<Vec<u8>> = voters
+            .iter()
+            .map(|decisions| decisions.iter().map(|&(bit, _)| bit).collect())
+            .collect();
+        let vote = ImageWatermarker::vote_channels(&voter_bits, 1);
+        Ok(vote
+            .bits
+            .iter()
+            .zip(&vote.bit_confidence)
+            .enumerate()
+            .map(|(i, (&bit, &agreement))| {
+                let agreeing: Vec<f64> = voters
+                    .iter()
+                    .filter(|decisions| decisions[i].0 == bit)
+                    .map(|decisions| decisions[i].1)
+                    .collect();
+                let confidence = agreeing.iter().sum::<f64>() / agreeing.len() as f64;
+                (bit, agreement * confidence)
+            })
+            .collect())
     }
 
     /// 将音频样本转换为二维数组
@@ -392,33 +845,14 @@ impl AudioWatermarker {
                 }
             }
             SampleFormat::Int => {
-                // 根据实际位数进行转换
-                match spec.bits_per_sample {
-                    16 => {
-                        for &sample in samples.iter() {
-                            let int_sample = (sample * i16::MAX as f64) as i16;
-                            writer.write_sample(int_sample)?;
-                        }
-                    }
-                    24 => {
-                        for &sample in samples.iter() {
-                            // 24位音频处理
-                            let max_24bit = (1 << 23) - 1; // 2^23 - 1
-                            let int_sample = (sample * max_24bit as f64) as i32;
-                            writer.write_sample(int_sample)?;
-                        }
-                    }
-                    32 => {
-                        for &sample in samples.iter() {
-                            let int_sample = (sample * i32::MAX as f64) as i32;
-                            writer.write_sample(int_sample)?;
-                        }
-                    }
-                    _ => {
-                        return Err(WatermarkError::UnsupportedFormat(format!(
-                            "不支持的位深度: {} bits",
-                            spec.bits_per_sample
-                        )));
+                // 根据实际位数进行转换，超出范围的样本截断到最大值
+                let max = Self::int_sample_max(spec.bits_per_sample)?;
+                for &sample in samples.iter() {
+                    let int_sample = (sample * max).round().clamp(-max - 1.0, max) as i32;
+                    match spec.bits_per_sample {
+                        8 => writer.write_sample(int_sample as i8)?,
+                        16 => writer.write_sample(int_sample as i16)?,
+                        _ => writer.write_sample(int_sample)?,
                     }
                 }
             }
@@ -440,45 +874,121 @@ impl AudioWatermarker {
         watermark_text: &str,
         algorithm: &dyn WatermarkAlgorithm,
     ) -> Result<bool> {
-        // 读取音频文件获取样本数量
-        let mut reader = WavReader::open(&path)?;
-        let spec = reader.spec();
-        let watermark_bits = WatermarkUtils::string_to_bits(watermark_text);
-
-        // 计算总样本数
-        let total_samples = match spec.sample_format {
-            SampleFormat::Float => reader.samples::<f32>().count(),
-            SampleFormat::Int => match spec.bits_per_sample {
-                16 => reader.samples::<i16>().count(),
-                24 | 32 => reader.samples::<i32>().count(),
-                _ => {
-                    return Err(WatermarkError::UnsupportedFormat(format!(
-                        "不支持的位深度: {} bits",
-                        spec.bits_per_sample
-                    )));
-                }
-            },
-        };
+        Self::check_payload_capacity(path, watermark_text.as_bytes(), algorithm)
+    }
 
-        let matrix_size = (total_samples as f64).sqrt().ceil() as usize;
+    /// 获取音频时长（秒）
+    pub fn get_audio_duration<P: AsRef<Path>>(path: P) -> Result<f64> {
+        let reader = WavReader::open(&path)?;
+        Ok(reader.duration() as f64 / reader.spec().sample_rate as f64)
+    }
 
-        let capacity = match algorithm.name() {
-            name if name.contains("DCT") => {
-                let adjusted_size = matrix_size.div_ceil(8) * 8;
-                (adjusted_size / 8) * (adjusted_size / 8)
-            }
-            name if name.contains("DWT") => {
-                let adjusted_size = if matrix_size % 2 == 0 {
-                    matrix_size
-                } else {
-                    matrix_size + 1
-                };
-                adjusted_size * adjusted_size / 4
+    /// 检查音频是否能容纳给定的字节载荷
+    pub fn check_payload_capacity<P: AsRef<Path>>(
+        path: P,
+        payload: &[u8],
+        algorithm: &dyn WatermarkAlgorithm,
+    ) -> Result<bool> {
+        let watermark_bits = WatermarkUtils::bytes_to_bits(payload);
+        Ok(watermark_bits.len() <= Self::payload_capacity(path, algorithm)?)
+    }
+
+    /// 计算音频可嵌入的最大比特数
+    ///
+    /// 每个声道各自嵌入一份完整的水印，容量按每个声道的样本数计算。
+    pub fn payload_capacity<P: AsRef<Path>>(
+        path: P,
+        algorithm: &dyn WatermarkAlgorithm,
+    ) -> Result<usize> {
+        let (frames, _) = Self::probe_frames(path)?;
+        Self::samples_capacity(frames, algorithm)
+    }
+
+    /// 计算音频最多可嵌入的载荷字节数，即 `payload_capacity` 向下取整到整字节
+    pub fn max_capacity_bytes<P: AsRef<Path>>(
+        path: P,
+        algorithm: &dyn WatermarkAlgorithm,
+    ) -> Result<usize> {
+        Ok(Self::payload_capacity(path, algorithm)? / 8)
+    }
+
+    /// 获取音频每个声道的样本数和采样率，不写出任何文件
+    ///
+    /// WAV直接读取文件头；其他格式按FFmpeg报告的时长和采样率估算。
+    pub fn probe_frames<P: AsRef<Path>>(path: P) -> Result<(usize, u32)> {
+        if let Ok(reader) = WavReader::open(&path) {
+            return Ok((reader.duration() as usize, reader.spec().sample_rate));
+        }
+
+        let input = path.as_ref().to_str().ok_or_else(|| {
+            WatermarkError::ProcessingError(Message::InputPathInvalid.text().to_string())
+        })?;
+        let mut child = FfmpegCommand::new()
+            .input(input)
+            .args(["-f", "null"])
+            .args(["-t", "0"])
+            .output("-")
+            .spawn()
+            .map_err(WatermarkError::Io)?;
+
+        let mut sample_rate = None;
+        let mut duration = None;
+        let events = child.iter().map_err(|e| {
+            WatermarkError::ProcessingError(Message::FfmpegOutputUnreadable.format(&[&e]))
+        })?;
+        let job = ffmpeg::watch(child);
+        for event in events {
+            match event {
+                FfmpegEvent::ParsedInputStream(stream) => {
+                    if let Some(audio) = stream.audio_data() {
+                        sample_rate.get_or_insert(audio.sample_rate);
+                    }
+                }
+                FfmpegEvent::ParsedDuration(parsed) if parsed.duration > 0.0 => {
+                    duration = Some(parsed.duration);
+                }
+                _ => {}
             }
-            _ => return Err(WatermarkError::Algorithm("未知算法".to_string())),
-        };
+        }
+        job.wait()?;
+
+        match (sample_rate, duration) {
+            (Some(rate), Some(duration)) => Ok(((duration * rate as f64) as usize, rate)),
+            (None, _) => Err(WatermarkError::UnsupportedFormat(
+                Message::NoAudioStream.text().to_string(),
+            )),
+            (Some(_), None) => Err(WatermarkError::ProcessingError(
+                Message::AudioDurationUnknown.text().to_string(),
+            )),
+        }
+    }
 
-        Ok(watermark_bits.len() <= capacity)
+    /// 计算给定样本数时可嵌入的最大比特数
+    ///
+    /// 与嵌入流程的矩阵布局一致：样本先按算法要求补齐，再按行排入边长为2的幂的方阵，
+    /// 超出原始样本长度的部分在写回时会被截掉，因此只统计完整落在原始样本内的行。
+    fn samples_capacity(sample_count: usize, algorithm: &dyn WatermarkAlgorithm) -> Result<usize> {
+        // 直接处理一维样本的算法不做矩阵折叠
+        if algorithm.name() == "ECHO" {
+            return Ok(algorithm.capacity(1, sample_count));
+        }
+
+        let matrix_size = (sample_count as f64).sqrt().ceil() as usize;
+
+        let side = Self::aligned_matrix_size(matrix_size, algorithm).next_power_of_two();
+
+        // 行数按算法的对齐单位取整，保证分块算法的块完整落在原始样本内
+        let alignment = algorithm.alignment().max(1);
+        let full_rows = sample_count / side;
+        Ok(algorithm.capacity(full_rows / alignment * alignment, side))
+    }
+
+    /// 样本折叠成方阵前把边长补齐到算法对齐单位的倍数
+    ///
+    /// 只决定填充方式，容量仍由算法的 capacity() 计算。
+    fn aligned_matrix_size(matrix_size: usize, algorithm: &dyn WatermarkAlgorithm) -> usize {
+        let alignment = algorithm.alignment().max(1);
+        matrix_size.div_ceil(alignment) * alignment
     }
 
     /// 调整音频格式以适应算法要求
@@ -487,70 +997,19 @@ impl AudioWatermarker {
         output_path: P,
         algorithm: &dyn WatermarkAlgorithm,
     ) -> Result<WavSpec> {
-        let mut reader = WavReader::open(&input_path)?;
-        let mut spec = reader.spec();
+        let (mut spec, samples) = Self::read_wav(input_path.as_ref())?;
 
         // 转换为单声道
         if spec.channels != 1 {
-            eprintln!("将音频转换为单声道...");
+            eprintln!("{}", Message::ConvertingToMono.text());
             // 这里简化处理，实际应该实现立体声到单声道的转换
             spec.channels = 1;
         }
 
-        // 读取样本并重新保存
-        let samples: Vec<f64> = match spec.sample_format {
-            SampleFormat::Float => reader
-                .samples::<f32>()
-                .collect::<std::result::Result<Vec<_>, _>>()?
-                .into_iter()
-                .map(|s| s as f64)
-                .collect(),
-            SampleFormat::Int => {
-                // 根据位深度选择正确的整数类型
-                match spec.bits_per_sample {
-                    16 => reader
-                        .samples::<i16>()
-                        .collect::<std::result::Result<Vec<_>, _>>()?
-                        .into_iter()
-                        .map(|s| s as f64 / i16::MAX as f64)
-                        .collect(),
-                    24 | 32 => reader
-                        .samples::<i32>()
-                        .collect::<std::result::Result<Vec<_>, _>>()?
-                        .into_iter()
-                        .map(|s| {
-                            if spec.bits_per_sample == 24 {
-                                let max_24bit = (1 << 23) - 1;
-                                s as f64 / max_24bit as f64
-                            } else {
-                                s as f64 / i32::MAX as f64
-                            }
-                        })
-                        .collect(),
-                    _ => {
-                        return Err(WatermarkError::UnsupportedFormat(format!(
-                            "不支持的位深度: {} bits",
-                            spec.bits_per_sample
-                        )));
-                    }
-                }
-            }
-        };
-
         // 调整样本数量以适应算法要求
         let len = samples.len();
         let matrix_size = (len as f64).sqrt().ceil() as usize;
-        let required_size = match algorithm.name() {
-            name if name.contains("DCT") => matrix_size.div_ceil(8) * 8, // 8的倍数
-            name if name.contains("DWT") => {
-                if matrix_size % 2 == 0 {
-                    matrix_size
-                } else {
-                    matrix_size + 1
-                }
-            } // 偶数
-            _ => return Err(WatermarkError::Algorithm("未知算法".to_string())),
-        };
+        let required_size = Self::aligned_matrix_size(matrix_size, algorithm);
 
         let required_samples = required_size * required_size;
         let mut adjusted_samples = samples;
@@ -575,22 +1034,28 @@ impl AudioWatermarker {
     }
 
     /// 超温和音频水印嵌入 - 使用专门的音频优化DCT算法
+    ///
+    /// `raw` 为真时任何算法都不做后处理，与LSB/STFT走同一路径
     fn ultra_gentle_embed(
         samples: &[f64],
         watermark_bits: &[u8],
         algorithm: &dyn WatermarkAlgorithm,
         strength: f64,
+        raw: bool,
     ) -> Result<Vec<f64>> {
+        // 回声隐藏直接处理一维样本，修改本身就是不可闻的回声，不需要任何后处理
+        if algorithm.name() == "ECHO" {
+            return algorithm.embed_samples(samples, watermark_bits, strength);
+        }
+
         // 检查是否是DCT算法，如果是则使用音频优化版本
-        if algorithm.name() == "DCT" {
-            // 使用专门的音频优化DCT算法
-            let dct_algorithm = DctWatermark::new();
+        if algorithm.name() == "DCT" && !raw {
+            // 使用专门的音频优化DCT算法（保留调用方配置，如块置换种子）
             let processed_samples = Self::prepare_samples_for_watermarking(samples, algorithm)?;
             let data = Self::audio_to_array(&processed_samples)?;
 
             // 调用音频优化的嵌入方法
-            let watermarked_data =
-                dct_algorithm.embed_audio_optimized(&data, watermark_bits, strength)?;
+            let watermarked_data = algorithm.embed_audio(&data, watermark_bits, strength)?;
             let mut watermarked_samples = Self::array_to_audio(&watermarked_data)?;
 
             // 截断到原始长度
@@ -601,6 +1066,16 @@ impl AudioWatermarker {
             // 应用轻量化的音频后处理
             Self::apply_minimal_audio_postprocessing(&mut watermarked_samples);
 
+            Ok(watermarked_samples)
+        } else if raw || algorithm.name() == "LSB" || algorithm.name() == "STFT" {
+            // LSB需要逐位精确保留，STFT的修改已由频带能量掩蔽，原始模式则明确要求不做处理，
+            // 均跳过限幅和平滑等后处理
+            let processed_samples = Self::prepare_samples_for_watermarking(samples, algorithm)?;
+            let data = Self::audio_to_array(&processed_samples)?;
+            let watermarked_data = algorithm.embed_audio(&data, watermark_bits, strength)?;
+            let mut watermarked_samples: Vec<f64> = watermarked_data.iter().copied().collect();
+            watermarked_samples.truncate(samples.len());
+
             Ok(watermarked_samples)
         } else {
             // 对于非DCT算法，使用原来的流程
@@ -614,7 +1089,7 @@ impl AudioWatermarker {
             }
 
             Self::apply_ultra_smooth_audio_pipeline(&mut watermarked_samples, samples);
-            eprintln!("✅ 通用音频水印嵌入完成");
+            eprintln!("{}", Message::AudioEmbedDone.text());
             Ok(watermarked_samples)
         }
     }
@@ -624,13 +1099,16 @@ impl AudioWatermarker {
         samples: &[f64],
         algorithm: &dyn WatermarkAlgorithm,
         bit_count: usize,
-    ) -> Result<Vec<u8>> {
-        // 无论什么算法，都使用标准提取流程
+    ) -> Result<Vec<(u8, f64)>> {
+        if algorithm.name() == "ECHO" {
+            return algorithm.extract_samples_soft(samples, bit_count);
+        }
+
+        // 其余算法都使用标准提取流程
         // 因为嵌入时虽然用了音频专用算法，但基本的DCT位置是相同的
         let processed_samples = Self::prepare_samples_for_watermarking(samples, algorithm)?;
         let data = Self::audio_to_array(&processed_samples)?;
-        let extracted_bits = algorithm.extract(&data, bit_count)?;
-        Ok(extracted_bits)
+        algorithm.extract_audio_soft(&data, bit_count)
     }
 
     /// 高级音频平滑处理流水线 - 彻底消除artifacts和噪声
@@ -642,7 +1120,7 @@ impl AudioWatermarker {
             return;
         }
 
-        eprintln!("🔧 应用高级音频平滑处理流水线...");
+        eprintln!("{}", Message::SmoothingPipeline.text());
 
         // 第1步：全局动态范围分析与保护性归一化
         let max_abs = watermarked_samples
@@ -654,7 +1132,10 @@ impl AudioWatermarker {
             for sample in watermarked_samples.iter_mut() {
                 *sample *= protection_factor;
             }
-            eprintln!("  📊 应用了保护性归一化，因子: {protection_factor:.4}");
+            eprintln!(
+                "{}",
+                Message::ProtectiveNormalization.format(&[&format!("{protection_factor:.4}")])
+            );
         }
 
         // 第2步：温和的全局低通滤波，减少高频artifacts
@@ -669,7 +1150,7 @@ impl AudioWatermarker {
         // 第5步：最终的感知优化限制
         Self::apply_perceptual_limiting(watermarked_samples);
 
-        eprintln!("✅ 高级音频平滑处理完成");
+        eprintln!("{}", Message::SmoothingDone.text());
     }
 
     /// 全局温和低通滤波
@@ -688,7 +1169,7 @@ impl AudioWatermarker {
         }
 
         samples.copy_from_slice(&filtered);
-        eprintln!("  🎛️ 应用了全局温和低通滤波");
+        eprintln!("{}", Message::GentleLowpass.text());
     }
 
     /// 自适应动态范围压缩
@@ -712,7 +1193,7 @@ impl AudioWatermarker {
             }
         }
 
-        eprintln!("  🎚️ 应用了自适应动态范围压缩");
+        eprintln!("{}", Message::AdaptiveCompression.text());
     }
 
     /// 边界平滑处理
@@ -732,7 +1213,7 @@ impl AudioWatermarker {
             samples[i] *= fade_factor;
         }
 
-        eprintln!("  🎭 应用了边界平滑处理，淡入淡出长度: {fade_length}样本");
+        eprintln!("{}", Message::BoundarySmoothing.format(&[&fade_length]));
     }
 
     /// 感知优化限制
@@ -748,7 +1229,7 @@ impl AudioWatermarker {
             }
         }
 
-        eprintln!("  🔊 应用了感知优化限制");
+        eprintln!("{}", Message::PerceptualLimiting.text());
     }
 
     /// 轻量化的音频后处理 - 专为音频优化DCT设计
@@ -809,3 +1290,226 @@ impl AudioWatermarker {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::watermark::prng::SeededRng;
+    use crate::watermark::{DctWatermark, LsbWatermark, Repetition, RepetitionWatermark};
+    use std::f64::consts::PI;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    const SPEC: WavSpec = WavSpec {
+        channels: 1,
+        sample_rate: 44_100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("seal_audio_{}_{name}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// 两个正弦波叠加少量白噪声的单声道16位WAV
+    fn write_host_wav(path: &Path, frames: usize) {
+        let mut rng = SeededRng::new(3);
+        let samples: Vec<f64> = (0..frames)
+            .map(|n| {
+                let t = n as f64 / SPEC.sample_rate as f64;
+                let noise = rng.next_below(2001) as f64 / 1000.0 - 1.0;
+                0.3 * (2.0 * PI * 220.0 * t).sin()
+                    + 0.2 * (2.0 * PI * 331.0 * t).sin()
+                    + 0.01 * noise
+            })
+            .collect();
+        AudioWatermarker::write_wav(path, &samples, SPEC).unwrap();
+    }
+
+    #[test]
+    fn embed_fills_exactly_the_reported_capacity() {
+        let dir = scratch_dir("capacity");
+        let original = dir.join("original.wav");
+        write_host_wav(&original, 44_100);
+        let algorithm = DctWatermark::new();
+        let max = AudioWatermarker::max_capacity_bytes(&original, &algorithm).unwrap();
+
+        let full = vec![0xa5; max];
+        AudioWatermarker::embed_bytes(&original, &dir.join("full.wav"), &full, &algorithm, 0.1)
+            .unwrap();
+
+        let over = vec![0xa5; max + 1];
+        let result =
+            AudioWatermarker::embed_bytes(&original, &dir.join("over.wav"), &over, &algorithm, 0.1);
+        assert!(matches!(
+            result,
+            Err(WatermarkError::CapacityExceeded { .. })
+        ));
+        assert!(!dir.join("over.wav").exists());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn detection_scores_clean_audio_low_and_marked_audio_high() {
+        let dir = scratch_dir("detect");
+        let (clean, marked) = (dir.join("clean.wav"), dir.join("marked.wav"));
+        write_host_wav(&clean, 4 * 44_100);
+        let algorithm = DctWatermark::new();
+        let options = AudioEmbedOptions {
+            sync: true,
+            ..Default::default()
+        };
+        AudioWatermarker::embed_bytes_with_options(
+            &clean,
+            &marked,
+            b"seal detection",
+            &algorithm,
+            0.1,
+            &options,
+        )
+        .unwrap();
+
+        let clean_score = AudioWatermarker::detect_watermark(&clean, &algorithm).unwrap();
+        let marked_score = AudioWatermarker::detect_watermark(&marked, &algorithm).unwrap();
+        assert!(clean_score < WatermarkUtils::DETECTION_THRESHOLD);
+        assert!(marked_score > WatermarkUtils::DETECTION_THRESHOLD);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn sync_realigns_extraction_after_prepended_samples() {
+        let dir = scratch_dir("sync");
+        let (input, marked, shifted) = (
+            dir.join("input.wav"),
+            dir.join("marked.wav"),
+            dir.join("shifted.wav"),
+        );
+        write_host_wav(&input, 6 * sync::PERIOD);
+
+        // 默认强度下DCT的修改量与同步标记同一量级，用满强度嵌入，使结果只取决于能否对齐
+        let algorithm = DctWatermark::new();
+        let options = AudioEmbedOptions {
+            sync: true,
+            ..Default::default()
+        };
+        AudioWatermarker::embed_bytes_with_options(
+            &input, &marked, b"hi", &algorithm, 1.0, &options,
+        )
+        .unwrap();
+
+        // 在开头插入一段随机样本，原有的分块位置整体后移
+        let (spec, samples) = AudioWatermarker::read_wav(&marked).unwrap();
+        let mut rng = SeededRng::new(11);
+        let mut prepended: Vec<f64> = (0..3000)
+            .map(|_| rng.next_below(2001) as f64 / 10_000.0 - 0.1)
+            .collect();
+        prepended.extend(samples);
+        AudioWatermarker::write_wav(&shifted, &prepended, spec).unwrap();
+
+        let (bytes, _, offset) =
+            AudioWatermarker::extract_bytes_with_sync(&shifted, &algorithm, 2).unwrap();
+        assert_eq!(offset, 3000);
+        assert_eq!(bytes, b"hi");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn raw_embed_writes_exactly_what_the_algorithm_produced() {
+        let dir = scratch_dir("raw");
+        let (input, first, second) = (
+            dir.join("input.wav"),
+            dir.join("first.wav"),
+            dir.join("second.wav"),
+        );
+        write_host_wav(&input, 16_384);
+
+        let algorithm = DctWatermark::new();
+        for output in [&first, &second] {
+            AudioWatermarker::embed_bytes_raw(&input, output, b"raw", &algorithm, 0.5, None)
+                .unwrap();
+        }
+        assert_eq!(
+            std::fs::read(&first).unwrap(),
+            std::fs::read(&second).unwrap()
+        );
+
+        // 写出的样本就是算法输出按16位量化的结果，没有任何后处理
+        let (_, original) = AudioWatermarker::read_wav(&input).unwrap();
+        let expected = AudioWatermarker::ultra_gentle_embed(
+            &original,
+            &WatermarkUtils::bytes_to_bits(b"raw"),
+            &algorithm,
+            (0.5 * DEFAULT_AUDIO_STRENGTH_SCALE).min(MAX_EFFECTIVE_AUDIO_STRENGTH),
+            true,
+        )
+        .unwrap();
+        let (_, written) = AudioWatermarker::read_wav(&first).unwrap();
+        let max = f64::from(i16::MAX);
+        let quantized: Vec<f64> = expected
+            .iter()
+            .map(|&sample| (sample * max).round().clamp(-max - 1.0, max) / max)
+            .collect();
+        assert_eq!(written, quantized);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn raw_embed_rejects_lossy_output() {
+        let dir = scratch_dir("raw_lossy");
+        let input = dir.join("input.wav");
+        write_host_wav(&input, 16_384);
+
+        let result = AudioWatermarker::embed_bytes_raw(
+            &input,
+            &dir.join("output.mp3"),
+            b"raw",
+            &DctWatermark::new(),
+            0.5,
+            None,
+        );
+        assert!(matches!(result, Err(WatermarkError::InvalidArgument(_))));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn canonical_wav_is_read_without_a_second_conversion() {
+        let dir = scratch_dir("canonical");
+        let (input, normalized) = (dir.join("input.wav"), dir.join("normalized.wav"));
+        write_host_wav(&input, 4096);
+
+        // 视频音轨按 CANONICAL_WAV_ARGS 提取得到的就是这种16位PCM WAV，读取时不应再经过ffmpeg
+        let (spec, samples) = AudioWatermarker::read_audio(&input, &normalized).unwrap();
+        assert!(!normalized.exists());
+        assert_eq!(spec, SPEC);
+        assert_eq!(samples.len(), 4096);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn matrix_is_aligned_to_the_algorithm_block_size() {
+        let dct16 = DctWatermark::new().with_block_size(16);
+        assert_eq!(AudioWatermarker::aligned_matrix_size(100, &dct16), 112);
+        assert_eq!(
+            AudioWatermarker::aligned_matrix_size(100, &DctWatermark::new()),
+            104
+        );
+
+        let repeated = RepetitionWatermark::new(Arc::new(dct16), Repetition::Times(2));
+        assert_eq!(AudioWatermarker::aligned_matrix_size(100, &repeated), 112);
+
+        let lsb = LsbWatermark::new();
+        assert_eq!(AudioWatermarker::aligned_matrix_size(100, &lsb), 100);
+        assert_eq!(
+            AudioWatermarker::samples_capacity(1000, &lsb).unwrap(),
+            lsb.capacity(1000 / 32, 32)
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use crate::error::Result;
+use image::{ImageBuffer, Rgb, RgbImage};
+use ndarray::Array2;
+use std::path::Path;
+
+/// 热力图中每个块占的像素边长上限，块很小时放大以便查看
+const HEATMAP_MAX_CELL: u32 = 8;
+
+/// 音频差异包络的点数上限，也是包络图的宽度
+pub const ENVELOPE_POINTS: usize = 1024;
+
+/// 包络图的高度（像素）
+const ENVELOPE_PLOT_HEIGHT: u32 = 256;
+
+/// 两张图片逐块比较的结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageDiff {
+    /// 整幅图片的PSNR (dB)，两者完全相同时为正无穷
+    pub psnr: f64,
+    /// 计算DCT系数差时的块边长
+    pub block_size: usize,
+    /// 每个块DCT系数差的均方根，形状为 (块行数, 块列数)
+    pub block_energy: Array2<f64>,
+    /// 每个系数位置 (u, v) 在所有块上的平均绝对差，形状为 (块边长, 块边长)
+    pub coefficient_energy: Array2<f64>,
+}
+
+impl ImageDiff {
+    /// 两张图片的所有像素都相同
+    pub fn is_identical(&self) -> bool {
+        self.block_energy.iter().all(|&energy| energy == 0.0)
+    }
+
+    /// 系数有变化的块数
+    pub fn changed_blocks(&self) -> usize {
+        self.block_energy
+            .iter()
+            .filter(|&&energy| energy > 0.0)
+            .count()
+    }
+
+    /// 平均修改量最大的 `count` 个系数位置 (u, v)，从大到小排列，不含没有变化的位置
+    pub fn peak_positions(&self, count: usize) -> Vec<(usize, usize)> {
+        let mut positions: Vec<((usize, usize), f64)> = self
+            .coefficient_energy
+            .indexed_iter()
+            .filter(|&(_, &energy)| energy > 0.0)
+            .map(|(position, &energy)| (position, energy))
+            .collect();
+        positions.sort_by(|a, b| b.1.total_cmp(&a.1));
+        positions
+            .into_iter()
+            .take(count)
+            .map(|(position, _)| position)
+            .collect()
+    }
+
+    /// 把逐块的差异保存为热力图，返回热力图的 (宽, 高)
+    ///
+    /// 颜色从黑经红、黄到白，按最大的块差异归一化，水印集中的区域最亮；
+    /// 每个块画成边长为 `block_size`（不超过8）像素的方格。
+    pub fn save_heatmap<P: AsRef<Path>>(&self, path: P) -> Result<(u32, u32)> {
+        let (rows, cols) = self.block_energy.dim();
+        let cell = (self.block_size as u32).min(HEATMAP_MAX_CELL);
+        let peak = self.block_energy.iter().copied().fold(0.0, f64::max);
+        let heatmap: RgbImage =
+            ImageBuffer::from_fn(cols as u32 * cell, rows as u32 * cell, |x, y| {
+                let energy = self.block_energy[[(y / cell) as usize, (x / cell) as usize]];
+                heat_color(if peak > 0.0 { energy / peak } else { 0.0 })
+            });
+        heatmap.save(path)?;
+
+        Ok((heatmap.width(), heatmap.height()))
+    }
+}
+
+/// 两段音频逐样本比较的结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioDiff {
+    /// 水印音频相对原始音频的SNR (dB)，两者完全相同时为正无穷
+    pub snr: f64,
+    /// 每个窗口内样本差的均方根（按声道平均后的样本计）
+    pub envelope: Vec<f64>,
+    /// 每个窗口的样本帧数
+    pub window: usize,
+    /// 采样率
+    pub sample_rate: u32,
+    /// 单个样本差的最大绝对值
+    pub max_difference: f64,
+}
+
+impl AudioDiff {
+    /// 两段音频的所有样本都相同
+    pub fn is_identical(&self) -> bool {
+        self.max_difference == 0.0
+    }
+
+    /// 把差异包络画成曲线图，返回图的 (宽, 高)
+    ///
+    /// 横轴为时间，每个窗口一列；纵轴按最大的窗口差异归一化，填充的高度即该窗口的差异。
+    pub fn save_envelope_plot<P: AsRef<Path>>(&self, path: P) -> Result<(u32, u32)> {
+        let width = self.envelope.len().max(1) as u32;
+        let height = ENVELOPE_PLOT_HEIGHT;
+        let peak = self.envelope.iter().copied().fold(0.0, f64::max);
+        let plot: RgbImage = ImageBuffer::from_fn(width, height, |x, y| {
+            let level = match self.envelope.get(x as usize) {
+                Some(&value) if peak > 0.0 => value / peak,
+                _ => 0.0,
+            };
+            let filled = (level * height as f64).round() as u32;
+            if height - y <= filled {
+                heat_color(level)
+            } else {
+                Rgb([0, 0, 0])
+            }
+        });
+        plot.save(path)?;
+
+        Ok((width, height))
+    }
+}
+
+/// 把 [0.0, 1.0] 的强度映射为黑-红-黄-白的颜色
+fn heat_color(level: f64) -> Rgb<u8> {
+    let level = level.clamp(0.0, 1.0) * 3.0;
+    let channel = |start: f64| ((level - start).clamp(0.0, 1.0) * 255.0).round() as u8;
+    Rgb([channel(0.0), channel(1.0), channel(2.0)])
+}
@@ -0,0 +1,59 @@
+use crate::media::FrameIndexRange;
+
+/// 提取到的载荷是否经过完整性校验
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Integrity {
+    /// 载荷不带校验值，无法判断是否被篡改
+    #[default]
+    Unchecked,
+    /// 校验值与载荷一致
+    Verified,
+}
+
+/// 文本水印的提取结果，图片、音频和视频的 `extract_watermark` 共用
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtractionResult {
+    /// 提取到的水印文本
+    pub text: String,
+    /// 平均软判决置信度 (0.0-1.0)
+    pub confidence: f64,
+    /// 参与提取的帧数，图片和音频为1
+    pub frames_used: usize,
+    /// 视频中恢复出的原始帧序号范围，未启用帧序号、没有帧通过校验或不是视频时为 `None`
+    pub frame_indices: Option<FrameIndexRange>,
+    /// 完整性校验结果
+    pub integrity: Integrity,
+}
+
+impl ExtractionResult {
+    /// 由单次提取（图片或音频）的文本和置信度创建结果
+    pub fn single(text: String, confidence: f64) -> Self {
+        Self {
+            text,
+            confidence,
+            frames_used: 1,
+            frame_indices: None,
+            integrity: Integrity::Unchecked,
+        }
+    }
+
+    /// 水印文本
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// 取出水印文本
+    pub fn into_text(self) -> String {
+        self.text
+    }
+
+    /// 平均置信度不低于 `threshold`
+    pub fn is_confident(&self, threshold: f64) -> bool {
+        self.confidence >= threshold
+    }
+
+    /// 载荷带有校验值且校验通过
+    pub fn is_verified(&self) -> bool {
+        self.integrity == Integrity::Verified
+    }
+}
@@ -0,0 +1,407 @@
+use crate::error::{Result, WatermarkError};
+use crate::i18n::Message;
+use colored::Colorize;
+use ffmpeg_sidecar::child::FfmpegChild;
+use ffmpeg_sidecar::command::FfmpegCommand;
+use std::io::Read;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// 单个FFmpeg进程默认的最长运行时间
+pub const DEFAULT_FFMPEG_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// 检查FFmpeg进程状态的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// 收到中断后等待FFmpeg自行退出的时间，超过后强制终止
+const INTERRUPT_GRACE: Duration = Duration::from_secs(5);
+
+/// 暂时性失败后默认的重试次数，即每条命令最多执行3次
+pub const DEFAULT_FFMPEG_RETRIES: u32 = 2;
+
+/// 第一次重试前的等待时间，之后每次翻倍
+const RETRY_BACKOFF: Duration = Duration::from_millis(200);
+
+/// 保留的stderr末尾字节数，用于判断失败原因
+const STDERR_TAIL_BYTES: usize = 8192;
+
+/// stderr中出现这些内容时，失败多半由机器繁忙引起，重试可能成功；
+/// 编解码器不支持、文件损坏等确定性的错误不在此列
+const TRANSIENT_MARKERS: [&str; 6] = [
+    "Resource temporarily unavailable",
+    "Cannot allocate memory",
+    "Device or resource busy",
+    "Too many open files",
+    "Text file busy",
+    "Interrupted system call",
+];
+
+/// 当前的超时秒数，0 表示不限制
+static TIMEOUT_SECS: AtomicU64 = AtomicU64::new(DEFAULT_FFMPEG_TIMEOUT.as_secs());
+static RETRIES: AtomicU32 = AtomicU32::new(DEFAULT_FFMPEG_RETRIES);
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+/// 本进程已创建的临时目录个数，作为目录名的序号
+static TEMP_DIRS: AtomicUsize = AtomicUsize::new(0);
+
+/// 设置之后启动的每个FFmpeg进程的最长运行时间，`None` 表示不限制
+///
+/// 超时的进程会被终止，所在的处理流程返回 `ProcessingError("ffmpeg 超时")`。
+pub fn set_timeout(timeout: Option<Duration>) {
+    let secs = timeout.map_or(0, |timeout| timeout.as_secs().max(1));
+    TIMEOUT_SECS.store(secs, Ordering::Relaxed);
+}
+
+/// 当前的FFmpeg超时设置
+pub fn timeout() -> Option<Duration> {
+    match TIMEOUT_SECS.load(Ordering::Relaxed) {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    }
+}
+
+/// 设置FFmpeg暂时性失败后的重试次数，0 表示不重试
+///
+/// 只有 `run` 执行的命令会重试；通过管道读写数据的进程无法重放输入，失败时直接报错。
+pub fn set_retries(retries: u32) {
+    RETRIES.store(retries, Ordering::Relaxed);
+}
+
+/// 当前的重试次数
+pub fn retries() -> u32 {
+    RETRIES.load(Ordering::Relaxed)
+}
+
+/// 请求中止所有正在运行和之后启动的FFmpeg进程
+///
+/// 只写入一个原子标志，可以在信号处理函数中调用。正在运行的进程有几秒时间自行退出，
+/// 之后被强制终止，所在的处理流程返回错误并清理临时文件。
+pub fn cancel() {
+    CANCELLED.store(true, Ordering::SeqCst);
+}
+
+/// 是否已经请求中止
+pub fn is_cancelled() -> bool {
+    CANCELLED.load(Ordering::SeqCst)
+}
+
+/// 是否有FFmpeg进程正在运行，可以在信号处理函数中调用
+pub fn is_running() -> bool {
+    RUNNING.load(Ordering::SeqCst) > 0
+}
+
+/// 由后台线程看管的FFmpeg进程，超时或请求中止时终止进程
+pub(crate) struct FfmpegJob {
+    handle: JoinHandle<Result<(ExitStatus, String)>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl FfmpegJob {
+    /// 等待进程结束，超时或被中止时返回 `ProcessingError`
+    pub(crate) fn wait(self) -> Result<ExitStatus> {
+        self.wait_with_stderr().map(|(status, _)| status)
+    }
+
+    /// 已经拿到需要的输出时提前终止进程并等待其退出
+    pub(crate) fn kill(self) -> Result<()> {
+        self.stop.store(true, Ordering::SeqCst);
+        self.wait().map(|_| ())
+    }
+
+    /// 等待进程结束，同时返回stderr的末尾部分（调用方已取走stderr时为空）
+    fn wait_with_stderr(self) -> Result<(ExitStatus, String)> {
+        self.handle.join().unwrap_or_else(|_| {
+            Err(WatermarkError::ProcessingError(
+                Message::FfmpegWatcherPanicked.text().to_string(),
+            ))
+        })
+    }
+}
+
+/// 开始看管进程，调用方需要的 stdin、stdout 或事件迭代器应在此之前取走，
+/// 未被取走的 stderr 在后台读完，只保留末尾部分，避免管道写满后进程阻塞
+pub(crate) fn watch(child: FfmpegChild) -> FfmpegJob {
+    watch_with_timeout(child, timeout())
+}
+
+/// 按给定的最长运行时间看管进程，`None` 表示不限制
+fn watch_with_timeout(mut child: FfmpegChild, limit: Option<Duration>) -> FfmpegJob {
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_requested = Arc::clone(&stop);
+    RUNNING.fetch_add(1, Ordering::SeqCst);
+    let handle = thread::spawn(move || {
+        let result = supervise(&mut child, &stop_requested, limit);
+        RUNNING.fetch_sub(1, Ordering::SeqCst);
+        result
+    });
+    FfmpegJob { handle, stop }
+}
+
+/// 启动命令并等待其结束，暂时性失败时按指数退避重试，最多重试 `retries()` 次
+///
+/// 启动进程时遇到资源暂时不足，或进程被信号终止、stderr 中有 `TRANSIENT_MARKERS` 之一时才重试；
+/// 其他失败照常返回退出状态，超时和中止不重试。
+pub(crate) fn run(command: &mut FfmpegCommand) -> Result<ExitStatus> {
+    let mut attempt = 0;
+    loop {
+        let (outcome, reason) = match command.spawn() {
+            Ok(child) => {
+                let (status, stderr) = watch(child).wait_with_stderr()?;
+                let reason = transient_exit(&status, &stderr);
+                (Ok(status), reason)
+            }
+            Err(e) => {
+                let reason = transient_spawn_error(&e).then(|| e.to_string());
+                (Err(WatermarkError::Io(e)), reason)
+            }
+        };
+
+        let Some(reason) = reason.filter(|_| attempt < retries() && !is_cancelled()) else {
+            return outcome;
+        };
+        attempt += 1;
+        let delay = RETRY_BACKOFF * 2u32.pow(attempt - 1);
+        eprintln!(
+            "{} {}",
+            "⚠️".yellow(),
+            Message::FfmpegRetry.format(&[&reason, &delay.as_millis(), &attempt])
+        );
+        thread::sleep(delay);
+    }
+}
+
+/// 启动进程时的错误是否可能在稍后消失
+fn transient_spawn_error(error: &std::io::Error) -> bool {
+    use std::io::ErrorKind;
+    matches!(
+        error.kind(),
+        ErrorKind::WouldBlock
+            | ErrorKind::Interrupted
+            | ErrorKind::ResourceBusy
+            | ErrorKind::ExecutableFileBusy
+            | ErrorKind::OutOfMemory
+    )
+}
+
+/// 进程的失败是否为暂时性的，是则返回原因
+fn transient_exit(status: &ExitStatus, stderr: &str) -> Option<String> {
+    if status.success() {
+        return None;
+    }
+    if let Some(marker) = TRANSIENT_MARKERS
+        .iter()
+        .find(|marker| stderr.contains(*marker))
+    {
+        return Some(marker.to_string());
+    }
+    // 没有退出码说明进程被信号终止，如内存不足时被系统杀掉
+    status
+        .code()
+        .is_none()
+        .then(|| Message::KilledBySignal.text().to_string())
+}
+
+fn supervise(
+    child: &mut FfmpegChild,
+    stop: &AtomicBool,
+    limit: Option<Duration>,
+) -> Result<(ExitStatus, String)> {
+    let stderr_tail = child.take_stderr().map(|mut stderr| {
+        thread::spawn(move || {
+            let mut tail = Vec::new();
+            let mut buffer = [0u8; 4096];
+            while let Ok(read @ 1..) = stderr.read(&mut buffer) {
+                tail.extend_from_slice(&buffer[..read]);
+                let excess = tail.len().saturating_sub(STDERR_TAIL_BYTES);
+                tail.drain(..excess);
+            }
+            String::from_utf8_lossy(&tail).into_owned()
+        })
+    });
+    let status = supervise_process(child, stop, limit)?;
+    let stderr = stderr_tail
+        .and_then(|handle| handle.join().ok())
+        .unwrap_or_default();
+    Ok((status, stderr))
+}
+
+fn supervise_process(
+    child: &mut FfmpegChild,
+    stop: &AtomicBool,
+    limit: Option<Duration>,
+) -> Result<ExitStatus> {
+    // 中止之后启动的进程不再等待，避免逐帧采样时每一帧都要等一次宽限时间
+    if is_cancelled() {
+        terminate(child);
+        return Err(interrupted());
+    }
+
+    let started = Instant::now();
+    let mut interrupted_at = None;
+    loop {
+        if let Some(status) = child.as_inner_mut().try_wait()? {
+            if is_cancelled() {
+                return Err(interrupted());
+            }
+            return Ok(status);
+        }
+        if stop.load(Ordering::SeqCst) {
+            let _ = child.kill();
+            return Ok(child.as_inner_mut().wait()?);
+        }
+
+        if limit.is_some_and(|limit| started.elapsed() >= limit) {
+            terminate(child);
+            return Err(WatermarkError::ProcessingError(
+                Message::FfmpegTimeout.text().to_string(),
+            ));
+        }
+        // 终端的 Ctrl-C 同时发给了FFmpeg，先给它机会正常收尾
+        if is_cancelled()
+            && interrupted_at.get_or_insert_with(Instant::now).elapsed() >= INTERRUPT_GRACE
+        {
+            terminate(child);
+            return Err(interrupted());
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn terminate(child: &mut FfmpegChild) {
+    let _ = child.kill();
+    let _ = child.as_inner_mut().wait();
+}
+
+fn interrupted() -> WatermarkError {
+    WatermarkError::ProcessingError(Message::FfmpegInterrupted.text().to_string())
+}
+
+/// 错误是否由超时或中止引起，逐帧采样时遇到这类错误应当停止而不是跳过该帧
+///
+/// 按当前界面语言的消息文本判断，语言在处理开始前设置，之后不再改变。
+pub(crate) fn is_aborted(error: &WatermarkError) -> bool {
+    matches!(error, WatermarkError::ProcessingError(message)
+        if message == Message::FfmpegTimeout.text()
+            || message == Message::FfmpegInterrupted.text())
+}
+
+/// 以进程号和序号区分的临时目录，离开作用域时连同其中的文件一起删除
+///
+/// 处理流程因错误、超时或中断提前返回时也会清理。
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    /// 在系统临时目录下创建 `<prefix>_<进程号>_<序号>`
+    ///
+    /// 序号在进程内递增，同一进程中多个线程同时处理（如批量处理或库调用）时各自使用独立的目录，
+    /// 一个流程结束时不会删除另一个流程仍在使用的文件。
+    pub(crate) fn new(prefix: &str) -> Result<Self> {
+        let index = TEMP_DIRS.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("{prefix}_{}_{index}", std::process::id()));
+        std::fs::create_dir_all(&path)?;
+        Ok(Self(path))
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn temp_dirs_with_the_same_prefix_do_not_collide() {
+        let first = TempDir::new("seal_temp_dir_test").unwrap();
+        let second = TempDir::new("seal_temp_dir_test").unwrap();
+        assert_ne!(first.to_path_buf(), second.to_path_buf());
+
+        let kept = second.join("kept");
+        std::fs::write(&kept, b"frame").unwrap();
+        let removed = first.to_path_buf();
+        drop(first);
+        assert!(!removed.exists());
+        assert!(kept.exists());
+    }
+
+    /// 在临时目录中写出一个代替FFmpeg的脚本，忽略传给它的所有参数
+    #[cfg(unix)]
+    fn fake_ffmpeg(dir: &Path, script: &str) -> FfmpegCommand {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = dir.join("ffmpeg");
+        std::fs::write(&path, format!("#!/bin/sh\n{script}\n")).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        FfmpegCommand::new_with_path(path)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn transient_failures_are_retried_and_deterministic_ones_are_not() {
+        let dir = TempDir::new("seal_ffmpeg_retry_test").unwrap();
+        let calls = dir.join("calls");
+        let count = || std::fs::read_to_string(&calls).unwrap().lines().count();
+
+        // 第一次报资源暂时不足后失败，第二次成功
+        let flaky = format!(
+            "echo run >> {0}\nif [ $(wc -l < {0}) -eq 1 ]; then\n  echo 'Resource temporarily unavailable' >&2\n  exit 1\nfi",
+            calls.display()
+        );
+        let status = run(&mut fake_ffmpeg(&dir, &flaky)).unwrap();
+        assert!(status.success());
+        assert_eq!(count(), 2);
+
+        // 输入文件损坏之类的错误每次都会重现，不重试
+        std::fs::remove_file(&calls).unwrap();
+        let broken = format!(
+            "echo run >> {}\necho 'Invalid data found when processing input' >&2\nexit 1",
+            calls.display()
+        );
+        let status = run(&mut fake_ffmpeg(&dir, &broken)).unwrap();
+        assert!(!status.success());
+        assert_eq!(count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn timeout_kills_the_process_and_reports_it() {
+        let dir = TempDir::new("seal_ffmpeg_timeout_test").unwrap();
+        let pid_file = dir.join("pid");
+        let script = format!("echo $$ > {}\nexec sleep 30", pid_file.display());
+        let child = fake_ffmpeg(&dir, &script).spawn().unwrap();
+
+        let started = Instant::now();
+        let err = watch_with_timeout(child, Some(Duration::from_millis(500)))
+            .wait()
+            .unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(
+            matches!(&err, WatermarkError::ProcessingError(message) if message == Message::FfmpegTimeout.text())
+        );
+        assert!(is_aborted(&err));
+
+        // 进程已被终止并回收，不再存在
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        let alive = std::process::Command::new("kill")
+            .args(["-0", pid.trim()])
+            .stderr(std::process::Stdio::null())
+            .status()
+            .unwrap();
+        assert!(!alive.success());
+    }
+}
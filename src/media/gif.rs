@@ -0,0 +1,77 @@
+use crate::error::Result;
+use image::codecs::gif::{GifDecoder, GifEncoder, Repeat};
+use image::{AnimationDecoder, Delay, DynamicImage, Frame};
+use std::fs::File;
+use std::io::{BufWriter, Cursor};
+use std::path::Path;
+
+/// GIF文件签名（GIF87a 和 GIF89a 共有的前缀）
+const GIF_SIGNATURE: &[u8] = b"GIF8";
+/// 记录循环次数的 NETSCAPE2.0 应用扩展标识
+const NETSCAPE_LOOP: &[u8] = b"NETSCAPE2.0";
+
+/// 解码后的动画GIF，每帧都是合成到整幅画布上的RGBA图片
+pub(crate) struct GifAnimation {
+    /// 各帧图片及其显示时长
+    pub frames: Vec<(DynamicImage, Delay)>,
+    /// NETSCAPE2.0 扩展中的循环次数，0 为无限循环，`None` 表示没有该扩展（只播放一次）
+    pub loop_count: Option<u16>,
+}
+
+impl GifAnimation {
+    /// 读取多帧GIF的全部帧；不是GIF或只有一帧时返回 `None`，按普通图片处理
+    pub fn open(path: &Path) -> Result<Option<Self>> {
+        let bytes = std::fs::read(path)?;
+        if !bytes.starts_with(GIF_SIGNATURE) {
+            return Ok(None);
+        }
+        let frames = GifDecoder::new(Cursor::new(&bytes))?
+            .into_frames()
+            .collect_frames()?;
+        if frames.len() < 2 {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            frames: frames
+                .into_iter()
+                .map(|frame| {
+                    let delay = frame.delay();
+                    (DynamicImage::ImageRgba8(frame.into_buffer()), delay)
+                })
+                .collect(),
+            loop_count: loop_count(&bytes),
+        }))
+    }
+
+    /// 按原有的帧时长和循环次数编码为GIF
+    ///
+    /// 每帧按整幅画布写出；GIF每帧最多256色，编码时的调色板量化会给水印带来额外误差。
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut encoder = GifEncoder::new(BufWriter::new(File::create(path)?));
+        if let Some(count) = self.loop_count {
+            encoder.set_repeat(match count {
+                0 => Repeat::Infinite,
+                count => Repeat::Finite(count),
+            })?;
+        }
+        for (img, delay) in &self.frames {
+            encoder.encode_frame(Frame::from_parts(img.to_rgba8(), 0, 0, *delay))?;
+        }
+        Ok(())
+    }
+}
+
+/// 读取 NETSCAPE2.0 扩展中的循环次数
+///
+/// 扩展标识之后的子块为：长度3、子块类型1、小端16位循环次数。
+fn loop_count(bytes: &[u8]) -> Option<u16> {
+    let start = bytes
+        .windows(NETSCAPE_LOOP.len())
+        .position(|window| window == NETSCAPE_LOOP)?
+        + NETSCAPE_LOOP.len();
+    match bytes.get(start..start + 4)? {
+        [3, 1, low, high] => Some(u16::from_le_bytes([*low, *high])),
+        _ => None,
+    }
+}
@@ -1,9 +1,84 @@
+use crate::cli::{Algorithm, ImageChannel, ImageRegion, VoteStrategy};
 use crate::error::{Result, WatermarkError};
-use crate::watermark::{WatermarkAlgorithm, WatermarkUtils};
-use image::{ColorType, DynamicImage, ImageBuffer, ImageFormat, Luma, Rgb};
+use crate::i18n::Message;
+use crate::media::diff::ImageDiff;
+use crate::media::ffmpeg::{self, TempDir};
+use crate::media::gif::GifAnimation;
+use crate::media::metadata::ImageMetadata;
+use crate::media::{EmbedOptions, ExtractionResult};
+use crate::robustness::Recovery;
+use crate::watermark::{
+    crypto, header, DctWatermark, WatermarkAlgorithm, WatermarkHeader, WatermarkUtils,
+};
+use colored::Colorize;
+use ffmpeg_sidecar::command::FfmpegCommand;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::{replace, FilterType};
+use image::{
+    ColorType, DynamicImage, ImageBuffer, ImageError, ImageFormat, Luma, LumaA, Rgb, Rgba,
+};
 use ndarray::Array2;
+use std::fs::File;
+use std::io::BufWriter;
 use std::path::Path;
 
+/// JPEG输出的默认编码质量，足够高以避免压缩破坏DCT水印
+pub const DEFAULT_JPEG_QUALITY: u8 = 95;
+
+/// 低于该JPEG质量保存DCT水印时给出警告
+const LOW_JPEG_QUALITY: u8 = 80;
+
+/// 自描述头部的最低嵌入强度，与命令行的默认强度相同
+const HEADER_MIN_STRENGTH: f64 = 0.1;
+
+/// 缩放到 `--normalize-size` 规范尺寸时使用的插值方式，嵌入和提取必须一致
+const NORMALIZE_FILTER: FilterType = FilterType::Lanczos3;
+
+/// 按目标PSNR自动选择强度时，结果允许高出目标的范围 (dB)
+const TARGET_PSNR_TOLERANCE: f64 = 0.25;
+
+/// 按目标PSNR自动选择强度时二分查找的最多次数
+const TARGET_PSNR_MAX_ITERATIONS: usize = 12;
+
+/// 置信度图中未承载比特的块的颜色，与灰度的置信度区分开
+const UNUSED_BLOCK_COLOR: [u8; 3] = [0, 0, 160];
+
+/// 图片嵌入的可选参数
+#[derive(Debug, Clone)]
+pub struct ImageEmbedOptions {
+    /// PSNR下限 (dB)，嵌入后低于此值则中止且不写出文件
+    pub min_psnr: Option<f64>,
+    /// 不保留原图的EXIF和ICC等元数据
+    pub strip_metadata: bool,
+    /// 输出为JPEG时的编码质量 (1-100)
+    pub jpeg_quality: u8,
+    /// 嵌入水印的颜色通道，提取时必须一致
+    pub channel: ImageChannel,
+    /// 按每段约多少行的水平条带分段嵌入，`None` 表示整幅图一次处理
+    pub strip_rows: Option<usize>,
+    /// 在图片顶部写入记录嵌入参数的自描述头部，载荷嵌入其下方的区域
+    pub header: Option<WatermarkHeader>,
+    /// 只在图片的矩形区域内嵌入，区域外的像素保持不变；`None` 表示整幅图
+    pub region: Option<ImageRegion>,
+    /// 先缩放到该规范尺寸（宽, 高）再嵌入，水印不随之后的缩放丢失；`None` 表示按原尺寸嵌入
+    pub normalize_size: Option<(u32, u32)>,
+}
+
+impl Default for ImageEmbedOptions {
+    fn default() -> Self {
+        Self {
+            min_psnr: None,
+            strip_metadata: false,
+            jpeg_quality: DEFAULT_JPEG_QUALITY,
+            channel: ImageChannel::All,
+            strip_rows: None,
+            header: None,
+            region: None,
+            normalize_size: None,
+        }
+    }
+}
+
 /// 图片水印处理器
 pub struct ImageWatermarker;
 
@@ -13,131 +88,1164 @@ impl ImageWatermarker {
         input_path: P,
         output_path: P,
         watermark_text: &str,
-        algorithm: &dyn WatermarkAlgorithm,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
         strength: f64,
     ) -> Result<()> {
-        // 加载图片
-        let img = image::open(&input_path)?;
-
-        // 将水印文本转换为比特
-        let watermark_bits = WatermarkUtils::string_to_bits(watermark_text);
-
-        let watermarked_img = match img.color() {
-            ColorType::L8 => {
-                // 灰度图片处理
-                let gray_img = img.to_luma8();
-                let data = Self::image_to_array_gray(&gray_img)?;
-                let watermarked_data = algorithm.embed(&data, &watermark_bits, strength)?;
-                Self::array_to_image_gray(&watermarked_data)?
+        Self::embed_bytes(
+            input_path,
+            output_path,
+            watermark_text.as_bytes(),
+            algorithm,
+            strength,
+        )
+    }
+
+    /// 按嵌入选项创建算法并嵌入水印文本，音频和视频的选项被忽略
+    ///
+    /// # 返回
+    /// 水印图片相对原图的PSNR (dB)
+    pub fn embed_with_options<P: AsRef<Path>>(
+        input_path: P,
+        output_path: P,
+        watermark_text: &str,
+        algorithm: Algorithm,
+        options: &EmbedOptions,
+    ) -> Result<f64> {
+        let algorithm = options.create_algorithm(algorithm);
+        Self::embed_bytes_with_options(
+            input_path,
+            output_path,
+            watermark_text.as_bytes(),
+            algorithm.as_ref(),
+            options.strength,
+            &options.image,
+        )
+    }
+
+    /// 嵌入任意字节载荷到图片中
+    pub fn embed_bytes<P: AsRef<Path>>(
+        input_path: P,
+        output_path: P,
+        payload: &[u8],
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        strength: f64,
+    ) -> Result<()> {
+        Self::embed_bytes_with_options(
+            input_path,
+            output_path,
+            payload,
+            algorithm,
+            strength,
+            &ImageEmbedOptions::default(),
+        )?;
+        Ok(())
+    }
+
+    /// 嵌入字节载荷到图片中，若PSNR低于下限则中止且不写出文件
+    ///
+    /// # 返回
+    /// 水印图片相对原图的PSNR (dB)
+    pub fn embed_bytes_with_min_psnr<P: AsRef<Path>>(
+        input_path: P,
+        output_path: P,
+        payload: &[u8],
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        strength: f64,
+        min_psnr: f64,
+    ) -> Result<f64> {
+        Self::embed_bytes_with_options(
+            input_path,
+            output_path,
+            payload,
+            algorithm,
+            strength,
+            &ImageEmbedOptions {
+                min_psnr: Some(min_psnr),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// 按给定的可选参数嵌入字节载荷到图片中
+    ///
+    /// # 返回
+    /// 水印图片相对原图的PSNR (dB)
+    pub fn embed_bytes_with_options<P: AsRef<Path>>(
+        input_path: P,
+        output_path: P,
+        payload: &[u8],
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        strength: f64,
+        options: &ImageEmbedOptions,
+    ) -> Result<f64> {
+        WatermarkUtils::validate_strength(strength)?;
+
+        // 动画GIF逐帧嵌入；输出为其他格式时只能保存单帧，按普通图片处理第一帧
+        if let Some(animation) = GifAnimation::open(input_path.as_ref())? {
+            if ImageFormat::from_path(output_path.as_ref()).ok() == Some(ImageFormat::Gif) {
+                return Self::embed_animation(
+                    animation,
+                    output_path.as_ref(),
+                    payload,
+                    algorithm,
+                    strength,
+                    options,
+                );
             }
-            ColorType::Rgb8 | ColorType::Rgba8 => {
-                // 彩色图片处理 - 转换为RGB并在每个通道嵌入水印
-                let rgb_img = img.to_rgb8();
-                let (r_data, g_data, b_data) = Self::image_to_array_rgb(&rgb_img)?;
+            eprintln!(
+                "{} {}",
+                "⚠️".yellow(),
+                Message::AnimatedGifFirstFrameOnly.format(&[&animation.frames.len()])
+            );
+        }
 
-                // 在三个通道分别嵌入水印
-                let watermarked_r = algorithm.embed(&r_data, &watermark_bits, strength)?;
-                let watermarked_g = algorithm.embed(&g_data, &watermark_bits, strength)?;
-                let watermarked_b = algorithm.embed(&b_data, &watermark_bits, strength)?;
+        // 加载图片；image 库解码时已把调色板展开为RGB，输出不再使用调色板
+        let img = Self::open_image(&input_path)?;
+        if is_palette_png(input_path.as_ref()) {
+            eprintln!(
+                "{} {}",
+                "📦".blue(),
+                Message::PaletteExpanded.format(&[&format!("{:?}", img.color())])
+            );
+        }
+        let watermarked_img =
+            Self::embed_with_image_options(&img, payload, algorithm, strength, options)?;
 
-                Self::array_to_image_rgb(&watermarked_r, &watermarked_g, &watermarked_b)?
+        // 质量门限：在写出文件之前检查
+        let psnr = Self::calculate_psnr(&img, &watermarked_img);
+        if let Some(floor) = options.min_psnr {
+            if psnr < floor {
+                return Err(WatermarkError::QualityTooLow {
+                    measured: psnr,
+                    floor,
+                });
             }
-            _ => {
-                // 其他格式转换为RGB处理
-                let rgb_img = img.to_rgb8();
-                let (r_data, g_data, b_data) = Self::image_to_array_rgb(&rgb_img)?;
+        }
 
-                let watermarked_r = algorithm.embed(&r_data, &watermark_bits, strength)?;
-                let watermarked_g = algorithm.embed(&g_data, &watermark_bits, strength)?;
-                let watermarked_b = algorithm.embed(&b_data, &watermark_bits, strength)?;
+        // 保存图片；image 编码器不会写出元数据，需要把原图的EXIF和ICC重新写入输出文件
+        Self::save_image(
+            &watermarked_img,
+            output_path.as_ref(),
+            algorithm,
+            options.jpeg_quality,
+        )?;
+        if !options.strip_metadata {
+            let metadata = ImageMetadata::read(&std::fs::read(&input_path)?);
+            if !metadata.is_empty() {
+                let encoded = std::fs::read(&output_path)?;
+                std::fs::write(&output_path, metadata.apply(&encoded)?)?;
+            }
+        }
+
+        Ok(psnr)
+    }
 
-                Self::array_to_image_rgb(&watermarked_r, &watermarked_g, &watermarked_b)?
+    /// 在内存中嵌入载荷并返回PSNR (dB)，不写出任何文件，用于预估嵌入后的画质
+    pub fn estimate_psnr<P: AsRef<Path>>(
+        input_path: P,
+        payload: &[u8],
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        strength: f64,
+        channel: ImageChannel,
+    ) -> Result<f64> {
+        WatermarkUtils::validate_strength(strength)?;
+        let img = Self::open_image(&input_path)?;
+        let watermarked_img = Self::embed_into_image(&img, payload, algorithm, strength, channel)?;
+        Ok(Self::calculate_psnr(&img, &watermarked_img))
+    }
+
+    /// 在动画GIF的每一帧嵌入同一份载荷，保留帧时长和循环次数，返回各帧中最低的PSNR (dB)
+    ///
+    /// PSNR在GIF调色板量化之前计算，反映的是水印本身；`min_psnr` 对每一帧检查。
+    fn embed_animation(
+        mut animation: GifAnimation,
+        output_path: &Path,
+        payload: &[u8],
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        strength: f64,
+        options: &ImageEmbedOptions,
+    ) -> Result<f64> {
+        let mut lowest_psnr = f64::INFINITY;
+        for (frame, _) in &mut animation.frames {
+            let watermarked =
+                Self::embed_with_image_options(frame, payload, algorithm, strength, options)?;
+            lowest_psnr = lowest_psnr.min(Self::calculate_psnr(frame, &watermarked));
+            *frame = watermarked;
+        }
+
+        if let Some(floor) = options.min_psnr {
+            if lowest_psnr < floor {
+                return Err(WatermarkError::QualityTooLow {
+                    measured: lowest_psnr,
+                    floor,
+                });
             }
+        }
+        animation.save(output_path)?;
+        Ok(lowest_psnr)
+    }
+
+    /// 二分查找使嵌入后的PSNR不低于且尽量接近 `target_psnr` 的强度，返回（强度, PSNR）
+    ///
+    /// 强度越大PSNR越低，每次尝试都在内存中对原尺寸图片完整嵌入一次；缩小后的图片中
+    /// 被修改的块所占比例不同，PSNR与原图不可比，因此不使用缩略图代替。
+    /// 最大强度仍不低于目标时直接使用1.0，最小强度也达不到目标时返回 `QualityTooLow`。
+    pub fn auto_strength<P: AsRef<Path>>(
+        input_path: P,
+        payload: &[u8],
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        target_psnr: f64,
+        options: &ImageEmbedOptions,
+    ) -> Result<(f64, f64)> {
+        let img = Self::open_image(&input_path)?;
+        let measure = |strength: f64| -> Result<f64> {
+            let watermarked_img =
+                Self::embed_with_image_options(&img, payload, algorithm, strength, options)?;
+            Ok(Self::calculate_psnr(&img, &watermarked_img))
         };
 
-        // 保存图片
-        watermarked_img.save(&output_path)?;
+        let strongest = measure(1.0)?;
+        if strongest >= target_psnr {
+            return Ok((1.0, strongest));
+        }
+        let weakest = measure(0.0)?;
+        if weakest < target_psnr {
+            return Err(WatermarkError::QualityTooLow {
+                measured: weakest,
+                floor: target_psnr,
+            });
+        }
+
+        // 始终保持 low 满足目标、high 不满足目标
+        let (mut low, mut low_psnr, mut high) = (0.0, weakest, 1.0);
+        for _ in 0..TARGET_PSNR_MAX_ITERATIONS {
+            if low_psnr - target_psnr <= TARGET_PSNR_TOLERANCE {
+                break;
+            }
+            let middle = (low + high) / 2.0;
+            let psnr = measure(middle)?;
+            if psnr >= target_psnr {
+                (low, low_psnr) = (middle, psnr);
+            } else {
+                high = middle;
+            }
+        }
+        Ok((low, low_psnr))
+    }
+
+    /// 按 `ImageEmbedOptions` 选择整幅、按条带、指定区域、规范尺寸或带自描述头部在内存中嵌入
+    fn embed_with_image_options(
+        img: &DynamicImage,
+        payload: &[u8],
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        strength: f64,
+        options: &ImageEmbedOptions,
+    ) -> Result<DynamicImage> {
+        if let Some(size) = options.normalize_size {
+            return Self::embed_normalized(img, payload, algorithm, strength, options, size);
+        }
+        if let Some(header) = &options.header {
+            return Self::embed_with_header(
+                img,
+                payload,
+                algorithm,
+                strength,
+                options.channel,
+                header,
+            );
+        }
+        if let Some(region) = options.region {
+            return Self::embed_in_region(
+                img,
+                payload,
+                algorithm,
+                strength,
+                options.channel,
+                region,
+            );
+        }
+        match options.strip_rows {
+            Some(strip_rows) => Self::embed_into_image_in_strips(
+                img,
+                payload,
+                algorithm,
+                strength,
+                options.channel,
+                strip_rows,
+            ),
+            None => Self::embed_into_image(img, payload, algorithm, strength, options.channel),
+        }
+    }
 
+    /// 在图片顶部的头部区域重复写入自描述头部，载荷嵌入其下方的区域
+    ///
+    /// 头部总是用默认参数的分块DCT写入R、G、B三个通道，强度不低于默认的0.1；
+    /// 头部区域的行数只取决于图片宽度，提取时无需任何参数即可定位。
+    fn embed_with_header(
+        img: &DynamicImage,
+        payload: &[u8],
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        strength: f64,
+        channel: ImageChannel,
+        header: &WatermarkHeader,
+    ) -> Result<DynamicImage> {
+        let (width, height) = (img.width(), img.height());
+        let rows = Self::header_rows(width, height)?;
+
+        let header_region = img.crop_imm(0, 0, width, rows);
+        let header_payload =
+            header.encode_repeated(header::region_capacity(rows as usize, width as usize))?;
+        let header_region = Self::embed_into_image(
+            &header_region,
+            &header_payload,
+            &header::region_algorithm(),
+            strength.max(HEADER_MIN_STRENGTH),
+            ImageChannel::All,
+        )?;
+
+        let payload_region = img.crop_imm(0, rows, width, height - rows);
+        let payload_region =
+            Self::embed_into_image(&payload_region, payload, algorithm, strength, channel)?;
+
+        let mut watermarked_img = blank_image(header_region.color(), width, height);
+        paste_at(&mut watermarked_img, &header_region, 0, 0)?;
+        paste_at(&mut watermarked_img, &payload_region, 0, rows as i64)?;
+        Ok(watermarked_img)
+    }
+
+    /// 在缩放到规范尺寸的图片上按其余选项嵌入，再把嵌入造成的改动缩放回原尺寸叠加到原图
+    ///
+    /// 只叠加改动而不是把嵌入结果整幅缩放回去，原图的细节不会因两次缩放而变模糊。
+    fn embed_normalized(
+        img: &DynamicImage,
+        payload: &[u8],
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        strength: f64,
+        options: &ImageEmbedOptions,
+        (width, height): (u32, u32),
+    ) -> Result<DynamicImage> {
+        let canonical = img.resize_exact(width, height, NORMALIZE_FILTER);
+        let watermarked = Self::embed_with_image_options(
+            &canonical,
+            payload,
+            algorithm,
+            strength,
+            &ImageEmbedOptions {
+                normalize_size: None,
+                ..options.clone()
+            },
+        )?;
+
+        let (rows, cols) = (img.height() as usize, img.width() as usize);
+        let channels: Vec<Array2<f64>> = Self::image_channels(img)?
+            .into_iter()
+            .zip(Self::image_channels(&canonical)?)
+            .zip(Self::image_channels(&watermarked)?)
+            .map(|((original, canonical), watermarked)| {
+                original + resize_array(&(watermarked - canonical), rows, cols)
+            })
+            .collect();
+        Self::channels_to_image(&channels, img)
+    }
+
+    /// 打开图片，指定了规范尺寸时缩放到该尺寸，与 `embed_normalized` 嵌入时一致
+    fn open_normalized<P: AsRef<Path>>(
+        input_path: P,
+        normalize_size: Option<(u32, u32)>,
+    ) -> Result<DynamicImage> {
+        let img = Self::open_image(&input_path)?;
+        Ok(match normalize_size {
+            Some((width, height)) => img.resize_exact(width, height, NORMALIZE_FILTER),
+            None => img,
+        })
+    }
+
+    /// 只在 `region` 内嵌入载荷，区域外的像素原样保留
+    fn embed_in_region(
+        img: &DynamicImage,
+        payload: &[u8],
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        strength: f64,
+        channel: ImageChannel,
+        region: ImageRegion,
+    ) -> Result<DynamicImage> {
+        Self::check_region(img, region)?;
+        let part = img.crop_imm(region.x, region.y, region.width, region.height);
+        let part = Self::embed_into_image(&part, payload, algorithm, strength, channel)?;
+
+        let mut watermarked_img = img.clone();
+        paste_at(
+            &mut watermarked_img,
+            &part,
+            region.x as i64,
+            region.y as i64,
+        )?;
+        Ok(watermarked_img)
+    }
+
+    /// 检查区域是否完全落在图片内
+    fn check_region(img: &DynamicImage, region: ImageRegion) -> Result<()> {
+        let fits = |start: u32, length: u32, limit: u32| {
+            start.checked_add(length).is_some_and(|end| end <= limit)
+        };
+        if !fits(region.x, region.width, img.width())
+            || !fits(region.y, region.height, img.height())
+        {
+            return Err(WatermarkError::InvalidArgument(
+                Message::RegionOutsideImage.format(&[
+                    &region.x,
+                    &region.y,
+                    &region.width,
+                    &region.height,
+                    &img.width(),
+                    &img.height(),
+                ]),
+            ));
+        }
         Ok(())
     }
 
-    /// 从图片中提取水印
+    /// 头部区域的行数，图片宽度不足一个块或高度放不下头部区域之外的载荷时报错
+    fn header_rows(width: u32, height: u32) -> Result<u32> {
+        let rows = header::region_rows(width as usize) as u32;
+        if rows == 0 || rows >= height {
+            return Err(WatermarkError::InvalidArgument(
+                Message::HeaderDoesNotFit.format(&[&width, &height, &rows]),
+            ));
+        }
+        Ok(rows)
+    }
+
+    /// 读取图片顶部的自描述头部，没有头部或头部损坏时返回 `InvalidWatermark`
+    ///
+    /// 嵌入时使用了 `--normalize-size` 的图片须给出相同的规范尺寸，先缩放再读取。
+    pub fn read_header<P: AsRef<Path>>(
+        input_path: P,
+        normalize_size: Option<(u32, u32)>,
+    ) -> Result<WatermarkHeader> {
+        let img = Self::open_normalized(&input_path, normalize_size)?;
+        let rows = Self::header_rows(img.width(), img.height())?;
+        let data = Self::select_channel(&img.crop_imm(0, 0, img.width(), rows), ImageChannel::All)?;
+        let capacity = header::region_capacity(rows as usize, img.width() as usize);
+        let decisions = header::region_algorithm()
+            .extract_soft(&data, capacity / header::HEADER_BITS * header::HEADER_BITS)?;
+        WatermarkHeader::decode_repeated(&decisions).ok_or(WatermarkError::InvalidWatermark)
+    }
+
+    /// 图片顶部是否带有可读取的自描述头部，用于发现已经嵌入过水印的输入
+    ///
+    /// 按原尺寸读取，给出 `normalize_size` 时也按该规范尺寸读取；图片太小放不下头部时视为没有。
+    pub fn has_header<P: AsRef<Path>>(input_path: P, normalize_size: Option<(u32, u32)>) -> bool {
+        Self::read_header(&input_path, None).is_ok()
+            || normalize_size.is_some_and(|size| Self::read_header(&input_path, Some(size)).is_ok())
+    }
+
+    /// 从自描述头部下方的区域提取原始载荷，同时返回平均软判决置信度
+    pub fn extract_bytes_after_header<P: AsRef<Path>>(
+        input_path: P,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        byte_length: usize,
+        channel: ImageChannel,
+        normalize_size: Option<(u32, u32)>,
+    ) -> Result<(Vec<u8>, f64)> {
+        let img = Self::open_normalized(&input_path, normalize_size)?;
+        let rows = Self::header_rows(img.width(), img.height())?;
+        let data = Self::select_channel(
+            &img.crop_imm(0, rows, img.width(), img.height() - rows),
+            channel,
+        )?;
+        let decisions = algorithm.extract_soft(&data, byte_length * 8)?;
+
+        let bits: Vec<u8> = decisions.iter().map(|&(bit, _)| bit).collect();
+        Ok((
+            WatermarkUtils::bits_to_bytes(&bits),
+            WatermarkUtils::mean_confidence(&decisions),
+        ))
+    }
+
+    /// 从嵌入时 `--region` 指定的矩形区域提取原始载荷，同时返回平均软判决置信度
+    pub fn extract_bytes_from_region<P: AsRef<Path>>(
+        input_path: P,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        byte_length: usize,
+        channel: ImageChannel,
+        region: ImageRegion,
+    ) -> Result<(Vec<u8>, f64)> {
+        let img = Self::open_image(&input_path)?;
+        Self::check_region(&img, region)?;
+        let data = Self::select_channel(
+            &img.crop_imm(region.x, region.y, region.width, region.height),
+            channel,
+        )?;
+        let decisions = algorithm.extract_soft(&data, byte_length * 8)?;
+
+        let bits: Vec<u8> = decisions.iter().map(|&(bit, _)| bit).collect();
+        Ok((
+            WatermarkUtils::bits_to_bytes(&bits),
+            WatermarkUtils::mean_confidence(&decisions),
+        ))
+    }
+
+    /// 把图片缩放到嵌入时 `--normalize-size` 指定的规范尺寸后提取原始载荷，
+    /// 同时返回平均软判决置信度
+    pub fn extract_bytes_normalized<P: AsRef<Path>>(
+        input_path: P,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        byte_length: usize,
+        channel: ImageChannel,
+        normalize_size: (u32, u32),
+    ) -> Result<(Vec<u8>, f64)> {
+        let img = Self::open_normalized(&input_path, Some(normalize_size))?;
+        let data = Self::select_channel(&img, channel)?;
+        let decisions = algorithm.extract_soft(&data, byte_length * 8)?;
+
+        let bits: Vec<u8> = decisions.iter().map(|&(bit, _)| bit).collect();
+        Ok((
+            WatermarkUtils::bits_to_bytes(&bits),
+            WatermarkUtils::mean_confidence(&decisions),
+        ))
+    }
+
+    /// 按输出扩展名保存图片，JPEG使用指定的编码质量而不是 image 库默认的75
+    fn save_image(
+        img: &DynamicImage,
+        output_path: &Path,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        jpeg_quality: u8,
+    ) -> Result<()> {
+        let format = ImageFormat::from_path(output_path).ok();
+        // 只有PNG和TIFF能保存每通道16位的数据，其余格式降为8位；JPEG不支持透明通道
+        let converted;
+        let img = if is_16bit(img.color())
+            && !matches!(format, Some(ImageFormat::Png | ImageFormat::Tiff))
+        {
+            eprintln!("{} {}", "⚠️".yellow(), Message::SixteenBitDowngraded.text());
+            converted = match img.color() {
+                ColorType::L16 => DynamicImage::ImageLuma8(img.to_luma8()),
+                ColorType::La16 => DynamicImage::ImageLumaA8(img.to_luma_alpha8()),
+                ColorType::Rgba16 => DynamicImage::ImageRgba8(img.to_rgba8()),
+                _ => DynamicImage::ImageRgb8(img.to_rgb8()),
+            };
+            &converted
+        } else {
+            img
+        };
+        let without_alpha;
+        let img = if img.color().has_alpha() && format == Some(ImageFormat::Jpeg) {
+            without_alpha = match img.color() {
+                ColorType::La8 | ColorType::La16 => DynamicImage::ImageLuma8(img.to_luma8()),
+                _ => DynamicImage::ImageRgb8(img.to_rgb8()),
+            };
+            &without_alpha
+        } else {
+            img
+        };
+
+        // image 库不带AVIF和HEIF编码器：AVIF交给FFmpeg编码，HEIF没有可用的封装器
+        let extension = output_path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_lowercase);
+        match extension.as_deref() {
+            Some("avif") => {
+                eprintln!(
+                    "{} {}",
+                    "⚠️".yellow(),
+                    Message::LossyImageFormat.format(&[&"AVIF"])
+                );
+                return Self::save_avif_via_ffmpeg(img, output_path, jpeg_quality);
+            }
+            Some(extension @ ("heic" | "heif")) => {
+                return Err(WatermarkError::UnsupportedFormat(
+                    Message::CannotWriteImage.format(&[&extension]),
+                ));
+            }
+            _ => {}
+        }
+
+        if format != Some(ImageFormat::Jpeg) {
+            img.save(output_path)?;
+            return Ok(());
+        }
+
+        if algorithm.name() == "DCT" && jpeg_quality < LOW_JPEG_QUALITY {
+            eprintln!(
+                "{} {}",
+                "⚠️".yellow(),
+                Message::LowJpegQuality.format(&[&jpeg_quality, &LOW_JPEG_QUALITY])
+            );
+        }
+
+        let writer = BufWriter::new(File::create(output_path)?);
+        img.write_with_encoder(JpegEncoder::new_with_quality(writer, jpeg_quality))?;
+        Ok(())
+    }
+
+    /// 对内存中的图片嵌入载荷，不做文件读写（视频逐帧流式处理时使用）
+    pub(crate) fn embed_into_image(
+        img: &DynamicImage,
+        payload: &[u8],
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        strength: f64,
+        channel: ImageChannel,
+    ) -> Result<DynamicImage> {
+        // 将水印载荷转换为比特
+        let watermark_bits = WatermarkUtils::bytes_to_bits(payload);
+        WatermarkUtils::ensure_capacity(
+            watermark_bits.len(),
+            algorithm.capacity(img.height() as usize, img.width() as usize),
+        )?;
+
+        // 灰度图片嵌入亮度；彩色图片默认在 R、G、B 三个通道分别嵌入同一份水印，
+        // 也可只嵌入单个通道，或把亮度上的改动同步加到三个通道
+        let mut channels = Self::image_channels(img)?;
+        let watermarked_channels = if channels.len() == 1 || channel == ImageChannel::All {
+            map_channels(&channels, |data| {
+                algorithm.embed(data, &watermark_bits, strength)
+            })?
+        } else if let Some(index) = channel_index(channel) {
+            channels[index] = algorithm.embed(&channels[index], &watermark_bits, strength)?;
+            channels
+        } else {
+            let luma = luma(&channels);
+            let delta = algorithm.embed(&luma, &watermark_bits, strength)? - &luma;
+            channels.iter().map(|data| data + &delta).collect()
+        };
+        let watermarked_img = Self::channels_to_image(&watermarked_channels, img)?;
+
+        Ok(watermarked_img)
+    }
+
+    /// 按水平条带分段嵌入载荷，结果与 `embed_into_image` 逐像素相同
+    ///
+    /// 每次只把约 `strip_rows` 行转换为浮点矩阵，超大图片的峰值内存从整幅图的若干份
+    /// 浮点副本降到解码后的原图和输出图各一份；只支持逐块独立处理的算法（DCT分块模式）。
+    pub(crate) fn embed_into_image_in_strips(
+        img: &DynamicImage,
+        payload: &[u8],
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        strength: f64,
+        channel: ImageChannel,
+        strip_rows: usize,
+    ) -> Result<DynamicImage> {
+        let watermark_bits = WatermarkUtils::bytes_to_bits(payload);
+        let (width, height) = (img.width(), img.height());
+        let plan = algorithm.strip_plan(height as usize, width as usize, watermark_bits.len())?;
+
+        let mut watermarked_img: Option<DynamicImage> = None;
+        for rows in plan.strips(height as usize, strip_rows) {
+            let part = img.crop_imm(0, rows.start as u32, width, rows.len() as u32);
+            let first_block_row = rows.start / plan.block_size;
+            let embed = |data: &Array2<f64>| {
+                algorithm.embed_strip(&plan, data, first_block_row, &watermark_bits, strength)
+            };
+
+            // 通道的处理方式与 embed_into_image 相同
+            let mut channels = Self::image_channels(&part)?;
+            let watermarked_channels = if channels.len() == 1 || channel == ImageChannel::All {
+                map_channels(&channels, embed)?
+            } else if let Some(index) = channel_index(channel) {
+                channels[index] = embed(&channels[index])?;
+                channels
+            } else {
+                let luma = luma(&channels);
+                let delta = embed(&luma)? - &luma;
+                channels.iter().map(|data| data + &delta).collect()
+            };
+            let strip = Self::channels_to_image(&watermarked_channels, &part)?;
+
+            let target =
+                watermarked_img.get_or_insert_with(|| blank_image(strip.color(), width, height));
+            paste_at(target, &strip, 0, rows.start as i64)?;
+        }
+
+        watermarked_img
+            .ok_or_else(|| WatermarkError::ProcessingError(Message::EmptyImage.text().to_string()))
+    }
+
+    /// 计算两幅图片之间的PSNR (dB)，两图完全相同时返回正无穷
+    ///
+    /// 两幅图片都是每通道16位时按16位的取值范围计算，否则按8位计算。
+    pub fn calculate_psnr(original: &DynamicImage, watermarked: &DynamicImage) -> f64 {
+        if is_16bit(original.color()) && is_16bit(watermarked.color()) {
+            return Self::psnr(
+                original.to_rgb16().as_raw(),
+                watermarked.to_rgb16().as_raw(),
+                u16::MAX as f64,
+            );
+        }
+        Self::psnr(
+            original.to_rgb8().as_raw(),
+            watermarked.to_rgb8().as_raw(),
+            u8::MAX as f64,
+        )
+    }
+
+    fn psnr<T: Copy + Into<f64>>(original: &[T], watermarked: &[T], peak: f64) -> f64 {
+        let count = original.len().min(watermarked.len());
+        if count == 0 {
+            return f64::INFINITY;
+        }
+
+        let mse = original
+            .iter()
+            .zip(watermarked)
+            .map(|(&a, &b)| (a.into() - b.into()).powi(2))
+            .sum::<f64>()
+            / count as f64;
+
+        if mse == 0.0 {
+            f64::INFINITY
+        } else {
+            10.0 * (peak * peak / mse).log10()
+        }
+    }
+
+    /// 从图片中提取水印文本，同时给出平均置信度
     pub fn extract_watermark<P: AsRef<Path>>(
         input_path: P,
-        algorithm: &dyn WatermarkAlgorithm,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
         watermark_length: usize,
-    ) -> Result<String> {
-        // 加载图片
-        let img = image::open(&input_path)?;
-
-        let extracted_bits = match img.color() {
-            ColorType::L8 => {
-                // 灰度图片处理
-                let gray_img = img.to_luma8();
-                let data = Self::image_to_array_gray(&gray_img)?;
-                algorithm.extract(&data, watermark_length * 8)?
+    ) -> Result<ExtractionResult> {
+        let (bytes, confidence) =
+            Self::extract_bytes_with_confidence(input_path, algorithm, watermark_length)?;
+
+        // 加密载荷无法直接作为文本读取
+        if crypto::is_encrypted(&bytes) {
+            return Err(WatermarkError::PasswordRequired);
+        }
+
+        // 转换为字符串
+        let text = WatermarkUtils::bytes_to_string(bytes)?;
+        Ok(ExtractionResult::single(text, confidence))
+    }
+
+    /// 从图片中提取指定字节数的原始载荷
+    pub fn extract_bytes<P: AsRef<Path>>(
+        input_path: P,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        byte_length: usize,
+    ) -> Result<Vec<u8>> {
+        Self::extract_bytes_with_confidence(input_path, algorithm, byte_length)
+            .map(|(payload, _)| payload)
+    }
+
+    /// 从图片中提取原始载荷，同时返回所有比特的平均软判决置信度
+    ///
+    /// 没有水印的图片中系数幅度普遍偏小，置信度会明显偏低。
+    pub fn extract_bytes_with_confidence<P: AsRef<Path>>(
+        input_path: P,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        byte_length: usize,
+    ) -> Result<(Vec<u8>, f64)> {
+        Self::extract_bytes_from_channel(input_path, algorithm, byte_length, ImageChannel::All)
+    }
+
+    /// 从嵌入时使用的颜色通道提取原始载荷，同时返回平均软判决置信度
+    ///
+    /// `ImageChannel::All` 读取R通道（多通道投票见 extract_bytes_with_channel_vote），
+    /// 灰度图片总是读取亮度。
+    pub fn extract_bytes_from_channel<P: AsRef<Path>>(
+        input_path: P,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        byte_length: usize,
+        channel: ImageChannel,
+    ) -> Result<(Vec<u8>, f64)> {
+        if let Some(animation) = GifAnimation::open(input_path.as_ref())? {
+            return Self::extract_from_animation(&animation, algorithm, byte_length, channel);
+        }
+
+        let data = Self::load_image_channel(input_path, channel)?;
+        let decisions = algorithm.extract_soft(&data, byte_length * 8)?;
+
+        let bits: Vec<u8> = decisions.iter().map(|&(bit, _)| bit).collect();
+        Ok((
+            WatermarkUtils::bits_to_bytes(&bits),
+            WatermarkUtils::mean_confidence(&decisions),
+        ))
+    }
+
+    /// 从动画GIF的每一帧提取软判决并合并：每个比特按各帧置信度加权投票，
+    /// 合并后的置信度为得票差占总帧数的比例
+    fn extract_from_animation(
+        animation: &GifAnimation,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        byte_length: usize,
+        channel: ImageChannel,
+    ) -> Result<(Vec<u8>, f64)> {
+        let mut scores = vec![0.0; byte_length * 8];
+        for (frame, _) in &animation.frames {
+            let data = Self::select_channel(frame, channel)?;
+            for (score, (bit, confidence)) in scores
+                .iter_mut()
+                .zip(algorithm.extract_soft(&data, byte_length * 8)?)
+            {
+                *score += if bit == 1 { confidence } else { -confidence };
             }
-            ColorType::Rgb8 | ColorType::Rgba8 => {
-                // 彩色图片处理 - 从R通道提取（也可以投票）
-                let rgb_img = img.to_rgb8();
-                let (r_data, _g_data, _b_data) = Self::image_to_array_rgb(&rgb_img)?;
-                algorithm.extract(&r_data, watermark_length * 8)?
+        }
+
+        let frames = animation.frames.len() as f64;
+        let decisions: Vec<(u8, f64)> = scores
+            .iter()
+            .map(|&score| (u8::from(score >= 0.0), score.abs() / frames))
+            .collect();
+        let bits: Vec<u8> = decisions.iter().map(|&(bit, _)| bit).collect();
+        Ok((
+            WatermarkUtils::bits_to_bytes(&bits),
+            WatermarkUtils::mean_confidence(&decisions),
+        ))
+    }
+
+    /// 按水平条带分段提取原始载荷，同时返回平均软判决置信度，结果与
+    /// `extract_bytes_from_channel` 相同
+    ///
+    /// 每次只把约 `strip_rows` 行转换为浮点矩阵；只支持逐块独立处理的算法（DCT分块模式）。
+    pub fn extract_bytes_in_strips<P: AsRef<Path>>(
+        input_path: P,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        byte_length: usize,
+        channel: ImageChannel,
+        strip_rows: usize,
+    ) -> Result<(Vec<u8>, f64)> {
+        let img = Self::open_image(&input_path)?;
+        let (width, height) = (img.width(), img.height());
+        let plan = algorithm.strip_plan(height as usize, width as usize, byte_length * 8)?;
+
+        let mut decisions = vec![(0u8, 0.0); plan.bits];
+        for rows in plan.strips(height as usize, strip_rows) {
+            let part = img.crop_imm(0, rows.start as u32, width, rows.len() as u32);
+            let data = Self::select_channel(&part, channel)?;
+            for (bit_idx, decision) in
+                algorithm.extract_strip_soft(&plan, &data, rows.start / plan.block_size)?
+            {
+                decisions[bit_idx] = decision;
             }
-            _ => {
-                // 其他格式转换为RGB处理
-                let rgb_img = img.to_rgb8();
-                let (r_data, _g_data, _b_data) = Self::image_to_array_rgb(&rgb_img)?;
-                algorithm.extract(&r_data, watermark_length * 8)?
+        }
+
+        let bits: Vec<u8> = decisions.iter().map(|&(bit, _)| bit).collect();
+        Ok((
+            WatermarkUtils::bits_to_bytes(&bits),
+            WatermarkUtils::mean_confidence(&decisions),
+        ))
+    }
+
+    /// 对文件依次尝试一组预处理后提取，返回平均置信度最高的结果
+    ///
+    /// 用于被缩放、轻微旋转或重新压缩过的图片，耗时约为直接提取的几十倍；
+    /// 某个预处理后容量不足等导致提取失败时跳过该项，全部失败时返回直接提取的错误。
+    pub fn extract_bytes_robust<P: AsRef<Path>>(
+        input_path: P,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        byte_length: usize,
+        channel: ImageChannel,
+    ) -> Result<RobustExtraction> {
+        let data = Self::load_image_channel(input_path, channel)?;
+        let (rows, cols) = data.dim();
+        let candidates = Recovery::candidates(rows, cols);
+        let attempts = candidates.len();
+
+        let mut best: Option<RobustExtraction> = None;
+        let mut first_error = None;
+        for recovery in candidates {
+            let decisions = match recovery
+                .apply(&data)
+                .and_then(|prepared| algorithm.extract_soft(&prepared, byte_length * 8))
+            {
+                Ok(decisions) => decisions,
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                    continue;
+                }
+            };
+            let confidence = WatermarkUtils::mean_confidence(&decisions);
+            if best
+                .as_ref()
+                .is_none_or(|best| confidence > best.confidence)
+            {
+                let bits: Vec<u8> = decisions.iter().map(|&(bit, _)| bit).collect();
+                best = Some(RobustExtraction {
+                    bytes: WatermarkUtils::bits_to_bytes(&bits),
+                    confidence,
+                    recovery,
+                    attempts,
+                });
+            }
+        }
+
+        match (best, first_error) {
+            (Some(best), _) => Ok(best),
+            (None, Some(e)) => Err(e),
+            (None, None) => unreachable!("候选预处理至少包含 Identity"),
+        }
+    }
+
+    /// 把每个块的提取置信度保存为灰度图，返回置信度图的 (宽, 高)
+    ///
+    /// 每个块对应一个像素，亮度为该块的置信度（0-255），与提取使用同一颜色通道；
+    /// 裁剪或涂改过的区域系数幅度偏小，在图中显得更暗。未承载比特的块画成 `UNUSED_BLOCK_COLOR`，
+    /// 与置信度为0的黑色区分开。
+    pub fn save_confidence_map<P: AsRef<Path>, Q: AsRef<Path>>(
+        input_path: P,
+        output_path: Q,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        byte_length: usize,
+        channel: ImageChannel,
+    ) -> Result<(u32, u32)> {
+        let data = Self::load_image_channel(input_path, channel)?;
+        let map = algorithm.block_confidence(&data, byte_length * 8)?;
+        let (rows, cols) = map.dim();
+        let map_img = ImageBuffer::from_fn(cols as u32, rows as u32, |x, y| {
+            let confidence = map[[y as usize, x as usize]];
+            if confidence.is_nan() {
+                Rgb(UNUSED_BLOCK_COLOR)
+            } else {
+                let level = (confidence.clamp(0.0, 1.0) * 255.0).round() as u8;
+                Rgb([level; 3])
             }
+        });
+        map_img.save(output_path)?;
+
+        Ok((cols as u32, rows as u32))
+    }
+
+    /// 比较原始图片和水印图片，给出逐块DCT系数差异和整幅图片的PSNR
+    ///
+    /// DCT是线性变换，系数差即像素差的逐块DCT；两张图片的尺寸必须相同。
+    pub fn diff_images<P: AsRef<Path>>(
+        original_path: P,
+        watermarked_path: P,
+        block_size: usize,
+        channel: ImageChannel,
+    ) -> Result<ImageDiff> {
+        let original = Self::open_image(&original_path)?;
+        let watermarked = Self::open_image(&watermarked_path)?;
+        let size = |img: &DynamicImage| format!("{}x{}", img.width(), img.height());
+        if size(&original) != size(&watermarked) {
+            return Err(WatermarkError::InvalidArgument(
+                Message::ImageSizesDiffer.format(&[&size(&original), &size(&watermarked)]),
+            ));
+        }
+
+        let difference = Self::select_channel(&watermarked, channel)?
+            - Self::select_channel(&original, channel)?;
+        let coefficients = DctWatermark::new()
+            .with_block_size(block_size)
+            .blockwise_dct(&difference);
+
+        let (rows, cols) = coefficients.dim();
+        let (blocks_h, blocks_w) = (rows / block_size, cols / block_size);
+        let mut block_energy = Array2::zeros((blocks_h, blocks_w));
+        let mut coefficient_energy = Array2::zeros((block_size, block_size));
+        for ((y, x), &coefficient) in coefficients.indexed_iter() {
+            if y >= blocks_h * block_size || x >= blocks_w * block_size {
+                continue;
+            }
+            block_energy[[y / block_size, x / block_size]] += coefficient * coefficient;
+            coefficient_energy[[y % block_size, x % block_size]] += coefficient.abs();
+        }
+        let block_count = (blocks_h * blocks_w).max(1) as f64;
+        block_energy.mapv_inplace(|sum: f64| (sum / (block_size * block_size) as f64).sqrt());
+        coefficient_energy.mapv_inplace(|sum| sum / block_count);
+
+        Ok(ImageDiff {
+            psnr: Self::calculate_psnr(&original, &watermarked),
+            block_size,
+            block_energy,
+            coefficient_energy,
+        })
+    }
+
+    /// 读取提取时使用的颜色通道：彩色图片为R通道，灰度图片为亮度
+    pub fn load_channel<P: AsRef<Path>>(input_path: P) -> Result<Array2<f64>> {
+        Self::load_image_channel(input_path, ImageChannel::All)
+    }
+
+    /// 读取指定的颜色通道，灰度图片总是返回亮度
+    pub fn load_image_channel<P: AsRef<Path>>(
+        input_path: P,
+        channel: ImageChannel,
+    ) -> Result<Array2<f64>> {
+        Self::select_channel(&Self::open_image(&input_path)?, channel)
+    }
+
+    /// 计算图片的水印存在性得分 (0.0-1.0)
+    ///
+    /// 对前 `DETECTION_PROBE_BITS` 个比特做软判决，取平均置信度；
+    /// 带水印的图片系数幅度被推到嵌入阈值以上，得分接近1.0。
+    pub fn detect_watermark<P: AsRef<Path>>(
+        input_path: P,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+    ) -> Result<f64> {
+        let (_, score) = Self::extract_bytes_with_confidence(
+            input_path,
+            algorithm,
+            WatermarkUtils::DETECTION_PROBE_BITS / 8,
+        )?;
+        Ok(score)
+    }
+
+    /// 从图片的各颜色通道分别提取比特并投票
+    ///
+    /// 彩色图片对 `channels` 中的通道分别提取，为空时使用 R、G、B 全部三个通道；
+    /// 灰度图片只有一个通道，忽略 `channels`。
+    /// 至少 `threshold` 个通道一致的比特才被接受，否则标记为不确定。
+    pub fn extract_bytes_with_channel_vote<P: AsRef<Path>>(
+        input_path: P,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        byte_length: usize,
+        channels: &[ImageChannel],
+        strategy: VoteStrategy,
+        threshold: usize,
+    ) -> Result<(Vec<u8>, ChannelVote)> {
+        let img = Self::open_image(&input_path)?;
+        let bit_count = byte_length * 8;
+
+        let mut data = Self::image_channels(&img)?;
+        if data.len() > 1 && !channels.is_empty() {
+            let selected: Vec<usize> = channels.iter().filter_map(|&c| channel_index(c)).collect();
+            data = data
+                .into_iter()
+                .enumerate()
+                .filter(|(index, _)| selected.contains(index))
+                .map(|(_, channel)| channel)
+                .collect();
+        }
+
+        let decisions = map_channels(&data, |channel| algorithm.extract_soft(channel, bit_count))?;
+
+        let vote = match strategy {
+            VoteStrategy::Majority => {
+                let channel_bits: Vec<Vec<u8>> = decisions
+                    .iter()
+                    .map(|channel| channel.iter().map(|&(bit, _)| bit).collect())
+                    .collect();
+                Self::vote_channels(&channel_bits, threshold)
+            }
+            VoteStrategy::Confidence => Self::vote_channels_weighted(&decisions, threshold),
         };
+        Ok((WatermarkUtils::bits_to_bytes(&vote.bits), vote))
+    }
 
-        // 转换为字符串
-        let watermark_text = WatermarkUtils::bits_to_string(&extracted_bits)?;
+    /// 对各通道提取到的比特做多数投票
+    ///
+    /// 每个比特的置信度为同意多数结果的通道比例，未达到 `threshold` 的比特置信度减半。
+    /// `threshold` 会被限制在 1 到通道数之间。
+    pub fn vote_channels(channel_bits: &[Vec<u8>], threshold: usize) -> ChannelVote {
+        let channels = channel_bits.len();
+        let threshold = threshold.clamp(1, channels.max(1));
+        let bit_count = channel_bits.iter().map(Vec::len).min().unwrap_or(0);
 
-        Ok(watermark_text)
+        let mut vote = ChannelVote {
+            bits: Vec::with_capacity(bit_count),
+            bit_confidence: Vec::with_capacity(bit_count),
+            uncertain_bits: Vec::new(),
+        };
+
+        for i in 0..bit_count {
+            let ones = channel_bits.iter().filter(|bits| bits[i] == 1).count();
+            let (bit, agree) = if ones * 2 > channels {
+                (1, ones)
+            } else {
+                (0, channels - ones)
+            };
+
+            let mut confidence = agree as f64 / channels as f64;
+            if agree < threshold {
+                confidence *= 0.5;
+                vote.uncertain_bits.push(i);
+            }
+
+            vote.bits.push(bit);
+            vote.bit_confidence.push(confidence);
+        }
+
+        vote
+    }
+
+    /// 对各通道的软判决按置信度加权投票
+    ///
+    /// 每个通道按软判决幅度投票，得分的符号决定比特；比特的置信度为同意的通道
+    /// 幅度之和占全部幅度的比例。同意的通道数未达到 `threshold` 的比特置信度减半。
+    /// 不提供软判决的算法各通道幅度都为1，结果与多数投票相同。
+    pub fn vote_channels_weighted(
+        channel_decisions: &[Vec<(u8, f64)>],
+        threshold: usize,
+    ) -> ChannelVote {
+        let channels = channel_decisions.len();
+        let threshold = threshold.clamp(1, channels.max(1));
+        let bit_count = channel_decisions.iter().map(Vec::len).min().unwrap_or(0);
+
+        let mut vote = ChannelVote {
+            bits: Vec::with_capacity(bit_count),
+            bit_confidence: Vec::with_capacity(bit_count),
+            uncertain_bits: Vec::new(),
+        };
+
+        for i in 0..bit_count {
+            let (ones, zeros) = channel_decisions.iter().map(|decisions| decisions[i]).fold(
+                ((0, 0.0), (0, 0.0)),
+                |((ones, one_weight), (zeros, zero_weight)), (bit, confidence)| {
+                    let confidence = confidence.abs();
+                    if bit == 1 {
+                        ((ones + 1, one_weight + confidence), (zeros, zero_weight))
+                    } else {
+                        ((ones, one_weight), (zeros + 1, zero_weight + confidence))
+                    }
+                },
+            );
+            // 幅度相同时按通道数决定，仍相同时取0，与多数投票一致
+            let (bit, (agree, weight)) =
+                if ones.1 > zeros.1 || (ones.1 == zeros.1 && ones.0 > zeros.0) {
+                    (1, ones)
+                } else {
+                    (0, zeros)
+                };
+
+            let total = ones.1 + zeros.1;
+            let mut confidence = if total > 0.0 {
+                weight / total
+            } else {
+                agree as f64 / channels as f64
+            };
+            if agree < threshold {
+                confidence *= 0.5;
+                vote.uncertain_bits.push(i);
+            }
+
+            vote.bits.push(bit);
+            vote.bit_confidence.push(confidence);
+        }
+
+        vote
     }
 
     /// 从图片中提取水印（调试模式）
     pub fn extract_watermark_debug<P: AsRef<Path>>(
         input_path: P,
-        algorithm: &dyn WatermarkAlgorithm,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
         watermark_length: usize,
         verbose: bool,
     ) -> Result<String> {
         // 加载图片
-        let img = image::open(&input_path)?;
+        let img = Self::open_image(&input_path)?;
 
         if verbose {
             eprintln!(
-                "图片信息: {}x{} 像素, 格式: {:?}",
-                img.width(),
-                img.height(),
-                img.color()
+                "{}",
+                Message::DebugImageInfo.format(&[
+                    &img.width(),
+                    &img.height(),
+                    &format!("{:?}", img.color())
+                ])
             );
         }
 
-        let data = match img.color() {
-            ColorType::L8 => {
-                let gray_img = img.to_luma8();
-                Self::image_to_array_gray(&gray_img)?
-            }
-            ColorType::Rgb8 | ColorType::Rgba8 => {
-                let rgb_img = img.to_rgb8();
-                let (r_data, _g_data, _b_data) = Self::image_to_array_rgb(&rgb_img)?;
-                r_data
-            }
-            _ => {
-                let rgb_img = img.to_rgb8();
-                let (r_data, _g_data, _b_data) = Self::image_to_array_rgb(&rgb_img)?;
-                r_data
-            }
-        };
+        let data = Self::select_channel(&img, ImageChannel::All)?;
 
         if verbose {
             eprintln!(
-                "尝试提取 {} 字符的水印 ({} 比特)...",
-                watermark_length,
-                watermark_length * 8
+                "{}",
+                Message::DebugExtracting.format(&[&watermark_length, &(watermark_length * 8)])
             );
         }
 
@@ -154,28 +1262,28 @@ impl ImageWatermarker {
                 // 尝试严格转换
                 match WatermarkUtils::bits_to_string(&extracted_bits) {
                     Ok(watermark_text) => {
-                        eprintln!("水印提取完成:");
-                        eprintln!("使用算法: {}", algorithm.name());
-                        eprintln!("提取到的水印: {watermark_text}");
+                        eprintln!("{}", Message::DebugExtracted.text());
+                        eprintln!("{}", Message::DebugAlgorithm.format(&[&algorithm.name()]));
+                        eprintln!("{}", Message::DebugWatermark.format(&[&watermark_text]));
                         Ok(watermark_text)
                     }
                     Err(_) => {
                         if verbose {
-                            eprintln!("严格UTF-8转换失败，尝试宽松模式...");
+                            eprintln!("{}", Message::DebugStrictUtf8Failed.text());
                         }
 
                         let lossy_text = WatermarkUtils::bits_to_string_lossy(&extracted_bits);
-                        eprintln!("水印提取完成 (宽松模式):");
-                        eprintln!("使用算法: {}", algorithm.name());
-                        eprintln!("提取到的水印: {lossy_text}");
+                        eprintln!("{}", Message::DebugExtractedLossy.text());
+                        eprintln!("{}", Message::DebugAlgorithm.format(&[&algorithm.name()]));
+                        eprintln!("{}", Message::DebugWatermark.format(&[&lossy_text]));
                         Ok(lossy_text)
                     }
                 }
             }
             Err(e) => {
                 if verbose {
-                    eprintln!("标准提取失败: {e}");
-                    eprintln!("尝试投票提取方法...");
+                    eprintln!("{}", Message::DebugStandardFailed.format(&[&e]));
+                    eprintln!("{}", Message::DebugTryVoting.text());
                 }
 
                 // 尝试投票提取
@@ -183,14 +1291,14 @@ impl ImageWatermarker {
                 {
                     Ok(voted_bits) => {
                         if verbose {
-                            eprintln!("投票提取结果:");
+                            eprintln!("{}", Message::DebugVotingResult.text());
                             eprintln!("{}", WatermarkUtils::analyze_extracted_bits(&voted_bits));
                         }
 
                         let lossy_text = WatermarkUtils::bits_to_string_lossy(&voted_bits);
-                        eprintln!("水印提取完成 (投票模式):");
-                        eprintln!("使用算法: {}", algorithm.name());
-                        eprintln!("提取到的水印: {lossy_text}");
+                        eprintln!("{}", Message::DebugExtractedVoting.text());
+                        eprintln!("{}", Message::DebugAlgorithm.format(&[&algorithm.name()]));
+                        eprintln!("{}", Message::DebugWatermark.format(&[&lossy_text]));
                         Ok(lossy_text)
                     }
                     Err(_) => Err(e),
@@ -199,6 +1307,96 @@ impl ImageWatermarker {
         }
     }
 
+    /// 读取图片各颜色通道并标准化到 [0.0, 1.0]：灰度图片（含带透明通道的）为亮度一个通道，
+    /// 其余为 R、G、B 三个通道
+    ///
+    /// 每通道16位的图片按65535标准化，不先降为8位；透明通道不参与嵌入。
+    fn image_channels(img: &DynamicImage) -> Result<Vec<Array2<f64>>> {
+        let channels = match img.color() {
+            ColorType::L8 | ColorType::La8 => vec![Self::image_to_array_gray(&img.to_luma8())?],
+            ColorType::L16 | ColorType::La16 => {
+                vec![Self::image_to_array_gray16(&img.to_luma16())?]
+            }
+            color if is_16bit(color) => {
+                let (r, g, b) = Self::image_to_array_rgb16(&img.to_rgb16())?;
+                vec![r, g, b]
+            }
+            _ => {
+                let (r, g, b) = Self::image_to_array_rgb(&img.to_rgb8())?;
+                vec![r, g, b]
+            }
+        };
+        Ok(channels)
+    }
+
+    /// 提取时使用的通道：灰度图片为亮度，彩色图片按 `channel` 选择，`All` 为R通道
+    fn select_channel(img: &DynamicImage, channel: ImageChannel) -> Result<Array2<f64>> {
+        let mut channels = Self::image_channels(img)?;
+        Ok(match channel_index(channel) {
+            _ if channels.len() == 1 => channels.swap_remove(0),
+            Some(index) => channels.swap_remove(index),
+            None => luma(&channels),
+        })
+    }
+
+    /// 把 `image_channels` 的结果按原图的颜色类型写回图片，保持原有的位深和透明通道
+    fn channels_to_image(
+        channels: &[Array2<f64>],
+        original: &DynamicImage,
+    ) -> Result<DynamicImage> {
+        let color = original.color();
+        let img = match (channels, is_16bit(color)) {
+            ([gray], false) => Self::array_to_image_gray(gray)?,
+            ([gray], true) => Self::array_to_image_gray16(gray)?,
+            ([r, g, b], false) => Self::array_to_image_rgb(r, g, b)?,
+            ([r, g, b], true) => Self::array_to_image_rgb16(r, g, b)?,
+            _ => {
+                return Err(WatermarkError::ProcessingError(
+                    Message::CannotRebuildImage.format(&[&channels.len()]),
+                ))
+            }
+        };
+        Ok(if color.has_alpha() {
+            Self::with_alpha(img, original)
+        } else {
+            img
+        })
+    }
+
+    /// 给嵌入后的图片接上原图的透明通道
+    fn with_alpha(img: DynamicImage, original: &DynamicImage) -> DynamicImage {
+        let (width, height) = (img.width(), img.height());
+        match img {
+            DynamicImage::ImageLuma8(gray) => {
+                let alpha = original.to_luma_alpha8();
+                DynamicImage::ImageLumaA8(ImageBuffer::from_fn(width, height, |x, y| {
+                    LumaA([gray.get_pixel(x, y)[0], alpha.get_pixel(x, y)[1]])
+                }))
+            }
+            DynamicImage::ImageLuma16(gray) => {
+                let alpha = original.to_luma_alpha16();
+                DynamicImage::ImageLumaA16(ImageBuffer::from_fn(width, height, |x, y| {
+                    LumaA([gray.get_pixel(x, y)[0], alpha.get_pixel(x, y)[1]])
+                }))
+            }
+            DynamicImage::ImageRgb8(rgb) => {
+                let alpha = original.to_rgba8();
+                DynamicImage::ImageRgba8(ImageBuffer::from_fn(width, height, |x, y| {
+                    let [r, g, b] = rgb.get_pixel(x, y).0;
+                    Rgba([r, g, b, alpha.get_pixel(x, y)[3]])
+                }))
+            }
+            DynamicImage::ImageRgb16(rgb) => {
+                let alpha = original.to_rgba16();
+                DynamicImage::ImageRgba16(ImageBuffer::from_fn(width, height, |x, y| {
+                    let [r, g, b] = rgb.get_pixel(x, y).0;
+                    Rgba([r, g, b, alpha.get_pixel(x, y)[3]])
+                }))
+            }
+            other => other,
+        }
+    }
+
     /// 将灰度图片转换为ndarray
     /// 标准化到 [0.0, 1.0] 范围以避免精度损失
     fn image_to_array_gray(img: &ImageBuffer<Luma<u8>, Vec<u8>>) -> Result<Array2<f64>> {
@@ -277,11 +1475,118 @@ impl ImageWatermarker {
         Ok(DynamicImage::ImageRgb8(img_buffer))
     }
 
+    /// 将16位灰度图片转换为ndarray，按65535标准化到 [0.0, 1.0]
+    fn image_to_array_gray16(img: &ImageBuffer<Luma<u16>, Vec<u16>>) -> Result<Array2<f64>> {
+        let (width, height) = img.dimensions();
+        Ok(Array2::from_shape_fn(
+            (height as usize, width as usize),
+            |(y, x)| img.get_pixel(x as u32, y as u32)[0] as f64 / u16::MAX as f64,
+        ))
+    }
+
+    /// 将ndarray转换为16位灰度图片，与 image_to_array_gray16 可逆
+    fn array_to_image_gray16(array: &Array2<f64>) -> Result<DynamicImage> {
+        let (height, width) = array.dim();
+        let img_buffer = ImageBuffer::from_fn(width as u32, height as u32, |x, y| {
+            Luma([to_u16(array[[y as usize, x as usize]])])
+        });
+        Ok(DynamicImage::ImageLuma16(img_buffer))
+    }
+
+    /// 将16位RGB图片转换为三个通道的ndarray，按65535标准化到 [0.0, 1.0]
+    fn image_to_array_rgb16(
+        img: &ImageBuffer<Rgb<u16>, Vec<u16>>,
+    ) -> Result<(Array2<f64>, Array2<f64>, Array2<f64>)> {
+        let (width, height) = img.dimensions();
+        let channel = |c: usize| {
+            Array2::from_shape_fn((height as usize, width as usize), |(y, x)| {
+                img.get_pixel(x as u32, y as u32)[c] as f64 / u16::MAX as f64
+            })
+        };
+        Ok((channel(0), channel(1), channel(2)))
+    }
+
+    /// 将三个通道的ndarray转换为16位RGB图片，与 image_to_array_rgb16 可逆
+    fn array_to_image_rgb16(
+        r_array: &Array2<f64>,
+        g_array: &Array2<f64>,
+        b_array: &Array2<f64>,
+    ) -> Result<DynamicImage> {
+        let (height, width) = r_array.dim();
+        let img_buffer = ImageBuffer::from_fn(width as u32, height as u32, |x, y| {
+            let index = [y as usize, x as usize];
+            Rgb([
+                to_u16(r_array[index]),
+                to_u16(g_array[index]),
+                to_u16(b_array[index]),
+            ])
+        });
+        Ok(DynamicImage::ImageRgb16(img_buffer))
+    }
+
+    /// 打开图片，`image` 库无法解码的格式（如CMYK JPEG、部分TIFF）回退到 FFmpeg 转换为PNG后读取
+    fn open_image<P: AsRef<Path>>(path: P) -> Result<DynamicImage> {
+        match image::open(&path) {
+            Ok(img) => Ok(img),
+            Err(err @ (ImageError::Decoding(_) | ImageError::Unsupported(_))) => {
+                Self::open_image_via_ffmpeg(path.as_ref()).map_err(|_| WatermarkError::Image(err))
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// 使用 FFmpeg 将图片转换为PNG后再解码
+    fn open_image_via_ffmpeg(path: &Path) -> Result<DynamicImage> {
+        let temp_dir = TempDir::new("image_convert")?;
+        let converted = temp_dir.join("converted.png");
+
+        if !ffmpeg::run(
+            FfmpegCommand::new()
+                .input(path.to_str().unwrap())
+                .args(["-frames:v", "1"])
+                .args(["-y"])
+                .output(converted.to_str().unwrap()),
+        )?
+        .success()
+        {
+            return Err(WatermarkError::ProcessingError(
+                Message::FfmpegImageConvertFailed.text().to_string(),
+            ));
+        }
+        Ok(image::open(&converted)?)
+    }
+
+    /// 使用 FFmpeg 把图片编码为单帧AV1的AVIF：先写出无损的PNG，
+    /// 再按与JPEG相同含义的质量（1-100）换算为CRF编码
+    fn save_avif_via_ffmpeg(img: &DynamicImage, output_path: &Path, quality: u8) -> Result<()> {
+        let temp_dir = TempDir::new("image_encode")?;
+        let source = temp_dir.join("source.png");
+        img.save(&source)?;
+
+        let crf = (100 - u32::from(quality.min(100))) * 63 / 100;
+        if !ffmpeg::run(
+            FfmpegCommand::new()
+                .input(source.to_str().unwrap())
+                .args(["-c:v", "libaom-av1", "-still-picture", "1"])
+                .args(["-crf", &crf.to_string()])
+                .args(["-y"])
+                .output(output_path.to_str().unwrap()),
+        )?
+        .success()
+        {
+            return Err(WatermarkError::ProcessingError(
+                Message::AvifEncodeFailed.text().to_string(),
+            ));
+        }
+        Ok(())
+    }
+
     /// 获取图片尺寸信息
     pub fn get_image_info<P: AsRef<Path>>(path: P) -> Result<(u32, u32, ImageFormat)> {
-        let img = image::open(&path)?;
-        let format = image::ImageFormat::from_path(&path)
-            .map_err(|_| WatermarkError::UnsupportedFormat("无法确定图片格式".to_string()))?;
+        let img = Self::open_image(&path)?;
+        let format = image::ImageFormat::from_path(&path).map_err(|_| {
+            WatermarkError::UnsupportedFormat(Message::ImageFormatUnknown.text().to_string())
+        })?;
 
         Ok((img.width(), img.height(), format))
     }
@@ -290,29 +1595,518 @@ impl ImageWatermarker {
     pub fn check_watermark_capacity<P: AsRef<Path>>(
         path: P,
         watermark_text: &str,
-        algorithm: &dyn WatermarkAlgorithm,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
     ) -> Result<bool> {
+        Self::check_payload_capacity(path, watermark_text.as_bytes(), algorithm)
+    }
+
+    /// 检查图片是否能容纳给定的字节载荷
+    pub fn check_payload_capacity<P: AsRef<Path>>(
+        path: P,
+        payload: &[u8],
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+    ) -> Result<bool> {
+        let watermark_bits = WatermarkUtils::bytes_to_bits(payload);
+        Ok(watermark_bits.len() <= Self::payload_capacity(path, algorithm)?)
+    }
+
+    /// 计算图片可嵌入的最大比特数
+    pub fn payload_capacity<P: AsRef<Path>>(
+        path: P,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+    ) -> Result<usize> {
         let (width, height, _) = Self::get_image_info(&path)?;
-        let watermark_bits = WatermarkUtils::string_to_bits(watermark_text);
-
-        // 根据算法计算容量
-        let capacity = match algorithm.name() {
-            name if name.contains("DCT") => {
-                // DCT算法基于8x8块，现在支持任意尺寸
-                let blocks_w = width.div_ceil(8);
-                let blocks_h = height.div_ceil(8);
-                (blocks_w * blocks_h) as usize
-            }
-            name if name.contains("DWT") => {
-                // DWT算法基于小波系数，支持偶数尺寸
-                let padded_width = if width % 2 == 0 { width } else { width + 1 };
-                let padded_height = if height % 2 == 0 { height } else { height + 1 };
-                let coeffs = (padded_width * padded_height) / 4;
-                coeffs as usize
+
+        // 容量由各算法的 capacity() 按自身的嵌入方式计算（DCT按块、LSB按像素和位平面等）
+        Ok(algorithm.capacity(height as usize, width as usize))
+    }
+
+    /// 计算图片最多可嵌入的载荷字节数，即 `payload_capacity` 向下取整到整字节
+    pub fn max_capacity_bytes<P: AsRef<Path>>(
+        path: P,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+    ) -> Result<usize> {
+        Ok(Self::payload_capacity(path, algorithm)? / 8)
+    }
+}
+
+/// 每通道16位的颜色类型，这类图片按16位读写
+fn is_16bit(color: ColorType) -> bool {
+    matches!(
+        color,
+        ColorType::L16 | ColorType::La16 | ColorType::Rgb16 | ColorType::Rgba16
+    )
+}
+
+/// 与 `channels_to_image` 输出的颜色类型相同、全部为0的空白图片
+fn blank_image(color: ColorType, width: u32, height: u32) -> DynamicImage {
+    match color {
+        ColorType::L8 => DynamicImage::new_luma8(width, height),
+        ColorType::La8 => DynamicImage::new_luma_a8(width, height),
+        ColorType::L16 => DynamicImage::new_luma16(width, height),
+        ColorType::La16 => DynamicImage::new_luma_a16(width, height),
+        ColorType::Rgb16 => DynamicImage::new_rgb16(width, height),
+        ColorType::Rgba16 => DynamicImage::new_rgba16(width, height),
+        ColorType::Rgba8 => DynamicImage::new_rgba8(width, height),
+        _ => DynamicImage::new_rgb8(width, height),
+    }
+}
+
+/// 把嵌入后的局部图片（条带、头部或区域）写回输出图片的 (`x`, `y`) 处
+///
+/// 直接替换同类型的像素缓冲区，避免经 `DynamicImage` 的8位像素接口丢失16位精度。
+fn paste_at(target: &mut DynamicImage, strip: &DynamicImage, x: i64, y: i64) -> Result<()> {
+    match (target, strip) {
+        (DynamicImage::ImageLuma8(t), DynamicImage::ImageLuma8(s)) => replace(t, s, x, y),
+        (DynamicImage::ImageLumaA8(t), DynamicImage::ImageLumaA8(s)) => replace(t, s, x, y),
+        (DynamicImage::ImageLuma16(t), DynamicImage::ImageLuma16(s)) => replace(t, s, x, y),
+        (DynamicImage::ImageLumaA16(t), DynamicImage::ImageLumaA16(s)) => replace(t, s, x, y),
+        (DynamicImage::ImageRgb8(t), DynamicImage::ImageRgb8(s)) => replace(t, s, x, y),
+        (DynamicImage::ImageRgba8(t), DynamicImage::ImageRgba8(s)) => replace(t, s, x, y),
+        (DynamicImage::ImageRgb16(t), DynamicImage::ImageRgb16(s)) => replace(t, s, x, y),
+        (DynamicImage::ImageRgba16(t), DynamicImage::ImageRgba16(s)) => replace(t, s, x, y),
+        (target, strip) => {
+            return Err(WatermarkError::ProcessingError(
+                Message::StripColorMismatch.format(&[
+                    &format!("{:?}", strip.color()),
+                    &format!("{:?}", target.color()),
+                ]),
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// 对每个通道分别执行 `f`，多个通道时各占一个线程同时处理，结果按通道顺序返回
+///
+/// 各通道的处理互不依赖，结果与逐个处理完全相同；任一通道出错时返回第一个出错通道的错误。
+fn map_channels<T, F>(channels: &[Array2<f64>], f: F) -> Result<Vec<T>>
+where
+    T: Send,
+    F: Fn(&Array2<f64>) -> Result<T> + Sync,
+{
+    if channels.len() < 2 {
+        return channels.iter().map(f).collect();
+    }
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = channels
+            .iter()
+            .map(|data| scope.spawn(|| f(data)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|e| std::panic::resume_unwind(e))
+            })
+            .collect()
+    })
+}
+
+/// 单个颜色通道在 R、G、B 中的下标，`All` 为R通道，`Luma` 没有对应的单个通道
+fn channel_index(channel: ImageChannel) -> Option<usize> {
+    match channel {
+        ImageChannel::All | ImageChannel::R => Some(0),
+        ImageChannel::G => Some(1),
+        ImageChannel::B => Some(2),
+        ImageChannel::Luma => None,
+    }
+}
+
+/// 按 BT.601 加权计算 R、G、B 三个通道的亮度
+///
+/// 三个权重之和为1，把亮度上的改动原样加到三个通道后，重新计算的亮度恰好改变同样的量。
+fn luma(channels: &[Array2<f64>]) -> Array2<f64> {
+    &channels[0] * 0.299 + &channels[1] * 0.587 + &channels[2] * 0.114
+}
+
+/// 用双线性插值把矩阵缩放到 `rows` 行 `cols` 列，按像素中心对齐
+///
+/// 与 `robustness::resize_to` 不同，不经过8位图片，也不把取值限制在 [0.0, 1.0]，
+/// 用于缩放正负都有的嵌入改动量。
+fn resize_array(data: &Array2<f64>, rows: usize, cols: usize) -> Array2<f64> {
+    let (source_rows, source_cols) = data.dim();
+    let sample = |i: usize, length: usize, source: usize| {
+        let position = ((i as f64 + 0.5) * source as f64 / length as f64 - 0.5)
+            .clamp(0.0, (source - 1) as f64);
+        let low = position.floor() as usize;
+        (low, (low + 1).min(source - 1), position - low as f64)
+    };
+    Array2::from_shape_fn((rows, cols), |(y, x)| {
+        let (y0, y1, fy) = sample(y, rows, source_rows);
+        let (x0, x1, fx) = sample(x, cols, source_cols);
+        let top = data[[y0, x0]] * (1.0 - fx) + data[[y0, x1]] * fx;
+        let bottom = data[[y1, x0]] * (1.0 - fx) + data[[y1, x1]] * fx;
+        top * (1.0 - fy) + bottom * fy
+    })
+}
+
+/// PNG文件头中的颜色类型为3（调色板）
+///
+/// PNG文件头固定为8字节签名加IHDR块，颜色类型位于第25字节。
+fn is_palette_png(path: &Path) -> bool {
+    let mut header = [0u8; 26];
+    File::open(path)
+        .and_then(|mut file| std::io::Read::read_exact(&mut file, &mut header))
+        .is_ok()
+        && header.starts_with(b"\x89PNG\r\n\x1a\n")
+        && &header[12..16] == b"IHDR"
+        && header[25] == 3
+}
+
+/// 把 [0.0, 1.0] 的值反标准化到 u16，四舍五入并限制范围
+fn to_u16(value: f64) -> u16 {
+    (value * u16::MAX as f64)
+        .round()
+        .clamp(0.0, u16::MAX as f64) as u16
+}
+
+/// `extract_bytes_robust` 的结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct RobustExtraction {
+    /// 提取到的原始载荷
+    pub bytes: Vec<u8>,
+    /// 平均软判决置信度 (0.0-1.0)
+    pub confidence: f64,
+    /// 置信度最高的预处理
+    pub recovery: Recovery,
+    /// 尝试过的预处理个数
+    pub attempts: usize,
+}
+
+/// 多通道投票结果
+#[derive(Debug, Clone)]
+pub struct ChannelVote {
+    /// 投票后的比特
+    pub bits: Vec<u8>,
+    /// 每个比特的置信度 (0.0-1.0)
+    pub bit_confidence: Vec<f64>,
+    /// 同意的通道数未达到阈值的比特位置
+    pub uncertain_bits: Vec<usize>,
+}
+
+impl ChannelVote {
+    /// 所有比特的平均置信度
+    pub fn confidence(&self) -> f64 {
+        if self.bit_confidence.is_empty() {
+            return 0.0;
+        }
+        self.bit_confidence.iter().sum::<f64>() / self.bit_confidence.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_channel_vote_is_flagged_uncertain_and_halved() {
+        let mut channel_bits = vec![vec![1, 0, 1, 1, 0, 0, 1, 0]; 3];
+        channel_bits[2][5] = 1;
+
+        let lenient = ImageWatermarker::vote_channels(&channel_bits, 1);
+        assert_eq!(lenient.bits, channel_bits[0]);
+        assert!(lenient.uncertain_bits.is_empty());
+        assert!((lenient.bit_confidence[5] - 2.0 / 3.0).abs() < 1e-12);
+
+        let strict = ImageWatermarker::vote_channels(&channel_bits, 3);
+        assert_eq!(strict.bits, channel_bits[0]);
+        assert_eq!(strict.uncertain_bits, vec![5]);
+        assert!((strict.bit_confidence[5] - 1.0 / 3.0).abs() < 1e-12);
+        for (i, &confidence) in strict.bit_confidence.iter().enumerate() {
+            if i != 5 {
+                assert_eq!(confidence, 1.0);
             }
-            _ => return Err(WatermarkError::Algorithm("未知算法".to_string())),
+        }
+    }
+
+    #[test]
+    fn confidence_vote_recovers_bits_that_majority_gets_wrong() {
+        let truth = WatermarkUtils::string_to_bits("seal");
+        // 一个通道以高置信度给出全部正确的比特，另外两个通道在偶数位上以低置信度出错
+        let clean: Vec<(u8, f64)> = truth.iter().map(|&bit| (bit, 0.9)).collect();
+        let damaged: Vec<(u8, f64)> = truth
+            .iter()
+            .enumerate()
+            .map(|(i, &bit)| {
+                if i % 2 == 0 {
+                    (1 - bit, 0.1)
+                } else {
+                    (bit, 0.6)
+                }
+            })
+            .collect();
+        let decisions = vec![clean, damaged.clone(), damaged];
+        let channel_bits: Vec<Vec<u8>> = decisions
+            .iter()
+            .map(|channel| channel.iter().map(|&(bit, _)| bit).collect())
+            .collect();
+
+        let majority = ImageWatermarker::vote_channels(&channel_bits, 1);
+        assert_ne!(majority.bits, truth);
+
+        let weighted = ImageWatermarker::vote_channels_weighted(&decisions, 1);
+        assert_eq!(weighted.bits, truth);
+        assert_eq!(
+            WatermarkUtils::bits_to_string(&weighted.bits).unwrap(),
+            "seal"
+        );
+    }
+
+    fn scratch_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("seal_image_{}_{name}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// 渐变叠加低频纹理的彩色图片，频谱接近自然照片，中频系数很小
+    fn smooth_rgb(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(image::RgbImage::from_fn(width, height, |x, y| {
+            let (x, y) = (x as f64, y as f64);
+            let texture = 40.0 * (x / 23.0).sin() * (y / 31.0).cos();
+            image::Rgb([0.0, 20.0, 40.0].map(|offset| (90.0 + offset + 0.2 * x + texture) as u8))
+        }))
+    }
+
+    #[test]
+    fn detection_scores_clean_images_low_and_marked_images_high() {
+        let dir = scratch_dir("detect");
+        let (clean, marked) = (dir.join("clean.png"), dir.join("marked.png"));
+        smooth_rgb(256, 256).save(&clean).unwrap();
+        let algorithm = DctWatermark::new();
+        ImageWatermarker::embed_watermark(&clean, &marked, "seal detection", &algorithm, 0.1)
+            .unwrap();
+
+        let clean_score = ImageWatermarker::detect_watermark(&clean, &algorithm).unwrap();
+        let marked_score = ImageWatermarker::detect_watermark(&marked, &algorithm).unwrap();
+        assert!(clean_score < WatermarkUtils::DETECTION_THRESHOLD);
+        assert!(marked_score > WatermarkUtils::DETECTION_THRESHOLD);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn confidence_vote_recovers_a_watermark_left_only_in_the_red_channel() {
+        let dir = scratch_dir("red_only");
+        let (clean, marked) = (dir.join("clean.png"), dir.join("marked.png"));
+        smooth_rgb(256, 256).save(&clean).unwrap();
+        let algorithm = DctWatermark::new();
+        let options = ImageEmbedOptions {
+            channel: ImageChannel::R,
+            ..Default::default()
+        };
+        ImageWatermarker::embed_bytes_with_options(
+            &clean,
+            &marked,
+            b"seal vote",
+            &algorithm,
+            0.1,
+            &options,
+        )
+        .unwrap();
+
+        // 绿色和蓝色通道没有水印，软判决幅度很小，但两者一致出错的比特会在多数投票中胜出
+        let extract = |strategy| {
+            ImageWatermarker::extract_bytes_with_channel_vote(
+                &marked,
+                &algorithm,
+                9,
+                &[],
+                strategy,
+                1,
+            )
+            .unwrap()
+            .0
+        };
+        assert_ne!(extract(VoteStrategy::Majority), b"seal vote");
+        assert_eq!(extract(VoteStrategy::Confidence), b"seal vote");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn soft_decisions_are_more_confident_on_marked_images() {
+        let dir = scratch_dir("soft");
+        let (clean, marked) = (dir.join("clean.png"), dir.join("marked.png"));
+        smooth_rgb(256, 256).save(&clean).unwrap();
+        let algorithm = DctWatermark::new();
+        ImageWatermarker::embed_watermark(&clean, &marked, "soft decision", &algorithm, 0.1)
+            .unwrap();
+
+        let decisions = |path: &Path| {
+            let data = ImageWatermarker::load_channel(path).unwrap();
+            algorithm.extract_soft(&data, 13 * 8).unwrap()
+        };
+
+        // 平滑图片的中频系数接近0，嵌入后每个承载系数都被推到阈值以上
+        assert!(WatermarkUtils::mean_confidence(&decisions(&clean)) < 0.1);
+        assert!(decisions(&marked)
+            .iter()
+            .all(|&(_, confidence)| confidence > 0.9));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn repetition_recovers_a_watermark_from_a_quality_50_jpeg() {
+        let dir = scratch_dir("repeat_jpeg");
+        let original = dir.join("original.png");
+        noise_rgb(512, 512).save(&original).unwrap();
+        let payload = b"a considerably longer watermark payload spanning several dozen bytes";
+        let options = ImageEmbedOptions {
+            jpeg_quality: 50,
+            ..Default::default()
+        };
+
+        // 默认的中频位置不经重复也能扛住质量50的JPEG，改用高频段，压缩会丢掉其中一部分比特
+        let band = || DctWatermark::new().with_band(crate::cli::FrequencyBand::High);
+        let repeated = crate::watermark::RepetitionWatermark::new(
+            std::sync::Arc::new(band()),
+            crate::watermark::Repetition::Auto,
+        );
+        let round_trip = |algorithm: &(dyn WatermarkAlgorithm + Sync), name: &str| {
+            let output = dir.join(name);
+            ImageWatermarker::embed_bytes_with_options(
+                &original, &output, payload, algorithm, 0.1, &options,
+            )
+            .unwrap();
+            ImageWatermarker::extract_bytes(&output, algorithm, payload.len()).unwrap()
+        };
+        assert_ne!(round_trip(&band(), "plain.jpg"), payload);
+        assert_eq!(round_trip(&repeated, "repeated.jpg"), payload);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn embed_fills_exactly_the_reported_capacity() {
+        let dir = scratch_dir("capacity");
+        let original = dir.join("original.png");
+        noise_rgb(72, 48).save(&original).unwrap();
+        let algorithm = DctWatermark::new();
+        let max = ImageWatermarker::max_capacity_bytes(&original, &algorithm).unwrap();
+        assert_eq!(max, 6);
+
+        let full = vec![0xa5; max];
+        let marked = dir.join("full.png");
+        ImageWatermarker::embed_bytes(&original, &marked, &full, &algorithm, 0.1).unwrap();
+        assert_eq!(
+            ImageWatermarker::extract_bytes(&marked, &algorithm, max).unwrap(),
+            full
+        );
+
+        let over = vec![0xa5; max + 1];
+        let result =
+            ImageWatermarker::embed_bytes(&original, &dir.join("over.png"), &over, &algorithm, 0.1);
+        assert!(matches!(
+            result,
+            Err(WatermarkError::CapacityExceeded { .. })
+        ));
+        assert!(!dir.join("over.png").exists());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    /// 随机像素的彩色图片
+    fn noise_rgb(width: u32, height: u32) -> DynamicImage {
+        let mut rng = crate::watermark::prng::SeededRng::new(3);
+        DynamicImage::ImageRgb8(image::RgbImage::from_fn(width, height, |_, _| {
+            image::Rgb([(); 3].map(|_| rng.next_below(256) as u8))
+        }))
+    }
+
+    #[test]
+    fn lsb_payload_survives_a_png_round_trip_on_one_and_two_planes() {
+        let dir = scratch_dir("lsb_planes");
+        let original = dir.join("original.png");
+        noise_rgb(64, 48).save(&original).unwrap();
+        let mut rng = crate::watermark::prng::SeededRng::new(11);
+        let payload: Vec<u8> = (0..256).map(|_| rng.next_below(256) as u8).collect();
+
+        for planes in [1, 2] {
+            let marked = dir.join(format!("marked_{planes}.png"));
+            let algorithm = crate::watermark::LsbWatermark::new().with_bit_planes(planes);
+            ImageWatermarker::embed_bytes(&original, &marked, &payload, &algorithm, 0.1).unwrap();
+
+            let extracted =
+                ImageWatermarker::extract_bytes(&marked, &algorithm, payload.len()).unwrap();
+            assert_eq!(extracted, payload, "{planes} bit planes");
+
+            // 只有最低的 `planes` 个比特平面被改动
+            let mask = !((1u8 << planes) - 1);
+            let before = image::open(&original).unwrap().to_rgb8();
+            let after = image::open(&marked).unwrap().to_rgb8();
+            assert!(before
+                .as_raw()
+                .iter()
+                .zip(after.as_raw())
+                .all(|(a, b)| (a ^ b) & mask == 0));
+        }
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn parallel_channels_match_serial_byte_for_byte() {
+        let img = noise_rgb(128, 96);
+        let algorithm = DctWatermark::new();
+        let bits = WatermarkUtils::bytes_to_bits(b"seal");
+        let channels = ImageWatermarker::image_channels(&img).unwrap();
+
+        let parallel =
+            ImageWatermarker::embed_into_image(&img, b"seal", &algorithm, 0.1, ImageChannel::All)
+                .unwrap();
+        let serial: Vec<Array2<f64>> = channels
+            .iter()
+            .map(|data| algorithm.embed(data, &bits, 0.1))
+            .collect::<Result<_>>()
+            .unwrap();
+        let serial = ImageWatermarker::channels_to_image(&serial, &img).unwrap();
+        assert_eq!(parallel.as_bytes(), serial.as_bytes());
+
+        let marked = ImageWatermarker::image_channels(&parallel).unwrap();
+        let parallel = map_channels(&marked, |data| algorithm.extract_soft(data, bits.len()));
+        let serial: Vec<_> = marked
+            .iter()
+            .map(|data| algorithm.extract_soft(data, bits.len()))
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(parallel.unwrap(), serial);
+    }
+
+    /// 三个通道逐个嵌入与并行嵌入的耗时对比，手动运行：
+    /// `cargo test --release channel_embed_timing -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn channel_embed_timing() {
+        let img = noise_rgb(2048, 2048);
+        let algorithm = DctWatermark::new();
+        let bits = WatermarkUtils::bytes_to_bits(b"seal-bench");
+        let channels = ImageWatermarker::image_channels(&img).unwrap();
+        let embed = |data: &Array2<f64>| algorithm.embed(data, &bits, 0.1);
+        let median = |run: &dyn Fn()| {
+            let mut times: Vec<_> = (0..5)
+                .map(|_| {
+                    let started = std::time::Instant::now();
+                    run();
+                    started.elapsed()
+                })
+                .collect();
+            times.sort();
+            times[2]
         };
 
-        Ok(watermark_bits.len() <= capacity)
+        let serial = median(&|| {
+            channels
+                .iter()
+                .map(embed)
+                .collect::<Result<Vec<_>>>()
+                .unwrap();
+        });
+        let parallel = median(&|| {
+            map_channels(&channels, embed).unwrap();
+        });
+        eprintln!("2048x2048 RGB: serial {serial:?}, parallel {parallel:?}");
     }
 }
@@ -0,0 +1,290 @@
+use crate::error::{Result, WatermarkError};
+use crate::i18n::Message;
+use colored::*;
+
+/// PNG文件签名
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+/// JPEG APP1段中EXIF数据的标识
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+/// JPEG APP2段中ICC配置文件的标识
+const ICC_HEADER: &[u8] = b"ICC_PROFILE\0";
+/// JPEG单个段的最大数据长度（不含标记，含2字节长度字段）
+const MAX_SEGMENT_LEN: usize = 0xFFFF;
+
+/// 图片文件中需要在水印前后保留的元数据
+///
+/// `exif` 为不含 `Exif\0\0` 前缀的原始TIFF结构（含拍摄时间、方向等），
+/// `icc` 为完整的ICC颜色配置文件。两者在JPEG和PNG之间可以互相转存。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImageMetadata {
+    pub exif: Option<Vec<u8>>,
+    pub icc: Option<Vec<u8>>,
+}
+
+impl ImageMetadata {
+    /// 从JPEG或PNG文件内容中读取元数据，其他格式返回空元数据
+    pub fn read(bytes: &[u8]) -> Self {
+        if bytes.starts_with(&[0xFF, 0xD8]) {
+            Self::read_jpeg(bytes)
+        } else if bytes.starts_with(&PNG_SIGNATURE) {
+            Self::read_png(bytes)
+        } else {
+            Self::default()
+        }
+    }
+
+    /// 是否没有任何需要保留的元数据
+    pub fn is_empty(&self) -> bool {
+        self.exif.is_none() && self.icc.is_none()
+    }
+
+    /// 把元数据写回编码后的JPEG或PNG文件内容，其他格式原样返回
+    pub fn apply(&self, encoded: &[u8]) -> Result<Vec<u8>> {
+        if self.is_empty() {
+            return Ok(encoded.to_vec());
+        }
+        if encoded.starts_with(&[0xFF, 0xD8]) {
+            Ok(self.apply_jpeg(encoded))
+        } else if encoded.starts_with(&PNG_SIGNATURE) {
+            self.apply_png(encoded)
+        } else {
+            Ok(encoded.to_vec())
+        }
+    }
+
+    /// 遍历SOS之前的所有段，收集APP1中的EXIF和APP2中分段存放的ICC配置文件
+    fn read_jpeg(bytes: &[u8]) -> Self {
+        let mut metadata = Self::default();
+        let mut icc_chunks: Vec<(u8, &[u8])> = Vec::new();
+
+        for (marker, data) in jpeg_segments(bytes) {
+            match marker {
+                0xE1 if data.starts_with(EXIF_HEADER) && metadata.exif.is_none() => {
+                    metadata.exif = Some(data[EXIF_HEADER.len()..].to_vec());
+                }
+                0xE2 if data.starts_with(ICC_HEADER) && data.len() >= ICC_HEADER.len() + 2 => {
+                    let sequence = data[ICC_HEADER.len()];
+                    icc_chunks.push((sequence, &data[ICC_HEADER.len() + 2..]));
+                }
+                _ => {}
+            }
+        }
+
+        if !icc_chunks.is_empty() {
+            icc_chunks.sort_by_key(|&(sequence, _)| sequence);
+            metadata.icc = Some(
+                icc_chunks
+                    .iter()
+                    .flat_map(|&(_, chunk)| chunk.iter().copied())
+                    .collect(),
+            );
+        }
+        metadata
+    }
+
+    /// 读取PNG的eXIf和iCCP数据块，iCCP中的配置文件需要解压
+    fn read_png(bytes: &[u8]) -> Self {
+        let mut metadata = Self::default();
+
+        for (chunk_type, data) in png_chunks(bytes) {
+            match chunk_type {
+                b"eXIf" => metadata.exif = Some(data.to_vec()),
+                b"iCCP" => {
+                    // 配置文件名以0结尾，随后是1字节压缩方式和zlib数据
+                    let profile = data
+                        .iter()
+                        .position(|&b| b == 0)
+                        .and_then(|end| data.get(end + 2..))
+                        .and_then(|compressed| {
+                            miniz_oxide::inflate::decompress_to_vec_zlib(compressed).ok()
+                        });
+                    if profile.is_some() {
+                        metadata.icc = profile;
+                    }
+                }
+                _ => {}
+            }
+        }
+        metadata
+    }
+
+    /// 在SOI和JFIF的APP0段之后插入EXIF和ICC段
+    fn apply_jpeg(&self, encoded: &[u8]) -> Vec<u8> {
+        let mut insert_at = 2;
+        if let Some((0xE0, data)) = jpeg_segments(encoded).next() {
+            insert_at += 4 + data.len();
+        }
+
+        let mut segments = Vec::new();
+        if let Some(exif) = &self.exif {
+            // 超出单段长度的EXIF无法按标准写入，放弃并提示，而不是写出损坏的文件
+            let payload = [EXIF_HEADER, exif.as_slice()].concat();
+            if payload.len() + 2 <= MAX_SEGMENT_LEN {
+                push_jpeg_segment(&mut segments, 0xE1, &payload);
+            } else {
+                eprintln!(
+                    "{} {}",
+                    "⚠️".yellow(),
+                    Message::ExifTooLarge.format(&[&exif.len()])
+                );
+            }
+        }
+        if let Some(icc) = &self.icc {
+            let chunk_len = MAX_SEGMENT_LEN - 2 - ICC_HEADER.len() - 2;
+            let chunks: Vec<&[u8]> = icc.chunks(chunk_len).collect();
+            if chunks.len() <= u8::MAX as usize {
+                for (i, chunk) in chunks.iter().enumerate() {
+                    let payload =
+                        [ICC_HEADER, &[(i + 1) as u8, chunks.len() as u8], chunk].concat();
+                    push_jpeg_segment(&mut segments, 0xE2, &payload);
+                }
+            } else {
+                eprintln!(
+                    "{} {}",
+                    "⚠️".yellow(),
+                    Message::IccTooLarge.format(&[&icc.len()])
+                );
+            }
+        }
+
+        [&encoded[..insert_at], &segments, &encoded[insert_at..]].concat()
+    }
+
+    /// 在IHDR之后插入iCCP和eXIf数据块
+    fn apply_png(&self, encoded: &[u8]) -> Result<Vec<u8>> {
+        let ihdr_len = png_chunks(encoded)
+            .next()
+            .filter(|&(chunk_type, _)| chunk_type == b"IHDR")
+            .map(|(_, data)| data.len())
+            .ok_or_else(|| {
+                WatermarkError::ProcessingError(Message::PngMissingIhdr.text().to_string())
+            })?;
+        let insert_at = PNG_SIGNATURE.len() + 12 + ihdr_len;
+
+        let mut chunks = Vec::new();
+        if let Some(icc) = &self.icc {
+            let compressed = miniz_oxide::deflate::compress_to_vec_zlib(icc, 6);
+            let data = [b"ICC Profile\0\0".as_slice(), &compressed].concat();
+            push_png_chunk(&mut chunks, b"iCCP", &data);
+        }
+        if let Some(exif) = &self.exif {
+            push_png_chunk(&mut chunks, b"eXIf", exif);
+        }
+
+        Ok([&encoded[..insert_at], &chunks, &encoded[insert_at..]].concat())
+    }
+}
+
+/// 遍历JPEG在SOS之前的带长度段，返回 (标记, 段数据)，遇到格式错误时提前结束
+fn jpeg_segments(bytes: &[u8]) -> impl Iterator<Item = (u8, &[u8])> {
+    let mut pos = 2;
+    std::iter::from_fn(move || loop {
+        // 段之间允许出现任意数量的0xFF填充字节
+        while bytes.get(pos) == Some(&0xFF) && bytes.get(pos + 1) == Some(&0xFF) {
+            pos += 1;
+        }
+        if bytes.get(pos) != Some(&0xFF) {
+            return None;
+        }
+        let marker = *bytes.get(pos + 1)?;
+        match marker {
+            // 扫描数据开始或图像结束，之后不再有元数据段
+            0xDA | 0xD9 => return None,
+            // 不带长度字段的独立标记
+            0x01 | 0xD0..=0xD7 => pos += 2,
+            _ => {
+                let len = u16::from_be_bytes([*bytes.get(pos + 2)?, *bytes.get(pos + 3)?]) as usize;
+                let data = bytes.get(pos + 4..pos + 2 + len.max(2))?;
+                pos += 2 + len.max(2);
+                return Some((marker, data));
+            }
+        }
+    })
+}
+
+/// 遍历PNG数据块，返回 (类型, 数据)，到IEND或格式错误时结束
+fn png_chunks(bytes: &[u8]) -> impl Iterator<Item = (&[u8; 4], &[u8])> {
+    let mut pos = PNG_SIGNATURE.len();
+    std::iter::from_fn(move || {
+        let len = u32::from_be_bytes(bytes.get(pos..pos + 4)?.try_into().ok()?) as usize;
+        let chunk_type: &[u8; 4] = bytes.get(pos + 4..pos + 8)?.try_into().ok()?;
+        let data = bytes.get(pos + 8..pos + 8 + len)?;
+        pos += 12 + len;
+        (chunk_type != b"IEND").then_some((chunk_type, data))
+    })
+}
+
+fn push_jpeg_segment(out: &mut Vec<u8>, marker: u8, payload: &[u8]) {
+    out.extend([0xFF, marker]);
+    out.extend(((payload.len() + 2) as u16).to_be_bytes());
+    out.extend(payload);
+}
+
+fn push_png_chunk(out: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(chunk_type);
+    hasher.update(data);
+    out.extend((data.len() as u32).to_be_bytes());
+    out.extend(chunk_type);
+    out.extend(data);
+    out.extend(hasher.finalize().to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageOutputFormat, RgbImage};
+    use std::io::Cursor;
+
+    const DATE_TIME: &[u8; 20] = b"2024:05:01 12:34:56\0";
+
+    /// 只含一个 DateTime (0x0132) 标签的大端TIFF结构
+    fn exif_with_date_time() -> Vec<u8> {
+        let mut tiff = b"MM\0\x2a\0\0\0\x08".to_vec();
+        tiff.extend(1u16.to_be_bytes());
+        tiff.extend(0x0132u16.to_be_bytes());
+        tiff.extend(2u16.to_be_bytes()); // ASCII
+        tiff.extend((DATE_TIME.len() as u32).to_be_bytes());
+        tiff.extend(26u32.to_be_bytes()); // 值紧跟在IFD之后
+        tiff.extend(0u32.to_be_bytes());
+        tiff.extend(DATE_TIME);
+        tiff
+    }
+
+    fn encode(format: ImageOutputFormat) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        RgbImage::new(16, 16)
+            .write_to(&mut Cursor::new(&mut bytes), format)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn exif_date_time_survives_jpeg_and_png() {
+        let metadata = ImageMetadata {
+            exif: Some(exif_with_date_time()),
+            icc: None,
+        };
+        for format in [ImageOutputFormat::Jpeg(90), ImageOutputFormat::Png] {
+            let written = metadata.apply(&encode(format)).unwrap();
+            let exif = ImageMetadata::read(&written).exif.unwrap();
+            assert_eq!(&exif[26..], DATE_TIME);
+            assert!(image::load_from_memory(&written).is_ok());
+        }
+    }
+
+    #[test]
+    fn oversized_exif_is_left_out_of_jpeg() {
+        let mut exif = exif_with_date_time();
+        exif.resize(MAX_SEGMENT_LEN, 0);
+        let metadata = ImageMetadata {
+            exif: Some(exif),
+            icc: None,
+        };
+        let written = metadata
+            .apply(&encode(ImageOutputFormat::Jpeg(90)))
+            .unwrap();
+        assert_eq!(ImageMetadata::read(&written).exif, None);
+        assert!(image::load_from_memory(&written).is_ok());
+    }
+}
@@ -1,13 +1,29 @@
 pub mod audio;
+pub mod diff;
+pub mod extraction;
+pub mod ffmpeg;
+pub(crate) mod gif;
 pub mod image;
+pub(crate) mod metadata;
+pub mod options;
+pub mod progress;
+pub(crate) mod resume;
+pub(crate) mod sync;
 pub mod video;
 
-pub use audio::AudioWatermarker;
-pub use image::ImageWatermarker;
-pub use video::VideoWatermarker;
+pub use audio::{AudioEmbedOptions, AudioWatermarker};
+pub use diff::{AudioDiff, ImageDiff};
+pub use extraction::{ExtractionResult, Integrity};
+pub use image::{ChannelVote, ImageEmbedOptions, ImageWatermarker, RobustExtraction};
+pub use options::EmbedOptions;
+pub use progress::{Progress, ProgressEvent};
+pub use video::{
+    FrameIndexRange, VideoEncodeOptions, VideoExtractOptions, VideoProperties, VideoWatermarker,
+};
 
 use crate::error::{Result, WatermarkError};
-use std::path::Path;
+use crate::i18n::Message;
+use std::path::{Path, PathBuf};
 
 /// 媒体文件类型检测
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -28,26 +44,32 @@ impl MediaUtils {
             .extension()
             .and_then(|e| e.to_str())
             .map(|e| e.to_lowercase())
-            .ok_or_else(|| WatermarkError::UnsupportedFormat("无法确定文件类型".to_string()))?;
+            .ok_or_else(|| {
+                WatermarkError::UnsupportedFormat(Message::FileTypeUnknown.text().to_string())
+            })?;
 
         match extension.as_str() {
-            "jpg" | "jpeg" | "png" | "bmp" | "gif" | "tiff" | "webp" => Ok(MediaType::Image),
-            "wav" | "wave" | "mp3" | "aac" => Ok(MediaType::Audio),
+            "jpg" | "jpeg" | "png" | "bmp" | "gif" | "tiff" | "webp" | "avif" | "heic" | "heif" => {
+                Ok(MediaType::Image)
+            }
+            "wav" | "wave" | "mp3" | "aac" | "m4a" | "flac" => Ok(MediaType::Audio),
             "mp4" | "avi" | "mov" | "mkv" => Ok(MediaType::Video),
-            _ => Err(WatermarkError::UnsupportedFormat(format!(
-                "不支持的文件格式: {extension}"
-            ))),
+            _ => Err(WatermarkError::UnsupportedFormat(
+                Message::UnsupportedExtension.format(&[&extension]),
+            )),
         }
     }
 
     /// 获取支持的图片格式列表
     pub fn supported_image_formats() -> Vec<&'static str> {
-        vec!["jpg", "jpeg", "png", "bmp", "gif", "tiff", "webp"]
+        vec![
+            "jpg", "jpeg", "png", "bmp", "gif", "tiff", "webp", "avif", "heic", "heif",
+        ]
     }
 
     /// 获取支持的音频格式列表
     pub fn supported_audio_formats() -> Vec<&'static str> {
-        vec!["wav", "wave"]
+        vec!["wav", "wave", "mp3", "aac", "m4a", "flac"]
     }
 
     /// 获取支持的视频格式列表（预留）
@@ -55,19 +77,201 @@ impl MediaUtils {
         vec!["mp4", "avi", "mov", "mkv"]
     }
 
+    /// 展开批处理输入：目录返回其中所有受支持的媒体文件，文件名部分含 `*` 或 `?`
+    /// 的通配符返回所在目录中匹配的文件，均按文件名排序；普通文件路径返回 `None`
+    ///
+    /// 通配符只在文件名部分展开，不存在的路径在目录部分含通配符时报错，而不是当作普通文件。
+    pub fn batch_inputs<P: AsRef<Path>>(input: P) -> Result<Option<Vec<PathBuf>>> {
+        let input = input.as_ref();
+        let wildcard_dir = input.parent().is_some_and(|parent| {
+            parent
+                .to_str()
+                .is_some_and(|parent| parent.contains(['*', '?']))
+        });
+        if wildcard_dir && !input.exists() {
+            return Err(WatermarkError::InvalidArgument(
+                Message::WildcardInDirectory.format(&[&format!("{input:?}")]),
+            ));
+        }
+        let pattern = input.file_name().and_then(|name| name.to_str());
+        let is_glob = !input.exists() && pattern.is_some_and(|p| p.contains(['*', '?']));
+        if !input.is_dir() && !is_glob {
+            return Ok(None);
+        }
+
+        let no_files = || {
+            WatermarkError::InvalidArgument(
+                Message::NoMatchingFiles.format(&[&format!("{input:?}")]),
+            )
+        };
+        let dir = Self::batch_root(input);
+        if !dir.is_dir() {
+            return Err(no_files());
+        }
+        let mut files: Vec<PathBuf> = std::fs::read_dir(&dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<Vec<_>>>()?
+            .into_iter()
+            .filter(|path| path.is_file())
+            .filter(|path| match (is_glob, pattern) {
+                (true, Some(pattern)) => path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| Self::wildcard_match(pattern, name)),
+                _ => Self::detect_media_type(path).is_ok(),
+            })
+            .collect();
+        files.sort();
+
+        if files.is_empty() {
+            return Err(no_files());
+        }
+        Ok(Some(files))
+    }
+
+    /// 批处理输入所在的目录：目录本身，或通配符的父目录
+    pub fn batch_root<P: AsRef<Path>>(input: P) -> PathBuf {
+        let input = input.as_ref();
+        if input.is_dir() {
+            return input.to_path_buf();
+        }
+        match input.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        }
+    }
+
+    /// 文件名通配符匹配，`*` 匹配任意个字符，`?` 匹配单个字符
+    fn wildcard_match(pattern: &str, name: &str) -> bool {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let name: Vec<char> = name.chars().collect();
+        // matched[j] 表示当前模式前缀能否匹配 name 的前 j 个字符
+        let mut matched = vec![false; name.len() + 1];
+        matched[0] = true;
+        for &p in &pattern {
+            let mut next = vec![false; name.len() + 1];
+            if p == '*' {
+                let mut any = false;
+                for j in 0..=name.len() {
+                    any |= matched[j];
+                    next[j] = any;
+                }
+            } else {
+                for j in 1..=name.len() {
+                    next[j] = matched[j - 1] && (p == '?' || p == name[j - 1]);
+                }
+            }
+            matched = next;
+        }
+        matched[name.len()]
+    }
+
     /// 检查文件是否存在
     pub fn file_exists<P: AsRef<Path>>(path: P) -> bool {
         path.as_ref().exists()
     }
 
-    /// 创建输出目录（如果不存在）
-    pub fn ensure_output_dir<P: AsRef<Path>>(path: P) -> Result<()> {
+    /// 检查输出文件能否写出：已存在且不允许覆盖时返回 `AlreadyExists`，否则按 `ensure_output_dir` 检查目录
+    ///
+    /// 避免输入和输出路径写反时覆盖原文件。
+    pub fn check_output<P: AsRef<Path>>(path: P, overwrite: bool) -> Result<()> {
         let path = path.as_ref();
-        if let Some(parent) = path.parent() {
-            if !parent.exists() {
-                std::fs::create_dir_all(parent)?;
-            }
+        if !overwrite && path.exists() {
+            return Err(WatermarkError::Io(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                Message::OutputExists.format(&[&format!("{path:?}")]),
+            )));
+        }
+        Self::ensure_output_dir(path)
+    }
+
+    /// 创建输出目录（如果不存在），并在其中试写一个临时文件确认可写
+    ///
+    /// 应在任何耗时处理之前调用，避免处理完成后才发现无法写出结果。
+    pub fn ensure_output_dir<P: AsRef<Path>>(path: P) -> Result<()> {
+        let dir = match path.as_ref().parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let not_writable = |e: std::io::Error| WatermarkError::OutputNotWritable {
+            path: dir.to_path_buf(),
+            reason: e.to_string(),
+        };
+
+        if !dir.exists() {
+            std::fs::create_dir_all(dir).map_err(not_writable)?;
         }
+
+        let probe = dir.join(format!(".seal_write_test_{}", std::process::id()));
+        std::fs::File::create(&probe).map_err(not_writable)?;
+        let _ = std::fs::remove_file(&probe);
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards_only_expand_in_the_file_name() {
+        let err = MediaUtils::batch_inputs("no_such_*/a.png").unwrap_err();
+        assert!(matches!(err, WatermarkError::InvalidArgument(_)));
+        assert!(MediaUtils::batch_inputs("no_such_dir/a.png")
+            .unwrap()
+            .is_none());
+    }
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("seal_media_{}_{name}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn existing_output_needs_overwrite() {
+        let dir = scratch_dir("check_output");
+        let output = dir.join("out.png");
+        assert!(MediaUtils::check_output(&output, false).is_ok());
+
+        std::fs::write(&output, "existing").unwrap();
+        let err = MediaUtils::check_output(&output, false).unwrap_err();
+        assert!(
+            matches!(&err, WatermarkError::Io(e) if e.kind() == std::io::ErrorKind::AlreadyExists)
+        );
+        assert!(MediaUtils::check_output(&output, true).is_ok());
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "existing");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn read_only_output_dir_is_reported_before_processing() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = scratch_dir("read_only");
+        let locked = dir.join("locked");
+        std::fs::create_dir_all(&locked).unwrap();
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o555)).unwrap();
+
+        // root 不受目录权限限制，此时只检查下面不依赖权限的情况
+        if std::fs::File::create(locked.join("probe")).is_err() {
+            let err = MediaUtils::ensure_output_dir(locked.join("out.png")).unwrap_err();
+            assert!(
+                matches!(err, WatermarkError::OutputNotWritable { path, .. } if path == locked)
+            );
+        } else {
+            eprintln!("skipped read-only case: directory permissions are not enforced");
+        }
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        // 父路径是普通文件时无法创建目录
+        let file = dir.join("file");
+        std::fs::write(&file, "").unwrap();
+        let err = MediaUtils::ensure_output_dir(file.join("sub").join("out.png")).unwrap_err();
+        assert!(matches!(err, WatermarkError::OutputNotWritable { .. }));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,153 @@
+use crate::cli::{Algorithm, ImageChannel, VideoWatermarkMode};
+use crate::media::{AudioEmbedOptions, ImageEmbedOptions, VideoEncodeOptions};
+use crate::watermark::{AlgorithmOptions, Repetition, WatermarkAlgorithm, WatermarkFactory};
+use std::sync::Arc;
+
+/// 默认的水印强度，也是CLI的 `--strength` 默认值
+pub const DEFAULT_STRENGTH: f64 = 0.1;
+
+/// 嵌入水印的选项，按需用 `with_*` 方法覆盖默认值，
+/// 如 `EmbedOptions::new().with_strength(0.3).with_seed(42)`
+///
+/// 各媒体的专有参数直接使用对应的选项结构，嵌入时原样传给该媒体的处理器，其余媒体的选项被忽略。
+#[derive(Debug, Clone)]
+pub struct EmbedOptions<'a> {
+    /// 水印强度 (0.0-1.0)
+    pub strength: f64,
+    /// 创建算法时的可选参数（种子、重复编码等）
+    pub algorithm: AlgorithmOptions,
+    /// 图片嵌入的可选参数
+    pub image: ImageEmbedOptions,
+    /// 音频嵌入的可选参数
+    pub audio: AudioEmbedOptions,
+    /// 视频水印写入的位置
+    pub video_mode: VideoWatermarkMode,
+    /// 视频重新编码的参数
+    pub video: VideoEncodeOptions<'a>,
+}
+
+impl Default for EmbedOptions<'_> {
+    fn default() -> Self {
+        Self {
+            strength: DEFAULT_STRENGTH,
+            algorithm: AlgorithmOptions::default(),
+            image: ImageEmbedOptions::default(),
+            audio: AudioEmbedOptions::default(),
+            video_mode: VideoWatermarkMode::Video,
+            video: VideoEncodeOptions::default(),
+        }
+    }
+}
+
+impl<'a> EmbedOptions<'a> {
+    /// 使用默认值创建嵌入选项
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置水印强度
+    pub fn with_strength(mut self, strength: f64) -> Self {
+        self.strength = strength;
+        self
+    }
+
+    /// 设置块置换种子，提取时必须使用相同的种子
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.algorithm.seed = Some(seed);
+        self
+    }
+
+    /// 设置载荷的重复编码方式
+    pub fn with_repeat(mut self, repeat: Repetition) -> Self {
+        self.algorithm.repeat = Some(repeat);
+        self
+    }
+
+    /// 整体替换创建算法时的可选参数
+    pub fn with_algorithm_options(mut self, options: AlgorithmOptions) -> Self {
+        self.algorithm = options;
+        self
+    }
+
+    /// 整体替换图片嵌入的可选参数
+    pub fn with_image_options(mut self, options: ImageEmbedOptions) -> Self {
+        self.image = options;
+        self
+    }
+
+    /// 整体替换音频嵌入的可选参数
+    pub fn with_audio_options(mut self, options: AudioEmbedOptions) -> Self {
+        self.audio = options;
+        self
+    }
+
+    /// 整体替换视频重新编码的参数
+    pub fn with_video_options(mut self, options: VideoEncodeOptions<'a>) -> Self {
+        self.video = options;
+        self
+    }
+
+    /// 视频使用无损编码
+    pub fn with_lossless(mut self, lossless: bool) -> Self {
+        self.video.lossless = lossless;
+        self
+    }
+
+    /// 设置视频水印的处理模式
+    pub fn with_video_mode(mut self, video_mode: VideoWatermarkMode) -> Self {
+        self.video_mode = video_mode;
+        self
+    }
+
+    /// 设置音频的强度系数，实际强度为 `strength * 系数`
+    pub fn with_audio_strength_scale(mut self, scale: f64) -> Self {
+        self.audio.strength_scale = scale;
+        self
+    }
+
+    /// 设置图片嵌入水印的颜色通道
+    pub fn with_image_channel(mut self, channel: ImageChannel) -> Self {
+        self.image.channel = channel;
+        self
+    }
+
+    /// 按这些选项创建算法实例
+    pub fn create_algorithm(
+        &self,
+        algorithm: Algorithm,
+    ) -> Arc<dyn WatermarkAlgorithm + Send + Sync> {
+        WatermarkFactory::create_algorithm_with_options(algorithm, &self.algorithm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::ImageWatermarker;
+
+    #[test]
+    fn image_options_reach_the_encoder() {
+        let dir = std::env::temp_dir().join(format!("seal_options_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("input.png");
+        image::RgbImage::from_fn(128, 128, |x, y| {
+            image::Rgb([(x * 2) as u8, (y * 2) as u8, ((x + y) % 256) as u8])
+        })
+        .save(&input)
+        .unwrap();
+
+        let embed = |quality: u8, name: &str| {
+            let output = dir.join(name);
+            let options = EmbedOptions::new().with_image_options(ImageEmbedOptions {
+                jpeg_quality: quality,
+                ..Default::default()
+            });
+            ImageWatermarker::embed_with_options(&input, &output, "seal", Algorithm::Dct, &options)
+                .unwrap();
+            std::fs::metadata(&output).unwrap().len()
+        };
+        assert!(embed(30, "low.jpg") < embed(95, "high.jpg"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,77 @@
+use std::fmt;
+
+/// 视频处理过程中的进度事件
+///
+/// 每个处理流程先发出一次 `Started`，之后每个步骤依次发出 `Stage` 和 `StepCompleted`，
+/// 结束时发出 `Finished` 或 `Abandoned`；逐帧处理时另外发出帧相关的事件。
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProgressEvent {
+    /// 开始一个共 `steps` 个步骤的处理流程
+    Started { steps: usize },
+    /// 开始当前步骤，附带说明文字
+    Stage(String),
+    /// 当前步骤完成
+    StepCompleted,
+    /// 已从视频中提取出这么多帧，随后逐帧处理
+    FramesExtracted(usize),
+    /// 第 `index` 帧（从0计数）处理完成，流式处理时总帧数未知，`total` 为 `None`
+    FrameProcessed { index: usize, total: Option<usize> },
+    /// 开始把处理后的帧重新编码为视频
+    Reassembling,
+    /// 全部步骤完成，附带结束说明
+    Finished(String),
+    /// 流程中止，附带原因
+    Abandoned(String),
+}
+
+/// 进度回调，默认不报告任何进度
+///
+/// 库本身不绘制进度条，需要展示进度时用 `Progress::new` 传入回调，
+/// 例如命令行据此驱动终端进度条。
+#[derive(Clone, Copy, Default)]
+pub struct Progress<'a>(Option<&'a dyn Fn(ProgressEvent)>);
+
+impl<'a> Progress<'a> {
+    /// 每个进度事件都调用一次 `callback`
+    pub fn new(callback: &'a dyn Fn(ProgressEvent)) -> Self {
+        Self(Some(callback))
+    }
+
+    /// 不报告进度
+    pub fn none() -> Self {
+        Self(None)
+    }
+
+    /// 发出一个进度事件，未设置回调时什么也不做
+    pub fn emit(&self, event: ProgressEvent) {
+        if let Some(callback) = self.0 {
+            callback(event);
+        }
+    }
+
+    pub(crate) fn started(&self, steps: usize) {
+        self.emit(ProgressEvent::Started { steps });
+    }
+
+    pub(crate) fn stage(&self, message: &str) {
+        self.emit(ProgressEvent::Stage(message.to_string()));
+    }
+
+    pub(crate) fn step(&self) {
+        self.emit(ProgressEvent::StepCompleted);
+    }
+
+    pub(crate) fn finished(&self, message: &str) {
+        self.emit(ProgressEvent::Finished(message.to_string()));
+    }
+}
+
+impl fmt::Debug for Progress<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.0.is_some() {
+            "Progress(callback)"
+        } else {
+            "Progress(none)"
+        })
+    }
+}
@@ -0,0 +1,210 @@
+use crate::error::{Result, WatermarkError};
+use crate::i18n::Message;
+use crate::watermark::crypto;
+use serde_json::json;
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// 断点目录中记录嵌入参数和帧数的清单，帧全部提取完成后才写入
+const MANIFEST: &str = "manifest.json";
+
+/// 已嵌入水印的帧序号，每完成一帧追加一行
+const DONE_LOG: &str = "done.log";
+
+/// `--resume` 使用的断点目录
+///
+/// 与以进程号区分的 `TempDir` 不同，目录位置只由输出路径决定，出错或中断时保留，
+/// 再次运行时据清单跳过已完成的帧；全部完成后由 `finish` 删除。
+pub(crate) struct ResumeState {
+    dir: PathBuf,
+    params: serde_json::Value,
+    frames: Option<usize>,
+    done: HashSet<usize>,
+    log: File,
+}
+
+impl ResumeState {
+    /// 打开输出路径对应的断点目录
+    ///
+    /// 已有完整清单且参数与 `params` 一致时继续上次的进度；参数不一致时报错而不覆盖，
+    /// 帧还没有提取完的目录清空后从头开始。
+    pub(crate) fn open(output_path: &Path, params: serde_json::Value) -> Result<Self> {
+        let dir = Self::dir_for(output_path)?;
+        let manifest = std::fs::read(dir.join(MANIFEST))
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<serde_json::Value>(&bytes).ok());
+
+        let frames = match &manifest {
+            Some(manifest) if manifest["params"] != params => {
+                return Err(WatermarkError::InvalidArgument(
+                    Message::ResumeParamsMismatch.format(&[&format!("{dir:?}")]),
+                ));
+            }
+            Some(manifest) => manifest["frames"].as_u64().map(|frames| frames as usize),
+            None => None,
+        };
+        if frames.is_none() {
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+        std::fs::create_dir_all(&dir)?;
+
+        let done = match File::open(dir.join(DONE_LOG)) {
+            Ok(file) if frames.is_some() => BufReader::new(file)
+                .lines()
+                .map_while(std::result::Result::ok)
+                .filter_map(|line| line.trim().parse().ok())
+                .collect(),
+            _ => HashSet::new(),
+        };
+        let log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(DONE_LOG))?;
+
+        Ok(Self {
+            dir,
+            params,
+            frames,
+            done,
+            log,
+        })
+    }
+
+    /// 断点目录：系统临时目录下的 `seal_resume_<输出绝对路径的摘要>`
+    fn dir_for(output_path: &Path) -> Result<PathBuf> {
+        let output = std::path::absolute(output_path)?;
+        let digest = crypto::sha256_hex(output.to_string_lossy().as_bytes());
+        Ok(std::env::temp_dir().join(format!("seal_resume_{}", &digest[..16])))
+    }
+
+    /// 断点目录的路径
+    pub(crate) fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// 上次运行已提取完的帧数，`None` 表示需要从头提取
+    pub(crate) fn frames(&self) -> Option<usize> {
+        self.frames
+    }
+
+    /// 帧提取完成后写入清单，之后的中断都可以继续
+    pub(crate) fn frames_extracted(&mut self, frames: usize) -> Result<()> {
+        let manifest = json!({
+            "params": self.params,
+            "frames": frames,
+        });
+        std::fs::write(self.dir.join(MANIFEST), manifest.to_string())?;
+        self.frames = Some(frames);
+        Ok(())
+    }
+
+    /// 已完成的帧数
+    pub(crate) fn done_count(&self) -> usize {
+        self.done.len()
+    }
+
+    /// 第 `index` 帧已嵌入水印
+    pub(crate) fn is_done(&self, index: usize) -> bool {
+        self.done.contains(&index)
+    }
+
+    /// 记录第 `index` 帧已嵌入水印
+    ///
+    /// 帧文件先写到临时文件再改名替换，之后才记录；两步之间中断时该帧会再嵌入一次。
+    pub(crate) fn mark_done(&mut self, index: usize) -> Result<()> {
+        writeln!(self.log, "{index}")?;
+        self.log.flush()?;
+        self.done.insert(index);
+        Ok(())
+    }
+
+    /// 全部完成后删除断点目录
+    pub(crate) fn finish(self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resume_is_refused_when_any_param_changed() {
+        let output =
+            std::env::temp_dir().join(format!("seal_resume_{}_out.mp4", std::process::id()));
+        let params =
+            json!({ "strength": 0.1, "algorithm_options": "seed: Some(1)", "channel": "All" });
+
+        let mut state = ResumeState::open(&output, params.clone()).unwrap();
+        state.frames_extracted(3).unwrap();
+        state.mark_done(0).unwrap();
+        drop(state);
+
+        let mut changed = params.clone();
+        changed["algorithm_options"] = json!("seed: Some(2)");
+        assert!(ResumeState::open(&output, changed).is_err());
+
+        let state = ResumeState::open(&output, params).unwrap();
+        assert_eq!(state.frames(), Some(3));
+        assert!(state.is_done(0));
+        state.finish();
+    }
+
+    /// 按视频嵌入的方式逐帧处理：跳过已完成的帧，处理后记录；处理到 `stop_after` 帧时模拟中断
+    fn process_frames(state: &mut ResumeState, processed: &mut Vec<usize>, stop_after: usize) {
+        let frames = state.frames().unwrap();
+        for index in 0..frames {
+            if state.is_done(index) {
+                continue;
+            }
+            if processed.len() == stop_after {
+                return;
+            }
+            let frame = state.dir().join(format!("frame_{index:06}.png"));
+            std::fs::write(frame, format!("marked {index}")).unwrap();
+            state.mark_done(index).unwrap();
+            processed.push(index);
+        }
+    }
+
+    #[test]
+    fn interrupted_run_resumes_with_the_remaining_frames() {
+        let output =
+            std::env::temp_dir().join(format!("seal_resume_{}_half.mp4", std::process::id()));
+        let params = json!({ "strength": 0.1, "watermark_sha256": "abc" });
+        const FRAMES: usize = 10;
+
+        // 第一次运行处理完一半的帧后中断，断点目录和 done.log 保留下来
+        let mut state = ResumeState::open(&output, params.clone()).unwrap();
+        assert_eq!(state.frames(), None);
+        state.frames_extracted(FRAMES).unwrap();
+        let mut first = Vec::new();
+        process_frames(&mut state, &mut first, FRAMES / 2);
+        let dir = state.dir().to_path_buf();
+        drop(state);
+        assert_eq!(first, [0, 1, 2, 3, 4]);
+        let log = std::fs::read_to_string(dir.join(DONE_LOG)).unwrap();
+        assert_eq!(log.lines().count(), FRAMES / 2);
+
+        // 再次运行只处理剩下的帧，已完成帧的文件保持不变
+        let mut state = ResumeState::open(&output, params).unwrap();
+        assert_eq!(state.frames(), Some(FRAMES));
+        assert_eq!(state.done_count(), FRAMES / 2);
+        let mut second = Vec::new();
+        process_frames(&mut state, &mut second, usize::MAX);
+        assert_eq!(second, [5, 6, 7, 8, 9]);
+        assert_eq!(state.done_count(), FRAMES);
+        for index in 0..FRAMES {
+            let frame = dir.join(format!("frame_{index:06}.png"));
+            assert_eq!(
+                std::fs::read_to_string(frame).unwrap(),
+                format!("marked {index}")
+            );
+        }
+
+        state.finish();
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,207 @@
+use crate::watermark::prng::SeededRng;
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+
+/// 同步周期（样本数，44.1kHz 下约0.37秒），定位时至少需要两个周期的样本
+pub const PERIOD: usize = 16384;
+
+/// 相位序列和序号序列各自的码片幅度（满幅为1，约-54dBFS）
+const AMPLITUDE: f64 = 0.002;
+
+/// 每个周期都相同的相位序列的种子，用于找到周期边界
+const PHASE_SEED: u64 = 0x5359_4E43;
+
+/// 序号序列的种子，第 j 个周期使用循环左移 j 位的序列，用于确定周期序号
+const INDEX_SEED: u64 = 0x5359_4E44;
+
+/// 相关峰须高出全部相关值均值的标准差倍数，低于此值视为没有同步标记
+const DETECTION_THRESHOLD: f64 = 6.0;
+
+/// 确定周期序号时最多使用的周期数，更多的周期只增加耗时
+const MAX_INDEX_PERIODS: usize = 64;
+
+/// 长度为 `len` 的同步信号，叠加到每个声道上
+///
+/// 每个周期由两段长为 `PERIOD` 的 ±1 伪随机序列叠加而成：相位序列在所有周期中相同，
+/// 序号序列在第 j 个周期循环左移 j 位，两者都与音乐本身不相关。
+pub fn pattern(len: usize) -> Vec<f64> {
+    let phase = chips(PHASE_SEED);
+    let index = chips(INDEX_SEED);
+    (0..len)
+        .map(|n| {
+            let (period, k) = (n / PERIOD, n % PERIOD);
+            AMPLITUDE * (phase[k] + index[(k + period) % PERIOD])
+        })
+        .collect()
+}
+
+/// 在样本中定位同步标记，返回原始第0个样本在 `samples` 中的位置
+///
+/// 正数表示前面插入了这么多样本，负数表示开头被裁掉了这么多样本；没有检测到同步标记时返回 `None`。
+/// 先做一阶差分抑制音乐中占主要能量的低频成分，把所有样本按周期折叠后与相位序列做循环相关，
+/// 得到周期边界；再把各周期与序号序列的循环相关按周期序号对齐累加，得到边界处周期的序号。
+/// 可定位的插入或裁剪长度约为 ±`PERIOD`/2 个周期。
+pub fn locate(samples: &[f64]) -> Option<i64> {
+    if samples.len() < 2 * PERIOD {
+        return None;
+    }
+    let whitened: Vec<f64> = samples.windows(2).map(|pair| pair[1] - pair[0]).collect();
+    let mut planner = FftPlanner::new();
+
+    let phase = phase_correlation(&mut planner, &whitened);
+    let boundary = peak(&phase)?;
+
+    let index_chips = whitened_chips(INDEX_SEED);
+    let mut aligned = vec![0.0; PERIOD];
+    let periods = (whitened.len() - boundary) / PERIOD;
+    for m in 0..periods.min(MAX_INDEX_PERIODS) {
+        let start = boundary + m * PERIOD;
+        let correlation =
+            circular_correlation(&mut planner, &index_chips, &whitened[start..start + PERIOD]);
+        for (s, value) in aligned.iter_mut().enumerate() {
+            *value += correlation[(s + m) % PERIOD];
+        }
+    }
+    let index = peak(&aligned)?;
+
+    // 序号超过半个序列长度时按负数处理，即边界处的周期位于插入的样本中
+    let index = if index > PERIOD / 2 {
+        index as i64 - PERIOD as i64
+    } else {
+        index as i64
+    };
+    Some(boundary as i64 - index * PERIOD as i64)
+}
+
+/// 同步标记的显著性：相位相关峰高出均值的标准差倍数与判定倍数之比
+///
+/// 不小于1时 `locate` 能找到周期边界；样本不足两个周期时为0。
+pub fn significance(samples: &[f64]) -> f64 {
+    if samples.len() < 2 * PERIOD {
+        return 0.0;
+    }
+    let whitened: Vec<f64> = samples.windows(2).map(|pair| pair[1] - pair[0]).collect();
+    let phase = phase_correlation(&mut FftPlanner::new(), &whitened);
+    z_score(&phase).map_or(0.0, |(_, z)| z / DETECTION_THRESHOLD)
+}
+
+/// 把差分后的样本按周期折叠，与相位序列做循环相关
+fn phase_correlation(planner: &mut FftPlanner<f64>, whitened: &[f64]) -> Vec<f64> {
+    let mut folded = vec![0.0; PERIOD];
+    for (n, &value) in whitened.iter().enumerate() {
+        folded[n % PERIOD] += value;
+    }
+    circular_correlation(planner, &folded, &whitened_chips(PHASE_SEED))
+}
+
+/// 由种子生成的 ±1 码片序列
+fn chips(seed: u64) -> Vec<f64> {
+    let mut rng = SeededRng::new(seed);
+    (0..PERIOD)
+        .map(|_| if rng.next_u64() & 1 == 1 { 1.0 } else { -1.0 })
+        .collect()
+}
+
+/// 按周期循环做一阶差分的码片序列，与差分后的样本相关
+fn whitened_chips(seed: u64) -> Vec<f64> {
+    let chips = chips(seed);
+    (0..PERIOD)
+        .map(|k| chips[(k + 1) % PERIOD] - chips[k])
+        .collect()
+}
+
+/// 循环互相关 `sum_k signal[(s + k) % PERIOD] * reference[k]`，两者长度均为 `PERIOD`
+fn circular_correlation(
+    planner: &mut FftPlanner<f64>,
+    signal: &[f64],
+    reference: &[f64],
+) -> Vec<f64> {
+    let fft = planner.plan_fft_forward(PERIOD);
+    let ifft = planner.plan_fft_inverse(PERIOD);
+    let spectrum = |values: &[f64]| {
+        let mut buffer: Vec<Complex<f64>> = values
+            .iter()
+            .map(|&value| Complex::new(value, 0.0))
+            .collect();
+        fft.process(&mut buffer);
+        buffer
+    };
+    let mut product: Vec<Complex<f64>> = spectrum(signal)
+        .into_iter()
+        .zip(spectrum(reference))
+        .map(|(signal, reference)| signal * reference.conj())
+        .collect();
+    ifft.process(&mut product);
+    product
+        .into_iter()
+        .map(|value| value.re / PERIOD as f64)
+        .collect()
+}
+
+/// 相关值的最大位置，未显著高于其余位置时返回 `None`
+fn peak(correlation: &[f64]) -> Option<usize> {
+    z_score(correlation).and_then(|(position, z)| (z >= DETECTION_THRESHOLD).then_some(position))
+}
+
+/// 相关值的最大位置及其高出均值的标准差倍数，相关值全部相同时返回 `None`
+fn z_score(correlation: &[f64]) -> Option<(usize, f64)> {
+    let n = correlation.len() as f64;
+    let mean = correlation.iter().sum::<f64>() / n;
+    let std = (correlation
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>()
+        / n)
+        .sqrt();
+    let (position, &max) = correlation
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))?;
+    (std > 0.0).then(|| (position, (max - mean) / std))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    /// 模拟音乐的宿主信号：两个正弦波叠加少量白噪声
+    fn host(len: usize, seed: u64) -> Vec<f64> {
+        let mut rng = SeededRng::new(seed);
+        (0..len)
+            .map(|n| {
+                let t = n as f64 / 44_100.0;
+                let noise = rng.next_below(2001) as f64 / 1000.0 - 1.0;
+                0.3 * (2.0 * PI * 220.0 * t).sin()
+                    + 0.2 * (2.0 * PI * 331.0 * t).sin()
+                    + 0.01 * noise
+            })
+            .collect()
+    }
+
+    fn marked(len: usize) -> Vec<f64> {
+        host(len, 1)
+            .into_iter()
+            .zip(pattern(len))
+            .map(|(sample, marker)| sample + marker)
+            .collect()
+    }
+
+    #[test]
+    fn locates_aligned_prepended_and_trimmed_audio() {
+        let samples = marked(8 * PERIOD);
+        assert_eq!(locate(&samples), Some(0));
+
+        let mut prepended = host(40_000, 2);
+        prepended.extend_from_slice(&samples);
+        assert_eq!(locate(&prepended), Some(40_000));
+
+        assert_eq!(locate(&samples[50_000..]), Some(-50_000));
+    }
+
+    #[test]
+    fn unmarked_or_short_audio_has_no_sync() {
+        assert_eq!(locate(&host(8 * PERIOD, 1)), None);
+        assert_eq!(locate(&marked(PERIOD)), None);
+    }
+}
@@ -1,11 +1,281 @@
-use crate::cli::VideoWatermarkMode;
+use crate::cli::{Algorithm, FrameSampling, ImageChannel, VideoCodec, VideoWatermarkMode};
 use crate::error::{Result, WatermarkError};
-use crate::watermark::WatermarkAlgorithm;
+use crate::i18n::Message;
+use crate::media::audio::CANONICAL_WAV_ARGS;
+use crate::media::ffmpeg::{self, TempDir};
+use crate::media::progress::{Progress, ProgressEvent};
+use crate::media::resume::ResumeState;
+use crate::media::{EmbedOptions, ExtractionResult, Integrity};
+use crate::watermark::{crypto, AlgorithmOptions, WatermarkAlgorithm, WatermarkUtils};
+use base64::prelude::*;
 use colored::*;
 use ffmpeg_sidecar::command::FfmpegCommand;
-use indicatif::{ProgressBar, ProgressStyle};
+use ffmpeg_sidecar::event::{FfmpegEvent, LogLevel};
 use std::path::Path;
 
+/// 关键帧区间模式下至少重新编码的时长（秒），之后从下一个关键帧起直接复制原始码流
+const KEYFRAME_REGION_SECS: f64 = 2.0;
+
+/// 容器元数据中水印值的前缀，写在 `comment` 标签里，后接base64编码的水印文本
+const CONTAINER_TAG_PREFIX: &str = "seal:";
+
+/// 样本数修正的先验帧数：帧数等于该值时，置信度向随机水平 0.5 收缩一半
+const CONFIDENCE_PRIOR_FRAMES: f64 = 2.0;
+
+/// 无法从FFmpeg输出中解析帧率时使用的默认帧率
+const DEFAULT_FPS: f64 = 30.0;
+
+/// 场景切换检测阈值，即 FFmpeg `select` 滤镜的 `scene` 得分下限
+const SCENE_CHANGE_THRESHOLD: f64 = 0.3;
+
+/// 场景切换后跳过的帧数，避开转场和运动模糊，取镜头稳定后的画面
+const SCENE_SETTLE_FRAMES: usize = 5;
+
+/// 场景采样时每个采样名额最多评估的候选帧数
+const SCENE_CANDIDATES_PER_SAMPLE: usize = 3;
+
+/// 启用帧序号时每帧载荷末尾追加的字节数
+///
+/// 格式: `帧序号(4, 大端) | 校验(2)`，校验为水印文本与帧序号的CRC32低16位。
+pub const FRAME_INDEX_LEN: usize = 6;
+
+/// 未指定 `--confidence-threshold` 时视频提取使用的置信度阈值
+pub const DEFAULT_CONFIDENCE_THRESHOLD: f64 = 0.6;
+
+/// # Video encoder options
+///
+/// 控制重新编码带水印视频帧时传给 FFmpeg 的编码参数。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VideoEncodeOptions<'a> {
+    /// 使用所选编码器的无损模式
+    pub lossless: bool,
+    /// 视频编码格式，`None` 时沿用源视频的编码格式
+    pub codec: Option<VideoCodec>,
+    /// 有损编码的质量参数，`None` 时使用编码器的默认值
+    pub crf: Option<u8>,
+    /// 编码线程数上限，`None` 时由 FFmpeg 自动选择（通常占满所有核心）
+    pub ffmpeg_threads: Option<usize>,
+    /// `video` 模式下先把所有帧写成临时PNG再重新组合，而不是经管道逐帧处理（用于调试）
+    pub disk_frames: bool,
+    /// 在每帧的载荷末尾追加该帧在源视频中的序号，提取时可据此判断片段来自哪些帧
+    pub frame_index: bool,
+    /// 每N帧只嵌入一帧，其余帧原样编码；首帧和最后一帧总是嵌入，`None` 时每帧都嵌入
+    pub embed_every: Option<usize>,
+    /// `video` 模式下按落盘PNG帧处理，并在由输出路径决定的固定目录中记录进度，
+    /// 中断后以相同参数再次运行时跳过已嵌入的帧
+    pub resume: bool,
+    /// 创建算法时的全部参数，`resume` 时记入清单，续传时与上次不同则拒绝继续
+    pub algorithm_options: Option<&'a AlgorithmOptions>,
+    /// 命令行指定的图片颜色通道，`resume` 时记入清单
+    pub channel: ImageChannel,
+    /// 处理进度回调，默认不报告进度
+    pub progress: Progress<'a>,
+}
+
+impl VideoEncodeOptions<'_> {
+    /// 序号为 `index` 的帧是否嵌入水印，`last` 表示该帧是最后一帧
+    fn embeds_frame(&self, index: usize, last: bool) -> bool {
+        last || self
+            .embed_every
+            .is_none_or(|every| index.is_multiple_of(every))
+    }
+}
+
+/// # Video extraction options
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VideoExtractOptions<'a> {
+    /// 采样帧的选择方式
+    pub sampling: FrameSampling,
+    /// 嵌入时启用了帧序号，按 `FRAME_INDEX_LEN` 读取每帧末尾的序号
+    pub frame_index: bool,
+    /// 嵌入时使用的 `embed_every`，采样帧对齐到带水印的帧上
+    pub embed_every: Option<usize>,
+    /// 启用帧序号并均匀采样时，某一帧的帧序号通过校验且该帧置信度不低于此值即停止采样，
+    /// 直接按已处理的帧给出结果；`None` 时总是处理全部采样帧
+    pub early_stop_confidence: Option<f64>,
+    /// 处理进度回调，默认不报告进度
+    pub progress: Progress<'a>,
+}
+
+/// 从视频帧中恢复的原始帧序号范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameIndexRange {
+    /// 最小的原始帧序号
+    pub first: u32,
+    /// 最大的原始帧序号
+    pub last: u32,
+    /// 帧序号通过校验的帧数
+    pub frames: usize,
+    /// 各帧的原始序号与其在输入中的位置是否保持固定偏移，即片段未经拼接或重排
+    pub contiguous: bool,
+}
+
+impl FrameIndexRange {
+    /// 由 `(帧在输入中的位置, 原始帧序号)` 汇总序号范围
+    fn from_pairs(pairs: &[(usize, u32)]) -> Option<Self> {
+        let first = pairs.iter().map(|&(_, index)| index).min()?;
+        let last = pairs.iter().map(|&(_, index)| index).max()?;
+        let offset = |&(position, index): &(usize, u32)| index as i64 - position as i64;
+        let contiguous = pairs.windows(2).all(|w| offset(&w[0]) == offset(&w[1]));
+        Some(Self {
+            first,
+            last,
+            frames: pairs.len(),
+            contiguous,
+        })
+    }
+}
+
+/// 逐帧提取的结果
+#[derive(Default)]
+struct FrameResults {
+    /// 每帧的水印比特及其质量权重，用于投票
+    votes: Vec<(Vec<u8>, f64)>,
+    /// 帧序号通过校验的帧，`(帧在输入中的位置, 原始帧序号)`
+    indices: Vec<(usize, u32)>,
+    /// 某一帧通过帧序号校验后提前停止了采样
+    stopped_early: bool,
+}
+
+impl FrameResults {
+    fn push(&mut self, position: usize, bits: Vec<u8>, quality: f64, index: Option<u32>) {
+        self.votes.push((bits, quality));
+        if let Some(index) = index {
+            self.indices.push((position, index));
+        }
+    }
+}
+
+impl VideoEncodeOptions<'_> {
+    /// 确定实际使用的编码格式并检查CRF范围
+    ///
+    /// 未指定编码格式时沿用源视频的编码，源编码无法识别或放不进输出扩展名对应的容器时
+    /// 退回该容器可用的默认编码；显式指定的编码与容器不兼容时报错。
+    fn resolve(mut self, source_codec: Option<&str>, output_path: &Path) -> Result<Self> {
+        let extension = output_path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        let codec = match self.codec {
+            Some(codec) if !codec.fits_container(&extension) => {
+                return Err(WatermarkError::InvalidArgument(
+                    Message::CodecContainerMismatch.format(&[&format!("{codec:?}"), &extension]),
+                ));
+            }
+            Some(codec) => codec,
+            None => source_codec
+                .and_then(VideoCodec::from_stream_format)
+                .filter(|codec| codec.fits_container(&extension))
+                .unwrap_or(if extension == "webm" {
+                    VideoCodec::Vp9
+                } else {
+                    VideoCodec::H264
+                }),
+        };
+
+        if let Some(crf) = self.crf {
+            if crf > codec.max_crf() {
+                return Err(WatermarkError::InvalidArgument(
+                    Message::CrfRange.format(&[&format!("{codec:?}"), &codec.max_crf(), &crf]),
+                ));
+            }
+        }
+
+        self.codec = Some(codec);
+        Ok(self)
+    }
+
+    /// 追加视频编码参数，须在设置输出路径之前调用
+    fn apply(&self, command: &mut FfmpegCommand) {
+        let codec = self.codec.unwrap_or(VideoCodec::H264);
+        command.args(["-c:v", codec.encoder()]);
+        if self.lossless {
+            match codec {
+                VideoCodec::H264 => command.args(["-crf", "0"]),
+                VideoCodec::Hevc => command.args(["-x265-params", "lossless=1"]),
+                VideoCodec::Vp9 => command.args(["-lossless", "1"]),
+                VideoCodec::Av1 => command.args(["-aom-params", "lossless=1"]),
+            };
+        } else {
+            let crf = self.crf.unwrap_or(codec.default_crf());
+            command.args(["-crf", &crf.to_string()]);
+        }
+        match codec {
+            VideoCodec::H264 | VideoCodec::Hevc => {
+                // 无损压缩时，使用ultrafast可以极大加快速度；有损压缩时，使用medium预设平衡质量和速度
+                let preset = if self.lossless { "ultrafast" } else { "medium" };
+                command.args(["-preset", preset]);
+            }
+            // libvpx 和 libaom 需要把码率设为0才进入纯 CRF 模式
+            VideoCodec::Vp9 => {
+                command.args(["-b:v", "0"]);
+            }
+            // libaom 默认的 cpu-used 1 极慢，6 在速度和压缩率之间比较均衡
+            VideoCodec::Av1 => {
+                command.args(["-b:v", "0", "-cpu-used", "6"]);
+            }
+        }
+        if let Some(threads) = self.ffmpeg_threads {
+            command.args(["-threads", &threads.to_string()]);
+        }
+    }
+}
+
+impl VideoCodec {
+    /// 由 FFmpeg 报告的视频流格式名识别编码，无法识别时返回 `None`
+    fn from_stream_format(format: &str) -> Option<Self> {
+        match format {
+            "h264" => Some(Self::H264),
+            "hevc" | "h265" => Some(Self::Hevc),
+            "vp9" => Some(Self::Vp9),
+            "av1" => Some(Self::Av1),
+            _ => None,
+        }
+    }
+
+    /// 能否写入扩展名为 `extension`（小写）的容器
+    ///
+    /// `.webm` 只接受 VP9 和 AV1，`.avi` 只可靠地支持 H.264，`.mov` 支持 H.264 和 HEVC，
+    /// `.mp4`、`.mkv` 以及其他扩展名不做限制，由 FFmpeg 决定。
+    fn fits_container(self, extension: &str) -> bool {
+        match extension {
+            "webm" => matches!(self, Self::Vp9 | Self::Av1),
+            "avi" => self == Self::H264,
+            "mov" => matches!(self, Self::H264 | Self::Hevc),
+            _ => true,
+        }
+    }
+
+    /// 对应的 FFmpeg 编码器名称
+    fn encoder(self) -> &'static str {
+        match self {
+            Self::H264 => "libx264",
+            Self::Hevc => "libx265",
+            Self::Vp9 => "libvpx-vp9",
+            Self::Av1 => "libaom-av1",
+        }
+    }
+
+    /// 未指定 `crf` 时使用的质量参数，取各编码器文档推荐的默认值
+    fn default_crf(self) -> u8 {
+        match self {
+            Self::H264 => 23,
+            Self::Hevc => 28,
+            Self::Vp9 => 31,
+            Self::Av1 => 30,
+        }
+    }
+
+    /// CRF 的最大取值
+    fn max_crf(self) -> u8 {
+        match self {
+            Self::H264 | Self::Hevc => 51,
+            Self::Vp9 | Self::Av1 => 63,
+        }
+    }
+}
+
 /// # Video watermark processor
 pub struct VideoWatermarker;
 
@@ -15,16 +285,102 @@ impl VideoWatermarker {
         input_path: P,
         output_path: P,
         watermark_text: &str,
-        algorithm: &dyn WatermarkAlgorithm,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
         strength: f64,
         lossless: bool,
         video_mode: VideoWatermarkMode,
     ) -> Result<usize> {
+        Self::embed_watermark_with_options(
+            input_path,
+            output_path,
+            watermark_text,
+            algorithm,
+            strength,
+            video_mode,
+            VideoEncodeOptions {
+                lossless,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// # Embed watermark to video, creating the algorithm from `options`
+    ///
+    /// 图片和音频的选项被忽略
+    pub fn embed_with_options<P: AsRef<Path>>(
+        input_path: P,
+        output_path: P,
+        watermark_text: &str,
+        algorithm: Algorithm,
+        options: &EmbedOptions,
+    ) -> Result<usize> {
+        let algorithm = options.create_algorithm(algorithm);
+        Self::embed_watermark_with_options(
+            input_path,
+            output_path,
+            watermark_text,
+            algorithm.as_ref(),
+            options.strength,
+            options.video_mode.clone(),
+            options.video,
+        )
+    }
+
+    /// # Embed watermark to video with explicit encoder options
+    pub fn embed_watermark_with_options<P: AsRef<Path>>(
+        input_path: P,
+        output_path: P,
+        watermark_text: &str,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        strength: f64,
+        video_mode: VideoWatermarkMode,
+        encode: VideoEncodeOptions,
+    ) -> Result<usize> {
+        WatermarkUtils::validate_strength(strength)?;
+
         let input_path = input_path.as_ref();
         let output_path = output_path.as_ref();
 
         let video_info = Self::get_video_info(input_path)?;
 
+        let encode = encode.resolve(video_info.codec.as_deref(), output_path)?;
+        // 关键帧区间模式直接复制源视频的 H.264 尾段，源视频和重新编码的开头片段都必须是 H.264
+        if matches!(video_mode, VideoWatermarkMode::Keyframe) {
+            if video_info.codec.as_deref() != Some("h264") {
+                return Err(WatermarkError::UnsupportedFormat(
+                    Message::KeyframeSourceH264Only.format(&[&video_info
+                        .codec
+                        .as_deref()
+                        .unwrap_or(Message::UnknownCodec.text())]),
+                ));
+            }
+            if let Some(codec) = encode.codec.filter(|&codec| codec != VideoCodec::H264) {
+                return Err(WatermarkError::InvalidArgument(
+                    Message::KeyframeOutputH264Only
+                        .format(&[&format!("{codec:?}"), &output_path.display()]),
+                ));
+            }
+            // 开头片段的各帧共用首帧的水印差值，无法各自携带帧序号
+            if encode.frame_index {
+                return Err(WatermarkError::InvalidArgument(
+                    Message::KeyframeFrameIndexUnsupported.text().to_string(),
+                ));
+            }
+        }
+
+        // 在逐帧处理之前检查容量，避免处理到一半才失败；音频模式由音频嵌入自行检查
+        if !matches!(video_mode, VideoWatermarkMode::Audio) {
+            let frame_payload_len = if encode.frame_index {
+                watermark_text.len() + FRAME_INDEX_LEN
+            } else {
+                watermark_text.len()
+            };
+            WatermarkUtils::ensure_capacity(
+                frame_payload_len * 8,
+                Self::frame_capacity(input_path, algorithm)?,
+            )?;
+        }
+
         match video_mode {
             VideoWatermarkMode::Video => Self::embed_video_only(
                 input_path,
@@ -32,7 +388,7 @@ impl VideoWatermarker {
                 watermark_text,
                 algorithm,
                 strength,
-                lossless,
+                encode,
                 &video_info,
             ),
             VideoWatermarkMode::Audio => Self::embed_audio_only(
@@ -42,6 +398,7 @@ impl VideoWatermarker {
                 algorithm,
                 strength,
                 &video_info,
+                encode.progress,
             ),
             VideoWatermarkMode::Both => Self::embed_both(
                 input_path,
@@ -49,20 +406,60 @@ impl VideoWatermarker {
                 watermark_text,
                 algorithm,
                 strength,
-                lossless,
+                encode,
+                &video_info,
+            ),
+            VideoWatermarkMode::Keyframe => Self::embed_keyframe_region(
+                input_path,
+                output_path,
+                watermark_text,
+                algorithm,
+                strength,
+                encode,
+                &video_info,
+            ),
+            VideoWatermarkMode::All => Self::embed_all(
+                input_path,
+                output_path,
+                watermark_text,
+                algorithm,
+                strength,
+                encode,
                 &video_info,
             ),
         }
     }
 
+    /// 从视频中提取水印文本，结果包含置信度和实际参与投票的帧数
     pub fn extract_watermark<P: AsRef<Path>>(
         input_path: P,
-        algorithm: &dyn WatermarkAlgorithm,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        watermark_length: usize,
+        sample_frames: Option<usize>,
+        confidence_threshold: Option<f64>,
+        video_mode: VideoWatermarkMode,
+    ) -> Result<ExtractionResult> {
+        Self::extract_watermark_with_options(
+            input_path,
+            algorithm,
+            watermark_length,
+            sample_frames,
+            confidence_threshold,
+            video_mode,
+            VideoExtractOptions::default(),
+        )
+    }
+
+    /// # Extract watermark from video with explicit sampling and frame index options
+    pub fn extract_watermark_with_options<P: AsRef<Path>>(
+        input_path: P,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
         watermark_length: usize,
         sample_frames: Option<usize>,
         confidence_threshold: Option<f64>,
         video_mode: VideoWatermarkMode,
-    ) -> Result<(String, f64, usize)> {
+        options: VideoExtractOptions,
+    ) -> Result<ExtractionResult> {
         let input_path = input_path.as_ref();
 
         let video_info = Self::get_video_info(input_path)?;
@@ -74,10 +471,26 @@ impl VideoWatermarker {
                 watermark_length,
                 sample_frames,
                 confidence_threshold,
+                None,
+                options,
+            ),
+            // 关键帧区间模式下只有开头一段带水印，提取该区间内的全部帧
+            VideoWatermarkMode::Keyframe => Self::extract_video_only(
+                input_path,
+                algorithm,
+                watermark_length,
+                Some(0),
+                confidence_threshold,
+                Some(KEYFRAME_REGION_SECS),
+                options,
+            ),
+            VideoWatermarkMode::Audio => Self::extract_audio_only(
+                input_path,
+                algorithm,
+                watermark_length,
+                &video_info,
+                options.progress,
             ),
-            VideoWatermarkMode::Audio => {
-                Self::extract_audio_only(input_path, algorithm, watermark_length, &video_info)
-            }
             VideoWatermarkMode::Both => Self::extract_both(
                 input_path,
                 algorithm,
@@ -85,134 +498,237 @@ impl VideoWatermarker {
                 sample_frames,
                 confidence_threshold,
                 &video_info,
+                options,
+            ),
+            VideoWatermarkMode::All => Self::extract_all(
+                input_path,
+                algorithm,
+                watermark_length,
+                sample_frames,
+                confidence_threshold,
+                &video_info,
+                options,
             ),
         }
     }
 
+    /// # 计算视频的水印存在性得分
+    ///
+    /// 对采样帧分别计算图片存在性得分并取平均，返回得分和实际使用的帧数。
+    pub fn detect_watermark<P: AsRef<Path>>(
+        input_path: P,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        sample_frames: usize,
+    ) -> Result<(f64, usize)> {
+        use crate::media::ImageWatermarker;
+
+        let input_path = input_path.as_ref();
+        let _video_info = Self::get_video_info(input_path)?;
+
+        let temp_dir = TempDir::new("video_detect")?;
+
+        // 与提取相同：跳过开头几帧避免编码问题
+        let skip_frames = 5;
+        let sample_frames = sample_frames.max(1);
+        let frame_indices = Self::generate_sample_frame_indices(
+            sample_frames,
+            skip_frames,
+            skip_frames + sample_frames,
+        );
+
+        let mut scores = Vec::new();
+        for (i, &frame_idx) in frame_indices.iter().enumerate() {
+            let frame_path = temp_dir.join(format!("detect_frame_{}.png", i));
+            match Self::extract_single_frame(input_path, &frame_path, frame_idx as u32) {
+                Err(e) if ffmpeg::is_aborted(&e) => return Err(e),
+                Ok(()) if frame_path.exists() => {}
+                _ => continue,
+            }
+            if let Ok(score) = ImageWatermarker::detect_watermark(&frame_path, algorithm) {
+                scores.push(score);
+            }
+        }
+
+        if scores.is_empty() {
+            return Err(WatermarkError::ProcessingError(
+                Message::NoSampledFrames.text().to_string(),
+            ));
+        }
+
+        let score = scores.iter().sum::<f64>() / scores.len() as f64;
+        Ok((score, scores.len()))
+    }
+
     /// # Check watermark capacity
     pub fn check_watermark_capacity<P: AsRef<Path>>(
         input_path: P,
         watermark_text: &str,
-        algorithm: &dyn WatermarkAlgorithm,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
     ) -> Result<bool> {
+        let watermark_bits = watermark_text.len() * 8;
+        Ok(watermark_bits <= Self::frame_capacity(input_path, algorithm)?)
+    }
+
+    /// # Capacity of a single video frame in bits
+    pub fn frame_capacity<P: AsRef<Path>>(
+        input_path: P,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+    ) -> Result<usize> {
         // Extract a frame for capacity check
-        let temp_dir = std::env::temp_dir().join(format!("video_capacity_{}", std::process::id()));
-        std::fs::create_dir_all(&temp_dir)?;
+        let temp_dir = TempDir::new("video_capacity")?;
 
         let sample_frame = temp_dir.join("sample_frame.png");
         Self::extract_single_frame(input_path.as_ref(), &sample_frame, 1)?;
 
-        // Use image watermark capacity check
+        // Use image watermark capacity
         use crate::media::ImageWatermarker;
-        let result =
-            ImageWatermarker::check_watermark_capacity(&sample_frame, watermark_text, algorithm);
+        ImageWatermarker::payload_capacity(&sample_frame, algorithm)
+    }
+
+    /// # Maximum payload bytes of a single video frame
+    ///
+    /// 抽取一帧按其尺寸计算，每帧写入的是同一份载荷，容量与帧数无关。
+    pub fn max_capacity_bytes<P: AsRef<Path>>(
+        input_path: P,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+    ) -> Result<usize> {
+        Ok(Self::frame_capacity(input_path, algorithm)? / 8)
+    }
 
-        // Clean up temporary files
-        std::fs::remove_dir_all(&temp_dir)?;
+    /// # Probe video stream properties
+    ///
+    /// 读取容器头中的分辨率、帧率、时长以及是否带音频轨道，不解码任何帧。
+    pub fn probe<P: AsRef<Path>>(input_path: P) -> Result<VideoProperties> {
+        let input_str = input_path.as_ref().to_str().ok_or_else(|| {
+            WatermarkError::ProcessingError(Message::InputPathInvalid.text().to_string())
+        })?;
 
-        result
+        let mut child = FfmpegCommand::new()
+            .input(input_str)
+            .args(["-frames:v", "0"])
+            .args(["-f", "null"])
+            .output("-")
+            .spawn()
+            .map_err(WatermarkError::Io)?;
+
+        let mut video = None;
+        let mut has_audio = false;
+        let mut duration = None;
+
+        let events = child.iter().map_err(|e| {
+            WatermarkError::ProcessingError(Message::FfmpegOutputUnreadable.format(&[&e]))
+        })?;
+        let job = ffmpeg::watch(child);
+        for event in events {
+            match event {
+                FfmpegEvent::ParsedInputStream(stream) => {
+                    if let Some(data) = stream.video_data() {
+                        video.get_or_insert((
+                            stream.format.clone(),
+                            data.width,
+                            data.height,
+                            data.fps,
+                        ));
+                    } else if stream.is_audio() {
+                        has_audio = true;
+                    }
+                }
+                FfmpegEvent::ParsedDuration(parsed) if parsed.duration > 0.0 => {
+                    duration = Some(parsed.duration);
+                }
+                _ => {}
+            }
+        }
+        job.wait()?;
+
+        let (codec, width, height, fps) = video.ok_or_else(|| {
+            WatermarkError::UnsupportedFormat(Message::NoVideoStream.text().to_string())
+        })?;
+        Ok(VideoProperties {
+            codec,
+            width,
+            height,
+            fps,
+            duration,
+            has_audio,
+        })
     }
 
     /// # Get video info
     fn get_video_info<P: AsRef<Path>>(input_path: P) -> Result<VideoInfo> {
         // Try to extract the first frame
-        let temp_dir = std::env::temp_dir().join(format!("video_info_{}", std::process::id()));
-        std::fs::create_dir_all(&temp_dir)?;
+        let temp_dir = TempDir::new("video_info")?;
 
         let test_frame = temp_dir.join("test_frame.png");
-        let mut child = FfmpegCommand::new()
-            .input(input_path.as_ref().to_str().unwrap())
-            .args(["-vframes", "1"])
-            .args(["-y"])
-            .output(test_frame.to_str().unwrap())
-            .spawn()
-            .map_err(WatermarkError::Io)?;
-
-        let status = child.wait().map_err(WatermarkError::Io)?;
+        let status = ffmpeg::run(
+            FfmpegCommand::new()
+                .input(input_path.as_ref().to_str().unwrap())
+                .args(["-vframes", "1"])
+                .args(["-y"])
+                .output(test_frame.to_str().unwrap()),
+        )?;
         let has_video = status.success();
 
         if !has_video {
-            std::fs::remove_dir_all(&temp_dir)?;
             return Err(WatermarkError::UnsupportedFormat(
-                "输入文件不包含视频流".to_string(),
+                Message::NoVideoStream.text().to_string(),
             ));
         }
 
         // Check if there is audio: try to extract audio
         let test_audio = temp_dir.join("test_audio.wav");
-        let mut child = FfmpegCommand::new()
-            .input(input_path.as_ref().to_str().unwrap())
-            .args(["-vn"]) // 不包含视频
-            .args(["-t", "0.1"]) // 只提取0.1秒
-            .args(["-y"])
-            .output(test_audio.to_str().unwrap())
-            .spawn()
-            .map_err(WatermarkError::Io)?;
-
-        let audio_status = child.wait().map_err(WatermarkError::Io)?;
+        let audio_status = ffmpeg::run(
+            FfmpegCommand::new()
+                .input(input_path.as_ref().to_str().unwrap())
+                .args(["-vn"]) // 不包含视频
+                .args(["-t", "0.1"]) // 只提取0.1秒
+                .args(["-y"])
+                .output(test_audio.to_str().unwrap()),
+        )?;
         let has_audio =
             audio_status.success() && test_audio.exists() && test_audio.metadata()?.len() > 0;
 
-        // Remove temp dir
-        std::fs::remove_dir_all(&temp_dir)?;
+        // 帧率和时长从FFmpeg的流信息中解析，解析不到时退回默认帧率
+        let properties = Self::probe(input_path).ok();
+        let fps = properties
+            .as_ref()
+            .map(|p| p.fps as f64)
+            .filter(|&fps| fps > 0.0)
+            .unwrap_or(DEFAULT_FPS);
 
         Ok(VideoInfo {
             has_audio,
             has_video,
-            duration: None, // 可以从ffmpeg输出中解析Duration信息
-            fps: 30.0,      // 默认值，可以从ffmpeg输出中解析
+            duration: properties.as_ref().and_then(|p| p.duration),
+            fps,
+            codec: properties.map(|p| p.codec),
         })
     }
 
-    /// # Extract audio from video
-    fn extract_audio<P: AsRef<Path>>(input_path: P, output_path: P) -> Result<()> {
-        let input_str = input_path
-            .as_ref()
-            .to_str()
-            .ok_or_else(|| WatermarkError::ProcessingError("输入路径包含无效字符".to_string()))?;
-        let output_str = output_path
-            .as_ref()
-            .to_str()
-            .ok_or_else(|| WatermarkError::ProcessingError("输出路径包含无效字符".to_string()))?;
-
-        let mut child = FfmpegCommand::new()
-            .input(input_str)
-            .args(["-vn"]) // Do not include video
-            .args(["-acodec", "pcm_s16le"]) // 使用无损PCM编码保护音频水印
-            .args(["-y"]) // Overwrite output file
-            .output(output_str)
-            .spawn()
-            .map_err(WatermarkError::Io)?;
-
-        let status = child.wait().map_err(WatermarkError::Io)?;
-
-        if !status.success() {
-            return Err(WatermarkError::ProcessingError(format!(
-                "音频提取失败: FFmpeg 命令执行失败, 错误码: {}",
-                status.code().unwrap_or(-1)
-            )));
-        }
-
-        Ok(())
-    }
-
-    /// 提取视频帧
-    fn extract_frames<P: AsRef<Path>>(input_path: P, output_dir: P) -> Result<()> {
+    /// 按源帧率逐帧提取视频帧，不做重采样，`max_duration` 限制只提取开头若干秒
+    ///
+    /// 提取出的帧数与源视频一致，按探测到的帧率重新组合后时长不变，不会与音轨错位。
+    fn extract_frames<P: AsRef<Path>>(
+        input_path: P,
+        output_dir: P,
+        max_duration: Option<f64>,
+    ) -> Result<()> {
         let output_pattern = output_dir.as_ref().join("frame_%06d.png");
 
-        let mut child = FfmpegCommand::new()
-            .input(input_path.as_ref().to_str().unwrap())
-            .args(["-vf", "fps=30"]) // 固定帧率
-            .args(["-y"])
-            .output(output_pattern.to_str().unwrap())
-            .spawn()
-            .map_err(WatermarkError::Io)?;
-
-        let status = child.wait().map_err(WatermarkError::Io)?;
+        let mut command = FfmpegCommand::new();
+        command.input(input_path.as_ref().to_str().unwrap());
+        if let Some(duration) = max_duration {
+            command.args(["-t", &duration.to_string()]);
+        }
+        let status = ffmpeg::run(
+            command
+                .args(["-y"])
+                .output(output_pattern.to_str().unwrap()),
+        )?;
 
         if !status.success() {
             return Err(WatermarkError::ProcessingError(
-                "视频帧提取失败".to_string(),
+                Message::FrameExtractionFailed.text().to_string(),
             ));
         }
 
@@ -225,19 +741,19 @@ impl VideoWatermarker {
         output_path: P,
         frame_number: u32,
     ) -> Result<()> {
-        let mut child = FfmpegCommand::new()
-            .input(input_path.as_ref().to_str().unwrap())
-            .args(["-vf", &format!("select=eq(n\\,{frame_number})")])
-            .args(["-vframes", "1"])
-            .args(["-y"])
-            .output(output_path.as_ref().to_str().unwrap())
-            .spawn()
-            .map_err(WatermarkError::Io)?;
-
-        let status = child.wait().map_err(WatermarkError::Io)?;
+        let status = ffmpeg::run(
+            FfmpegCommand::new()
+                .input(input_path.as_ref().to_str().unwrap())
+                .args(["-vf", &format!("select=eq(n\\,{frame_number})")])
+                .args(["-vframes", "1"])
+                .args(["-y"])
+                .output(output_path.as_ref().to_str().unwrap()),
+        )?;
 
         if !status.success() {
-            return Err(WatermarkError::ProcessingError("单帧提取失败".to_string()));
+            return Err(WatermarkError::ProcessingError(
+                Message::SingleFrameFailed.text().to_string(),
+            ));
         }
 
         Ok(())
@@ -247,25 +763,40 @@ impl VideoWatermarker {
     fn extract_multiple_frames_watermark<P: AsRef<Path>>(
         input_path: P,
         temp_dir: &Path,
-        algorithm: &dyn WatermarkAlgorithm,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
         watermark_length: usize,
         sample_frames: usize,
-    ) -> Result<Vec<(Vec<u8>, f64)>> {
+        max_duration: Option<f64>,
+        options: VideoExtractOptions,
+    ) -> Result<FrameResults> {
         if sample_frames == 0 {
             // 提取所有帧
-            return Self::extract_all_frames_watermark(input_path, temp_dir, algorithm, watermark_length);
+            return Self::extract_all_frames_watermark(
+                input_path,
+                temp_dir,
+                algorithm,
+                watermark_length,
+                max_duration,
+                options,
+            );
         }
 
-        let mut results = Vec::new();
-        use crate::media::ImageWatermarker;
+        let mut results = FrameResults::default();
 
         // 生成采样帧位置：跳过前5%帧，在剩余帧中均匀采样
-        let skip_frames = 5; // 跳过前5帧避免编码问题
-        let mut frame_indices = Self::generate_sample_frame_indices(
-            sample_frames,
-            skip_frames,
-            skip_frames + sample_frames,
-        );
+        let skip_frames: usize = 5; // 跳过前5帧避免编码问题
+        let mut frame_indices = match options.embed_every {
+            // 隔帧嵌入时只取带水印的帧：跳过的帧之后依次取连续的带水印帧
+            Some(every) if every > 1 => {
+                let first = skip_frames.div_ceil(every);
+                (first..first + sample_frames).map(|k| k * every).collect()
+            }
+            _ => Self::generate_sample_frame_indices(
+                sample_frames,
+                skip_frames,
+                skip_frames + sample_frames,
+            ),
+        };
         frame_indices.sort_unstable();
         frame_indices.dedup();
         // 控制最终抽样数量不超过请求值
@@ -273,6 +804,28 @@ impl VideoWatermarker {
             frame_indices.truncate(sample_frames);
         }
 
+        // 场景采样：候选帧取自各场景切换点之后，评估画面质量后只保留最好的若干帧
+        if matches!(options.sampling, FrameSampling::Scene) {
+            frame_indices = Self::scene_candidate_indices(
+                input_path.as_ref(),
+                sample_frames,
+                max_duration,
+                &frame_indices,
+            );
+            // 隔帧嵌入时把候选帧向前对齐到最近的带水印帧
+            if let Some(every) = options.embed_every.filter(|&every| every > 1) {
+                frame_indices = frame_indices.iter().map(|i| i - i % every).collect();
+                frame_indices.dedup();
+            }
+        }
+
+        // 均匀采样时逐帧提取，帧序号校验通过且置信度足够的帧出现后不再采样后续帧；
+        // 场景采样需要先比较全部候选帧的质量，不提前停止
+        let early_stop = options
+            .early_stop_confidence
+            .filter(|_| options.frame_index && !matches!(options.sampling, FrameSampling::Scene));
+
+        let mut scored_frames = Vec::new();
         for (i, &frame_idx) in frame_indices.iter().enumerate() {
             let frame_path = temp_dir.join(format!("sample_frame_{}.png", i));
 
@@ -290,33 +843,41 @@ impl VideoWatermarker {
                         }
                     }
                     // 计算帧质量
-                    let quality = match Self::assess_frame_quality(&frame_path) {
-                        Ok(q) => q,
-                        Err(_) => {
-                            // 质量评估失败则跳过此帧
-                            let _ = std::fs::remove_file(&frame_path);
-                            continue;
-                        }
-                    };
-
-                    // 提取水印
-                    match ImageWatermarker::extract_watermark(
-                        &frame_path,
-                        algorithm,
-                        watermark_length,
-                    ) {
-                        Ok(watermark_text) => {
-                            // 将字符串转换为比特数组进行投票
-                            let bits = Self::string_to_bits(&watermark_text, watermark_length);
-                            results.push((bits, quality));
+                    match Self::assess_frame_quality(&frame_path) {
+                        Ok(quality) if early_stop.is_some() => {
+                            let Ok((bits, index, confidence)) = Self::extract_frame_bits(
+                                &frame_path,
+                                algorithm,
+                                watermark_length,
+                                true,
+                            ) else {
+                                let _ = std::fs::remove_file(&frame_path);
+                                continue;
+                            };
+                            results.push(frame_idx, bits, quality, index);
+                            if index.is_some() && early_stop.is_some_and(|t| confidence >= t) {
+                                eprintln!(
+                                    "{} {}",
+                                    "⏩".green(),
+                                    Message::EarlyStop.format(&[
+                                        &frame_idx,
+                                        &format!("{:.1}", confidence * 100.0),
+                                        &(frame_indices.len() - i - 1)
+                                    ])
+                                );
+                                results.stopped_early = true;
+                                break;
+                            }
                         }
+                        Ok(quality) => scored_frames.push((frame_path, quality, frame_idx)),
                         Err(_) => {
-                            // 提取失败，跳过这一帧
+                            // 质量评估失败则跳过此帧
                             let _ = std::fs::remove_file(&frame_path);
                             continue;
                         }
                     }
                 }
+                Err(e) if ffmpeg::is_aborted(&e) => return Err(e),
                 Err(_) => {
                     // 帧提取失败，跳过
                     continue;
@@ -324,42 +885,177 @@ impl VideoWatermarker {
             }
         }
 
-        if results.is_empty() {
-            return Err(WatermarkError::ProcessingError(
-                "所有采样帧的水印提取都失败".to_string(),
-            ));
+        // 场景采样的候选帧多于采样数，按质量从高到低保留
+        if scored_frames.len() > sample_frames {
+            scored_frames.sort_by(|a, b| b.1.total_cmp(&a.1));
+            for (frame_path, _, _) in scored_frames.drain(sample_frames..) {
+                let _ = std::fs::remove_file(frame_path);
+            }
         }
 
-        Ok(results)
-    }
-
-    /// # Extract all frames watermark
-    fn extract_all_frames_watermark<P: AsRef<Path>>(
+        for (frame_path, quality, position) in scored_frames {
+            // 提取水印，失败则跳过这一帧
+            match Self::extract_frame_bits(
+                &frame_path,
+                algorithm,
+                watermark_length,
+                options.frame_index,
+            ) {
+                Ok((bits, index, _)) => results.push(position, bits, quality, index),
+                Err(_) => {
+                    let _ = std::fs::remove_file(&frame_path);
+                }
+            }
+        }
+
+        if results.votes.is_empty() {
+            return Err(WatermarkError::ProcessingError(
+                Message::AllSampledFramesFailed.text().to_string(),
+            ));
+        }
+
+        Ok(results)
+    }
+
+    /// 场景采样的候选帧：开头以及每个场景切换点之后 `SCENE_SETTLE_FRAMES` 帧
+    ///
+    /// 候选帧数限制在采样数的 `SCENE_CANDIDATES_PER_SAMPLE` 倍以内并均匀抽取，
+    /// 场景太少时用均匀采样的帧补足；检测失败时退回均匀采样。
+    fn scene_candidate_indices(
+        input_path: &Path,
+        sample_frames: usize,
+        max_duration: Option<f64>,
+        uniform_indices: &[usize],
+    ) -> Vec<usize> {
+        let cuts = match Self::scene_change_frames(input_path, max_duration) {
+            Ok(cuts) => cuts,
+            Err(e) => {
+                eprintln!(
+                    "{} {}",
+                    "⚠️".yellow(),
+                    Message::SceneDetectionFailed.format(&[&e])
+                );
+                return uniform_indices.to_vec();
+            }
+        };
+
+        let mut candidates: Vec<usize> = std::iter::once(0)
+            .chain(cuts)
+            .map(|frame| frame + SCENE_SETTLE_FRAMES)
+            .collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        let limit = sample_frames * SCENE_CANDIDATES_PER_SAMPLE;
+        if candidates.len() > limit {
+            candidates = (0..limit)
+                .map(|i| candidates[i * candidates.len() / limit])
+                .collect();
+        }
+        if candidates.len() < sample_frames {
+            candidates.extend_from_slice(uniform_indices);
+            candidates.sort_unstable();
+            candidates.dedup();
+        }
+
+        eprintln!(
+            "{} {}",
+            "🎞️".blue(),
+            Message::SceneSampling
+                .format(&[&candidates.len(), &(sample_frames.min(candidates.len()))])
+        );
+        candidates
+    }
+
+    /// 用 FFmpeg 的 `scene` 得分找出场景切换点，返回切换处的帧序号
+    fn scene_change_frames(input_path: &Path, max_duration: Option<f64>) -> Result<Vec<usize>> {
+        let mut command = FfmpegCommand::new();
+        command.input(input_path.to_str().unwrap());
+        if let Some(duration) = max_duration {
+            command.args(["-t", &duration.to_string()]);
+        }
+        command.args(["-an"]);
+        command.args([
+            "-vf",
+            &format!("select=gt(scene\\,{SCENE_CHANGE_THRESHOLD}),showinfo"),
+        ]);
+        command.args(["-f", "null"]);
+        command.output("-");
+
+        let mut child = command.spawn().map_err(WatermarkError::Io)?;
+        let events = child.iter().map_err(|e| {
+            WatermarkError::ProcessingError(Message::FfmpegOutputUnreadable.format(&[&e]))
+        })?;
+        let job = ffmpeg::watch(child);
+
+        let mut fps = DEFAULT_FPS;
+        let mut cuts = Vec::new();
+        for event in events {
+            match event {
+                FfmpegEvent::ParsedInputStream(stream) => {
+                    if let Some(video) = stream.video_data() {
+                        if video.fps > 0.0 {
+                            fps = video.fps as f64;
+                        }
+                    }
+                }
+                FfmpegEvent::Log(_, line) => {
+                    if let Some(pts) = Self::parse_pts_time(&line) {
+                        cuts.push((pts * fps).round() as usize);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let status = job.wait()?;
+        if !status.success() {
+            return Err(WatermarkError::ProcessingError(
+                Message::SceneDetectionError.text().to_string(),
+            ));
+        }
+        Ok(cuts)
+    }
+
+    /// # Extract all frames watermark
+    fn extract_all_frames_watermark<P: AsRef<Path>>(
         input_path: P,
         temp_dir: &Path,
-        algorithm: &dyn WatermarkAlgorithm,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
         watermark_length: usize,
-    ) -> Result<Vec<(Vec<u8>, f64)>> {
-        let mut results = Vec::new();
-        use crate::media::ImageWatermarker;
+        max_duration: Option<f64>,
+        options: VideoExtractOptions,
+    ) -> Result<FrameResults> {
+        let mut results = FrameResults::default();
 
         let frames_dir = temp_dir.join("all_frames");
         std::fs::create_dir_all(&frames_dir)?;
 
         // Extract all frames
-        Self::extract_frames(input_path.as_ref(), &frames_dir)?;
+        Self::extract_frames(input_path.as_ref(), &frames_dir, max_duration)?;
 
         // Get all frame files
         let frame_files = Self::get_frame_files(&frames_dir)?;
 
         if frame_files.is_empty() {
             return Err(WatermarkError::ProcessingError(
-                "未能提取任何视频帧".to_string(),
+                Message::NoFramesExtracted.text().to_string(),
             ));
         }
 
+        // 隔帧嵌入时只处理带水印的帧，与嵌入端一致包括最后一帧
+        let embedded = |i: usize| {
+            i + 1 == frame_files.len()
+                || options
+                    .embed_every
+                    .is_none_or(|every| i.is_multiple_of(every))
+        };
+
         // Process each frame
         for (i, frame_file) in frame_files.iter().enumerate() {
+            if !embedded(i) {
+                continue;
+            }
             // Ensure frame file exists and is not empty
             if !frame_file.exists() {
                 continue;
@@ -381,16 +1077,13 @@ impl VideoWatermarker {
             };
 
             // Extract watermark
-            match ImageWatermarker::extract_watermark(
+            match Self::extract_frame_bits(
                 frame_file,
                 algorithm,
                 watermark_length,
+                options.frame_index,
             ) {
-                Ok(watermark_text) => {
-                    // Convert string to bit array for voting
-                    let bits = Self::string_to_bits(&watermark_text, watermark_length);
-                    results.push((bits, quality));
-                }
+                Ok((bits, index, _)) => results.push(i, bits, quality, index),
                 Err(_) => {
                     // Extraction failed, skip this frame
                     continue;
@@ -399,21 +1092,88 @@ impl VideoWatermarker {
 
             // Print progress every 100 frames to avoid too much output
             if (i + 1) % 100 == 0 {
-                eprintln!("🎬 已处理 {} 帧", i + 1);
+                eprintln!("{}", Message::FramesProcessedSoFar.format(&[&(i + 1)]));
             }
         }
 
-        if results.is_empty() {
+        if results.votes.is_empty() {
             return Err(WatermarkError::ProcessingError(
-                "所有帧的水印提取都失败".to_string(),
+                Message::AllFramesFailed.text().to_string(),
             ));
         }
 
-        eprintln!("🎬 总共处理了 {} 帧，成功提取水印的帧数: {}", frame_files.len(), results.len());
+        eprintln!(
+            "{}",
+            Message::FramesProcessedSummary.format(&[&frame_files.len(), &results.votes.len()])
+        );
 
         Ok(results)
     }
 
+    /// 从单帧读取参与投票的水印比特和该帧的平均置信度，启用帧序号时同时解析该帧的原始序号
+    ///
+    /// 帧序号未通过校验时返回 `None`，水印文本仍然参与投票。
+    fn extract_frame_bits(
+        frame_path: &Path,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        watermark_length: usize,
+        frame_index: bool,
+    ) -> Result<(Vec<u8>, Option<u32>, f64)> {
+        use crate::media::ImageWatermarker;
+
+        if !frame_index {
+            let result =
+                ImageWatermarker::extract_watermark(frame_path, algorithm, watermark_length)?;
+            let confidence = result.confidence;
+            let text = result.into_text();
+            return Ok((
+                Self::string_to_bits(&text, watermark_length),
+                None,
+                confidence,
+            ));
+        }
+
+        let (bytes, confidence) = ImageWatermarker::extract_bytes_with_confidence(
+            frame_path,
+            algorithm,
+            watermark_length + FRAME_INDEX_LEN,
+        )?;
+        let (text, trailer) = bytes.split_at(watermark_length);
+        let text = std::str::from_utf8(text).map_err(|_| WatermarkError::InvalidWatermark)?;
+        Ok((
+            Self::string_to_bits(text, watermark_length),
+            Self::parse_frame_index(text.as_bytes(), trailer),
+            confidence,
+        ))
+    }
+
+    /// 第 `index` 帧实际嵌入的载荷，启用帧序号时在水印文本后追加帧序号和校验
+    fn frame_payload(watermark_text: &str, index: usize, frame_index: bool) -> Vec<u8> {
+        let text = watermark_text.as_bytes();
+        if !frame_index {
+            return text.to_vec();
+        }
+        let index = (index as u32).to_be_bytes();
+        let check = Self::frame_index_check(text, &index);
+        [text, &index, &check].concat()
+    }
+
+    /// 解析载荷末尾的帧序号，校验不通过时返回 `None`
+    fn parse_frame_index(text: &[u8], trailer: &[u8]) -> Option<u32> {
+        let index: [u8; 4] = trailer.get(..4)?.try_into().ok()?;
+        (trailer.get(4..FRAME_INDEX_LEN)? == Self::frame_index_check(text, &index))
+            .then(|| u32::from_be_bytes(index))
+    }
+
+    /// 帧序号的2字节校验，同时覆盖水印文本，其他载荷的帧无法通过
+    fn frame_index_check(text: &[u8], index: &[u8; 4]) -> [u8; 2] {
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(text);
+        hasher.update(index);
+        let crc = hasher.finalize().to_be_bytes();
+        [crc[2], crc[3]]
+    }
+
     /// 生成采样帧索引
     fn generate_sample_frame_indices(
         sample_count: usize,
@@ -464,8 +1224,8 @@ impl VideoWatermarker {
     /// 处理单帧
     fn process_frame<P: AsRef<Path>>(
         frame_path: P,
-        watermark_text: &str,
-        algorithm: &dyn WatermarkAlgorithm,
+        payload: &[u8],
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
         strength: f64,
     ) -> Result<()> {
         use crate::media::ImageWatermarker;
@@ -474,10 +1234,10 @@ impl VideoWatermarker {
         let temp_output = frame_path.as_ref().with_extension("tmp.png");
 
         // 使用静默模式的图片水印算法处理帧（不打印日志）
-        ImageWatermarker::embed_watermark(
+        ImageWatermarker::embed_bytes(
             frame_path.as_ref(),
             &temp_output,
-            watermark_text,
+            payload,
             algorithm,
             strength,
         )?;
@@ -494,9 +1254,11 @@ impl VideoWatermarker {
 
         // 读取图像
         let img = ImageReader::open(frame_path.as_ref())
-            .map_err(|e| WatermarkError::ProcessingError(format!("无法读取图像: {}", e)))?
+            .map_err(|e| WatermarkError::ProcessingError(Message::ImageUnreadable.format(&[&e])))?
             .decode()
-            .map_err(|e| WatermarkError::ProcessingError(format!("无法解码图像: {}", e)))?;
+            .map_err(|e| {
+                WatermarkError::ProcessingError(Message::ImageUndecodable.format(&[&e]))
+            })?;
 
         let gray = img.to_luma8();
         let (width, height) = gray.dimensions();
@@ -537,6 +1299,19 @@ impl VideoWatermarker {
         Ok(quality)
     }
 
+    /// 按实际参与投票的帧数修正置信度
+    ///
+    /// 投票置信度的随机水平为0.5，帧数越少越向0.5收缩：
+    /// `0.5 + (confidence - 0.5) * n / (n + CONFIDENCE_PRIOR_FRAMES)`。
+    /// 例如2帧0.7修正后为0.6，而15帧0.65修正后约为0.63。
+    pub fn sample_adjusted_confidence(confidence: f64, frames_used: usize) -> f64 {
+        if frames_used == 0 {
+            return 0.0;
+        }
+        let n = frames_used as f64;
+        0.5 + (confidence - 0.5) * n / (n + CONFIDENCE_PRIOR_FRAMES)
+    }
+
     /// 投票机制确定最终水印
     fn vote_watermark_bits(results: Vec<(Vec<u8>, f64)>, expected_length: usize) -> (String, f64) {
         if results.is_empty() {
@@ -640,182 +1415,669 @@ impl VideoWatermarker {
         String::from_utf8_lossy(&bytes).to_string()
     }
 
-    /// 重新组合视频
+    /// 按源视频的帧率把帧序列重新组合为不含音频的视频
     fn reassemble_video(
         frames_dir: &Path,
-        audio_path: &Path,
         output_path: &Path,
         video_info: &VideoInfo,
-        lossless: bool,
+        encode: VideoEncodeOptions,
     ) -> Result<()> {
         let frame_pattern = frames_dir.join("frame_%06d.png");
 
         let mut command = FfmpegCommand::new();
-        command.args(["-framerate", "30"]);
+        command.args(["-framerate", &video_info.fps.to_string()]);
         command.input(frame_pattern.to_str().unwrap());
-
-        // 如果有音频，添加音频输入
-        if video_info.has_audio && audio_path.exists() {
-            command.input(audio_path.to_str().unwrap());
-            if lossless {
-                command.args(["-c:v", "libx264", "-crf", "0", "-c:a", "copy"]);
-                command.args(["-preset", "ultrafast"]); // 无损压缩时，使用ultrafast可以极大加快速度
-            } else {
-                command.args(["-c:v", "libx264", "-crf", "23", "-c:a", "copy"]);
-                command.args(["-preset", "medium"]); // 有损压缩时，使用medium预设平衡质量和速度
-            }
-        } else if lossless {
-            command.args(["-c:v", "libx264", "-crf", "0"]);
-            command.args(["-preset", "ultrafast"]); // 无损压缩时，使用ultrafast可以极大加快速度
-        } else {
-            command.args(["-c:v", "libx264", "-crf", "23"]);
-            command.args(["-preset", "medium"]); // 有损压缩时，使用medium预设平衡质量和速度
-        }
+        encode.apply(&mut command);
 
         command.args(["-pix_fmt", "yuv420p"]);
         command.args(["-y"]);
         command.output(output_path.to_str().unwrap());
 
-        let mut child = command.spawn().map_err(WatermarkError::Io)?;
-        let status = child.wait().map_err(WatermarkError::Io)?;
+        let status = ffmpeg::run(&mut command)?;
 
         if !status.success() {
-            return Err(WatermarkError::ProcessingError("视频重组失败".to_string()));
+            return Err(WatermarkError::ProcessingError(
+                Message::ReassembleFailed.text().to_string(),
+            ));
         }
 
         Ok(())
     }
 
     /// 仅对视频帧嵌入水印（原有逻辑）
+    ///
+    /// 默认单次解码，帧数据经管道在内存中嵌入水印后直接送入编码器，原始音轨按原样复用；
+    /// 设置 `disk_frames` 或 `resume` 时改用落盘PNG帧的方式。
     fn embed_video_only<P: AsRef<Path>>(
         input_path: P,
         output_path: P,
         watermark_text: &str,
-        algorithm: &dyn WatermarkAlgorithm,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
         strength: f64,
-        lossless: bool,
+        encode: VideoEncodeOptions,
         video_info: &VideoInfo,
     ) -> Result<usize> {
         let input_path = input_path.as_ref();
         let output_path = output_path.as_ref();
 
-        // 创建总进度条
-        let progress = ProgressBar::new(5);
-        progress.set_style(
-            ProgressStyle::default_bar()
-                .template(
-                    "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}",
-                )
-                .unwrap()
-                .progress_chars("█▉▊▋▌▍▎▏  "),
-        );
+        if encode.disk_frames || encode.resume {
+            return Self::embed_video_frames_on_disk(
+                input_path,
+                output_path,
+                watermark_text,
+                algorithm,
+                strength,
+                encode,
+                video_info,
+            );
+        }
 
-        // 创建临时目录用于处理视频帧
-        progress.set_message("🗂️  创建临时目录".to_string());
-        let temp_dir = std::env::temp_dir().join(format!("video_watermark_{}", std::process::id()));
-        std::fs::create_dir_all(&temp_dir)?;
-        progress.inc(1);
+        let temp_dir = TempDir::new("video_watermark")?;
 
-        // 提取音频轨道（如果存在）
-        let audio_path = temp_dir.join("audio.wav");
-        if video_info.has_audio {
-            progress.set_message("🎵  提取音频轨道".to_string());
-            Self::extract_audio(input_path, &audio_path)?;
+        let encoded_path = if video_info.has_audio {
+            temp_dir.join("video_no_audio.mp4")
+        } else {
+            output_path.to_path_buf()
+        };
+        let result = Self::stream_watermark_frames(
+            input_path,
+            None,
+            &encoded_path,
+            watermark_text,
+            algorithm,
+            strength,
+            encode,
+        )
+        .and_then(|frame_count| {
+            if video_info.has_audio {
+                Self::mux_original_audio(&encoded_path, input_path, output_path)?;
+            }
+            Ok(frame_count)
+        });
+
+        drop(temp_dir);
+        if result.is_ok() {
+            eprintln!(
+                "{} {}",
+                "🎉".green(),
+                Message::VideoEmbedDone.text().green().bold()
+            );
         }
-        progress.inc(1);
+        result
+    }
 
-        // 提取视频帧
-        progress.set_message("🎬  提取视频帧".to_string());
-        let frames_dir = temp_dir.join("frames");
-        std::fs::create_dir_all(&frames_dir)?;
-        Self::extract_frames(input_path, &frames_dir)?;
-        progress.inc(1);
+    /// 把所有帧提取为PNG文件，逐个嵌入水印后再按源帧率重新组合为视频
+    ///
+    /// 设置 `resume` 时帧保存在断点目录中，中断后再次运行只处理尚未完成的帧。
+    fn embed_video_frames_on_disk(
+        input_path: &Path,
+        output_path: &Path,
+        watermark_text: &str,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        strength: f64,
+        encode: VideoEncodeOptions,
+        video_info: &VideoInfo,
+    ) -> Result<usize> {
+        let progress = encode.progress;
+        // 创建总进度条
+        progress.started(5);
+
+        // 创建临时目录用于处理视频帧；断点续传时使用固定的断点目录
+        progress.stage(Message::StageTempDir.text());
+        let mut resume = if encode.resume {
+            let params = serde_json::json!({
+                "input_sha256": crypto::file_sha256_hex(input_path)?,
+                "watermark_sha256": crypto::sha256_hex(watermark_text.as_bytes()),
+                "algorithm": algorithm.name(),
+                "strength": strength,
+                "algorithm_options": format!("{:?}", encode.algorithm_options),
+                "channel": format!("{:?}", encode.channel),
+                "frame_index": encode.frame_index,
+                "embed_every": encode.embed_every,
+            });
+            Some(ResumeState::open(output_path, params)?)
+        } else {
+            None
+        };
+        let temp_dir = match &resume {
+            Some(_) => None,
+            None => Some(TempDir::new("video_watermark")?),
+        };
+        let work_dir = match (&resume, &temp_dir) {
+            (Some(resume), _) => resume.dir().to_path_buf(),
+            (None, Some(temp_dir)) => temp_dir.to_path_buf(),
+            (None, None) => unreachable!("没有断点目录时总会创建临时目录"),
+        };
+        progress.step();
+
+        // 提取视频帧；上次已提取完时直接使用，只删除中断时残留的半成品帧
+        progress.stage(Message::StageExtractFrames.text());
+        let frames_dir = work_dir.join("frames");
+        match resume.as_ref().and_then(ResumeState::frames) {
+            Some(_) => {
+                for entry in std::fs::read_dir(&frames_dir)? {
+                    let path = entry?.path();
+                    if path.to_string_lossy().ends_with(".tmp.png") {
+                        std::fs::remove_file(path)?;
+                    }
+                }
+            }
+            None => {
+                std::fs::create_dir_all(&frames_dir)?;
+                Self::extract_frames(input_path, &frames_dir, None)?;
+            }
+        }
+        progress.step();
 
         // 处理每一帧，添加水印
-        progress.set_message("🎯  处理视频帧".to_string());
+        progress.stage(Message::StageProcessFrames.text());
         let frame_files = Self::get_frame_files(&frames_dir)?;
+        progress.emit(ProgressEvent::FramesExtracted(frame_files.len()));
+        if let Some(resume) = &mut resume {
+            match resume.frames() {
+                Some(frames) if frames != frame_files.len() => {
+                    return Err(WatermarkError::ProcessingError(
+                        Message::ResumeFrameMismatch.format(&[
+                            &format!("{:?}", resume.dir()),
+                            &frame_files.len(),
+                            &frames,
+                        ]),
+                    ));
+                }
+                Some(frames) => eprintln!(
+                    "{} {}",
+                    "♻️".blue(),
+                    Message::ResumingFrames.format(&[&resume.done_count(), &frames])
+                ),
+                None => resume.frames_extracted(frame_files.len())?,
+            }
+        }
 
-        // 创建帧处理进度条
-        let frame_progress = ProgressBar::new(frame_files.len() as u64);
-        frame_progress.set_style(
-            ProgressStyle::default_bar()
-                .template(
-                    "{spinner:.green} [{elapsed_precise}] [{bar:30.yellow/red}] {pos}/{len} 帧",
-                )
-                .unwrap()
-                .progress_chars("█▉▊▋▌▍▎▏  "),
-        );
-
-        for frame_file in &frame_files {
-            Self::process_frame(frame_file, watermark_text, algorithm, strength)?;
-            frame_progress.inc(1);
+        for (i, frame_file) in frame_files.iter().enumerate() {
+            let done = resume.as_ref().is_some_and(|resume| resume.is_done(i));
+            if !done && encode.embeds_frame(i, i + 1 == frame_files.len()) {
+                let payload = Self::frame_payload(watermark_text, i, encode.frame_index);
+                Self::process_frame(frame_file, &payload, algorithm, strength)?;
+                if let Some(resume) = &mut resume {
+                    resume.mark_done(i)?;
+                }
+            }
+            progress.emit(ProgressEvent::FrameProcessed {
+                index: i,
+                total: Some(frame_files.len()),
+            });
         }
-        frame_progress.finish_with_message(
-            format!("✅ 已处理 {} 帧", frame_files.len())
-                .green()
-                .to_string(),
-        );
-        progress.inc(1);
+        progress.step();
 
         // 重新组合视频
-        progress.set_message("🎞️  重新组合视频".to_string());
-        Self::reassemble_video(&frames_dir, &audio_path, output_path, video_info, lossless)?;
-        progress.inc(1);
+        progress.emit(ProgressEvent::Reassembling);
+        let encoded_path = if video_info.has_audio {
+            work_dir.join("video_no_audio.mp4")
+        } else {
+            output_path.to_path_buf()
+        };
+        Self::reassemble_video(&frames_dir, &encoded_path, video_info, encode)?;
+        progress.step();
+
+        // 原始音轨直接从源文件复制，保持编码和数据不变
+        if video_info.has_audio {
+            progress.stage(Message::StageCopyAudio.text());
+            Self::mux_original_audio(&encoded_path, input_path, output_path)?;
+        }
+        progress.step();
 
         // 完成并清理
-        progress.finish_with_message("🎉 视频水印嵌入完成!".green().bold().to_string());
+        progress.finished(Message::StageVideoEmbedDone.text());
 
         // 清理临时文件
-        std::fs::remove_dir_all(&temp_dir)?;
-        eprintln!("{} {}", "🧹".blue(), "临时文件已清理".blue());
+        drop(temp_dir);
+        if let Some(resume) = resume {
+            resume.finish();
+        }
+        eprintln!(
+            "{} {}",
+            "🧹".blue(),
+            Message::TempFilesCleaned.text().blue()
+        );
 
         Ok(frame_files.len())
     }
 
+    /// 仅对首个关键帧区间嵌入水印，其余码流直接复制
+    ///
+    /// 只在开头片段的首帧上运行一次水印算法，得到的像素差值由 FFmpeg 的 blend 滤镜
+    /// 叠加到从开头到 `KEYFRAME_REGION_SECS` 之后第一个关键帧为止的各帧上并重新编码，
+    /// 不逐帧导出PNG；之后的视频和全部音频按原样复制，画质损失仅限于开头一段。
+    /// 调用方已确认源视频和输出编码都是 H.264。
+    fn embed_keyframe_region<P: AsRef<Path>>(
+        input_path: P,
+        output_path: P,
+        watermark_text: &str,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        strength: f64,
+        encode: VideoEncodeOptions,
+        video_info: &VideoInfo,
+    ) -> Result<usize> {
+        let progress = encode.progress;
+        let input_path = input_path.as_ref();
+        let output_path = output_path.as_ref();
+
+        progress.started(5);
+
+        // 定位切分点
+        progress.stage(Message::StageLocateKeyframe.text());
+        let probe = Self::probe_keyframe(input_path, KEYFRAME_REGION_SECS)?;
+        let Some(split_secs) = probe.keyframe_secs else {
+            // 整段视频只有一个关键帧区间，只能全部重新编码
+            eprintln!(
+                "{} {}",
+                "⚠️".yellow(),
+                Message::KeyframeNotFound.format(&[&KEYFRAME_REGION_SECS])
+            );
+            progress.emit(ProgressEvent::Abandoned(
+                Message::ProcessAllFrames.text().to_string(),
+            ));
+            return Self::embed_video_only(
+                input_path,
+                output_path,
+                watermark_text,
+                algorithm,
+                strength,
+                encode,
+                video_info,
+            );
+        };
+        progress.step();
+
+        progress.stage(Message::StageTempDir.text());
+        let temp_dir = TempDir::new("video_keyframe")?;
+        progress.step();
+
+        // 在首帧上嵌入水印，记录嵌入前后的像素差值
+        progress.stage(Message::StageKeyframeDelta.text());
+        let first_frame = temp_dir.join("first.png");
+        let delta_path = temp_dir.join("delta.png");
+        Self::extract_single_frame(input_path, first_frame.as_path(), 0)?;
+        Self::write_watermark_delta(
+            &first_frame,
+            &delta_path,
+            watermark_text.as_bytes(),
+            algorithm,
+            strength,
+        )?;
+        progress.step();
+
+        // 叠加差值并重新编码开头片段，复制剩余片段；两段都写成 Annex B 码流的 MPEG-TS，
+        // 每段的 SPS/PPS 随各自的 IDR 帧写在码流里，拼接后解码器遇到新参数集即切换
+        progress.stage(Message::StageEncodeHead.text());
+        let head_path = temp_dir.join("head.ts");
+        let tail_path = temp_dir.join("tail.ts");
+        Self::encode_head_with_delta(
+            input_path,
+            &delta_path,
+            &head_path,
+            split_secs,
+            &probe,
+            encode,
+        )?;
+        Self::copy_tail_segment(input_path, &tail_path, split_secs)?;
+        progress.step();
+
+        progress.stage(Message::StageJoin.text());
+        let joined_path = temp_dir.join("joined.mp4");
+        Self::concat_segments(&temp_dir, &[&head_path, &tail_path], &joined_path)?;
+        if let Err(reason) = Self::check_join(input_path, &joined_path) {
+            // 拼接处无法正确解码时宁可全部重新编码，也不输出花屏的视频
+            eprintln!(
+                "{} {}",
+                "⚠️".yellow(),
+                Message::KeyframeJoinFailed.format(&[&reason])
+            );
+            progress.emit(ProgressEvent::Abandoned(
+                Message::ProcessAllFrames.text().to_string(),
+            ));
+            drop(temp_dir);
+            return Self::embed_video_only(
+                input_path,
+                output_path,
+                watermark_text,
+                algorithm,
+                strength,
+                encode,
+                video_info,
+            );
+        }
+        Self::mux_original_audio(&joined_path, input_path, output_path)?;
+        progress.step();
+
+        progress.finished(Message::StageVideoEmbedDone.text());
+
+        drop(temp_dir);
+        eprintln!(
+            "{} {}",
+            "🧹".blue(),
+            Message::TempFilesCleaned.text().blue()
+        );
+
+        Ok((split_secs * f64::from(probe.fps)).round() as usize)
+    }
+
+    /// 在首帧上嵌入 `payload`，把逐像素差值加128后写成PNG，供 blend 滤镜叠加到其余帧
+    ///
+    /// 开头片段位于同一个关键帧区间内，各帧画面与首帧相近，同一份差值在这些帧上
+    /// 产生的系数变化与首帧基本一致；差值超出 ±127 的像素被截断。
+    fn write_watermark_delta(
+        first_frame: &Path,
+        delta_path: &Path,
+        payload: &[u8],
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        strength: f64,
+    ) -> Result<()> {
+        use crate::media::ImageWatermarker;
+        use image::DynamicImage;
+
+        let original = image::open(first_frame)?.to_rgb8();
+        let marked = ImageWatermarker::embed_into_image(
+            &DynamicImage::ImageRgb8(original.clone()),
+            payload,
+            algorithm,
+            strength,
+            ImageChannel::All,
+        )?
+        .to_rgb8();
+        let delta = image::RgbImage::from_fn(original.width(), original.height(), |x, y| {
+            let (before, after) = (original.get_pixel(x, y), marked.get_pixel(x, y));
+            image::Rgb(
+                [0, 1, 2].map(|c| (after[c] as i16 - before[c] as i16 + 128).clamp(0, 255) as u8),
+            )
+        });
+        delta.save(delta_path)?;
+        Ok(())
+    }
+
+    /// 探测视频的档次、像素格式、帧率以及 `min_secs` 之后的第一个关键帧时间
+    fn probe_keyframe(input_path: &Path, min_secs: f64) -> Result<KeyframeProbe> {
+        let mut child = FfmpegCommand::new()
+            .args(["-skip_frame", "nokey"]) // 只解码关键帧
+            .input(input_path.to_str().unwrap())
+            .args(["-an"])
+            .args(["-vf", "showinfo"])
+            .args(["-f", "null"])
+            .output("-")
+            .spawn()
+            .map_err(WatermarkError::Io)?;
+
+        let mut probe = KeyframeProbe {
+            profile: None,
+            pix_fmt: None,
+            fps: 30.0,
+            keyframe_secs: None,
+        };
+
+        let events = child.iter().map_err(|e| {
+            WatermarkError::ProcessingError(Message::FfmpegOutputUnreadable.format(&[&e]))
+        })?;
+        let job = ffmpeg::watch(child);
+        for event in events {
+            match event {
+                FfmpegEvent::ParsedInputStream(stream) => {
+                    if let Some(video) = stream.video_data() {
+                        probe.profile = Self::parse_h264_profile(&stream.raw_log_message);
+                        probe.pix_fmt = Some(video.pix_fmt.clone());
+                        if video.fps > 0.0 {
+                            probe.fps = video.fps;
+                        }
+                    }
+                }
+                FfmpegEvent::Log(_, line) => {
+                    if let Some(pts) = Self::parse_pts_time(&line) {
+                        if pts >= min_secs {
+                            probe.keyframe_secs = Some(pts);
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // 找到切分点后无需继续解码
+        job.kill()?;
+
+        Ok(probe)
+    }
+
+    /// 从 showinfo 日志中解析 `pts_time`
+    fn parse_pts_time(line: &str) -> Option<f64> {
+        if !line.contains("showinfo") {
+            return None;
+        }
+        let rest = &line[line.find("pts_time:")? + "pts_time:".len()..];
+        rest.split_whitespace().next()?.parse().ok()
+    }
+
+    /// 从流信息行（如 `Video: h264 (High) (avc1 / 0x31637661), ...`）中解析 H.264 档次，
+    /// 返回 libx264 `-profile:v` 的取值，无法识别时返回 `None`
+    fn parse_h264_profile(line: &str) -> Option<&'static str> {
+        let rest = &line[line.find("h264 (")? + "h264 (".len()..];
+        match &rest[..rest.find(')')?] {
+            "Baseline" | "Constrained Baseline" => Some("baseline"),
+            "Main" => Some("main"),
+            "High" => Some("high"),
+            "High 10" => Some("high10"),
+            "High 4:2:2" => Some("high422"),
+            "High 4:4:4 Predictive" => Some("high444"),
+            _ => None,
+        }
+    }
+
+    /// 用 blend 滤镜把差值图叠加到开头 `duration` 秒的各帧上，编码为 H.264 片段
+    ///
+    /// 像素格式和档次与源视频一致；设置了 `embed_every` 时只叠加到序号为其整数倍的帧。
+    /// 无损模式需要 High 4:4:4 档次，此时不指定档次，由拼接后的解码校验兜底。
+    fn encode_head_with_delta(
+        input_path: &Path,
+        delta_path: &Path,
+        output_path: &Path,
+        duration: f64,
+        probe: &KeyframeProbe,
+        encode: VideoEncodeOptions,
+    ) -> Result<()> {
+        let pix_fmt = probe.pix_fmt.as_deref().unwrap_or("yuv420p");
+        let enable = encode
+            .embed_every
+            .map(|every| format!(":enable='not(mod(n,{every}))'"))
+            .unwrap_or_default();
+        let filter = format!(
+            "[0:v]format=gbrp[frame];[1:v]format=gbrp[delta];\
+             [frame][delta]blend=all_expr='clip(A+B-128,0,255)':shortest=1{enable},\
+             format={pix_fmt}[marked]"
+        );
+
+        let mut command = FfmpegCommand::new();
+        command.args(["-t", &duration.to_string()]);
+        command.input(input_path.to_str().unwrap());
+        command.args(["-loop", "1"]);
+        command.input(delta_path.to_str().unwrap());
+        command.args(["-filter_complex", &filter, "-map", "[marked]", "-an"]);
+        command.args(["-vsync", "passthrough"]);
+        encode.apply(&mut command);
+        command.args(["-pix_fmt", pix_fmt]);
+        if let Some(profile) = probe.profile.filter(|_| !encode.lossless) {
+            command.args(["-profile:v", profile]);
+        }
+        command.args(["-y"]);
+        command.output(output_path.to_str().unwrap());
+
+        let status = ffmpeg::run(&mut command)?;
+
+        if !status.success() {
+            return Err(WatermarkError::ProcessingError(
+                Message::HeadEncodeFailed.text().to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// 从关键帧 `start_secs` 开始直接复制剩余视频流
+    fn copy_tail_segment(input_path: &Path, output_path: &Path, start_secs: f64) -> Result<()> {
+        let status = ffmpeg::run(
+            FfmpegCommand::new()
+                .args(["-ss", &start_secs.to_string()]) // 输入端定位，切在关键帧上
+                .input(input_path.to_str().unwrap())
+                .args(["-an"])
+                .args(["-c:v", "copy"])
+                .args(["-bsf:v", "h264_mp4toannexb"]) // 参数集随 IDR 帧写入码流
+                .args(["-avoid_negative_ts", "make_zero"])
+                .args(["-y"])
+                .output(output_path.to_str().unwrap()),
+        )?;
+
+        if !status.success() {
+            return Err(WatermarkError::ProcessingError(
+                Message::TailCopyFailed.text().to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// 使用 concat 分离器无损拼接视频片段
+    fn concat_segments(temp_dir: &Path, segments: &[&Path], output_path: &Path) -> Result<()> {
+        let list_path = temp_dir.join("segments.txt");
+        let list = segments
+            .iter()
+            .map(|segment| format!("file '{}'\n", segment.display()))
+            .collect::<String>();
+        std::fs::write(&list_path, list)?;
+
+        let status = ffmpeg::run(
+            FfmpegCommand::new()
+                .args(["-f", "concat", "-safe", "0"])
+                .input(list_path.to_str().unwrap())
+                .args(["-c", "copy"])
+                .args(["-y"])
+                .output(output_path.to_str().unwrap()),
+        )?;
+
+        if !status.success() {
+            return Err(WatermarkError::ProcessingError(
+                Message::SegmentJoinFailed.text().to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// 检查拼接结果能否完整解码，且帧数与源视频一致，不通过时返回原因
+    fn check_join(input_path: &Path, joined_path: &Path) -> std::result::Result<(), String> {
+        let expected = Self::count_frames(input_path, false).map_err(|e| e.to_string())?;
+        let decoded = Self::count_frames(joined_path, true).map_err(|e| e.to_string())?;
+        match (expected, decoded) {
+            (_, None) => Err(Message::DecodeErrors.text().to_string()),
+            (Some(expected), Some(decoded)) if expected != decoded => {
+                Err(Message::FrameCountMismatch.format(&[&decoded, &expected]))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 统计第一个视频流的帧数
+    ///
+    /// `decode` 为 `true` 时逐帧解码，出现解码错误时返回 `Ok(None)`；否则只复制数据包计数，
+    /// 源文件的帧数因此不需要完整解码。进度中读不到帧数时同样返回 `Ok(None)`。
+    fn count_frames(input_path: &Path, decode: bool) -> Result<Option<u32>> {
+        let mut command = FfmpegCommand::new();
+        command.input(input_path.to_str().unwrap());
+        command.args(["-map", "0:v:0"]);
+        if !decode {
+            command.args(["-c", "copy"]);
+        }
+        let mut child = command
+            .args(["-f", "null"])
+            .output("-")
+            .spawn()
+            .map_err(WatermarkError::Io)?;
+
+        let events = child.iter().map_err(|e| {
+            WatermarkError::ProcessingError(Message::FfmpegOutputUnreadable.format(&[&e]))
+        })?;
+        let job = ffmpeg::watch(child);
+        let mut frames = None;
+        let mut errors = false;
+        for event in events {
+            match event {
+                FfmpegEvent::Progress(progress) => frames = Some(progress.frame),
+                FfmpegEvent::Log(LogLevel::Error | LogLevel::Fatal, _) => errors = true,
+                _ => {}
+            }
+        }
+
+        let status = job.wait()?;
+        Ok(frames.filter(|_| status.success() && !errors))
+    }
+
+    /// 将原始文件中的音频（如有）原样复用到新视频
+    fn mux_original_audio(
+        video_path: &Path,
+        original_path: &Path,
+        output_path: &Path,
+    ) -> Result<()> {
+        let status = ffmpeg::run(
+            FfmpegCommand::new()
+                .input(video_path.to_str().unwrap())
+                .input(original_path.to_str().unwrap())
+                .args(["-map", "0:v:0", "-map", "1:a?"])
+                .args(["-c", "copy"])
+                .args(["-y"])
+                .output(output_path.to_str().unwrap()),
+        )?;
+
+        if !status.success() {
+            return Err(WatermarkError::ProcessingError(
+                Message::MuxFailed.text().to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
     /// # Embed watermark only to audio
     fn embed_audio_only<P: AsRef<Path>>(
         input_path: P,
         output_path: P,
         watermark_text: &str,
-        algorithm: &dyn WatermarkAlgorithm,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
         strength: f64,
         video_info: &VideoInfo,
+        progress: Progress,
     ) -> Result<usize> {
         let input_path = input_path.as_ref();
         let output_path = output_path.as_ref();
 
         if !video_info.has_audio {
             return Err(WatermarkError::ProcessingError(
-                "视频文件不包含音频轨道，无法嵌入音频水印".to_string(),
+                Message::NoAudioTrackForEmbed.text().to_string(),
             ));
         }
 
         // 创建总进度条
-        let progress = ProgressBar::new(5);
-        progress.set_style(
-            ProgressStyle::default_bar()
-                .template(
-                    "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}",
-                )
-                .unwrap()
-                .progress_chars("█▉▊▋▌▍▎▏  "),
-        );
+        progress.started(5);
 
         // 创建临时目录
-        progress.set_message("🗂️  创建临时目录".to_string());
-        let temp_dir =
-            std::env::temp_dir().join(format!("video_audio_watermark_{}", std::process::id()));
-        std::fs::create_dir_all(&temp_dir)?;
-        progress.inc(1);
+        progress.stage(Message::StageTempDir.text());
+        let temp_dir = TempDir::new("video_audio_watermark")?;
+        progress.step();
 
         // 提取音频轨道
-        progress.set_message("🎵  提取音频轨道".to_string());
+        progress.stage(Message::StageExtractAudio.text());
         let audio_path = temp_dir.join("original_audio.wav");
         Self::extract_audio_as_wav(input_path, &audio_path)?;
-        progress.inc(1);
+        progress.step();
 
         // 对音频嵌入水印
-        progress.set_message("🎯  处理音频水印".to_string());
+        progress.stage(Message::StageProcessAudio.text());
         let watermarked_audio_path = temp_dir.join("watermarked_audio.wav");
 
         use crate::media::AudioWatermarker;
@@ -826,70 +2088,85 @@ impl VideoWatermarker {
             algorithm,
             strength,
         )?;
-        progress.inc(1);
+        progress.step();
 
         // 提取视频流（无音频）
-        progress.set_message("🎬  提取视频流".to_string());
+        progress.stage(Message::StageExtractVideoStream.text());
         let video_no_audio_path = temp_dir.join("video_no_audio.mp4");
         Self::extract_video_stream(input_path, &video_no_audio_path)?;
-        progress.inc(1);
+        progress.step();
 
         // 合并处理后的音频和原视频
-        progress.set_message("🎞️  合并音视频".to_string());
+        progress.stage(Message::StageMux.text());
         Self::merge_audio_video(
             &video_no_audio_path,
             &watermarked_audio_path,
             &output_path.to_path_buf(),
         )?;
-        progress.inc(1);
+        progress.step();
 
         // 完成并清理
-        progress.finish_with_message("🎉 音频水印嵌入完成!".green().bold().to_string());
+        progress.finished(Message::StageAudioEmbedDone.text());
 
         // 清理临时文件
-        std::fs::remove_dir_all(&temp_dir)?;
-        eprintln!("{} {}", "🧹".blue(), "临时文件已清理".blue());
+        drop(temp_dir);
+        eprintln!(
+            "{} {}",
+            "🧹".blue(),
+            Message::TempFilesCleaned.text().blue()
+        );
 
         Ok(1) // 音频作为单个流处理，返回1
     }
 
     /// 同时对视频帧和音频嵌入水印
+    ///
+    /// 源文件只完整解码一次：同一个 FFmpeg 进程把音轨写成WAV、把视频帧以 rgb24
+    /// 原始数据输出到管道，逐帧嵌入水印后直接写入编码进程的标准输入，
+    /// 不再落盘PNG帧。音频水印完成后仅以流复制方式与编码结果合并。
     fn embed_both<P: AsRef<Path>>(
         input_path: P,
         output_path: P,
         watermark_text: &str,
-        algorithm: &dyn WatermarkAlgorithm,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
         strength: f64,
-        lossless: bool,
+        encode: VideoEncodeOptions,
         video_info: &VideoInfo,
     ) -> Result<usize> {
+        let progress = encode.progress;
         let input_path = input_path.as_ref();
         let output_path = output_path.as_ref();
 
         // 创建总进度条
-        let progress = ProgressBar::new(7);
-        progress.set_style(
-            ProgressStyle::default_bar()
-                .template(
-                    "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}",
-                )
-                .unwrap()
-                .progress_chars("█▉▊▋▌▍▎▏  "),
-        );
+        progress.started(4);
 
         // 创建临时目录
-        progress.set_message("🗂️  创建临时目录".to_string());
-        let temp_dir =
-            std::env::temp_dir().join(format!("video_both_watermark_{}", std::process::id()));
-        std::fs::create_dir_all(&temp_dir)?;
-        progress.inc(1);
+        progress.stage(Message::StageTempDir.text());
+        let temp_dir = TempDir::new("video_both_watermark")?;
+        progress.step();
 
-        // 处理音频水印（如果有音频）
-        let watermarked_audio_path = if video_info.has_audio {
-            progress.set_message("🎵  提取并处理音频水印".to_string());
-            let audio_path = temp_dir.join("original_audio.wav");
-            Self::extract_audio_as_wav(input_path, &audio_path)?;
+        // 单次解码：音频写入WAV，视频帧经管道逐帧嵌入水印后送入编码器
+        progress.stage(Message::StageSinglePass.text());
+        let audio_path = temp_dir.join("original_audio.wav");
+        let encoded_path = if video_info.has_audio {
+            temp_dir.join("video_no_audio.mp4")
+        } else {
+            output_path.to_path_buf()
+        };
+        let frame_count = Self::stream_watermark_frames(
+            input_path,
+            video_info.has_audio.then_some(audio_path.as_path()),
+            &encoded_path,
+            watermark_text,
+            algorithm,
+            strength,
+            encode,
+        )?;
+        progress.step();
 
+        // 处理音频水印（如果有音频）
+        if video_info.has_audio {
+            progress.stage(Message::StageAudioWatermark.text());
             let watermarked_audio_path = temp_dir.join("watermarked_audio.wav");
             use crate::media::AudioWatermarker;
             AudioWatermarker::embed_watermark(
@@ -899,103 +2176,249 @@ impl VideoWatermarker {
                 algorithm,
                 strength,
             )?;
-            Some(watermarked_audio_path)
+            progress.step();
+
+            // 视频流直接复制，不再重新解码
+            progress.stage(Message::StageMux.text());
+            Self::merge_audio_video(
+                &encoded_path,
+                &watermarked_audio_path,
+                &output_path.to_path_buf(),
+            )?;
         } else {
-            None
-        };
-        progress.inc(1);
+            progress.step();
+        }
+        progress.step();
 
-        // 提取视频帧
-        progress.set_message("🎬  提取视频帧".to_string());
-        let frames_dir = temp_dir.join("frames");
-        std::fs::create_dir_all(&frames_dir)?;
-        Self::extract_frames(input_path, &frames_dir)?;
-        progress.inc(1);
+        // 完成并清理
+        progress.finished(Message::StageBothEmbedDone.text());
 
-        // 处理每一帧，添加水印
-        progress.set_message("🎯  处理视频帧水印".to_string());
-        let frame_files = Self::get_frame_files(&frames_dir)?;
+        // 清理临时文件
+        drop(temp_dir);
+        eprintln!(
+            "{} {}",
+            "🧹".blue(),
+            Message::TempFilesCleaned.text().blue()
+        );
+
+        Ok(frame_count)
+    }
+
+    /// 读取一次源文件，逐帧嵌入水印并编码到 `encoded_path`，返回处理的帧数
+    ///
+    /// 给定 `audio_path` 时，同一解码进程会顺带把第一条音轨导出为 16 位 WAV，采样率和声道数不变。
+    /// 每帧延后一帧写出，解码结束时才能确定哪一帧是最后一帧。
+    fn stream_watermark_frames(
+        input_path: &Path,
+        audio_path: Option<&Path>,
+        encoded_path: &Path,
+        watermark_text: &str,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        strength: f64,
+        encode: VideoEncodeOptions,
+    ) -> Result<usize> {
+        use image::RgbImage;
+
+        let mut decoder_command = FfmpegCommand::new();
+        decoder_command.args(["-y"]);
+        decoder_command.input(input_path.to_str().unwrap());
+        if let Some(audio_path) = audio_path {
+            decoder_command.args(["-map", "0:a:0", "-vn"]);
+            decoder_command.args(CANONICAL_WAV_ARGS);
+            decoder_command.output(audio_path.to_str().unwrap());
+        }
+        decoder_command.args(["-map", "0:v:0"]);
+        decoder_command.rawvideo();
+
+        let mut decoder = decoder_command.spawn().map_err(WatermarkError::Io)?;
+        let events = decoder.iter().map_err(|e| {
+            WatermarkError::ProcessingError(Message::FfmpegOutputUnreadable.format(&[&e]))
+        })?;
+        let decoder = ffmpeg::watch(decoder);
+
+        let mut fps = 30.0_f32;
+        let mut encoder = None;
+        let mut pending = None;
+        let mut frame_count = 0;
+
+        for event in events {
+            match event {
+                FfmpegEvent::ParsedInputStream(stream) => {
+                    if let Some(video) = stream.video_data() {
+                        if video.fps > 0.0 {
+                            fps = video.fps;
+                        }
+                    }
+                }
+                FfmpegEvent::OutputFrame(frame) => {
+                    // 首帧到达时才知道尺寸，此时启动编码进程
+                    if encoder.is_none() {
+                        encoder = Some(Self::spawn_rawvideo_encoder(
+                            encoded_path,
+                            frame.width,
+                            frame.height,
+                            fps,
+                            encode,
+                        )?);
+                    }
+                    let (_, stdin) = encoder.as_mut().unwrap();
+
+                    let rgb = RgbImage::from_raw(frame.width, frame.height, frame.data)
+                        .ok_or_else(|| {
+                            WatermarkError::ProcessingError(
+                                Message::FrameSizeMismatch.text().to_string(),
+                            )
+                        })?;
+                    let Some(previous) = pending.replace(rgb) else {
+                        continue;
+                    };
+                    let payload = encode.embeds_frame(frame_count, false).then(|| {
+                        Self::frame_payload(watermark_text, frame_count, encode.frame_index)
+                    });
+                    if let Err(e) =
+                        Self::write_stream_frame(stdin, previous, payload, algorithm, strength)
+                    {
+                        // 编码进程超时或被中止时报告其原因，而不是写入失败
+                        let _ = decoder.kill();
+                        let (encoder, stdin) = encoder.take().unwrap();
+                        drop(stdin);
+                        encoder.wait()?;
+                        return Err(e);
+                    }
+
+                    encode.progress.emit(ProgressEvent::FrameProcessed {
+                        index: frame_count,
+                        total: None,
+                    });
+                    frame_count += 1;
+                }
+                _ => {}
+            }
+        }
+
+        let decode_status = decoder.wait()?;
+
+        let (Some((encoder, mut stdin)), Some(last)) = (encoder, pending) else {
+            return Err(WatermarkError::ProcessingError(
+                Message::NoFramesDecoded.text().to_string(),
+            ));
+        };
+        let payload = Self::frame_payload(watermark_text, frame_count, encode.frame_index);
+        if let Err(e) =
+            Self::write_stream_frame(&mut stdin, last, Some(payload), algorithm, strength)
+        {
+            drop(stdin);
+            encoder.wait()?;
+            return Err(e);
+        }
+        encode.progress.emit(ProgressEvent::FrameProcessed {
+            index: frame_count,
+            total: None,
+        });
+        frame_count += 1;
+        // 关闭标准输入通知编码器结束
+        drop(stdin);
+        let encode_status = encoder.wait()?;
+
+        if !decode_status.success() {
+            return Err(WatermarkError::ProcessingError(
+                Message::VideoDecodeFailed.text().to_string(),
+            ));
+        }
+        if !encode_status.success() {
+            return Err(WatermarkError::ProcessingError(
+                Message::VideoEncodeFailed.text().to_string(),
+            ));
+        }
 
-        // 创建帧处理进度条
-        let frame_progress = ProgressBar::new(frame_files.len() as u64);
-        frame_progress.set_style(
-            ProgressStyle::default_bar()
-                .template(
-                    "{spinner:.green} [{elapsed_precise}] [{bar:30.yellow/red}] {pos}/{len} 帧",
-                )
-                .unwrap()
-                .progress_chars("█▉▊▋▌▍▎▏  "),
+        eprintln!(
+            "{} {}",
+            "✅".green(),
+            Message::FramesProcessed.format(&[&frame_count]).green()
         );
 
-        for frame_file in &frame_files {
-            Self::process_frame(frame_file, watermark_text, algorithm, strength)?;
-            frame_progress.inc(1);
-        }
-        frame_progress.finish_with_message(
-            format!("✅ 已处理 {} 帧", frame_files.len())
-                .green()
-                .to_string(),
-        );
-        progress.inc(1);
+        Ok(frame_count)
+    }
 
-        // 重新组合视频
-        progress.set_message("🎞️  重新组合视频".to_string());
-        if let Some(audio_path) = &watermarked_audio_path {
-            Self::reassemble_video_with_custom_audio(
-                &frames_dir,
-                audio_path,
-                output_path,
-                lossless,
-            )?;
-        } else {
-            Self::reassemble_video(
-                &frames_dir,
-                &temp_dir.join("dummy.aac"),
-                output_path,
-                video_info,
-                lossless,
-            )?;
-        }
-        progress.inc(1);
+    /// 把一帧写入编码进程，给定 `payload` 时先嵌入水印，否则原样写出
+    fn write_stream_frame(
+        stdin: &mut std::process::ChildStdin,
+        frame: image::RgbImage,
+        payload: Option<Vec<u8>>,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        strength: f64,
+    ) -> Result<()> {
+        use crate::media::ImageWatermarker;
+        use image::DynamicImage;
+        use std::io::Write;
 
-        // 完成并清理
-        progress.finish_with_message("🎉 音视频水印嵌入完成!".green().bold().to_string());
+        let frame = match payload {
+            Some(payload) => ImageWatermarker::embed_into_image(
+                &DynamicImage::ImageRgb8(frame),
+                &payload,
+                algorithm,
+                strength,
+                ImageChannel::All,
+            )?
+            .to_rgb8(),
+            None => frame,
+        };
+        stdin
+            .write_all(frame.as_raw())
+            .map_err(|_| WatermarkError::ProcessingError(Message::EncoderExited.text().to_string()))
+    }
 
-        // 清理临时文件
-        std::fs::remove_dir_all(&temp_dir)?;
-        eprintln!("{} {}", "🧹".blue(), "临时文件已清理".blue());
+    /// 启动从标准输入读取 rgb24 原始帧的 H.264 编码进程
+    fn spawn_rawvideo_encoder(
+        output_path: &Path,
+        width: u32,
+        height: u32,
+        fps: f32,
+        encode: VideoEncodeOptions,
+    ) -> Result<(ffmpeg::FfmpegJob, std::process::ChildStdin)> {
+        let mut command = FfmpegCommand::new();
+        command.args(["-f", "rawvideo", "-pix_fmt", "rgb24"]);
+        command.args(["-s", &format!("{width}x{height}")]);
+        command.args(["-framerate", &fps.to_string()]);
+        command.input("-");
+        encode.apply(&mut command);
+        command.args(["-pix_fmt", "yuv420p"]);
+        command.args(["-y"]);
+        command.output(output_path.to_str().unwrap());
 
-        Ok(frame_files.len())
+        let mut child = command.spawn().map_err(WatermarkError::Io)?;
+        let stdin = child.take_stdin().ok_or_else(|| {
+            WatermarkError::ProcessingError(Message::EncoderStdinUnavailable.text().to_string())
+        })?;
+
+        // 在后台持续读取编码器日志，避免 stderr 管道写满后阻塞编码器
+        let _ = child.iter();
+
+        Ok((ffmpeg::watch(child), stdin))
     }
 
     /// # Extract audio as WAV format
     fn extract_audio_as_wav<P: AsRef<Path>>(input_path: P, output_path: P) -> Result<()> {
-        let input_str = input_path
-            .as_ref()
-            .to_str()
-            .ok_or_else(|| WatermarkError::ProcessingError("输入路径包含无效字符".to_string()))?;
-        let output_str = output_path
-            .as_ref()
-            .to_str()
-            .ok_or_else(|| WatermarkError::ProcessingError("输出路径包含无效字符".to_string()))?;
-
-        let mut child = FfmpegCommand::new()
-            .input(input_str)
-            .args(["-vn"]) // 不包含视频
-            .args(["-acodec", "pcm_s16le"]) // 转换为WAV格式
-            .args(["-ar", "44100"]) // 采样率
-            .args(["-y"]) // 覆盖输出文件
-            .output(output_str)
-            .spawn()
-            .map_err(WatermarkError::Io)?;
-
-        let status = child.wait().map_err(WatermarkError::Io)?;
+        let input_str = input_path.as_ref().to_str().ok_or_else(|| {
+            WatermarkError::ProcessingError(Message::InputPathInvalid.text().to_string())
+        })?;
+        let output_str = output_path.as_ref().to_str().ok_or_else(|| {
+            WatermarkError::ProcessingError(Message::OutputPathInvalid.text().to_string())
+        })?;
+
+        let status = ffmpeg::run(
+            FfmpegCommand::new()
+                .input(input_str)
+                .args(["-vn"]) // 不包含视频
+                .args(CANONICAL_WAV_ARGS) // 直接输出音频水印读取的格式，不再二次转换
+                .args(["-y"]) // 覆盖输出文件
+                .output(output_str),
+        )?;
 
         if !status.success() {
-            return Err(WatermarkError::ProcessingError(format!(
-                "音频提取失败: FFmpeg 命令执行失败, 错误码: {}",
-                status.code().unwrap_or(-1)
-            )));
+            return Err(WatermarkError::ProcessingError(
+                Message::AudioExtractFailed.format(&[&status.code().unwrap_or(-1)]),
+            ));
         }
 
         Ok(())
@@ -1003,29 +2426,25 @@ impl VideoWatermarker {
 
     /// 提取视频流（不包含音频）
     fn extract_video_stream<P: AsRef<Path>>(input_path: P, output_path: P) -> Result<()> {
-        let input_str = input_path
-            .as_ref()
-            .to_str()
-            .ok_or_else(|| WatermarkError::ProcessingError("输入路径包含无效字符".to_string()))?;
-        let output_str = output_path
-            .as_ref()
-            .to_str()
-            .ok_or_else(|| WatermarkError::ProcessingError("输出路径包含无效字符".to_string()))?;
-
-        let mut child = FfmpegCommand::new()
-            .input(input_str)
-            .args(["-an"]) // 不包含音频
-            .args(["-c:v", "copy"]) // 视频流复制
-            .args(["-y"]) // 覆盖输出文件
-            .output(output_str)
-            .spawn()
-            .map_err(WatermarkError::Io)?;
-
-        let status = child.wait().map_err(WatermarkError::Io)?;
+        let input_str = input_path.as_ref().to_str().ok_or_else(|| {
+            WatermarkError::ProcessingError(Message::InputPathInvalid.text().to_string())
+        })?;
+        let output_str = output_path.as_ref().to_str().ok_or_else(|| {
+            WatermarkError::ProcessingError(Message::OutputPathInvalid.text().to_string())
+        })?;
+
+        let status = ffmpeg::run(
+            FfmpegCommand::new()
+                .input(input_str)
+                .args(["-an"]) // 不包含音频
+                .args(["-c:v", "copy"]) // 视频流复制
+                .args(["-y"]) // 覆盖输出文件
+                .output(output_str),
+        )?;
 
         if !status.success() {
             return Err(WatermarkError::ProcessingError(
-                "视频流提取失败".to_string(),
+                Message::VideoStreamExtractFailed.text().to_string(),
             ));
         }
 
@@ -1038,343 +2457,556 @@ impl VideoWatermarker {
         audio_path: P,
         output_path: P,
     ) -> Result<()> {
-        let video_str = video_path
-            .as_ref()
-            .to_str()
-            .ok_or_else(|| WatermarkError::ProcessingError("视频路径包含无效字符".to_string()))?;
-        let audio_str = audio_path
-            .as_ref()
-            .to_str()
-            .ok_or_else(|| WatermarkError::ProcessingError("音频路径包含无效字符".to_string()))?;
-        let output_str = output_path
-            .as_ref()
-            .to_str()
-            .ok_or_else(|| WatermarkError::ProcessingError("输出路径包含无效字符".to_string()))?;
-
-        let mut child = FfmpegCommand::new()
-            .input(video_str)
-            .input(audio_str)
-            .args(["-c:v", "copy"]) // 视频流复制
-            .args(["-c:a", "pcm_s16le"]) // 使用无损PCM编码保护音频水印
-            .args(["-y"]) // 覆盖输出文件
-            .output(output_str)
-            .spawn()
-            .map_err(WatermarkError::Io)?;
-
-        let status = child.wait().map_err(WatermarkError::Io)?;
+        let video_str = video_path.as_ref().to_str().ok_or_else(|| {
+            WatermarkError::ProcessingError(Message::VideoPathInvalid.text().to_string())
+        })?;
+        let audio_str = audio_path.as_ref().to_str().ok_or_else(|| {
+            WatermarkError::ProcessingError(Message::AudioPathInvalid.text().to_string())
+        })?;
+        let output_str = output_path.as_ref().to_str().ok_or_else(|| {
+            WatermarkError::ProcessingError(Message::OutputPathInvalid.text().to_string())
+        })?;
+
+        let status = ffmpeg::run(
+            FfmpegCommand::new()
+                .input(video_str)
+                .input(audio_str)
+                .args(["-c:v", "copy"]) // 视频流复制
+                .args(["-c:a", "pcm_s16le"]) // 使用无损PCM编码保护音频水印
+                .args(["-y"]) // 覆盖输出文件
+                .output(output_str),
+        )?;
 
         if !status.success() {
             return Err(WatermarkError::ProcessingError(
-                "音视频合并失败".to_string(),
+                Message::MuxFailed.text().to_string(),
             ));
         }
 
         Ok(())
     }
 
-    /// 使用自定义音频重新组合视频
-    fn reassemble_video_with_custom_audio(
-        frames_dir: &Path,
-        audio_path: &Path,
-        output_path: &Path,
-        lossless: bool,
-    ) -> Result<()> {
-        let frame_pattern = frames_dir.join("frame_%06d.png");
-
-        let mut command = FfmpegCommand::new();
-        command.args(["-framerate", "30"]);
-        command.input(frame_pattern.to_str().unwrap());
-        command.input(audio_path.to_str().unwrap());
-
-        if lossless {
-            command.args(["-c:v", "libx264", "-crf", "0", "-c:a", "pcm_s16le"]);
-            command.args(["-preset", "ultrafast"]);
-        } else {
-            command.args(["-c:v", "libx264", "-crf", "23", "-c:a", "pcm_s16le"]);
-            command.args(["-preset", "medium"]);
-        }
-
-        command.args(["-pix_fmt", "yuv420p"]);
-        command.args(["-y"]);
-        command.output(output_path.to_str().unwrap());
-
-        let mut child = command.spawn().map_err(WatermarkError::Io)?;
-        let status = child.wait().map_err(WatermarkError::Io)?;
-
-        if !status.success() {
-            return Err(WatermarkError::ProcessingError("视频重组失败".to_string()));
-        }
-
-        Ok(())
-    }
-
-    /// 仅从视频帧提取水印（原有逻辑）
+    /// 仅从视频帧提取水印（原有逻辑），`max_duration` 限制只读取开头若干秒
     fn extract_video_only<P: AsRef<Path>>(
         input_path: P,
-        algorithm: &dyn WatermarkAlgorithm,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
         watermark_length: usize,
         sample_frames: Option<usize>,
         confidence_threshold: Option<f64>,
-    ) -> Result<(String, f64, usize)> {
+        max_duration: Option<f64>,
+        options: VideoExtractOptions,
+    ) -> Result<ExtractionResult> {
+        let progress = options.progress;
         let input_path = input_path.as_ref();
         // 当 sample_frames=0 时表示提取所有帧，保持原值；否则使用默认值7
         let sample_frames = sample_frames.unwrap_or(0);
-        let confidence_threshold = confidence_threshold.unwrap_or(0.6);
+        let confidence_threshold = confidence_threshold.unwrap_or(DEFAULT_CONFIDENCE_THRESHOLD);
 
         // 创建提取进度条
-        let progress = ProgressBar::new(4);
-        progress.set_style(
-            ProgressStyle::default_bar()
-                .template(
-                    "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}",
-                )
-                .unwrap()
-                .progress_chars("█▉▊▋▌▍▎▏  "),
-        );
+        progress.started(4);
 
         // 创建临时目录
-        progress.set_message("🗂️  创建临时目录".to_string());
-        let temp_dir = std::env::temp_dir().join(format!("video_extract_{}", std::process::id()));
-        std::fs::create_dir_all(&temp_dir)?;
-        progress.inc(1);
+        progress.stage(Message::StageTempDir.text());
+        let temp_dir = TempDir::new("video_extract")?;
+        progress.step();
 
         // 获取视频信息
-        progress.set_message("📊  分析视频信息".to_string());
+        progress.stage(Message::StageAnalyze.text());
         let _video_info = Self::get_video_info(input_path)?;
-        progress.inc(1);
+        progress.step();
 
         // 多帧采样提取
         let extract_message = if sample_frames == 0 {
-            "🎬  提取所有帧".to_string()
+            Message::StageExtractAll.text().to_string()
         } else {
-            format!("🎬  提取{}个样本帧", sample_frames)
+            Message::StageExtractSamples.format(&[&sample_frames])
         };
-        progress.set_message(extract_message);
+        progress.stage(&extract_message);
         let frame_results = Self::extract_multiple_frames_watermark(
             input_path,
             &temp_dir,
             algorithm,
             watermark_length,
             sample_frames,
+            max_duration,
+            options,
         )?;
-        let actual_frames_used = frame_results.len();
-        progress.inc(1);
+        let actual_frames_used = frame_results.votes.len();
+        let frame_indices = FrameIndexRange::from_pairs(&frame_results.indices);
+        progress.step();
 
         // 投票机制确定最终结果
-        progress.set_message("🗳️  多帧投票分析".to_string());
+        progress.stage(Message::StageVote.text());
         let (final_watermark, confidence) =
-            Self::vote_watermark_bits(frame_results, watermark_length);
+            Self::vote_watermark_bits(frame_results.votes, watermark_length);
 
-        // 检查置信度
-        if confidence < confidence_threshold {
+        // 检查置信度：少量帧得到的高置信度同样不可靠，按样本数修正后再比较；
+        // 提前停止时已有一帧通过帧序号校验，帧数少不代表结果不可靠
+        let adjusted_confidence = Self::sample_adjusted_confidence(confidence, actual_frames_used);
+        if adjusted_confidence < confidence_threshold && !frame_results.stopped_early {
             eprintln!(
-                "{} 警告：提取置信度较低 ({:.1}%)，建议检查视频质量或增加采样帧数",
+                "{} {}",
                 "⚠️".yellow(),
-                confidence * 100.0
+                Message::VideoLowConfidence.format(&[
+                    &format!("{:.1}", adjusted_confidence * 100.0),
+                    &format!("{:.1}", confidence * 100.0),
+                    &actual_frames_used
+                ])
             );
         }
 
-        progress.inc(1);
+        progress.step();
 
         // 完成提取
-        progress.finish_with_message(
-            format!("🎉 视频水印提取完成! 置信度: {:.1}%", confidence * 100.0)
-                .green()
-                .bold()
-                .to_string(),
+        progress.finished(
+            &Message::StageVideoExtractDone.format(&[&format!("{:.1}", confidence * 100.0)]),
         );
 
-        // 清理临时文件
-        std::fs::remove_dir_all(&temp_dir)?;
-
-        Ok((final_watermark, confidence, actual_frames_used))
+        Ok(ExtractionResult {
+            text: final_watermark,
+            confidence,
+            frames_used: actual_frames_used,
+            frame_indices,
+            integrity: Integrity::Unchecked,
+        })
     }
 
     /// 仅从音频提取水印
     fn extract_audio_only<P: AsRef<Path>>(
         input_path: P,
-        algorithm: &dyn WatermarkAlgorithm,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
         watermark_length: usize,
         video_info: &VideoInfo,
-    ) -> Result<(String, f64, usize)> {
+        progress: Progress,
+    ) -> Result<ExtractionResult> {
         let input_path = input_path.as_ref();
 
         if !video_info.has_audio {
             return Err(WatermarkError::ProcessingError(
-                "视频文件不包含音频轨道，无法提取音频水印".to_string(),
+                Message::NoAudioTrackForExtract.text().to_string(),
             ));
         }
 
         // 创建提取进度条
-        let progress = ProgressBar::new(4);
-        progress.set_style(
-            ProgressStyle::default_bar()
-                .template(
-                    "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}",
-                )
-                .unwrap()
-                .progress_chars("█▉▊▋▌▍▎▏  "),
-        );
+        progress.started(4);
 
         // 创建临时目录
-        progress.set_message("🗂️  创建临时目录".to_string());
-        let temp_dir =
-            std::env::temp_dir().join(format!("video_audio_extract_{}", std::process::id()));
-        std::fs::create_dir_all(&temp_dir)?;
-        progress.inc(1);
+        progress.stage(Message::StageTempDir.text());
+        let temp_dir = TempDir::new("video_audio_extract")?;
+        progress.step();
 
         // 提取音频轨道
-        progress.set_message("🎵  提取音频轨道".to_string());
+        progress.stage(Message::StageExtractAudio.text());
         let audio_path = temp_dir.join("extracted_audio.wav");
         Self::extract_audio_as_wav(input_path, &audio_path)?;
-        progress.inc(1);
+        progress.step();
 
         // 从音频提取水印
-        progress.set_message("🎯  提取音频水印".to_string());
+        progress.stage(Message::StageExtractAudioWatermark.text());
         use crate::media::AudioWatermarker;
-        let watermark =
+        let extraction =
             AudioWatermarker::extract_watermark(&audio_path, algorithm, watermark_length)?;
-        progress.inc(1);
+        progress.step();
 
-        progress.inc(1);
+        progress.step();
 
         // 完成提取
-        progress.finish_with_message("🎉 音频水印提取完成!".green().bold().to_string());
-
-        // 清理临时文件
-        std::fs::remove_dir_all(&temp_dir)?;
+        progress.finished(Message::StageAudioExtractDone.text());
 
-        Ok((watermark, 1.0, 1)) // 音频始终置信度100%，使用1帧
+        Ok(extraction)
     }
 
     /// 同时从视频帧和音频提取水印，并进行融合
     fn extract_both<P: AsRef<Path>>(
         input_path: P,
-        algorithm: &dyn WatermarkAlgorithm,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
         watermark_length: usize,
         sample_frames: Option<usize>,
         confidence_threshold: Option<f64>,
         video_info: &VideoInfo,
-    ) -> Result<(String, f64, usize)> {
+        options: VideoExtractOptions,
+    ) -> Result<ExtractionResult> {
+        let progress = options.progress;
         let input_path = input_path.as_ref();
         // 当 sample_frames=0 时表示提取所有帧，保持原值；否则使用默认值7
         let sample_frames = sample_frames.unwrap_or(0);
-        let confidence_threshold = confidence_threshold.unwrap_or(0.6);
+        let confidence_threshold = confidence_threshold.unwrap_or(DEFAULT_CONFIDENCE_THRESHOLD);
 
         // 创建提取进度条
-        let progress = ProgressBar::new(6);
-        progress.set_style(
-            ProgressStyle::default_bar()
-                .template(
-                    "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}",
-                )
-                .unwrap()
-                .progress_chars("█▉▊▋▌▍▎▏  "),
-        );
+        progress.started(6);
 
         // 创建临时目录
-        progress.set_message("🗂️  创建临时目录".to_string());
-        let temp_dir =
-            std::env::temp_dir().join(format!("video_both_extract_{}", std::process::id()));
-        std::fs::create_dir_all(&temp_dir)?;
-        progress.inc(1);
+        progress.stage(Message::StageTempDir.text());
+        let temp_dir = TempDir::new("video_both_extract")?;
+        progress.step();
 
         // 从音频提取水印（如果有音频）
         let audio_result = if video_info.has_audio {
-            progress.set_message("🎵  提取音频水印".to_string());
+            progress.stage(Message::StageExtractAudioMark.text());
             let audio_path = temp_dir.join("extracted_audio.wav");
             Self::extract_audio_as_wav(input_path, &audio_path)?;
 
+            // 使用音频自身的软判决置信度，而不是把提取成功一律当作完全可信
             use crate::media::AudioWatermarker;
-            match AudioWatermarker::extract_watermark(&audio_path, algorithm, watermark_length) {
-                Ok(watermark) => Some((watermark, 1.0)),
-                Err(_) => None,
-            }
+            AudioWatermarker::extract_bytes_with_confidence(
+                &audio_path,
+                algorithm,
+                watermark_length,
+            )
+            .ok()
+            .filter(|(bytes, _)| !crypto::is_encrypted(bytes))
+            .and_then(|(bytes, confidence)| {
+                String::from_utf8(bytes)
+                    .ok()
+                    .map(|watermark| (watermark, confidence))
+            })
         } else {
             None
         };
-        progress.inc(1);
+        progress.step();
 
         // 从视频帧提取水印
         let extract_message = if sample_frames == 0 {
-            "🎬  提取所有帧".to_string()
+            Message::StageExtractAll.text().to_string()
         } else {
-            format!("🎬  提取{}个样本帧", sample_frames)
+            Message::StageExtractSamples.format(&[&sample_frames])
         };
-        progress.set_message(extract_message);
+        progress.stage(&extract_message);
         let frame_results = Self::extract_multiple_frames_watermark(
             input_path,
             &temp_dir,
             algorithm,
             watermark_length,
             sample_frames,
+            None,
+            options,
         )?;
-        let actual_frames_used = frame_results.len();
-        progress.inc(1);
+        let actual_frames_used = frame_results.votes.len();
+        let frame_indices = FrameIndexRange::from_pairs(&frame_results.indices);
+        progress.step();
 
         // 投票机制确定视频水印结果
-        progress.set_message("🗳️  多帧投票分析".to_string());
+        progress.stage(Message::StageVote.text());
         let (video_watermark, video_confidence) =
-            Self::vote_watermark_bits(frame_results, watermark_length);
-        progress.inc(1);
+            Self::vote_watermark_bits(frame_results.votes, watermark_length);
+        progress.step();
 
         // 融合音频和视频的结果
-        progress.set_message("🔀  融合音视频水印结果".to_string());
+        progress.stage(Message::StageFuse.text());
         let (final_watermark, final_confidence) = match audio_result {
             Some((audio_watermark, audio_confidence)) => {
                 // 如果音频和视频都有结果，选择置信度更高的
                 if audio_confidence > video_confidence {
                     eprintln!(
-                        "{} 选择音频水印结果（置信度: {:.1}%）",
+                        "{} {}",
                         "🎵".green(),
-                        audio_confidence * 100.0
+                        Message::ChoseAudioResult
+                            .format(&[&format!("{:.1}", audio_confidence * 100.0)])
                     );
                     (audio_watermark, audio_confidence)
                 } else {
                     eprintln!(
-                        "{} 选择视频水印结果（置信度: {:.1}%）",
+                        "{} {}",
                         "🎬".green(),
-                        video_confidence * 100.0
+                        Message::ChoseVideoResult
+                            .format(&[&format!("{:.1}", video_confidence * 100.0)])
                     );
                     (video_watermark, video_confidence)
                 }
             }
             None => {
-                eprintln!("{} 仅使用视频水印结果", "🎬".blue());
+                eprintln!("{} {}", "🎬".blue(), Message::VideoResultOnly.text());
                 (video_watermark, video_confidence)
             }
         };
-        progress.inc(1);
+        progress.step();
 
-        // 检查置信度
+        // 选中的是置信度最高的一路，低于阈值说明音频和视频都不可靠，
+        // 此时返回失败而不是输出一个看似可信的错误结果
         if final_confidence < confidence_threshold {
+            progress.emit(ProgressEvent::Abandoned(
+                Message::BothBelowThreshold.text().to_string(),
+            ));
             eprintln!(
-                "{} 警告：提取置信度较低 ({:.1}%)，建议检查媒体质量",
-                "⚠️".yellow(),
-                final_confidence * 100.0
+                "{} {}",
+                "❌".red(),
+                Message::BestBelowThreshold.format(&[
+                    &format!("{:.1}", final_confidence * 100.0),
+                    &format!("{:.1}", confidence_threshold * 100.0)
+                ])
             );
+            return Err(WatermarkError::ExtractionFailed);
         }
 
-        progress.inc(1);
+        progress.step();
 
         // 完成提取
-        progress.finish_with_message(
-            format!(
-                "🎉 音视频水印提取完成! 置信度: {:.1}%",
-                final_confidence * 100.0
-            )
-            .green()
-            .bold()
-            .to_string(),
+        progress.finished(
+            &Message::StageBothExtractDone.format(&[&format!("{:.1}", final_confidence * 100.0)]),
         );
 
-        // 清理临时文件
-        std::fs::remove_dir_all(&temp_dir)?;
+        Ok(ExtractionResult {
+            text: final_watermark,
+            confidence: final_confidence,
+            frames_used: actual_frames_used,
+            frame_indices,
+            integrity: Integrity::Unchecked,
+        })
+    }
+
+    /// 冗余模式嵌入：先按 `both` 模式写入视频帧和音频，再把水印写入容器元数据
+    ///
+    /// 三份副本各自面对不同的破坏方式：替换视频流或静音转发只会去掉其中一份，
+    /// 提取时由剩下的副本投票。
+    fn embed_all<P: AsRef<Path>>(
+        input_path: P,
+        output_path: P,
+        watermark_text: &str,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        strength: f64,
+        encode: VideoEncodeOptions,
+        video_info: &VideoInfo,
+    ) -> Result<usize> {
+        let output_path = output_path.as_ref();
+
+        let temp_dir = TempDir::new("video_all_watermark")?;
+
+        // 临时文件沿用输出的扩展名，保证复用同一种封装格式
+        let mut both_path = temp_dir.join("both");
+        if let Some(ext) = output_path.extension() {
+            both_path.set_extension(ext);
+        }
+        let frame_count = Self::embed_both(
+            input_path.as_ref(),
+            &both_path,
+            watermark_text,
+            algorithm,
+            strength,
+            encode,
+            video_info,
+        )?;
+
+        eprintln!(
+            "{} {}",
+            "📦".blue(),
+            Message::WritingContainerTag.text().blue()
+        );
+        Self::write_container_watermark(&both_path, output_path, watermark_text)?;
+
+        Ok(frame_count)
+    }
+
+    /// 复制全部码流并把水印写入容器的 `comment` 标签
+    fn write_container_watermark(
+        input_path: &Path,
+        output_path: &Path,
+        watermark_text: &str,
+    ) -> Result<()> {
+        let tag = format!(
+            "comment={CONTAINER_TAG_PREFIX}{}",
+            BASE64_STANDARD_NO_PAD.encode(watermark_text)
+        );
+        let status = ffmpeg::run(
+            FfmpegCommand::new()
+                .input(input_path.to_str().unwrap())
+                .args(["-map", "0", "-c", "copy"])
+                .args(["-metadata", &tag])
+                .args(["-y"])
+                .output(output_path.to_str().unwrap()),
+        )?;
+        if !status.success() {
+            return Err(WatermarkError::ProcessingError(
+                Message::ContainerTagFailed.text().to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// 从容器的 `comment` 标签读取水印文本，标签不存在或不是本工具写入的则返回 `None`
+    fn read_container_watermark(input_path: &Path, temp_dir: &Path) -> Result<Option<String>> {
+        let metadata_path = temp_dir.join("metadata.txt");
+        let status = ffmpeg::run(
+            FfmpegCommand::new()
+                .input(input_path.to_str().unwrap())
+                .args(["-f", "ffmetadata"])
+                .args(["-y"])
+                .output(metadata_path.to_str().unwrap()),
+        )?;
+        if !status.success() {
+            return Ok(None);
+        }
+
+        // 只看第一个分节之前的全局标签；base64 不含需要转义的字符
+        let metadata = std::fs::read_to_string(&metadata_path)?;
+        Ok(metadata
+            .lines()
+            .take_while(|line| !line.starts_with('['))
+            .find_map(|line| line.strip_prefix("comment="))
+            .and_then(|value| value.strip_prefix(CONTAINER_TAG_PREFIX))
+            .and_then(|encoded| BASE64_STANDARD_NO_PAD.decode(encoded.trim()).ok())
+            .map(|bytes| String::from_utf8_lossy(&bytes).to_string()))
+    }
+
+    /// 冗余模式提取：分别从容器元数据、音频和视频帧提取，再按置信度加权逐比特投票
+    ///
+    /// 元数据是精确副本，权重为1.0；音频和视频帧使用各自的平均置信度。
+    /// 任意一路缺失或失败时由其余各路投票，全部失败才报错。
+    fn extract_all<P: AsRef<Path>>(
+        input_path: P,
+        algorithm: &(dyn WatermarkAlgorithm + Sync),
+        watermark_length: usize,
+        sample_frames: Option<usize>,
+        confidence_threshold: Option<f64>,
+        video_info: &VideoInfo,
+        options: VideoExtractOptions,
+    ) -> Result<ExtractionResult> {
+        let input_path = input_path.as_ref();
+        let sample_frames = sample_frames.unwrap_or(0);
+        let confidence_threshold = confidence_threshold.unwrap_or(DEFAULT_CONFIDENCE_THRESHOLD);
+
+        let temp_dir = TempDir::new("video_all_extract")?;
+
+        let mut sources = Vec::new();
+
+        match Self::read_container_watermark(input_path, &temp_dir)? {
+            Some(text) => {
+                eprintln!("{} {}", "📦".green(), Message::ContainerTagFound.text());
+                sources.push((Self::string_to_bits(&text, watermark_length), 1.0));
+            }
+            None => eprintln!("{} {}", "📦".yellow(), Message::ContainerTagMissing.text()),
+        }
+
+        // 任意一路失败都只跳过该路，由其余各路投票；超时和中止仍然直接返回
+        if video_info.has_audio {
+            let audio_path = temp_dir.join("extracted_audio.wav");
+
+            use crate::media::AudioWatermarker;
+            let extracted = Self::extract_audio_as_wav(input_path, &audio_path).and_then(|()| {
+                AudioWatermarker::extract_bytes_with_confidence(
+                    &audio_path,
+                    algorithm,
+                    watermark_length,
+                )
+            });
+            match extracted {
+                Ok((bytes, confidence)) => {
+                    eprintln!(
+                        "{} {}",
+                        "🎵".green(),
+                        Message::AudioResultConfidence
+                            .format(&[&format!("{:.1}", confidence * 100.0)])
+                    );
+                    sources.push((WatermarkUtils::bytes_to_bits(&bytes), confidence));
+                }
+                Err(e) if ffmpeg::is_aborted(&e) => return Err(e),
+                Err(e) => eprintln!(
+                    "{} {}",
+                    "🎵".yellow(),
+                    Message::AudioResultFailed.format(&[&e])
+                ),
+            }
+        }
+
+        let mut actual_frames_used = 0;
+        let mut frame_indices = None;
+        let frame_results = if video_info.has_video {
+            match Self::extract_multiple_frames_watermark(
+                input_path,
+                &temp_dir,
+                algorithm,
+                watermark_length,
+                sample_frames,
+                None,
+                options,
+            ) {
+                Ok(frame_results) => Some(frame_results),
+                Err(e) if ffmpeg::is_aborted(&e) => return Err(e),
+                Err(e) => {
+                    eprintln!(
+                        "{} {}",
+                        "🎬".yellow(),
+                        Message::VideoResultFailed.format(&[&e])
+                    );
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        if let Some(frame_results) = frame_results {
+            actual_frames_used = frame_results.votes.len();
+            frame_indices = FrameIndexRange::from_pairs(&frame_results.indices);
+            if !frame_results.votes.is_empty() {
+                let (video_watermark, video_confidence) =
+                    Self::vote_watermark_bits(frame_results.votes, watermark_length);
+                eprintln!(
+                    "{} {}",
+                    "🎬".green(),
+                    Message::VideoResultConfidence.format(&[
+                        &format!("{:.1}", video_confidence * 100.0),
+                        &actual_frames_used
+                    ])
+                );
+                sources.push((
+                    Self::string_to_bits(&video_watermark, watermark_length),
+                    video_confidence,
+                ));
+            }
+        }
+
+        drop(temp_dir);
+
+        if sources.is_empty() {
+            return Err(WatermarkError::ProcessingError(
+                Message::AllCopiesFailed.text().to_string(),
+            ));
+        }
+
+        let source_count = sources.len();
+        let (final_watermark, final_confidence) =
+            Self::vote_watermark_bits(sources, watermark_length);
+        eprintln!(
+            "{} {}",
+            "🗳️".green(),
+            Message::CopiesVoted
+                .format(&[&source_count, &format!("{:.1}", final_confidence * 100.0)])
+        );
 
-        Ok((final_watermark, final_confidence, actual_frames_used))
+        if final_confidence < confidence_threshold {
+            eprintln!(
+                "{} {}",
+                "⚠️".yellow(),
+                Message::FusedLowConfidence.format(&[&format!("{:.1}", final_confidence * 100.0)])
+            );
+        }
+
+        Ok(ExtractionResult {
+            text: final_watermark,
+            confidence: final_confidence,
+            frames_used: actual_frames_used,
+            frame_indices,
+            integrity: Integrity::Unchecked,
+        })
     }
 }
 
+/// 视频流属性，由 [`VideoWatermarker::probe`] 读取
+#[derive(Debug, Clone)]
+pub struct VideoProperties {
+    /// 视频编码格式，即 FFmpeg 报告的流格式名（如 `h264`、`hevc`）
+    pub codec: String,
+    /// 宽度（像素）
+    pub width: u32,
+    /// 高度（像素）
+    pub height: u32,
+    /// 帧率
+    pub fps: f32,
+    /// 时长（秒），容器未记录时为 `None`
+    pub duration: Option<f64>,
+    /// 是否包含音频轨道
+    pub has_audio: bool,
+}
+
 /// 视频信息结构
 #[allow(dead_code)]
 #[derive(Debug)]
@@ -1383,4 +3015,351 @@ struct VideoInfo {
     has_video: bool,
     duration: Option<f64>,
     fps: f64,
+    /// 视频编码格式，无法解析时为 `None`
+    codec: Option<String>,
+}
+
+/// 关键帧探测结果
+#[derive(Debug)]
+struct KeyframeProbe {
+    /// 源码流的 H.264 档次（libx264 的写法），无法识别时为 `None`
+    profile: Option<&'static str>,
+    pix_fmt: Option<String>,
+    fps: f32,
+    keyframe_secs: Option<f64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::watermark::DctWatermark;
+    use ffmpeg_sidecar::command::ffmpeg_is_installed;
+    use std::path::PathBuf;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("seal_video_{}_{name}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// 5秒、每秒一个关键帧的 H.264 测试视频，`audio` 为真时带一条正弦波音轨
+    fn write_test_video(path: &Path, audio: bool) {
+        let mut command = FfmpegCommand::new();
+        command
+            .args(["-f", "lavfi"])
+            .input("testsrc=size=160x128:rate=25:duration=5");
+        if audio {
+            command
+                .args(["-f", "lavfi"])
+                .input("sine=frequency=440:sample_rate=44100:duration=5")
+                .args(["-c:a", "aac"]);
+        }
+        command
+            .args(["-c:v", "libx264", "-profile:v", "main", "-g", "25"])
+            .args(["-pix_fmt", "yuv420p", "-y"])
+            .output(path.to_str().unwrap());
+        assert!(ffmpeg::run(&mut command).unwrap().success());
+    }
+
+    /// `apply` 追加的全部参数
+    fn applied_args(encode: VideoEncodeOptions) -> Vec<String> {
+        let mut command = FfmpegCommand::new();
+        encode.apply(&mut command);
+        command
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    fn has_pair(args: &[String], flag: &str, value: &str) -> bool {
+        args.windows(2)
+            .any(|pair| pair[0] == flag && pair[1] == value)
+    }
+
+    /// 解码 `secs` 秒处的一帧
+    fn frame_at(path: &Path, secs: f64, output: &Path) -> image::RgbImage {
+        let status = ffmpeg::run(
+            FfmpegCommand::new()
+                .input(path.to_str().unwrap())
+                .args(["-ss", &secs.to_string(), "-vframes", "1", "-y"])
+                .output(output.to_str().unwrap()),
+        )
+        .unwrap();
+        assert!(status.success());
+        image::open(output).unwrap().to_rgb8()
+    }
+
+    fn resolved(
+        codec: Option<VideoCodec>,
+        source: Option<&str>,
+        output: &str,
+    ) -> Result<Option<VideoCodec>> {
+        VideoEncodeOptions {
+            codec,
+            ..Default::default()
+        }
+        .resolve(source, Path::new(output))
+        .map(|encode| encode.codec)
+    }
+
+    #[test]
+    fn codec_must_fit_the_output_container() {
+        use VideoCodec::*;
+        assert_eq!(resolved(None, Some("hevc"), "a.mkv").unwrap(), Some(Hevc));
+        assert_eq!(resolved(None, Some("vp9"), "a.mp4").unwrap(), Some(Vp9));
+        // 源编码放不进容器时退回该容器的默认编码
+        assert_eq!(resolved(None, Some("h264"), "a.webm").unwrap(), Some(Vp9));
+        assert_eq!(resolved(None, Some("hevc"), "a.avi").unwrap(), Some(H264));
+        assert_eq!(resolved(None, Some("av1"), "a.MOV").unwrap(), Some(H264));
+        // 显式指定的编码放不进容器时报错
+        for (codec, output) in [
+            (Hevc, "a.webm"),
+            (Vp9, "a.avi"),
+            (Hevc, "a.avi"),
+            (Av1, "a.mov"),
+        ] {
+            assert!(matches!(
+                resolved(Some(codec), None, output),
+                Err(WatermarkError::InvalidArgument(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn ffmpeg_threads_are_passed_to_the_encoder() {
+        let args = applied_args(VideoEncodeOptions {
+            ffmpeg_threads: Some(2),
+            ..Default::default()
+        });
+        assert!(has_pair(&args, "-threads", "2"));
+
+        let args = applied_args(VideoEncodeOptions::default());
+        assert!(!args.iter().any(|arg| arg == "-threads"));
+    }
+
+    #[test]
+    fn keyframe_mode_output_decodes_like_the_source() {
+        if !ffmpeg_is_installed() {
+            eprintln!("skipped: ffmpeg is not installed");
+            return;
+        }
+        let dir = scratch_dir("keyframe");
+        let source = dir.join("source.mp4");
+        let output = dir.join("output.mp4");
+        write_test_video(&source, false);
+
+        VideoWatermarker::embed_watermark_with_options(
+            &source,
+            &output,
+            "key",
+            &DctWatermark::new(),
+            0.1,
+            VideoWatermarkMode::Keyframe,
+            VideoEncodeOptions::default(),
+        )
+        .unwrap();
+
+        // 拼接处之后的帧必须逐帧解码无误，帧数与源视频相同
+        let expected = VideoWatermarker::count_frames(&source, false).unwrap();
+        assert!(expected.is_some());
+        assert_eq!(
+            VideoWatermarker::count_frames(&output, true).unwrap(),
+            expected
+        );
+
+        // 尾段是直接复制的，解码结果与源视频完全相同
+        assert_eq!(
+            frame_at(&source, 4.0, &dir.join("source.png")),
+            frame_at(&output, 4.0, &dir.join("output.png"))
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keyframe_mode_beats_the_frame_dump_on_quality_and_time() {
+        if !ffmpeg_is_installed() {
+            eprintln!("skipped: ffmpeg is not installed");
+            return;
+        }
+        let dir = scratch_dir("keyframe_vs_dump");
+        let source = dir.join("source.mp4");
+        write_test_video(&source, false);
+        let algorithm = DctWatermark::new();
+
+        let timed = |output: &Path, mode: VideoWatermarkMode, disk_frames: bool| {
+            let started = std::time::Instant::now();
+            VideoWatermarker::embed_watermark_with_options(
+                source.as_path(),
+                output,
+                "key",
+                &algorithm,
+                0.1,
+                mode,
+                VideoEncodeOptions {
+                    disk_frames,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            started.elapsed()
+        };
+        let (keyframe, dump) = (dir.join("keyframe.mp4"), dir.join("dump.mp4"));
+        let keyframe_time = timed(&keyframe, VideoWatermarkMode::Keyframe, false);
+        let dump_time = timed(&dump, VideoWatermarkMode::Video, true);
+
+        // 逐秒取帧与源视频比较，直接复制的帧PSNR为无穷大，按100 dB计
+        let mean_psnr = |output: &Path, name: &str| {
+            let times = [0.5, 1.5, 2.5, 3.5, 4.5];
+            times
+                .iter()
+                .map(|&secs| {
+                    let original = frame_at(&source, secs, &dir.join("source.png"));
+                    let marked = frame_at(output, secs, &dir.join(format!("{name}.png")));
+                    crate::media::ImageWatermarker::calculate_psnr(
+                        &image::DynamicImage::ImageRgb8(original),
+                        &image::DynamicImage::ImageRgb8(marked),
+                    )
+                    .min(100.0)
+                })
+                .sum::<f64>()
+                / times.len() as f64
+        };
+        let (keyframe_psnr, dump_psnr) =
+            (mean_psnr(&keyframe, "keyframe"), mean_psnr(&dump, "dump"));
+        eprintln!(
+            "keyframe: {keyframe_psnr:.1} dB in {keyframe_time:?}, frame dump: {dump_psnr:.1} dB in {dump_time:?}"
+        );
+        assert!(keyframe_psnr > dump_psnr);
+        assert!(keyframe_time < dump_time);
+
+        // 开头片段的帧经滤镜叠加后仍携带水印
+        let first = dir.join("first.png");
+        frame_at(&keyframe, 0.0, &first);
+        let (bytes, _) =
+            crate::media::ImageWatermarker::extract_bytes_with_confidence(&first, &algorithm, 3)
+                .unwrap();
+        assert_eq!(bytes, b"key");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn few_confident_frames_rank_below_many_slightly_less_confident_ones() {
+        let few = VideoWatermarker::sample_adjusted_confidence(0.7, 2);
+        let many = VideoWatermarker::sample_adjusted_confidence(0.65, 15);
+        assert!((few - 0.6).abs() < 1e-12);
+        assert!(few < many);
+        assert_eq!(VideoWatermarker::sample_adjusted_confidence(0.9, 0), 0.0);
+    }
+
+    #[test]
+    fn detection_averages_frame_scores_above_the_threshold_only_when_marked() {
+        if !ffmpeg_is_installed() {
+            eprintln!("skipped: ffmpeg is not installed");
+            return;
+        }
+        let dir = scratch_dir("detect");
+        let (clean, marked) = (dir.join("clean.mp4"), dir.join("marked.mkv"));
+        write_test_video(&clean, false);
+        let algorithm = DctWatermark::new();
+        VideoWatermarker::embed_watermark(
+            &clean,
+            &marked,
+            "seal detection",
+            &algorithm,
+            0.1,
+            true,
+            VideoWatermarkMode::Video,
+        )
+        .unwrap();
+
+        let (clean_score, clean_frames) =
+            VideoWatermarker::detect_watermark(&clean, &algorithm, 5).unwrap();
+        let (marked_score, marked_frames) =
+            VideoWatermarker::detect_watermark(&marked, &algorithm, 5).unwrap();
+        assert_eq!((clean_frames, marked_frames), (5, 5));
+        assert!(clean_score < WatermarkUtils::DETECTION_THRESHOLD);
+        assert!(marked_score > WatermarkUtils::DETECTION_THRESHOLD);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keyframe_mode_rejects_outputs_that_cannot_hold_h264() {
+        if !ffmpeg_is_installed() {
+            eprintln!("skipped: ffmpeg is not installed");
+            return;
+        }
+        let dir = scratch_dir("keyframe_webm");
+        let source = dir.join("source.mp4");
+        write_test_video(&source, false);
+
+        let result = VideoWatermarker::embed_watermark_with_options(
+            &source,
+            &dir.join("output.webm"),
+            "key",
+            &DctWatermark::new(),
+            0.1,
+            VideoWatermarkMode::Keyframe,
+            VideoEncodeOptions::default(),
+        );
+        assert!(matches!(result, Err(WatermarkError::InvalidArgument(_))));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn all_mode_survives_a_replaced_video_stream() {
+        if !ffmpeg_is_installed() {
+            eprintln!("skipped: ffmpeg is not installed");
+            return;
+        }
+        let dir = scratch_dir("all_mode");
+        let (source, marked, replaced) = (
+            dir.join("source.mp4"),
+            dir.join("marked.mp4"),
+            dir.join("replaced.mp4"),
+        );
+        write_test_video(&source, true);
+
+        let algorithm = DctWatermark::new();
+        VideoWatermarker::embed_watermark_with_options(
+            &source,
+            &marked,
+            "all",
+            &algorithm,
+            0.1,
+            VideoWatermarkMode::All,
+            VideoEncodeOptions::default(),
+        )
+        .unwrap();
+
+        // 换上一条没有水印的视频流，音轨和容器元数据原样保留
+        let status = ffmpeg::run(
+            FfmpegCommand::new()
+                .input(marked.to_str().unwrap())
+                .args(["-f", "lavfi"])
+                .input("mandelbrot=size=160x128:rate=25")
+                .args(["-map", "1:v", "-map", "0:a", "-map_metadata", "0"])
+                .args(["-c:v", "libx264", "-c:a", "copy", "-shortest", "-y"])
+                .output(replaced.to_str().unwrap()),
+        )
+        .unwrap();
+        assert!(status.success());
+
+        let extraction = VideoWatermarker::extract_watermark_with_options(
+            &replaced,
+            &algorithm,
+            3,
+            None,
+            None,
+            VideoWatermarkMode::All,
+            VideoExtractOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(extraction.text, "all");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,364 @@
+use crate::error::{Result, WatermarkError};
+use crate::i18n::Message;
+use crate::watermark::prng::SeededRng;
+use crate::watermark::WatermarkAlgorithm;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{ColorType, GrayImage, ImageFormat, Luma};
+use ndarray::Array2;
+use std::f64::consts::PI;
+use std::fmt;
+
+/// 一次攻击及其参数，文本形式为 `名称:参数`，如 `jpeg:50`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Attack {
+    /// JPEG重新压缩，参数为质量 1-100
+    Jpeg(u8),
+    /// 叠加高斯噪声，参数为标准差（与数据同单位，1/255约为0.004）
+    Noise(f64),
+    /// 按比例缩放后再缩放回原尺寸，参数为缩放比例
+    Resize(f64),
+    /// 绕中心旋转，参数为角度（度），尺寸不变
+    Rotate(f64),
+    /// 从右侧和下方各裁掉一定比例，参数为裁掉的比例 0-1
+    Crop(f64),
+}
+
+impl Attack {
+    /// 对数据施加攻击，`seed` 只影响噪声攻击
+    pub fn apply(&self, data: &Array2<f64>, seed: u64) -> Result<Array2<f64>> {
+        match *self {
+            Attack::Jpeg(quality) => jpeg(data, quality),
+            Attack::Noise(sigma) => Ok(gaussian_noise(data, sigma, seed)),
+            Attack::Resize(scale) => resize(data, scale),
+            Attack::Rotate(degrees) => Ok(rotate(data, degrees)),
+            Attack::Crop(fraction) => crop(data, fraction),
+        }
+    }
+
+    /// 解析 `名称:参数` 形式的攻击描述
+    pub fn parse(spec: &str) -> std::result::Result<Self, String> {
+        let spec = spec.trim();
+        let (name, value) = spec
+            .split_once(':')
+            .ok_or_else(|| Message::AttackSpecInvalid.format(&[&spec]))?;
+        let number = value
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .ok_or_else(|| Message::AttackValueInvalid.format(&[&spec]))?;
+
+        match name.trim().to_ascii_lowercase().as_str() {
+            "jpeg" if (1.0..=100.0).contains(&number) && number.fract() == 0.0 => {
+                Ok(Attack::Jpeg(number as u8))
+            }
+            "jpeg" => Err(Message::AttackJpegQuality.format(&[&spec])),
+            "noise" if number >= 0.0 => Ok(Attack::Noise(number)),
+            "noise" => Err(Message::AttackNoiseNegative.format(&[&spec])),
+            "resize" if number > 0.0 => Ok(Attack::Resize(number)),
+            "resize" => Err(Message::AttackResizeScale.format(&[&spec])),
+            "rotate" => Ok(Attack::Rotate(number)),
+            "crop" if (0.0..1.0).contains(&number) => Ok(Attack::Crop(number)),
+            "crop" => Err(Message::AttackCropFraction.format(&[&spec])),
+            _ => Err(Message::AttackUnknown.format(&[&name])),
+        }
+    }
+}
+
+impl fmt::Display for Attack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Attack::Jpeg(quality) => write!(f, "jpeg:{quality}"),
+            Attack::Noise(sigma) => write!(f, "noise:{sigma}"),
+            Attack::Resize(scale) => write!(f, "resize:{scale}"),
+            Attack::Rotate(degrees) => write!(f, "rotate:{degrees}"),
+            Attack::Crop(fraction) => write!(f, "crop:{fraction}"),
+        }
+    }
+}
+
+/// 提取前对文件做的一次预处理，用于抵消传播中常见的缩放、轻微旋转和模糊
+///
+/// 与 `Attack` 相反，预处理不会恢复原尺寸：缩放直接改变数据的尺寸，
+/// 以便与嵌入时的块网格重新对齐。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Recovery {
+    /// 不做处理
+    Identity,
+    /// 缩放到指定的行数和列数
+    Resize { rows: usize, cols: usize },
+    /// 绕中心旋转，参数为角度（度），尺寸不变
+    Rotate(f64),
+    /// 反锐化掩模锐化，抵消缩放和压缩带来的模糊
+    Sharpen,
+}
+
+/// 缩放预处理尝试的常见图片宽度（像素），按原图宽高比计算对应的高度
+const COMMON_WIDTHS: [usize; 16] = [
+    256, 320, 480, 512, 640, 720, 768, 800, 1024, 1080, 1280, 1440, 1920, 2048, 2560, 3840,
+];
+
+/// 缩放预处理尝试的比例，对应文件被缩放到原尺寸的 1/比例
+const RECOVERY_SCALES: [f64; 10] = [2.0, 1.5, 1.25, 1.1, 1.05, 0.95, 0.9, 0.8, 0.75, 0.5];
+
+/// 旋转预处理尝试的角度（度），用于摆正轻微的倾斜
+const RECOVERY_ANGLES: [f64; 6] = [-2.0, -1.0, -0.5, 0.5, 1.0, 2.0];
+
+impl Recovery {
+    /// 对数据做预处理
+    pub fn apply(&self, data: &Array2<f64>) -> Result<Array2<f64>> {
+        match *self {
+            Recovery::Identity => Ok(data.clone()),
+            Recovery::Resize { rows, cols } => resize_to(data, rows, cols),
+            Recovery::Rotate(degrees) => Ok(rotate(data, degrees)),
+            Recovery::Sharpen => Ok(sharpen(data)),
+        }
+    }
+
+    /// 对 `rows` 行 `cols` 列的数据依次尝试的预处理，第一个总是 `Identity`
+    ///
+    /// 缩放候选包括保持宽高比的常见宽度和若干固定比例，目标尺寸不超过原尺寸的两倍，
+    /// 重复的尺寸只保留一个。
+    pub fn candidates(rows: usize, cols: usize) -> Vec<Recovery> {
+        let mut sizes: Vec<(usize, usize)> = Vec::new();
+        let aspect = rows as f64 / cols as f64;
+        let by_width = COMMON_WIDTHS
+            .iter()
+            .map(|&width| (((width as f64 * aspect).round() as usize), width));
+        let by_scale = RECOVERY_SCALES.iter().map(|&scale| {
+            (
+                (rows as f64 * scale).round() as usize,
+                (cols as f64 * scale).round() as usize,
+            )
+        });
+        for size in by_width.chain(by_scale) {
+            let fits = size.0 >= 1 && size.1 >= 1 && size.0 <= rows * 2 && size.1 <= cols * 2;
+            if fits && size != (rows, cols) && !sizes.contains(&size) {
+                sizes.push(size);
+            }
+        }
+
+        std::iter::once(Recovery::Identity)
+            .chain(
+                sizes
+                    .into_iter()
+                    .map(|(rows, cols)| Recovery::Resize { rows, cols }),
+            )
+            .chain(
+                RECOVERY_ANGLES
+                    .iter()
+                    .map(|&degrees| Recovery::Rotate(degrees)),
+            )
+            .chain(std::iter::once(Recovery::Sharpen))
+            .collect()
+    }
+}
+
+impl fmt::Display for Recovery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Recovery::Identity => f.write_str("none"),
+            Recovery::Resize { rows, cols } => write!(f, "resize:{cols}x{rows}"),
+            Recovery::Rotate(degrees) => write!(f, "rotate:{degrees}"),
+            Recovery::Sharpen => f.write_str("sharpen"),
+        }
+    }
+}
+
+/// 按灰度图做一次JPEG编码和解码
+///
+/// 彩色图片实际编码时还会做YCbCr转换和色度抽样，这里只模拟单个通道的量化损失。
+pub fn jpeg(data: &Array2<f64>, quality: u8) -> Result<Array2<f64>> {
+    let image = to_gray(data);
+    let mut encoded = Vec::new();
+    JpegEncoder::new_with_quality(&mut encoded, quality.clamp(1, 100)).encode(
+        image.as_raw(),
+        image.width(),
+        image.height(),
+        ColorType::L8,
+    )?;
+    let decoded = image::load_from_memory_with_format(&encoded, ImageFormat::Jpeg)?.to_luma8();
+    Ok(from_gray(&decoded))
+}
+
+/// 叠加均值为0、标准差为 `sigma` 的高斯噪声，结果限制在 [0.0, 1.0]
+pub fn gaussian_noise(data: &Array2<f64>, sigma: f64, seed: u64) -> Array2<f64> {
+    let mut rng = SeededRng::new(seed);
+    let mut uniform = move || (rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+    data.mapv(|value| {
+        // Box-Muller 变换
+        let u1 = uniform().max(f64::MIN_POSITIVE);
+        let u2 = uniform();
+        let gaussian = (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos();
+        (value + sigma * gaussian).clamp(0.0, 1.0)
+    })
+}
+
+/// 缩放到 `scale` 倍后再用双线性插值缩放回原尺寸，模拟降采样带来的细节损失
+pub fn resize(data: &Array2<f64>, scale: f64) -> Result<Array2<f64>> {
+    let (rows, cols) = data.dim();
+    let scaled = |n: usize| ((n as f64 * scale).round() as u32).max(1);
+    if !(scale.is_finite() && scale > 0.0) {
+        return Err(WatermarkError::InvalidArgument(
+            Message::ResizeScaleInvalid.format(&[&scale]),
+        ));
+    }
+
+    let image = to_gray(data);
+    let small = image::imageops::resize(&image, scaled(cols), scaled(rows), FilterType::Triangle);
+    let restored = image::imageops::resize(&small, cols as u32, rows as u32, FilterType::Triangle);
+    Ok(from_gray(&restored))
+}
+
+/// 用双线性插值缩放到 `rows` 行 `cols` 列
+pub fn resize_to(data: &Array2<f64>, rows: usize, cols: usize) -> Result<Array2<f64>> {
+    if rows == 0 || cols == 0 {
+        return Err(WatermarkError::InvalidArgument(
+            Message::ResizeTargetInvalid.format(&[&cols, &rows]),
+        ));
+    }
+    let image = to_gray(data);
+    let resized = image::imageops::resize(&image, cols as u32, rows as u32, FilterType::Triangle);
+    Ok(from_gray(&resized))
+}
+
+/// 反锐化掩模：加强高于一个像素尺度的细节，结果限制在 [0.0, 1.0]
+pub fn sharpen(data: &Array2<f64>) -> Array2<f64> {
+    from_gray(&image::imageops::unsharpen(&to_gray(data), 1.0, 0))
+}
+
+/// 绕中心旋转 `degrees` 度，双线性插值，超出原图的位置取最近的边缘像素
+pub fn rotate(data: &Array2<f64>, degrees: f64) -> Array2<f64> {
+    let (rows, cols) = data.dim();
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let (cy, cx) = ((rows as f64 - 1.0) / 2.0, (cols as f64 - 1.0) / 2.0);
+    let at = |y: isize, x: isize| {
+        data[[
+            y.clamp(0, rows as isize - 1) as usize,
+            x.clamp(0, cols as isize - 1) as usize,
+        ]]
+    };
+
+    Array2::from_shape_fn((rows, cols), |(y, x)| {
+        // 对输出像素做反向旋转，找到它在原图中的位置
+        let (dy, dx) = (y as f64 - cy, x as f64 - cx);
+        let sy = cy + dy * cos - dx * sin;
+        let sx = cx + dy * sin + dx * cos;
+        let (y0, x0) = (sy.floor(), sx.floor());
+        let (fy, fx) = (sy - y0, sx - x0);
+        let (y0, x0) = (y0 as isize, x0 as isize);
+        at(y0, x0) * (1.0 - fy) * (1.0 - fx)
+            + at(y0, x0 + 1) * (1.0 - fy) * fx
+            + at(y0 + 1, x0) * fy * (1.0 - fx)
+            + at(y0 + 1, x0 + 1) * fy * fx
+    })
+}
+
+/// 从右侧和下方各裁掉 `fraction` 比例的像素，左上角的原点不变，DCT块网格保持对齐
+pub fn crop(data: &Array2<f64>, fraction: f64) -> Result<Array2<f64>> {
+    if !(0.0..1.0).contains(&fraction) {
+        return Err(WatermarkError::InvalidArgument(
+            Message::CropFractionInvalid.format(&[&fraction]),
+        ));
+    }
+    let (rows, cols) = data.dim();
+    let keep = |n: usize| ((n as f64 * (1.0 - fraction)).round() as usize).clamp(1, n);
+    Ok(data
+        .slice(ndarray::s![..keep(rows), ..keep(cols)])
+        .to_owned())
+}
+
+/// 从攻击后的数据中提取 `length` 个比特，返回与 `original_bits` 前 `length` 个比特相比的误码率
+pub fn measure_ber(
+    algorithm: &dyn WatermarkAlgorithm,
+    original_bits: &[u8],
+    attacked_data: &Array2<f64>,
+    length: usize,
+) -> Result<f64> {
+    if length == 0 || length > original_bits.len() {
+        return Err(WatermarkError::InvalidArgument(
+            Message::BerLengthInvalid.format(&[&original_bits.len(), &length]),
+        ));
+    }
+
+    let extracted = algorithm.extract(attacked_data, length)?;
+    let errors = extracted
+        .iter()
+        .zip(&original_bits[..length])
+        .filter(|(a, b)| a != b)
+        .count();
+    Ok(errors as f64 / length as f64)
+}
+
+fn to_gray(data: &Array2<f64>) -> GrayImage {
+    let (rows, cols) = data.dim();
+    GrayImage::from_fn(cols as u32, rows as u32, |x, y| {
+        Luma([(data[[y as usize, x as usize]] * 255.0)
+            .round()
+            .clamp(0.0, 255.0) as u8])
+    })
+}
+
+fn from_gray(image: &GrayImage) -> Array2<f64> {
+    Array2::from_shape_fn(
+        (image.height() as usize, image.width() as usize),
+        |(y, x)| image.get_pixel(x as u32, y as u32)[0] as f64 / 255.0,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::watermark::{DctWatermark, LsbWatermark};
+
+    /// 量化到8位灰度的伪随机纹理，与从图片读入的数据一样
+    fn textured(size: usize) -> Array2<f64> {
+        let mut rng = SeededRng::new(5);
+        Array2::from_shape_fn((size, size), |_| (64 + rng.next_below(128)) as f64 / 255.0)
+    }
+
+    fn ber_after(algorithm: &dyn WatermarkAlgorithm, attack: Attack) -> f64 {
+        let bits: Vec<u8> = (0..128).map(|i| ((i * 7) % 3 == 0) as u8).collect();
+        let marked = algorithm.embed(&textured(256), &bits, 0.5).unwrap();
+        // 先按8位像素保存一次，与实际写出图片一致
+        let saved = from_gray(&to_gray(&marked));
+        assert_eq!(
+            measure_ber(algorithm, &bits, &saved, bits.len()).unwrap(),
+            0.0
+        );
+        let attacked = attack.apply(&saved, 1).unwrap();
+        measure_ber(algorithm, &bits, &attacked, bits.len()).unwrap()
+    }
+
+    #[test]
+    fn dct_survives_light_jpeg_and_lsb_does_not() {
+        assert!(ber_after(&DctWatermark::new(), Attack::Jpeg(90)) < 0.05);
+        assert!(ber_after(&LsbWatermark::new(), Attack::Jpeg(90)) > 0.3);
+    }
+
+    #[test]
+    fn parses_attack_specs() {
+        assert_eq!(Attack::parse("jpeg:50"), Ok(Attack::Jpeg(50)));
+        assert_eq!(Attack::parse(" NOISE : 0.01 "), Ok(Attack::Noise(0.01)));
+        assert_eq!(Attack::parse("resize:0.5"), Ok(Attack::Resize(0.5)));
+        assert_eq!(Attack::parse("rotate:-1.5"), Ok(Attack::Rotate(-1.5)));
+        assert_eq!(Attack::parse("crop:0.1"), Ok(Attack::Crop(0.1)));
+        for spec in [
+            "jpeg",
+            "jpeg:0",
+            "jpeg:50.5",
+            "jpeg:101",
+            "noise:-1",
+            "resize:0",
+            "crop:1",
+            "rotate:nan",
+            "blur:2",
+        ] {
+            assert!(Attack::parse(spec).is_err(), "{spec}");
+        }
+        // 文本形式可以原样解析回来
+        let attack = Attack::Resize(0.75);
+        assert_eq!(Attack::parse(&attack.to_string()), Ok(attack));
+    }
+}
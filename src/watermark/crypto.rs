@@ -0,0 +1,214 @@
+use crate::error::{Result, WatermarkError};
+use crate::i18n::Message;
+use ring::aead::{self, Aad, LessSafeKey, Nonce, UnboundKey};
+use ring::digest;
+use ring::hmac;
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use std::io::Read;
+use std::num::NonZeroU32;
+use std::path::Path;
+
+/// 加密载荷的魔数，首字节 0xFE 不可能出现在合法的UTF-8文本开头
+const MAGIC: [u8; 2] = [0xFE, 0x01];
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = aead::NONCE_LEN;
+const TAG_LEN: usize = 16;
+const PBKDF2_ITERATIONS: u32 = 100_000;
+
+/// 加密带来的额外字节数（魔数 + 盐 + nonce + 认证标签）
+pub const ENCRYPTION_OVERHEAD: usize = MAGIC.len() + SALT_LEN + NONCE_LEN + TAG_LEN;
+
+/// 签名附加在载荷末尾的字节数，HMAC-SHA256 截断到前8字节
+pub const SIGNATURE_LEN: usize = 8;
+
+/// 使用口令加密水印载荷
+///
+/// 输出格式: `魔数(2) | 盐(16) | nonce(12) | 密文 | 认证标签(16)`，
+/// 密钥由 PBKDF2-HMAC-SHA256 从口令和随机盐派生，使用 AES-256-GCM 加密。
+pub fn encrypt_payload(plaintext: &[u8], password: &str) -> Result<Vec<u8>> {
+    let rng = SystemRandom::new();
+
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill(&mut salt)
+        .and_then(|_| rng.fill(&mut nonce))
+        .map_err(|_| WatermarkError::Algorithm(Message::RandomUnavailable.text().to_string()))?;
+
+    let key = derive_key(password, &salt)?;
+    let mut in_out = plaintext.to_vec();
+    key.seal_in_place_append_tag(
+        Nonce::assume_unique_for_key(nonce),
+        Aad::from(MAGIC),
+        &mut in_out,
+    )
+    .map_err(|_| WatermarkError::Algorithm(Message::EncryptionFailed.text().to_string()))?;
+
+    let mut framed = Vec::with_capacity(ENCRYPTION_OVERHEAD + plaintext.len());
+    framed.extend_from_slice(&MAGIC);
+    framed.extend_from_slice(&salt);
+    framed.extend_from_slice(&nonce);
+    framed.extend_from_slice(&in_out);
+    Ok(framed)
+}
+
+/// 使用口令解密水印载荷，口令错误或数据损坏时返回 `DecryptionFailed`
+pub fn decrypt_payload(framed: &[u8], password: &str) -> Result<Vec<u8>> {
+    if !is_encrypted(framed) || framed.len() < ENCRYPTION_OVERHEAD {
+        return Err(WatermarkError::DecryptionFailed);
+    }
+
+    let salt = &framed[MAGIC.len()..MAGIC.len() + SALT_LEN];
+    let nonce_start = MAGIC.len() + SALT_LEN;
+    let mut nonce = [0u8; NONCE_LEN];
+    nonce.copy_from_slice(&framed[nonce_start..nonce_start + NONCE_LEN]);
+
+    let key = derive_key(password, salt)?;
+    let mut in_out = framed[nonce_start + NONCE_LEN..].to_vec();
+    let plaintext = key
+        .open_in_place(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(MAGIC),
+            &mut in_out,
+        )
+        .map_err(|_| WatermarkError::DecryptionFailed)?;
+
+    Ok(plaintext.to_vec())
+}
+
+/// 使用密钥对载荷签名，返回 `载荷 | HMAC-SHA256(密钥, 载荷)的前8字节`
+pub fn sign_payload(payload: &[u8], key: &str) -> Vec<u8> {
+    let mut signed = payload.to_vec();
+    signed.extend_from_slice(&signature(payload, key));
+    signed
+}
+
+/// 校验 `sign_payload` 的结果并去掉签名，密钥错误或载荷被改动时返回 `SignatureInvalid`
+pub fn verify_payload(signed: &[u8], key: &str) -> Result<Vec<u8>> {
+    let Some(split) = signed.len().checked_sub(SIGNATURE_LEN) else {
+        return Err(WatermarkError::SignatureInvalid);
+    };
+    let (payload, tag) = signed.split_at(split);
+
+    // 逐字节异或后再判断，比较时间与签名内容无关
+    let difference = signature(payload, key)
+        .iter()
+        .zip(tag)
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b));
+    if difference != 0 {
+        return Err(WatermarkError::SignatureInvalid);
+    }
+    Ok(payload.to_vec())
+}
+
+fn signature(payload: &[u8], key: &str) -> [u8; SIGNATURE_LEN] {
+    let tag = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key.as_bytes()), payload);
+    let mut truncated = [0u8; SIGNATURE_LEN];
+    truncated.copy_from_slice(&tag.as_ref()[..SIGNATURE_LEN]);
+    truncated
+}
+
+/// 判断载荷是否为加密格式
+pub fn is_encrypted(payload: &[u8]) -> bool {
+    payload.starts_with(&MAGIC)
+}
+
+/// 数据的SHA-256摘要，小写十六进制
+pub fn sha256_hex(data: &[u8]) -> String {
+    to_hex(digest::digest(&digest::SHA256, data).as_ref())
+}
+
+/// 文件内容的SHA-256摘要，小写十六进制，按块读取，不把整个文件载入内存
+pub fn file_sha256_hex(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut context = digest::Context::new(&digest::SHA256);
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        context.update(&buffer[..read]);
+    }
+    Ok(to_hex(context.finish().as_ref()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// 从口令和盐派生AES-256密钥
+fn derive_key(password: &str, salt: &[u8]) -> Result<LessSafeKey> {
+    let mut key_bytes = [0u8; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        NonZeroU32::new(PBKDF2_ITERATIONS).expect("迭代次数非零"),
+        salt,
+        password.as_bytes(),
+        &mut key_bytes,
+    );
+
+    let unbound = UnboundKey::new(&aead::AES_256_GCM, &key_bytes)
+        .map_err(|_| WatermarkError::Algorithm(Message::KeyInvalid.text().to_string()))?;
+    Ok(LessSafeKey::new(unbound))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypted_payload_opens_only_with_the_right_password() {
+        let framed = encrypt_payload("秘密水印".as_bytes(), "correct horse").unwrap();
+        assert!(is_encrypted(&framed));
+        assert_eq!(framed.len(), ENCRYPTION_OVERHEAD + "秘密水印".len());
+        assert_eq!(
+            decrypt_payload(&framed, "correct horse").unwrap(),
+            "秘密水印".as_bytes()
+        );
+
+        assert!(matches!(
+            decrypt_payload(&framed, "wrong horse"),
+            Err(WatermarkError::DecryptionFailed)
+        ));
+
+        // 密文、认证标签或盐中任一字节被改动都无法通过认证
+        for position in [MAGIC.len(), ENCRYPTION_OVERHEAD - TAG_LEN, framed.len() - 1] {
+            let mut tampered = framed.clone();
+            tampered[position] ^= 0x01;
+            assert!(matches!(
+                decrypt_payload(&tampered, "correct horse"),
+                Err(WatermarkError::DecryptionFailed)
+            ));
+        }
+        assert!(matches!(
+            decrypt_payload(&framed[..ENCRYPTION_OVERHEAD - 1], "correct horse"),
+            Err(WatermarkError::DecryptionFailed)
+        ));
+    }
+
+    #[test]
+    fn signature_rejects_wrong_key_and_tampered_payload() {
+        let signed = sign_payload(b"seal", "key");
+        assert_eq!(signed.len(), 4 + SIGNATURE_LEN);
+        assert_eq!(verify_payload(&signed, "key").unwrap(), b"seal");
+
+        assert!(matches!(
+            verify_payload(&signed, "other key"),
+            Err(WatermarkError::SignatureInvalid)
+        ));
+
+        for position in [0, signed.len() - 1] {
+            let mut tampered = signed.clone();
+            tampered[position] ^= 0x01;
+            assert!(matches!(
+                verify_payload(&tampered, "key"),
+                Err(WatermarkError::SignatureInvalid)
+            ));
+        }
+        assert!(matches!(
+            verify_payload(&signed[..SIGNATURE_LEN - 1], "key"),
+            Err(WatermarkError::SignatureInvalid)
+        ));
+    }
+}
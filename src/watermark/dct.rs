@@ -1,11 +1,66 @@
+use crate::cli::{DctMode, FrequencyBand};
 use crate::error::{Result, WatermarkError};
-use crate::watermark::r#trait::WatermarkAlgorithm;
+use crate::i18n::Message;
+use crate::watermark::prng::SeededRng;
+use crate::watermark::r#trait::{StripPlan, WatermarkAlgorithm, WatermarkUtils};
 use ndarray::{s, Array2};
 use rustdct::DctPlanner;
 
+/// 符号嵌入时系数幅度的最低阈值，也用作软判决置信度的满分参考
+const MIN_EMBED_THRESHOLD: f64 = 1.0;
+
+/// 感知加权默认的块方差阈值，空域方差低于此值的平坦块只做一半的修改
+///
+/// 按归一化到 [0, 1] 的像素计，相当于8位灰度下方差10（标准差约3个灰度级）。
+pub const DEFAULT_PERCEPTUAL_THRESHOLD: f64 = 10.0 / (255.0 * 255.0);
+
+/// 扩频模式未设置种子时使用的默认伪随机序列种子
+const DEFAULT_SPREAD_SEED: u64 = 0x5EA1_5EA1_5EA1_5EA1;
+
+/// 分块模式下每个图块附加的CRC-32校验比特数
+const TILE_CRC_BITS: usize = 32;
+
+/// 全图模式承载水印的频带内径（全图DCT系数下标 `sqrt(u^2 + v^2)`）
+///
+/// 缩放后同一下标对应的仍是整幅图像上周期数相同的纹理，系数下标因此不随尺寸改变。
+const GLOBAL_BAND_LOW: f64 = 16.0;
+/// 全图模式承载水印的频带外径，更高的下标在小尺寸图像上接近高频，容易被缩放抹掉
+const GLOBAL_BAND_HIGH: f64 = 40.0;
+/// 全图模式要求的最小边长，保证频带在缩小后的图像中仍处于中低频
+const GLOBAL_MIN_SIZE: usize = 128;
+/// 全图模式强度为0时系数的最低幅度，以空域余弦纹理的振幅（像素值）表示
+const GLOBAL_MIN_AMPLITUDE: f64 = 0.001;
+/// 全图模式强度从0到1时最低幅度的增量
+const GLOBAL_AMPLITUDE_RANGE: f64 = 0.004;
+
+/// 单个块的软判决：(块坐标 (行, 列), (比特, 置信度))
+type BlockDecision = ((usize, usize), (u8, f64));
+
+/// 差分模式的一对系数位置
+type CoefficientPair = ((usize, usize), (usize, usize));
+
+/// 分块嵌入和提取共用的系数参数
+struct BlockCoefficients {
+    /// 第 i 个比特使用第 `i % positions.len()` 个系数位置
+    positions: Vec<(usize, usize)>,
+    /// 差分模式的系数对，未启用时为空
+    pairs: Vec<CoefficientPair>,
+    /// 按块大小放大后的QIM步长
+    qim_step: Option<f64>,
+}
+
 /// DCT水印算法实现 - 使用rustdct库
 pub struct DctWatermark {
     block_size: usize,
+    seed: Option<u64>,
+    positions: Option<Vec<(usize, usize)>>,
+    spread_chips: Option<usize>,
+    tile_size: Option<usize>,
+    differential: bool,
+    qim_step: Option<f64>,
+    mode: DctMode,
+    band: FrequencyBand,
+    perceptual_threshold: f64,
     dct2_planner: DctPlanner<f64>,
     dct3_planner: DctPlanner<f64>,
 }
@@ -15,6 +70,15 @@ impl DctWatermark {
     pub fn new() -> Self {
         Self {
             block_size: 8,
+            seed: None,
+            positions: None,
+            spread_chips: None,
+            tile_size: None,
+            differential: false,
+            qim_step: None,
+            mode: DctMode::Block,
+            band: FrequencyBand::Mid,
+            perceptual_threshold: DEFAULT_PERCEPTUAL_THRESHOLD,
             dct2_planner: DctPlanner::new(),
             dct3_planner: DctPlanner::new(),
         }
@@ -26,6 +90,211 @@ impl DctWatermark {
         self
     }
 
+    /// 设置块置换种子
+    ///
+    /// 设置后水印比特按种子生成的置换写入各块，而不是按光栅顺序写入；
+    /// 提取时必须使用相同的种子，否则得到的只是噪声。
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// 使用自定义的DCT系数位置列表，完全替代默认的中频位置
+    ///
+    /// 第 i 个比特写入第 `i % positions.len()` 个位置，提取时必须使用相同的列表。
+    pub fn with_positions(mut self, positions: Vec<(usize, usize)>) -> Self {
+        self.positions = Some(positions);
+        self
+    }
+
+    /// 启用扩频嵌入模式，每个比特调制到 `chips` 个中频系数上
+    ///
+    /// 每个比特对应一段由种子生成的 ±1 伪随机序列，分散在多个块的中频系数中，
+    /// 提取时通过相关运算判决，相关值可作为该比特的置信度。
+    /// 容量降为原来的 `1/chips`（按系数位置数折算），换取对噪声和压缩的鲁棒性。
+    pub fn with_spread_spectrum(mut self, chips: usize) -> Self {
+        self.spread_chips = Some(chips);
+        self
+    }
+
+    /// 启用抗裁剪的分块模式，在每个 `tile_size`x`tile_size` 的完整图块中独立嵌入一份载荷
+    ///
+    /// 每份载荷后附加CRC-32校验。提取时在所有块对齐方式和图块偏移上搜索，
+    /// 返回第一个通过校验的图块，因此裁剪后只要保留一个完整图块即可恢复水印。
+    /// 图块边长必须是块大小的倍数，且不能与扩频模式同时使用。
+    pub fn with_tiles(mut self, tile_size: usize) -> Self {
+        self.tile_size = Some(tile_size);
+        self
+    }
+
+    /// 启用差分嵌入模式，比特由块内一对中频系数的大小关系表示，而不是单个系数的符号
+    ///
+    /// 系数位置列表中相邻的两个位置组成一对，第 i 个比特使用第 `i % 对数` 对：
+    /// 比特1令前一个系数大于后一个，比特0相反。整体的亮度和对比度变化对两个系数的影响相同，
+    /// 大小关系因此保持不变。不能与扩频模式同时使用，提取时必须一致。
+    pub fn with_differential(mut self, differential: bool) -> Self {
+        self.differential = differential;
+        self
+    }
+
+    /// 启用量化索引调制（QIM）嵌入，替代默认的条件符号嵌入
+    ///
+    /// 承载比特的系数量化到 `step` 的整数倍，倍数为偶数表示比特0、奇数表示比特1；
+    /// 提取时取最近的格点按奇偶判决，幅度小于 `step/2` 的扰动不会改变结果。
+    /// 步长越大越稳健、失真也越大，以8x8块的系数为单位，大块按块边长等比放大。
+    /// 启用后嵌入强度不再起作用，不能与扩频、差分或全图模式同时使用，提取时必须使用相同的步长。
+    pub fn with_qim(mut self, step: f64) -> Self {
+        self.qim_step = Some(step);
+        self
+    }
+
+    /// 选择分块DCT或全图DCT
+    ///
+    /// 全图模式对整幅数据做一次DCT，在固定下标的中低频环带内逐个系数按符号嵌入，
+    /// 没有块边界可供攻击，缩放后也能提取，但容量只有约一千比特，与自定义位置、分块、
+    /// 扩频和差分模式互斥，提取时必须一致。
+    pub fn with_mode(mut self, mode: DctMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// 选择默认系数位置所在的频带，设置了自定义位置时不起作用
+    ///
+    /// 各频带的系数个数相同，容量不变：低频抗模糊和缩放但修改更明显，
+    /// 高频最不易察觉但容易被模糊和有损压缩抹掉。提取时必须使用相同的频带。
+    pub fn with_band(mut self, band: FrequencyBand) -> Self {
+        self.band = band;
+        self
+    }
+
+    /// 设置感知加权的块方差阈值，方差低于阈值的平坦块只做一半的修改，0 表示不区分
+    ///
+    /// 阈值与输入数据同一尺度：图片像素归一化到 [0, 1]，音频样本在 [-1, 1]。
+    /// 只影响符号嵌入和差分模式的修改量，提取时无需一致。
+    pub fn with_perceptual_threshold(mut self, threshold: f64) -> Self {
+        self.perceptual_threshold = threshold;
+        self
+    }
+
+    /// 差分模式使用的系数对，相邻两个位置为一对，列表长度为奇数时最后一个位置不使用
+    fn coefficient_pairs(&self) -> Result<Vec<CoefficientPair>> {
+        if self.spread_chips.is_some() {
+            return Err(WatermarkError::InvalidArgument(
+                Message::DifferentialWithSpread.text().to_string(),
+            ));
+        }
+        let pairs: Vec<_> = self
+            .get_mid_frequency_positions()
+            .chunks_exact(2)
+            .map(|pair| (pair[0], pair[1]))
+            .collect();
+        if pairs.is_empty() {
+            return Err(WatermarkError::InvalidArgument(
+                Message::DifferentialNeedsTwoPositions.text().to_string(),
+            ));
+        }
+        Ok(pairs)
+    }
+
+    /// 调整一对系数，使两者之差的符号表示比特，差值不小于阈值，平均值保持不变
+    fn embed_differential_bit(
+        &self,
+        dct_block: &mut Array2<f64>,
+        (first, second): CoefficientPair,
+        bit: u8,
+        strength: f64,
+        perceptual_weight: f64,
+    ) {
+        let (a, b) = (dct_block[first], dct_block[second]);
+        let mean = (a + b) / 2.0;
+        let difference = if bit == 1 { a - b } else { b - a };
+        let adaptive_threshold = self.calculate_adaptive_threshold(dct_block, strength);
+        let target_change = strength * difference.abs().max(1.0) * perceptual_weight;
+
+        // 与符号嵌入相同：大小关系已满足时温和拉开，否则强制到阈值以上
+        let difference = if difference + target_change >= adaptive_threshold {
+            difference + target_change
+        } else {
+            difference.abs().max(adaptive_threshold) + target_change * 0.5
+        };
+        let (high, low) = (mean + difference / 2.0, mean - difference / 2.0);
+        let (a, b) = if bit == 1 { (high, low) } else { (low, high) };
+        dct_block[first] = a;
+        dct_block[second] = b;
+    }
+
+    /// 校验QIM步长并返回按块大小放大后的实际步长，未启用时返回 `None`
+    fn qim_lattice_step(&self) -> Result<Option<f64>> {
+        let Some(step) = self.qim_step else {
+            return Ok(None);
+        };
+        if !(step.is_finite() && step > 0.0) {
+            return Err(WatermarkError::InvalidArgument(
+                Message::QimStepInvalid.format(&[&step]),
+            ));
+        }
+        if self.spread_chips.is_some() || self.differential {
+            return Err(WatermarkError::InvalidArgument(
+                Message::QimWithOtherModes.text().to_string(),
+            ));
+        }
+        Ok(Some(step * self.threshold_scale()))
+    }
+
+    /// 把系数量化到奇偶性与比特一致的最近格点
+    fn qim_quantize(coeff: f64, bit: u8, step: f64) -> f64 {
+        let scaled = coeff / step;
+        let index = scaled.round();
+        let index = if (index as i64).rem_euclid(2) == i64::from(bit) {
+            index
+        } else if scaled >= index {
+            index + 1.0
+        } else {
+            index - 1.0
+        };
+        index * step
+    }
+
+    /// 按最近格点的奇偶性判决比特，离格点越近置信度越高，落在两格点正中时为0
+    fn qim_decide(coeff: f64, step: f64) -> (u8, f64) {
+        let scaled = coeff / step;
+        let index = scaled.round();
+        let bit = (index as i64).rem_euclid(2) as u8;
+        (bit, 1.0 - 2.0 * (scaled - index).abs())
+    }
+
+    /// 检查系数位置列表非空且每个位置都落在块内
+    fn validate_positions(&self) -> Result<()> {
+        let positions = self.get_mid_frequency_positions();
+        if positions.is_empty() {
+            return Err(WatermarkError::InvalidArgument(
+                Message::PositionsEmpty.text().to_string(),
+            ));
+        }
+        if let Some(&(u, v)) = positions
+            .iter()
+            .find(|&&(u, v)| u >= self.block_size || v >= self.block_size)
+        {
+            return Err(WatermarkError::InvalidArgument(
+                Message::PositionOutOfBlock.format(&[&u, &v, &self.block_size, &self.block_size]),
+            ));
+        }
+        Ok(())
+    }
+
+    /// 生成承载水印比特的块顺序，第 i 个比特写入返回列表中的第 i 个块
+    fn block_order(&self, blocks_h: usize, blocks_w: usize) -> Vec<(usize, usize)> {
+        let mut order: Vec<(usize, usize)> = (0..blocks_h)
+            .flat_map(|block_y| (0..blocks_w).map(move |block_x| (block_y, block_x)))
+            .collect();
+
+        if let Some(seed) = self.seed {
+            SeededRng::new(seed).shuffle(&mut order);
+        }
+
+        order
+    }
+
     /// 将图像填充到块大小的倍数
     fn pad_to_block_size(&self, data: &Array2<f64>) -> Array2<f64> {
         let (height, width) = data.dim();
@@ -77,6 +346,23 @@ impl DctWatermark {
             .to_owned()
     }
 
+    /// 逐块做2D DCT，返回与 `data` 同尺寸的系数数组
+    ///
+    /// 每个块的系数放在该块原来的位置上，尾部不足一块的行和列为0。
+    pub fn blockwise_dct(&mut self, data: &Array2<f64>) -> Array2<f64> {
+        let n = self.block_size;
+        let (rows, cols) = data.dim();
+        let mut coefficients = Array2::zeros((rows, cols));
+        for by in 0..rows / n {
+            for bx in 0..cols / n {
+                let region = s![by * n..(by + 1) * n, bx * n..(bx + 1) * n];
+                let dct_block = self.dct_2d(&data.slice(region).to_owned());
+                coefficients.slice_mut(region).assign(&dct_block);
+            }
+        }
+        coefficients
+    }
+
     /// 执行2D DCT变换
     fn dct_2d(&mut self, block: &Array2<f64>) -> Array2<f64> {
         let (rows, cols) = block.dim();
@@ -134,35 +420,60 @@ impl DctWatermark {
             }
         }
 
-        // DCT-III需要除以2N来得到正确的逆变换
-        result.mapv(|x| x / (2.0 * cols as f64))
+        // 未归一化的DCT-II与DCT-III相继作用于长度N的序列会放大N/2倍，二维共放大 rows*cols/4 倍
+        result.mapv(|x| x * 4.0 / (rows * cols) as f64)
     }
 
-    /// 获取中频DCT系数的位置（适合嵌入水印）
+    /// 获取承载水印的DCT系数位置，默认为中频，设置了自定义位置时返回自定义列表
     fn get_mid_frequency_positions(&self) -> Vec<(usize, usize)> {
-        // 选择中频系数位置，避免低频（视觉重要）和高频（容易被压缩丢失）
-        vec![
-            (2, 1),
-            (1, 2),
-            (3, 1),
-            (2, 2),
-            (1, 3),
-            (4, 1),
-            (3, 2),
-            (2, 3),
-            (1, 4),
-            (5, 1),
-            (4, 2),
-            (3, 3),
-            (2, 4),
-            (1, 5),
-            (6, 1),
-            (5, 2),
-            (4, 3),
-            (3, 4),
-            (2, 5),
-            (1, 6),
-        ]
+        if let Some(positions) = &self.positions {
+            return positions.clone();
+        }
+
+        // 默认选择中频系数位置，避免低频（视觉重要）和高频（容易被压缩丢失）：
+        // 按反对角线 u+v 从低到高遍历中间频段，跳过 u=0 或 v=0 的纯水平/垂直频率，
+        // 同一条对角线上 u 从大到小排列。8x8块时为对角线3-7，与早期使用的固定位置表完全一致
+        let n = self.block_size;
+        let mid_first = (3 * n / 8).max(2);
+        let mid_last = 7 * n / 8;
+        let count = Self::diagonal_positions(n, mid_first..=mid_last).count();
+
+        // 低频从对角线2开始、高频从中频之后开始按同样的顺序取相同个数的位置
+        let first_diagonal = match self.band {
+            FrequencyBand::Low => 2,
+            FrequencyBand::Mid => mid_first,
+            FrequencyBand::High => mid_last + 1,
+        };
+        Self::diagonal_positions(n, first_diagonal..=2 * n)
+            .take(count)
+            .collect()
+    }
+
+    /// 按反对角线依次列出 `diagonals` 上 u、v 均不为0的系数位置，同一条对角线上 u 从大到小
+    fn diagonal_positions(
+        n: usize,
+        diagonals: std::ops::RangeInclusive<usize>,
+    ) -> impl Iterator<Item = (usize, usize)> {
+        diagonals
+            .flat_map(|d| (1..d).rev().map(move |u| (u, d - u)))
+            .filter(move |&(u, v)| u < n && v < n)
+    }
+
+    /// 系数幅度阈值相对8x8块的缩放倍数
+    ///
+    /// 未归一化DCT的系数幅度以及像素取整带来的系数误差都随块边长线性增长，
+    /// 阈值按同样比例放大，大块才不会被取整误差翻转符号。
+    fn threshold_scale(&self) -> f64 {
+        self.block_size as f64 / 8.0
+    }
+
+    /// 感知加权：方差低于阈值的平坦块修改更容易被看出，只用一半的修改量
+    fn perceptual_weight(&self, block: &Array2<f64>) -> f64 {
+        if self.calculate_block_variance(block) < self.perceptual_threshold {
+            0.5
+        } else {
+            1.0
+        }
     }
 
     /// 计算块的方差用于感知加权
@@ -189,7 +500,8 @@ impl DctWatermark {
         }
 
         let mean_coeff = coeffs.iter().sum::<f64>() / coeffs.len() as f64;
-        (mean_coeff * base_strength * 0.1).clamp(1.0, 5.0)
+        let scale = self.threshold_scale();
+        (mean_coeff * base_strength * 0.1).clamp(MIN_EMBED_THRESHOLD * scale, 5.0 * scale)
     }
 }
 
@@ -201,6 +513,17 @@ impl Default for DctWatermark {
 
 impl WatermarkAlgorithm for DctWatermark {
     fn embed(&self, data: &Array2<f64>, watermark: &[u8], strength: f64) -> Result<Array2<f64>> {
+        self.qim_lattice_step()?;
+        if self.mode == DctMode::Global {
+            return self.embed_global(data, watermark, strength);
+        }
+        if let Some(tile_size) = self.tile_size {
+            return self.embed_tiled(data, watermark, strength, tile_size);
+        }
+        if let Some(chips) = self.spread_chips {
+            return self.embed_spread_spectrum(data, watermark, strength, chips);
+        }
+
         let original_height = data.nrows();
         let original_width = data.ncols();
 
@@ -213,99 +536,296 @@ impl WatermarkAlgorithm for DctWatermark {
         let blocks_w = width / self.block_size;
         let total_blocks = blocks_h * blocks_w;
 
-        if watermark.len() > total_blocks {
-            return Err(WatermarkError::InvalidArgument(format!(
-                "水印数据太长，超过了可嵌入的块数。最大可嵌入{}比特，实际需要{}比特",
-                total_blocks,
-                watermark.len()
-            )));
-        }
+        WatermarkUtils::ensure_capacity(watermark.len(), total_blocks)?;
 
-        let positions = self.get_mid_frequency_positions();
-        let mut watermark_idx = 0;
+        let coefficients = self.block_coefficients()?;
         let mut dct_algorithm = DctWatermark::new();
 
-        for block_y in 0..blocks_h {
-            for block_x in 0..blocks_w {
-                if watermark_idx >= watermark.len() {
-                    break;
-                }
+        let block_order = self.block_order(blocks_h, blocks_w);
+        for (watermark_idx, &(block_y, block_x)) in
+            block_order.iter().take(watermark.len()).enumerate()
+        {
+            // 提取当前块
+            let start_y = block_y * self.block_size;
+            let start_x = block_x * self.block_size;
+            let end_y = start_y + self.block_size;
+            let end_x = start_x + self.block_size;
+
+            let block = padded_data
+                .slice(s![start_y..end_y, start_x..end_x])
+                .to_owned();
+            let watermarked_block = self.embed_block(
+                &mut dct_algorithm,
+                &coefficients,
+                &block,
+                watermark_idx,
+                watermark[watermark_idx],
+                strength,
+            );
+
+            // 将修改后的块写回结果
+            result
+                .slice_mut(s![start_y..end_y, start_x..end_x])
+                .assign(&watermarked_block);
+        }
+
+        // 移除填充，返回原始尺寸
+        let final_result = self.unpad_from_block_size(&result, original_height, original_width);
+        Ok(final_result)
+    }
+
+    fn extract(&self, data: &Array2<f64>, expected_length: usize) -> Result<Vec<u8>> {
+        let decisions = self.extract_soft(data, expected_length)?;
+        Ok(decisions.into_iter().map(|(bit, _)| bit).collect())
+    }
+
+    fn extract_soft(&self, data: &Array2<f64>, expected_length: usize) -> Result<Vec<(u8, f64)>> {
+        self.qim_lattice_step()?;
+        if self.mode == DctMode::Global {
+            return self.extract_global(data, expected_length);
+        }
+        if let Some(tile_size) = self.tile_size {
+            return self.extract_tiled(data, expected_length, tile_size);
+        }
+        if self.spread_chips.is_some() {
+            return self.extract_spread_spectrum(data, expected_length);
+        }
+
+        let (_, decisions) = self.block_decisions(data, expected_length)?;
+        Ok(decisions
+            .into_iter()
+            .map(|(_, decision)| decision)
+            .collect())
+    }
+
+    fn extract_audio_soft(
+        &self,
+        data: &Array2<f64>,
+        expected_length: usize,
+    ) -> Result<Vec<(u8, f64)>> {
+        self.extract_soft(data, expected_length)
+    }
+
+    fn block_confidence(&self, data: &Array2<f64>, expected_length: usize) -> Result<Array2<f64>> {
+        if self.tile_size.is_some() || self.spread_chips.is_some() || self.mode == DctMode::Global {
+            return Err(WatermarkError::InvalidArgument(
+                Message::ConfidenceMapModeUnsupported.text().to_string(),
+            ));
+        }
+
+        // QIM的判决余量已经是相对量化步长的置信度；其余方式的余量是系数幅度，
+        // 以所有承载块余量的中位数近似实际的嵌入幅度，只要涂改的块不超过一半，中位数就落在完好的块上
+        let (grid, margins) = self.block_margins(data, expected_length)?;
+        let reference = if self.qim_lattice_step()?.is_some() {
+            1.0
+        } else {
+            let mut sorted: Vec<f64> = margins.iter().map(|&(_, (_, margin))| margin).collect();
+            sorted.sort_by(f64::total_cmp);
+            sorted.get(sorted.len() / 2).copied().unwrap_or(1.0)
+        };
+        let mut map = Array2::from_elem(grid, f64::NAN);
+        for ((block_y, block_x), (_, margin)) in margins {
+            map[[block_y, block_x]] = if reference > 0.0 {
+                (margin / reference).min(1.0)
+            } else {
+                0.0
+            };
+        }
+        Ok(map)
+    }
+
+    fn strip_plan(&self, rows: usize, cols: usize, bits: usize) -> Result<StripPlan> {
+        if self.tile_size.is_some() || self.spread_chips.is_some() || self.mode == DctMode::Global {
+            return Err(WatermarkError::InvalidArgument(
+                Message::StripModeUnsupported.text().to_string(),
+            ));
+        }
+        self.qim_lattice_step()?;
+        self.block_coefficients()?;
+
+        let grid = (
+            rows.div_ceil(self.block_size),
+            cols.div_ceil(self.block_size),
+        );
+        let total_blocks = grid.0 * grid.1;
+        WatermarkUtils::ensure_capacity(bits, total_blocks)?;
+
+        let mut bit_index = vec![None; total_blocks];
+        for (bit_idx, (block_y, block_x)) in self
+            .block_order(grid.0, grid.1)
+            .into_iter()
+            .take(bits)
+            .enumerate()
+        {
+            bit_index[block_y * grid.1 + block_x] = Some(bit_idx);
+        }
+        Ok(StripPlan {
+            block_size: self.block_size,
+            grid,
+            bit_index,
+            bits,
+        })
+    }
+
+    fn embed_strip(
+        &self,
+        plan: &StripPlan,
+        strip: &Array2<f64>,
+        first_block_row: usize,
+        watermark: &[u8],
+        strength: f64,
+    ) -> Result<Array2<f64>> {
+        if watermark.len() != plan.bits {
+            return Err(WatermarkError::InvalidArgument(
+                Message::StripPlanBitsMismatch.format(&[&plan.bits, &watermark.len()]),
+            ));
+        }
+
+        let (original_height, original_width) = strip.dim();
+        let padded_data = self.pad_to_block_size(strip);
+        let mut result = padded_data.clone();
+        let coefficients = self.block_coefficients()?;
+        let mut dct_algorithm = DctWatermark::new();
 
-                // 提取当前块
+        for block_y in 0..padded_data.nrows() / self.block_size {
+            for block_x in 0..plan.grid.1 {
+                let Some(watermark_idx) = plan.bit_at(first_block_row + block_y, block_x) else {
+                    continue;
+                };
                 let start_y = block_y * self.block_size;
                 let start_x = block_x * self.block_size;
-                let end_y = start_y + self.block_size;
-                let end_x = start_x + self.block_size;
+                let region = s![
+                    start_y..start_y + self.block_size,
+                    start_x..start_x + self.block_size
+                ];
+                let block = padded_data.slice(region).to_owned();
+                let watermarked_block = self.embed_block(
+                    &mut dct_algorithm,
+                    &coefficients,
+                    &block,
+                    watermark_idx,
+                    watermark[watermark_idx],
+                    strength,
+                );
+                result.slice_mut(region).assign(&watermarked_block);
+            }
+        }
 
+        Ok(self.unpad_from_block_size(&result, original_height, original_width))
+    }
+
+    fn extract_strip_soft(
+        &self,
+        plan: &StripPlan,
+        strip: &Array2<f64>,
+        first_block_row: usize,
+    ) -> Result<Vec<(usize, (u8, f64))>> {
+        let padded_data = self.pad_to_block_size(strip);
+        let coefficients = self.block_coefficients()?;
+        let mut dct_algorithm = DctWatermark::new();
+        let mut decisions = Vec::new();
+
+        for block_y in 0..padded_data.nrows() / self.block_size {
+            for block_x in 0..plan.grid.1 {
+                let Some(bit_idx) = plan.bit_at(first_block_row + block_y, block_x) else {
+                    continue;
+                };
+                let start_y = block_y * self.block_size;
+                let start_x = block_x * self.block_size;
                 let block = padded_data
-                    .slice(s![start_y..end_y, start_x..end_x])
+                    .slice(s![
+                        start_y..start_y + self.block_size,
+                        start_x..start_x + self.block_size
+                    ])
                     .to_owned();
+                let decision =
+                    self.decide_block(&mut dct_algorithm, &coefficients, &block, bit_idx);
+                decisions.push((bit_idx, decision));
+            }
+        }
+        Ok(decisions)
+    }
 
-                // 执行DCT
-                let mut dct_block = dct_algorithm.dct_2d(&block);
-
-                // 嵌入水印比特
-                let bit = watermark[watermark_idx];
-                let pos_idx = watermark_idx % positions.len();
-                let (u, v) = positions[pos_idx];
-
-                if u < self.block_size && v < self.block_size {
-                    // 条件符号嵌入法：智能选择温和调整或符号强制
-                    let coeff = dct_block[[u, v]];
-                    let magnitude = coeff.abs();
-
-                    // 计算自适应阈值和感知加权
-                    let adaptive_threshold =
-                        self.calculate_adaptive_threshold(&dct_block, strength);
-                    let block_variance = self.calculate_block_variance(&block);
-                    let perceptual_weight = if block_variance < 10.0 { 0.5 } else { 1.0 };
-
-                    let target_change = strength * magnitude.max(1.0) * perceptual_weight;
-
-                    if bit == 1 {
-                        // 目标：确保系数为正且足够大
-                        if coeff + target_change >= adaptive_threshold {
-                            // 温和增加就足够了，保持原有符号特性
-                            dct_block[[u, v]] = coeff + target_change;
-                        } else {
-                            // 需要符号强制，但使用最小必要强度
-                            dct_block[[u, v]] =
-                                magnitude.max(adaptive_threshold) + target_change * 0.5;
-                        }
-                    } else {
-                        // 目标：确保系数为负且绝对值够大
-                        if coeff - target_change <= -adaptive_threshold {
-                            // 温和减少就足够了，保持原有符号特性
-                            dct_block[[u, v]] = coeff - target_change;
-                        } else {
-                            // 需要符号强制，但使用最小必要强度
-                            dct_block[[u, v]] =
-                                -(magnitude.max(adaptive_threshold) + target_change * 0.5);
-                        }
-                    }
-                }
+    fn embed_audio(
+        &self,
+        data: &Array2<f64>,
+        watermark: &[u8],
+        strength: f64,
+    ) -> Result<Array2<f64>> {
+        // 扩频、差分、QIM和全图模式的提取与图片相同，嵌入也走同一路径
+        if self.spread_chips.is_some()
+            || self.differential
+            || self.qim_step.is_some()
+            || self.mode == DctMode::Global
+        {
+            return self.embed(data, watermark, strength);
+        }
+        self.embed_audio_optimized(data, watermark, strength)
+    }
 
-                // 执行逆DCT
-                let watermarked_block = dct_algorithm.idct_2d(&dct_block);
+    fn capacity(&self, rows: usize, cols: usize) -> usize {
+        if self.mode == DctMode::Global {
+            return self.global_positions(rows, cols).len();
+        }
+        if let Some(tile_size) = self.tile_size {
+            if rows < tile_size || cols < tile_size {
+                return 0;
+            }
+            return (tile_size / self.block_size)
+                .pow(2)
+                .saturating_sub(TILE_CRC_BITS);
+        }
 
-                // 将修改后的块写回结果
-                result
-                    .slice_mut(s![start_y..end_y, start_x..end_x])
-                    .assign(&watermarked_block);
+        let blocks = rows.div_ceil(self.block_size) * cols.div_ceil(self.block_size);
+        match self.spread_chips {
+            Some(chips) if chips > 0 => blocks * self.get_mid_frequency_positions().len() / chips,
+            _ => blocks,
+        }
+    }
 
-                watermark_idx += 1;
-            }
-            if watermark_idx >= watermark.len() {
-                break;
-            }
+    /// 分块模式按块的边长对齐，全图模式对尺寸没有要求
+    fn alignment(&self) -> usize {
+        match self.mode {
+            DctMode::Block => self.block_size,
+            DctMode::Global => 1,
         }
+    }
 
-        // 移除填充，返回原始尺寸
-        let final_result = self.unpad_from_block_size(&result, original_height, original_width);
-        Ok(final_result)
+    fn name(&self) -> &'static str {
+        "DCT"
     }
+}
 
-    fn extract(&self, data: &Array2<f64>, expected_length: usize) -> Result<Vec<u8>> {
+impl DctWatermark {
+    /// 逐块读取软判决，同时返回块网格尺寸和每个判决所在的块坐标
+    fn block_decisions(
+        &self,
+        data: &Array2<f64>,
+        expected_length: usize,
+    ) -> Result<((usize, usize), Vec<BlockDecision>)> {
+        let threshold = MIN_EMBED_THRESHOLD * self.threshold_scale();
+        let qim = self.qim_lattice_step()?.is_some();
+        let (grid, margins) = self.block_margins(data, expected_length)?;
+        let decisions = margins
+            .into_iter()
+            .map(|(block, (bit, margin))| {
+                let confidence = if qim {
+                    margin
+                } else {
+                    (margin / threshold).min(1.0)
+                };
+                (block, (bit, confidence))
+            })
+            .collect();
+        Ok((grid, decisions))
+    }
+
+    /// 逐块读取比特和未归一化的判决余量，见 `block_margin`
+    fn block_margins(
+        &self,
+        data: &Array2<f64>,
+        expected_length: usize,
+    ) -> Result<((usize, usize), Vec<BlockDecision>)> {
         // 填充到块大小的倍数
         let padded_data = self.pad_to_block_size(data);
         let (height, width) = padded_data.dim();
@@ -315,55 +835,166 @@ impl WatermarkAlgorithm for DctWatermark {
         let total_blocks = blocks_h * blocks_w;
 
         if expected_length > total_blocks {
-            return Err(WatermarkError::InvalidArgument(format!(
-                "期望长度{expected_length}超过了可提取的块数{total_blocks}"
-            )));
+            return Err(WatermarkError::InvalidArgument(
+                Message::ExpectedExceedsBlocks.format(&[&expected_length, &total_blocks]),
+            ));
         }
 
-        let positions = self.get_mid_frequency_positions();
-        let mut extracted_bits = Vec::new();
+        let coefficients = self.block_coefficients()?;
+        let mut decisions = Vec::new();
         let mut dct_algorithm = DctWatermark::new();
 
-        for block_y in 0..blocks_h {
-            for block_x in 0..blocks_w {
-                if extracted_bits.len() >= expected_length {
-                    break;
-                }
-
-                // 提取当前块
-                let start_y = block_y * self.block_size;
-                let start_x = block_x * self.block_size;
-                let end_y = start_y + self.block_size;
-                let end_x = start_x + self.block_size;
-
-                let block = padded_data
-                    .slice(s![start_y..end_y, start_x..end_x])
-                    .to_owned();
+        let block_order = self.block_order(blocks_h, blocks_w);
+        for &(block_y, block_x) in block_order.iter().take(expected_length) {
+            // 提取当前块
+            let start_y = block_y * self.block_size;
+            let start_x = block_x * self.block_size;
+            let end_y = start_y + self.block_size;
+            let end_x = start_x + self.block_size;
+
+            let block = padded_data
+                .slice(s![start_y..end_y, start_x..end_x])
+                .to_owned();
+            let margin =
+                self.block_margin(&mut dct_algorithm, &coefficients, &block, decisions.len());
+            decisions.push(((block_y, block_x), margin));
+        }
 
-                // 执行DCT
-                let dct_block = dct_algorithm.dct_2d(&block);
+        Ok(((blocks_h, blocks_w), decisions))
+    }
 
-                // 提取水印比特
-                let pos_idx = extracted_bits.len() % positions.len();
-                let (u, v) = positions[pos_idx];
+    /// 校验系数位置、差分和QIM参数，得到分块嵌入和提取共用的系数参数
+    fn block_coefficients(&self) -> Result<BlockCoefficients> {
+        self.validate_positions()?;
+        Ok(BlockCoefficients {
+            positions: self.get_mid_frequency_positions(),
+            pairs: if self.differential {
+                self.coefficient_pairs()?
+            } else {
+                Vec::new()
+            },
+            qim_step: self.qim_lattice_step()?,
+        })
+    }
 
-                if u < self.block_size && v < self.block_size {
-                    // 根据DCT系数的符号确定比特值
-                    let bit = if dct_block[[u, v]] >= 0.0 { 1 } else { 0 };
-                    extracted_bits.push(bit);
+    /// 在单个块中嵌入第 `watermark_idx` 个比特，返回嵌入后的空域块
+    fn embed_block(
+        &self,
+        dct_algorithm: &mut DctWatermark,
+        coefficients: &BlockCoefficients,
+        block: &Array2<f64>,
+        watermark_idx: usize,
+        bit: u8,
+        strength: f64,
+    ) -> Array2<f64> {
+        let BlockCoefficients {
+            positions,
+            pairs,
+            qim_step,
+        } = coefficients;
+
+        // 执行DCT
+        let mut dct_block = dct_algorithm.dct_2d(block);
+
+        // 嵌入水印比特
+        let pos_idx = watermark_idx % positions.len();
+        let (u, v) = positions[pos_idx];
+
+        if let Some(step) = *qim_step {
+            dct_block[[u, v]] = Self::qim_quantize(dct_block[[u, v]], bit, step);
+        } else if self.differential {
+            self.embed_differential_bit(
+                &mut dct_block,
+                pairs[watermark_idx % pairs.len()],
+                bit,
+                strength,
+                self.perceptual_weight(block),
+            );
+        } else if u < self.block_size && v < self.block_size {
+            // 条件符号嵌入法：智能选择温和调整或符号强制
+            let coeff = dct_block[[u, v]];
+            let magnitude = coeff.abs();
+
+            // 计算自适应阈值和感知加权
+            let adaptive_threshold = self.calculate_adaptive_threshold(&dct_block, strength);
+            let perceptual_weight = self.perceptual_weight(block);
+
+            let target_change = strength * magnitude.max(1.0) * perceptual_weight;
+
+            if bit == 1 {
+                // 目标：确保系数为正且足够大
+                if coeff + target_change >= adaptive_threshold {
+                    // 温和增加就足够了，保持原有符号特性
+                    dct_block[[u, v]] = coeff + target_change;
+                } else {
+                    // 需要符号强制，但使用最小必要强度
+                    dct_block[[u, v]] = magnitude.max(adaptive_threshold) + target_change * 0.5;
+                }
+            } else {
+                // 目标：确保系数为负且绝对值够大
+                if coeff - target_change <= -adaptive_threshold {
+                    // 温和减少就足够了，保持原有符号特性
+                    dct_block[[u, v]] = coeff - target_change;
+                } else {
+                    // 需要符号强制，但使用最小必要强度
+                    dct_block[[u, v]] = -(magnitude.max(adaptive_threshold) + target_change * 0.5);
                 }
-            }
-            if extracted_bits.len() >= expected_length {
-                break;
             }
         }
 
-        extracted_bits.truncate(expected_length);
-        Ok(extracted_bits)
+        // 执行逆DCT
+        dct_algorithm.idct_2d(&dct_block)
     }
 
-    fn name(&self) -> &'static str {
-        "DCT"
+    /// 读取单个块承载的第 `bit_idx` 个比特的软判决
+    ///
+    /// 幅度相对嵌入最低阈值的比例作为置信度，QIM直接使用相对量化步长的置信度。
+    fn decide_block(
+        &self,
+        dct_algorithm: &mut DctWatermark,
+        coefficients: &BlockCoefficients,
+        block: &Array2<f64>,
+        bit_idx: usize,
+    ) -> (u8, f64) {
+        let (bit, margin) = self.block_margin(dct_algorithm, coefficients, block, bit_idx);
+        if coefficients.qim_step.is_some() {
+            (bit, margin)
+        } else {
+            let threshold = MIN_EMBED_THRESHOLD * self.threshold_scale();
+            (bit, (margin / threshold).min(1.0))
+        }
+    }
+
+    /// 读取单个块承载的第 `bit_idx` 个比特及其判决余量
+    ///
+    /// 符号嵌入为系数的幅度，差分嵌入为两个系数之差的幅度，QIM为相对量化步长的置信度。
+    fn block_margin(
+        &self,
+        dct_algorithm: &mut DctWatermark,
+        coefficients: &BlockCoefficients,
+        block: &Array2<f64>,
+        bit_idx: usize,
+    ) -> (u8, f64) {
+        // 执行DCT
+        let dct_block = dct_algorithm.dct_2d(block);
+
+        // 提取水印比特
+        let (u, v) = coefficients.positions[bit_idx % coefficients.positions.len()];
+
+        if let Some(step) = coefficients.qim_step {
+            Self::qim_decide(dct_block[[u, v]], step)
+        } else if self.differential {
+            // 前一个系数更大为1
+            let (first, second) = coefficients.pairs[bit_idx % coefficients.pairs.len()];
+            let difference = dct_block[first] - dct_block[second];
+            let bit = if difference >= 0.0 { 1 } else { 0 };
+            (bit, difference.abs())
+        } else {
+            // 根据DCT系数的符号确定比特值
+            let coeff = dct_block[[u, v]];
+            let bit = if coeff >= 0.0 { 1 } else { 0 };
+            (bit, coeff.abs())
+        }
     }
 }
 
@@ -387,60 +1018,46 @@ impl DctWatermark {
         let blocks_w = width / self.block_size;
         let total_blocks = blocks_h * blocks_w;
 
-        if watermark.len() > total_blocks {
-            return Err(WatermarkError::InvalidArgument(format!(
-                "水印数据太长，超过了可嵌入的块数。最大可嵌入{}比特，实际需要{}比特",
-                total_blocks,
-                watermark.len()
-            )));
-        }
+        WatermarkUtils::ensure_capacity(watermark.len(), total_blocks)?;
 
         // 使用与标准DCT完全相同的位置，确保兼容性
+        self.validate_positions()?;
         let audio_positions = self.get_mid_frequency_positions();
-        let mut watermark_idx = 0;
         let mut dct_algorithm = DctWatermark::new();
 
-        for block_y in 0..blocks_h {
-            for block_x in 0..blocks_w {
-                if watermark_idx >= watermark.len() {
-                    break;
-                }
+        let block_order = self.block_order(blocks_h, blocks_w);
+        for (watermark_idx, &(block_y, block_x)) in
+            block_order.iter().take(watermark.len()).enumerate()
+        {
+            // 提取当前块
+            let start_y = block_y * self.block_size;
+            let start_x = block_x * self.block_size;
+            let end_y = start_y + self.block_size;
+            let end_x = start_x + self.block_size;
 
-                // 提取当前块
-                let start_y = block_y * self.block_size;
-                let start_x = block_x * self.block_size;
-                let end_y = start_y + self.block_size;
-                let end_x = start_x + self.block_size;
-
-                let block = padded_data
-                    .slice(s![start_y..end_y, start_x..end_x])
-                    .to_owned();
-
-                // 执行DCT
-                let mut dct_block = dct_algorithm.dct_2d(&block);
+            let block = padded_data
+                .slice(s![start_y..end_y, start_x..end_x])
+                .to_owned();
 
-                // 使用音频友好的温和嵌入
-                let bit = watermark[watermark_idx];
-                let pos_idx = watermark_idx % audio_positions.len();
-                let (u, v) = audio_positions[pos_idx];
+            // 执行DCT
+            let mut dct_block = dct_algorithm.dct_2d(&block);
 
-                if u < self.block_size && v < self.block_size {
-                    self.embed_audio_friendly_bit(&mut dct_block, u, v, bit, strength);
-                }
+            // 使用音频友好的温和嵌入
+            let bit = watermark[watermark_idx];
+            let pos_idx = watermark_idx % audio_positions.len();
+            let (u, v) = audio_positions[pos_idx];
 
-                // 执行逆DCT
-                let watermarked_block = dct_algorithm.idct_2d(&dct_block);
+            if u < self.block_size && v < self.block_size {
+                self.embed_audio_friendly_bit(&mut dct_block, u, v, bit, strength);
+            }
 
-                // 将修改后的块写回结果
-                result
-                    .slice_mut(s![start_y..end_y, start_x..end_x])
-                    .assign(&watermarked_block);
+            // 执行逆DCT
+            let watermarked_block = dct_algorithm.idct_2d(&dct_block);
 
-                watermark_idx += 1;
-            }
-            if watermark_idx >= watermark.len() {
-                break;
-            }
+            // 将修改后的块写回结果
+            result
+                .slice_mut(s![start_y..end_y, start_x..end_x])
+                .assign(&watermarked_block);
         }
 
         // 移除填充，返回原始尺寸
@@ -463,46 +1080,39 @@ impl DctWatermark {
         let total_blocks = blocks_h * blocks_w;
 
         if expected_length > total_blocks {
-            return Err(WatermarkError::InvalidArgument(format!(
-                "期望长度{expected_length}超过了可提取的块数{total_blocks}"
-            )));
+            return Err(WatermarkError::InvalidArgument(
+                Message::ExpectedExceedsBlocks.format(&[&expected_length, &total_blocks]),
+            ));
         }
 
+        self.validate_positions()?;
         let audio_positions = self.get_mid_frequency_positions();
         let mut extracted_bits = Vec::new();
         let mut dct_algorithm = DctWatermark::new();
 
-        for block_y in 0..blocks_h {
-            for block_x in 0..blocks_w {
-                if extracted_bits.len() >= expected_length {
-                    break;
-                }
+        let block_order = self.block_order(blocks_h, blocks_w);
+        for &(block_y, block_x) in block_order.iter().take(expected_length) {
+            // 提取当前块
+            let start_y = block_y * self.block_size;
+            let start_x = block_x * self.block_size;
+            let end_y = start_y + self.block_size;
+            let end_x = start_x + self.block_size;
 
-                // 提取当前块
-                let start_y = block_y * self.block_size;
-                let start_x = block_x * self.block_size;
-                let end_y = start_y + self.block_size;
-                let end_x = start_x + self.block_size;
+            let block = padded_data
+                .slice(s![start_y..end_y, start_x..end_x])
+                .to_owned();
 
-                let block = padded_data
-                    .slice(s![start_y..end_y, start_x..end_x])
-                    .to_owned();
-
-                // 执行DCT
-                let dct_block = dct_algorithm.dct_2d(&block);
+            // 执行DCT
+            let dct_block = dct_algorithm.dct_2d(&block);
 
-                // 提取水印比特
-                let pos_idx = extracted_bits.len() % audio_positions.len();
-                let (u, v) = audio_positions[pos_idx];
+            // 提取水印比特
+            let pos_idx = extracted_bits.len() % audio_positions.len();
+            let (u, v) = audio_positions[pos_idx];
 
-                if u < self.block_size && v < self.block_size {
-                    // 使用更稳健的提取逻辑
-                    let bit = self.extract_audio_friendly_bit(&dct_block, u, v);
-                    extracted_bits.push(bit);
-                }
-            }
-            if extracted_bits.len() >= expected_length {
-                break;
+            if u < self.block_size && v < self.block_size {
+                // 使用更稳健的提取逻辑
+                let bit = self.extract_audio_friendly_bit(&dct_block, u, v);
+                extracted_bits.push(bit);
             }
         }
 
@@ -563,3 +1173,860 @@ impl DctWatermark {
         }
     }
 }
+
+impl DctWatermark {
+    /// 扩频模式嵌入
+    ///
+    /// 第 j 个系数槽位（按块顺序 × 系数位置展开）承载第 `j % 比特数` 个比特，
+    /// 使每个比特的码片交错分布在不同块中。嵌入时先抵消宿主系数在伪随机序列上的投影，
+    /// 再叠加 `±alpha`，使无攻击时的相关值恰好为 `±alpha`。
+    fn embed_spread_spectrum(
+        &self,
+        data: &Array2<f64>,
+        watermark: &[u8],
+        strength: f64,
+        chips: usize,
+    ) -> Result<Array2<f64>> {
+        self.validate_positions()?;
+        if chips == 0 {
+            return Err(WatermarkError::InvalidArgument(
+                Message::SpreadChipsZero.text().to_string(),
+            ));
+        }
+
+        let original_height = data.nrows();
+        let original_width = data.ncols();
+        let padded_data = self.pad_to_block_size(data);
+        let mut result = padded_data.clone();
+
+        let capacity = self.capacity(original_height, original_width);
+        WatermarkUtils::ensure_capacity(watermark.len(), capacity)?;
+        if watermark.is_empty() {
+            return Ok(data.clone());
+        }
+
+        let positions = self.get_mid_frequency_positions();
+        let (blocks, mut dct_blocks) =
+            self.spread_spectrum_blocks(&padded_data, watermark.len() * chips, positions.len());
+        let slots = self.spread_spectrum_slots(watermark.len(), chips, positions.len());
+
+        // 第一遍：计算每个比特的宿主相关值和能量
+        let mut correlation = vec![0.0; watermark.len()];
+        let mut energy = vec![0.0; watermark.len()];
+        for &(bit_idx, block_idx, pos_idx, chip) in &slots {
+            let (u, v) = positions[pos_idx];
+            let coeff = dct_blocks[block_idx][[u, v]];
+            correlation[bit_idx] += coeff * chip;
+            energy[bit_idx] += coeff * coeff;
+        }
+
+        // 每个比特的调整量：抵消宿主投影后叠加 ±alpha
+        let deltas: Vec<f64> = watermark
+            .iter()
+            .enumerate()
+            .map(|(bit_idx, &bit)| {
+                let rms = (energy[bit_idx] / chips as f64).sqrt();
+                let alpha = strength * rms.max(1.0);
+                let sign = if bit == 1 { 1.0 } else { -1.0 };
+                sign * alpha - correlation[bit_idx] / chips as f64
+            })
+            .collect();
+
+        // 第二遍：写入调制后的码片
+        for &(bit_idx, block_idx, pos_idx, chip) in &slots {
+            let (u, v) = positions[pos_idx];
+            dct_blocks[block_idx][[u, v]] += deltas[bit_idx] * chip;
+        }
+
+        let mut dct_algorithm = DctWatermark::new();
+        for (&(block_y, block_x), dct_block) in blocks.iter().zip(&dct_blocks) {
+            let start_y = block_y * self.block_size;
+            let start_x = block_x * self.block_size;
+            result
+                .slice_mut(s![
+                    start_y..start_y + self.block_size,
+                    start_x..start_x + self.block_size
+                ])
+                .assign(&dct_algorithm.idct_2d(dct_block));
+        }
+
+        Ok(self.unpad_from_block_size(&result, original_height, original_width))
+    }
+
+    /// 扩频模式提取，返回每个比特的判决值和归一化相关值（0.0-1.0，越大越可信）
+    pub fn extract_spread_spectrum(
+        &self,
+        data: &Array2<f64>,
+        expected_length: usize,
+    ) -> Result<Vec<(u8, f64)>> {
+        self.validate_positions()?;
+        let chips = self.spread_chips.unwrap_or(1);
+        if chips == 0 {
+            return Err(WatermarkError::InvalidArgument(
+                Message::SpreadChipsZero.text().to_string(),
+            ));
+        }
+
+        let capacity = self.capacity(data.nrows(), data.ncols());
+        if expected_length > capacity {
+            return Err(WatermarkError::InvalidArgument(
+                Message::ExpectedExceedsSpread.format(&[&expected_length, &capacity]),
+            ));
+        }
+
+        let padded_data = self.pad_to_block_size(data);
+        let positions = self.get_mid_frequency_positions();
+        let (_, dct_blocks) =
+            self.spread_spectrum_blocks(&padded_data, expected_length * chips, positions.len());
+
+        let mut correlation = vec![0.0; expected_length];
+        let mut energy = vec![0.0; expected_length];
+        for (bit_idx, block_idx, pos_idx, chip) in
+            self.spread_spectrum_slots(expected_length, chips, positions.len())
+        {
+            let (u, v) = positions[pos_idx];
+            let coeff = dct_blocks[block_idx][[u, v]];
+            correlation[bit_idx] += coeff * chip;
+            energy[bit_idx] += coeff * coeff;
+        }
+
+        Ok(correlation
+            .iter()
+            .zip(&energy)
+            .map(|(&corr, &energy)| {
+                let bit = if corr >= 0.0 { 1 } else { 0 };
+                let normalized = if energy > 0.0 {
+                    (corr.abs() / (chips as f64 * energy).sqrt()).min(1.0)
+                } else {
+                    0.0
+                };
+                (bit, normalized)
+            })
+            .collect())
+    }
+
+    /// 对承载 `slot_count` 个码片所需的块做DCT，返回块坐标和对应的DCT系数
+    fn spread_spectrum_blocks(
+        &self,
+        padded_data: &Array2<f64>,
+        slot_count: usize,
+        positions_per_block: usize,
+    ) -> (Vec<(usize, usize)>, Vec<Array2<f64>>) {
+        let (height, width) = padded_data.dim();
+        let blocks_needed = slot_count.div_ceil(positions_per_block);
+        let blocks: Vec<(usize, usize)> = self
+            .block_order(height / self.block_size, width / self.block_size)
+            .into_iter()
+            .take(blocks_needed)
+            .collect();
+
+        let mut dct_algorithm = DctWatermark::new();
+        let dct_blocks = blocks
+            .iter()
+            .map(|&(block_y, block_x)| {
+                let start_y = block_y * self.block_size;
+                let start_x = block_x * self.block_size;
+                let block = padded_data
+                    .slice(s![
+                        start_y..start_y + self.block_size,
+                        start_x..start_x + self.block_size
+                    ])
+                    .to_owned();
+                dct_algorithm.dct_2d(&block)
+            })
+            .collect();
+
+        (blocks, dct_blocks)
+    }
+
+    /// 生成码片槽位：(比特序号, 块序号, 系数位置序号, ±1码片)
+    fn spread_spectrum_slots(
+        &self,
+        bit_count: usize,
+        chips: usize,
+        positions_per_block: usize,
+    ) -> Vec<(usize, usize, usize, f64)> {
+        let mut rng = SeededRng::new(self.seed.unwrap_or(DEFAULT_SPREAD_SEED));
+        (0..bit_count * chips)
+            .map(|slot| {
+                let chip = if rng.next_u64() & 1 == 1 { 1.0 } else { -1.0 };
+                (
+                    slot % bit_count,
+                    slot / positions_per_block,
+                    slot % positions_per_block,
+                    chip,
+                )
+            })
+            .collect()
+    }
+}
+
+impl DctWatermark {
+    /// 校验分块参数：图块边长是块大小的非零倍数，且未启用扩频模式
+    fn validate_tiles(&self, tile_size: usize) -> Result<()> {
+        if tile_size == 0 || !tile_size.is_multiple_of(self.block_size) {
+            return Err(WatermarkError::InvalidArgument(
+                Message::TileSizeInvalid.format(&[&tile_size, &self.block_size]),
+            ));
+        }
+        if self.spread_chips.is_some() {
+            return Err(WatermarkError::InvalidArgument(
+                Message::TilesWithSpread.text().to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// 去掉分块设置的副本，用于在单个图块内按普通符号嵌入
+    fn untiled(&self) -> DctWatermark {
+        let mut plain = DctWatermark::new().with_block_size(self.block_size);
+        plain.seed = self.seed;
+        plain.positions = self.positions.clone();
+        plain.differential = self.differential;
+        plain.qim_step = self.qim_step;
+        plain.mode = self.mode;
+        plain
+    }
+
+    /// 载荷比特后附加CRC-32校验比特
+    fn append_tile_crc(watermark: &[u8]) -> Vec<u8> {
+        let crc = crc32fast::hash(&WatermarkUtils::bits_to_bytes(watermark));
+        let mut framed = watermark.to_vec();
+        framed.extend(WatermarkUtils::bytes_to_bits(&crc.to_be_bytes()));
+        framed
+    }
+
+    /// 判决结果的末尾CRC-32是否与前面的载荷比特一致
+    fn tile_crc_matches(decisions: &[(u8, f64)], payload_bits: usize) -> bool {
+        let bits: Vec<u8> = decisions.iter().map(|&(bit, _)| bit).collect();
+        let (payload, crc) = bits.split_at(payload_bits);
+        WatermarkUtils::bits_to_bytes(crc)
+            == crc32fast::hash(&WatermarkUtils::bits_to_bytes(payload)).to_be_bytes()
+    }
+
+    /// 分块模式嵌入：从左上角开始把图像划分为图块，每个完整图块写入载荷和CRC，
+    /// 右侧和底部不足一个图块的边缘保持不变
+    fn embed_tiled(
+        &self,
+        data: &Array2<f64>,
+        watermark: &[u8],
+        strength: f64,
+        tile_size: usize,
+    ) -> Result<Array2<f64>> {
+        self.validate_tiles(tile_size)?;
+        let (height, width) = data.dim();
+        if height < tile_size || width < tile_size {
+            return Err(WatermarkError::InvalidArgument(
+                Message::ImageSmallerThanTile.format(&[&width, &height, &tile_size, &tile_size]),
+            ));
+        }
+        WatermarkUtils::ensure_capacity(watermark.len(), self.capacity(height, width))?;
+
+        let framed = Self::append_tile_crc(watermark);
+        let plain = self.untiled();
+        let mut result = data.clone();
+        for tile_y in 0..height / tile_size {
+            for tile_x in 0..width / tile_size {
+                let rows = tile_y * tile_size..(tile_y + 1) * tile_size;
+                let cols = tile_x * tile_size..(tile_x + 1) * tile_size;
+                let tile = data.slice(s![rows.clone(), cols.clone()]).to_owned();
+                let watermarked = plain.embed(&tile, &framed, strength)?;
+                result.slice_mut(s![rows, cols]).assign(&watermarked);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// 分块模式提取
+    ///
+    /// 裁剪会同时打乱8x8块的对齐和图块网格的位置，因此对每种块对齐方式计算一次
+    /// 全图的块DCT，再在该对齐下尝试所有图块偏移和所有完整图块，返回第一个CRC正确的结果。
+    fn extract_tiled(
+        &self,
+        data: &Array2<f64>,
+        expected_length: usize,
+        tile_size: usize,
+    ) -> Result<Vec<(u8, f64)>> {
+        self.validate_tiles(tile_size)?;
+        self.validate_positions()?;
+
+        let (height, width) = data.dim();
+        let capacity = self.capacity(height, width);
+        if expected_length > capacity {
+            return Err(WatermarkError::InvalidArgument(
+                Message::ExpectedExceedsTiles.format(&[&expected_length, &capacity]),
+            ));
+        }
+
+        let tile_blocks = tile_size / self.block_size;
+        let framed_len = expected_length + TILE_CRC_BITS;
+        let positions = self.get_mid_frequency_positions();
+        let qim_step = self.qim_lattice_step()?;
+        let order: Vec<(usize, usize)> = self
+            .block_order(tile_blocks, tile_blocks)
+            .into_iter()
+            .take(framed_len)
+            .collect();
+
+        let mut dct_algorithm = DctWatermark::new();
+        for offset_y in 0..self.block_size {
+            for offset_x in 0..self.block_size {
+                let blocks_h = (height - offset_y) / self.block_size;
+                let blocks_w = (width - offset_x) / self.block_size;
+                if blocks_h < tile_blocks || blocks_w < tile_blocks {
+                    continue;
+                }
+
+                let dct_blocks: Vec<Array2<f64>> = (0..blocks_h * blocks_w)
+                    .map(|idx| {
+                        let start_y = offset_y + idx / blocks_w * self.block_size;
+                        let start_x = offset_x + idx % blocks_w * self.block_size;
+                        let block = data
+                            .slice(s![
+                                start_y..start_y + self.block_size,
+                                start_x..start_x + self.block_size
+                            ])
+                            .to_owned();
+                        dct_algorithm.dct_2d(&block)
+                    })
+                    .collect();
+
+                // 图块左上角所在的块坐标，按网格偏移逐一尝试
+                let tile_origins = (0..tile_blocks).flat_map(|grid_y| {
+                    (0..tile_blocks).flat_map(move |grid_x| {
+                        (grid_y..=blocks_h - tile_blocks)
+                            .step_by(tile_blocks)
+                            .flat_map(move |origin_y| {
+                                (grid_x..=blocks_w - tile_blocks)
+                                    .step_by(tile_blocks)
+                                    .map(move |origin_x| (origin_y, origin_x))
+                            })
+                    })
+                });
+
+                for (origin_y, origin_x) in tile_origins {
+                    let mut decisions: Vec<(u8, f64)> = order
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, &(block_y, block_x))| {
+                            let (u, v) = positions[idx % positions.len()];
+                            let block_idx = (origin_y + block_y) * blocks_w + origin_x + block_x;
+                            let coeff = dct_blocks[block_idx][[u, v]];
+                            if let Some(step) = qim_step {
+                                return Self::qim_decide(coeff, step);
+                            }
+                            let bit = if coeff >= 0.0 { 1 } else { 0 };
+                            (
+                                bit,
+                                (coeff.abs() / (MIN_EMBED_THRESHOLD * self.threshold_scale()))
+                                    .min(1.0),
+                            )
+                        })
+                        .collect();
+
+                    if Self::tile_crc_matches(&decisions, expected_length) {
+                        decisions.truncate(expected_length);
+                        return Ok(decisions);
+                    }
+                }
+            }
+        }
+
+        Err(WatermarkError::ProcessingError(
+            Message::NoValidTile.text().to_string(),
+        ))
+    }
+}
+
+impl DctWatermark {
+    /// 校验全图模式没有与其他嵌入方式同时启用
+    fn validate_global(&self) -> Result<()> {
+        if self.positions.is_some()
+            || self.tile_size.is_some()
+            || self.spread_chips.is_some()
+            || self.differential
+            || self.qim_step.is_some()
+        {
+            return Err(WatermarkError::InvalidArgument(
+                Message::GlobalWithOtherModes.text().to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// 全图模式承载水印的系数下标，按离原点由近到远排序，设置了种子时再按种子置换
+    ///
+    /// 跳过 u=0 或 v=0 的纯水平/垂直频率；任一边小于最小边长时没有可用位置。
+    fn global_positions(&self, rows: usize, cols: usize) -> Vec<(usize, usize)> {
+        if rows < GLOBAL_MIN_SIZE || cols < GLOBAL_MIN_SIZE {
+            return Vec::new();
+        }
+
+        let radius = |(u, v): (usize, usize)| ((u * u + v * v) as f64).sqrt();
+        let limit = GLOBAL_BAND_HIGH.ceil() as usize;
+        let mut positions: Vec<(usize, usize)> = (1..limit)
+            .flat_map(|u| (1..limit).map(move |v| (u, v)))
+            .filter(|&position| (GLOBAL_BAND_LOW..GLOBAL_BAND_HIGH).contains(&radius(position)))
+            .collect();
+        positions.sort_by(|&a, &b| radius(a).total_cmp(&radius(b)).then(a.cmp(&b)));
+
+        if let Some(seed) = self.seed {
+            SeededRng::new(seed).shuffle(&mut positions);
+        }
+        positions
+    }
+
+    /// 全图DCT系数与对应空域余弦纹理振幅之间的比例（u、v均不为0时）
+    fn global_amplitude_scale(rows: usize, cols: usize) -> f64 {
+        (rows * cols) as f64 / 4.0
+    }
+
+    /// 全图模式嵌入：符号与比特不符或幅度不足阈值的系数直接设为带正确符号的阈值
+    fn embed_global(
+        &self,
+        data: &Array2<f64>,
+        watermark: &[u8],
+        strength: f64,
+    ) -> Result<Array2<f64>> {
+        self.validate_global()?;
+        let (rows, cols) = data.dim();
+        let positions = self.global_positions(rows, cols);
+        WatermarkUtils::ensure_capacity(watermark.len(), positions.len())?;
+
+        let threshold = (GLOBAL_MIN_AMPLITUDE + GLOBAL_AMPLITUDE_RANGE * strength)
+            * Self::global_amplitude_scale(rows, cols);
+        let mut dct_algorithm = DctWatermark::new();
+        let mut spectrum = dct_algorithm.dct_2d(data);
+        for (&bit, &(u, v)) in watermark.iter().zip(&positions) {
+            let sign = if bit == 1 { 1.0 } else { -1.0 };
+            if spectrum[[u, v]] * sign < threshold {
+                spectrum[[u, v]] = sign * threshold;
+            }
+        }
+        Ok(dct_algorithm.idct_2d(&spectrum))
+    }
+
+    /// 全图模式提取：按系数符号判决，幅度相对强度为0时阈值的比例作为置信度
+    fn extract_global(&self, data: &Array2<f64>, expected_length: usize) -> Result<Vec<(u8, f64)>> {
+        self.validate_global()?;
+        let (rows, cols) = data.dim();
+        let positions = self.global_positions(rows, cols);
+        if expected_length > positions.len() {
+            return Err(WatermarkError::InvalidArgument(
+                Message::ExpectedExceedsGlobal.format(&[&expected_length, &positions.len()]),
+            ));
+        }
+
+        let reference = GLOBAL_MIN_AMPLITUDE * Self::global_amplitude_scale(rows, cols);
+        let spectrum = DctWatermark::new().dct_2d(data);
+        Ok(positions[..expected_length]
+            .iter()
+            .map(|&(u, v)| {
+                let coeff = spectrum[[u, v]];
+                let bit = if coeff >= 0.0 { 1 } else { 0 };
+                (bit, (coeff.abs() / reference).min(1.0))
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 平缓的水平渐变，每个8x8块内的方差远低于默认的感知加权阈值
+    fn flat_gradient(size: usize) -> Array2<f64> {
+        Array2::from_shape_fn((size, size), |(_, x)| 0.3 + 0.1 * x as f64 / size as f64)
+    }
+
+    /// 均匀分布的伪随机纹理，各块都有足够的中频能量
+    fn textured(size: usize, seed: u64) -> Array2<f64> {
+        let mut rng = SeededRng::new(seed);
+        Array2::from_shape_fn((size, size), |_| rng.next_below(1000) as f64 / 1000.0)
+    }
+
+    fn squared_error(a: &Array2<f64>, b: &Array2<f64>) -> f64 {
+        (a - b).mapv(|d| d * d).sum()
+    }
+
+    fn bit_errors(a: &[u8], b: &[u8]) -> usize {
+        a.iter().zip(b).filter(|(x, y)| x != y).count()
+    }
+
+    /// 用双三次插值把数据缩放到 `scale` 倍
+    fn resized(data: &Array2<f64>, scale: f64) -> Array2<f64> {
+        let (rows, cols) = data.dim();
+        let buffer: image::ImageBuffer<image::Luma<f32>, Vec<f32>> =
+            image::ImageBuffer::from_fn(cols as u32, rows as u32, |x, y| {
+                image::Luma([data[[y as usize, x as usize]] as f32])
+            });
+        let width = (cols as f64 * scale).round() as u32;
+        let height = (rows as f64 * scale).round() as u32;
+        let scaled = image::imageops::resize(
+            &buffer,
+            width,
+            height,
+            image::imageops::FilterType::CatmullRom,
+        );
+        Array2::from_shape_fn((height as usize, width as usize), |(y, x)| {
+            scaled.get_pixel(x as u32, y as u32)[0] as f64
+        })
+    }
+
+    #[test]
+    fn seeded_watermark_only_extracts_with_the_same_seed() {
+        let data = textured(128, 1);
+        let bits = WatermarkUtils::string_to_bits("seeded");
+        let marked = DctWatermark::new()
+            .with_seed(42)
+            .embed(&data, &bits, 0.1)
+            .unwrap();
+
+        let same = DctWatermark::new().with_seed(42);
+        assert_eq!(same.extract(&marked, bits.len()).unwrap(), bits);
+
+        // 错误的种子或不带种子时读到的是其他块，结果接近随机
+        for wrong in [DctWatermark::new().with_seed(43), DctWatermark::new()] {
+            let extracted = wrong.extract(&marked, bits.len()).unwrap();
+            assert!(bit_errors(&extracted, &bits) > bits.len() / 4);
+        }
+    }
+
+    #[test]
+    fn oversized_watermark_reports_needed_and_available_bits() {
+        // 64x64 的图片有 8x8 = 64 个块，每块 1 比特
+        let data = textured(64, 2);
+        let bits = vec![1u8; 65];
+        let error = DctWatermark::new().embed(&data, &bits, 0.1).unwrap_err();
+        match &error {
+            WatermarkError::CapacityExceeded { needed, available } => {
+                assert_eq!((*needed, *available), (65, 64));
+            }
+            other => panic!("expected CapacityExceeded, got {other:?}"),
+        }
+        let message = error.to_string();
+        assert!(
+            message.contains("65") && message.contains("64"),
+            "{message}"
+        );
+
+        assert!(DctWatermark::new().embed(&data, &bits[..64], 0.1).is_ok());
+    }
+
+    #[test]
+    fn qim_round_trips_and_needs_the_same_step() {
+        let data = textured(128, 5) * 255.0;
+        let bits = WatermarkUtils::string_to_bits("qim step");
+        let algorithm = DctWatermark::new().with_qim(20.0);
+        let marked = algorithm.embed(&data, &bits, 0.1).unwrap();
+        assert_eq!(algorithm.extract(&marked, bits.len()).unwrap(), bits);
+
+        let extracted = DctWatermark::new()
+            .with_qim(13.0)
+            .extract(&marked, bits.len())
+            .unwrap();
+        assert!(bit_errors(&extracted, &bits) > bits.len() / 4);
+    }
+
+    #[test]
+    fn qim_decodes_through_noise_below_half_a_step() {
+        // 格点层面：任意小于 step/2 的偏移都判回原比特，超过后落到相邻格点
+        let step = 20.0;
+        for coeff in [-37.3, -4.0, 0.0, 6.2, 91.7] {
+            for bit in [0, 1] {
+                let quantized = DctWatermark::qim_quantize(coeff, bit, step);
+                for offset in [-0.49, -0.25, 0.0, 0.25, 0.49] {
+                    let (decided, _) = DctWatermark::qim_decide(quantized + offset * step, step);
+                    assert_eq!(decided, bit);
+                }
+                let (decided, _) = DctWatermark::qim_decide(quantized + 0.51 * step, step);
+                assert_ne!(decided, bit);
+            }
+        }
+
+        // 图片层面：未归一化的8x8 DCT把幅度不超过a的像素噪声放大到至多64a，
+        // 像素噪声限制在 step/128 以内时系数扰动必定小于 step/2
+        let data = textured(128, 6) * 255.0;
+        let bits = WatermarkUtils::string_to_bits("noisy qim");
+        let algorithm = DctWatermark::new().with_qim(step);
+        let marked = algorithm.embed(&data, &bits, 0.1).unwrap();
+        let bound = 0.99 * step / 128.0;
+        let mut rng = SeededRng::new(7);
+        let noisy = marked.mapv(|v| v + (rng.next_below(2001) as f64 / 1000.0 - 1.0) * bound);
+        assert_eq!(algorithm.extract(&noisy, bits.len()).unwrap(), bits);
+    }
+
+    /// 在每个8x8块中给 `positions` 上的系数加上同一个偏移
+    fn shift_coefficients(
+        data: &Array2<f64>,
+        positions: &[(usize, usize)],
+        offset: f64,
+    ) -> Array2<f64> {
+        let mut dct = DctWatermark::new();
+        let mut shifted = data.clone();
+        for mut block in shifted.exact_chunks_mut((8, 8)) {
+            let mut coefficients = dct.dct_2d(&block.to_owned());
+            for &position in positions {
+                coefficients[position] += offset;
+            }
+            block.assign(&dct.idct_2d(&coefficients));
+        }
+        shifted
+    }
+
+    #[test]
+    fn differential_survives_an_offset_shared_by_the_coefficient_pair() {
+        let data = textured(128, 8) * 255.0;
+        let bits = WatermarkUtils::string_to_bits("differential");
+        let sign = DctWatermark::new();
+        let differential = DctWatermark::new().with_differential(true);
+        let positions = sign.get_mid_frequency_positions();
+
+        for algorithm in [&sign, &differential] {
+            let marked = algorithm.embed(&data, &bits, 0.1).unwrap();
+
+            // 全图统一的亮度偏移只改变各块的直流分量，两种方式都不受影响
+            let brighter = marked.mapv(|v| v + 40.0);
+            assert_eq!(algorithm.extract(&brighter, bits.len()).unwrap(), bits);
+
+            // 承载系数上共同的偏移超过嵌入幅度后，符号法全部读成1，差分法比较的是两者之差，不受影响
+            let shifted = shift_coefficients(&marked, &positions, 2000.0);
+            let errors = bit_errors(&algorithm.extract(&shifted, bits.len()).unwrap(), &bits);
+            if algorithm.differential {
+                assert_eq!(errors, 0);
+            } else {
+                assert_eq!(errors, bits.iter().filter(|&&bit| bit == 0).count());
+            }
+        }
+    }
+
+    /// 可分离的高斯模糊，边缘按最近的像素延伸
+    fn gaussian_blur(data: &Array2<f64>, sigma: f64) -> Array2<f64> {
+        let radius = (3.0 * sigma).ceil() as isize;
+        let kernel: Vec<f64> = (-radius..=radius)
+            .map(|i| (-(i * i) as f64 / (2.0 * sigma * sigma)).exp())
+            .collect();
+        let total: f64 = kernel.iter().sum();
+        let (rows, cols) = data.dim();
+        let pass = |source: &Array2<f64>, horizontal: bool| {
+            Array2::from_shape_fn((rows, cols), |(y, x)| {
+                let taps = kernel.iter().zip(-radius..).map(|(weight, offset)| {
+                    let (y, x) = if horizontal {
+                        (
+                            y,
+                            (x as isize + offset).clamp(0, cols as isize - 1) as usize,
+                        )
+                    } else {
+                        (
+                            (y as isize + offset).clamp(0, rows as isize - 1) as usize,
+                            x,
+                        )
+                    };
+                    weight * source[[y, x]]
+                });
+                taps.sum::<f64>() / total
+            })
+        };
+        pass(&pass(data, true), false)
+    }
+
+    #[test]
+    fn low_band_survives_a_blur_that_erases_the_mid_band() {
+        let data = flat_gradient(256) + textured(256, 4) * 0.1;
+        let bits = WatermarkUtils::string_to_bits("blur");
+        let quantized =
+            |data: Array2<f64>| data.mapv(|v| (v * 255.0).round().clamp(0.0, 255.0) / 255.0);
+
+        let extract_blurred = |band| {
+            let algorithm = DctWatermark::new().with_block_size(32).with_band(band);
+            let marked = quantized(algorithm.embed(&data, &bits, 0.1).unwrap());
+            assert_eq!(algorithm.extract(&marked, bits.len()).unwrap(), bits);
+            let blurred = quantized(gaussian_blur(&marked, 3.5));
+            algorithm.extract(&blurred, bits.len()).unwrap()
+        };
+        assert_eq!(extract_blurred(FrequencyBand::Low), bits);
+        assert!(bit_errors(&extract_blurred(FrequencyBand::Mid), &bits) > bits.len() / 8);
+    }
+
+    #[test]
+    fn custom_positions_only_extract_with_the_same_list() {
+        let data = textured(128, 4);
+        let bits = WatermarkUtils::string_to_bits("positions");
+        let algorithm = DctWatermark::new().with_positions(vec![(1, 1), (6, 7), (7, 6)]);
+        let marked = algorithm.embed(&data, &bits, 0.1).unwrap();
+        assert_eq!(algorithm.extract(&marked, bits.len()).unwrap(), bits);
+
+        // 默认的中频位置不包含这些系数，读到的是未经修改的原始系数
+        let extracted = DctWatermark::new().extract(&marked, bits.len()).unwrap();
+        assert!(bit_errors(&extracted, &bits) > bits.len() / 4);
+    }
+
+    #[test]
+    fn spread_spectrum_survives_gaussian_noise() {
+        let data = textured(256, 5).mapv(|x| x * 255.0);
+        let bits = WatermarkUtils::string_to_bits("spread");
+        let algorithm = DctWatermark::new().with_spread_spectrum(32);
+        let marked = algorithm.embed(&data, &bits, 0.5).unwrap();
+
+        // Box-Muller 生成标准差为5的高斯噪声，约等于34dB的PSNR
+        let mut rng = SeededRng::new(6);
+        let mut uniform = || (rng.next_below(1_000_000) as f64 + 0.5) / 1_000_000.0;
+        let noisy = marked.mapv(|x| {
+            let (u1, u2) = (uniform(), uniform());
+            x + 5.0 * (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+        });
+
+        let decisions = algorithm.extract_soft(&noisy, bits.len()).unwrap();
+        let extracted: Vec<u8> = decisions.iter().map(|&(bit, _)| bit).collect();
+        assert_eq!(extracted, bits);
+
+        // 嵌入时抵消了宿主投影，归一化相关值约等于强度；噪声只让它略有下降，
+        // 而未嵌入的宿主只有 1/sqrt(码片数) 量级的偶然相关
+        let confidence = WatermarkUtils::mean_confidence(&decisions);
+        let unmarked =
+            WatermarkUtils::mean_confidence(&algorithm.extract_soft(&data, bits.len()).unwrap());
+        assert!(confidence > 0.4);
+        assert!(unmarked < 0.2);
+    }
+
+    #[test]
+    fn tiled_watermark_survives_keeping_only_one_quadrant() {
+        let data = textured(256, 7).mapv(|x| x * 255.0);
+        let bits = WatermarkUtils::string_to_bits("tile");
+        let algorithm = DctWatermark::new().with_tiles(64);
+        let marked = algorithm.embed(&data, &bits, 0.1).unwrap();
+
+        // 只保留右下角约四分之一，裁剪起点与块和图块网格都不对齐
+        let quadrant = marked.slice(s![131.., 125..]).to_owned();
+        assert_eq!(algorithm.extract(&quadrant, bits.len()).unwrap(), bits);
+
+        // 不分块时载荷只写在整幅图的前若干块中，裁剪后无法恢复
+        let untiled = DctWatermark::new();
+        let marked = untiled.embed(&data, &bits, 0.1).unwrap();
+        let quadrant = marked.slice(s![131.., 125..]).to_owned();
+        assert_ne!(untiled.extract(&quadrant, bits.len()).unwrap(), bits);
+    }
+
+    #[test]
+    fn flat_blocks_are_changed_less_with_perceptual_weighting() {
+        let data = flat_gradient(64);
+        let bits = WatermarkUtils::string_to_bits("flat");
+
+        let weighted = DctWatermark::new().embed(&data, &bits, 0.1).unwrap();
+        let unweighted = DctWatermark::new()
+            .with_perceptual_threshold(0.0)
+            .embed(&data, &bits, 0.1)
+            .unwrap();
+        assert!(squared_error(&data, &weighted) < squared_error(&data, &unweighted));
+
+        assert_eq!(
+            DctWatermark::new().extract(&weighted, bits.len()).unwrap(),
+            bits
+        );
+    }
+
+    #[test]
+    fn perceptual_weighting_only_reduces_distortion_in_flat_blocks() {
+        // 左半边平缓渐变、右半边纹理，每个8x8块都承载一个比特
+        let (flat, texture) = (flat_gradient(64), textured(64, 11));
+        let data = Array2::from_shape_fn((64, 64), |(y, x)| {
+            if x < 32 {
+                flat[[y, x]]
+            } else {
+                texture[[y, x]]
+            }
+        });
+        let bits: Vec<u8> = (0..64).map(|i| (i % 3 == 0) as u8).collect();
+
+        let weighted = DctWatermark::new().embed(&data, &bits, 0.1).unwrap();
+        let unweighted = DctWatermark::new()
+            .with_perceptual_threshold(0.0)
+            .embed(&data, &bits, 0.1)
+            .unwrap();
+
+        let algorithm = DctWatermark::new();
+        for by in 0..8 {
+            for bx in 0..8 {
+                let block = s![by * 8..(by + 1) * 8, bx * 8..(bx + 1) * 8];
+                let original = data.slice(block).to_owned();
+                let error = |marked: &Array2<f64>| {
+                    squared_error(&original, &marked.slice(block).to_owned())
+                };
+                let variance = algorithm.calculate_block_variance(&original);
+                if bx < 4 {
+                    assert!(variance < DEFAULT_PERCEPTUAL_THRESHOLD);
+                    assert!(error(&weighted) < error(&unweighted));
+                } else {
+                    assert!(variance > DEFAULT_PERCEPTUAL_THRESHOLD);
+                    assert!((error(&weighted) - error(&unweighted)).abs() < 1e-12);
+                }
+            }
+        }
+
+        assert_eq!(algorithm.extract(&weighted, bits.len()).unwrap(), bits);
+    }
+
+    #[test]
+    fn confidence_map_marks_unused_and_tampered_blocks() {
+        let algorithm = DctWatermark::new();
+        let bits: Vec<u8> = (0..64).map(|i| (i % 3 == 0) as u8).collect();
+        let mut marked = algorithm.embed(&textured(128, 2), &bits, 0.1).unwrap();
+
+        let map = algorithm.block_confidence(&marked, bits.len()).unwrap();
+        assert_eq!(map.iter().filter(|c| c.is_nan()).count(), 256 - 64);
+
+        // 把前16个承载块涂成纯色，这些块的中频系数归零
+        let carrying: Vec<(usize, usize)> = map
+            .indexed_iter()
+            .filter(|(_, c)| !c.is_nan())
+            .map(|(block, _)| block)
+            .collect();
+        let (tampered, intact) = carrying.split_at(16);
+        for &(y, x) in tampered {
+            marked
+                .slice_mut(s![y * 8..(y + 1) * 8, x * 8..(x + 1) * 8])
+                .fill(0.5);
+        }
+
+        let map = algorithm.block_confidence(&marked, bits.len()).unwrap();
+        let mean = |blocks: &[(usize, usize)]| {
+            blocks.iter().map(|&(y, x)| map[[y, x]]).sum::<f64>() / blocks.len() as f64
+        };
+        assert!(mean(tampered) < 0.05);
+        assert!(mean(intact) > 0.5);
+    }
+
+    #[test]
+    fn default_positions_fit_every_block_size() {
+        for size in [8, 16, 32] {
+            for band in [FrequencyBand::Low, FrequencyBand::Mid, FrequencyBand::High] {
+                let positions = DctWatermark::new()
+                    .with_block_size(size)
+                    .with_band(band)
+                    .get_mid_frequency_positions();
+                assert!(!positions.is_empty());
+                assert!(positions
+                    .iter()
+                    .all(|&(u, v)| (1..size).contains(&u) && (1..size).contains(&v)));
+                let mut unique = positions.clone();
+                unique.sort();
+                unique.dedup();
+                assert_eq!(unique.len(), positions.len());
+            }
+        }
+        assert_eq!(DctWatermark::new().get_mid_frequency_positions().len(), 20);
+    }
+
+    #[test]
+    fn global_mode_survives_resizing_to_80_percent() {
+        let data = textured(256, 3);
+        let bits = WatermarkUtils::string_to_bits("global");
+        let algorithm = DctWatermark::new().with_mode(DctMode::Global);
+        let marked = algorithm.embed(&data, &bits, 0.1).unwrap();
+
+        let shrunk = resized(&marked, 0.8);
+        assert_eq!(shrunk.dim(), (205, 205));
+        assert_eq!(algorithm.extract(&shrunk, bits.len()).unwrap(), bits);
+    }
+}
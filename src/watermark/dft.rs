@@ -0,0 +1,215 @@
+use crate::error::{Result, WatermarkError};
+use crate::i18n::Message;
+use crate::media::MediaType;
+use crate::watermark::r#trait::{WatermarkAlgorithm, WatermarkUtils};
+use ndarray::Array2;
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+
+/// 承载水印的环带内径（每像素周期数），更低的频率修改后容易被看出
+const BAND_LOW: f64 = 0.06;
+/// 承载水印的环带外径（每像素周期数），更高的频率容易被压缩和缩放抹掉
+const BAND_HIGH: f64 = 0.2;
+/// 强度为0时一对系数的幅度差，按 `行数 * 列数` 缩放
+///
+/// 幅度差为 `d * N` 时每个系数在空域上是振幅约 `d` 的正弦纹理，72比特时整幅的
+/// 均方根改动约为1/255，保存为8位像素后不会被取整抹掉。
+const MIN_DIFFERENCE: f64 = 0.0004;
+/// 强度从0到1时幅度差的增量
+const DIFFERENCE_RANGE: f64 = 0.001;
+
+/// 频率坐标 (行方向, 列方向)，可为负数
+type Frequency = (i64, i64);
+
+/// DFT幅度水印
+///
+/// 对整幅数据做二维傅里叶变换，在中频环带内取同一行上相邻的两个系数为一组，
+/// 以两者的平均幅度为中心拉开固定的幅度差：比特1令前一个系数更大，比特0令后一个更大，
+/// 两者的相位保持不变。
+/// 平移只改变相位，幅度关系不受影响；修改时同时写入共轭对称位置，逆变换结果仍为实数。
+/// 旋转和缩放会移动系数所在的频率位置，没有对数极坐标变换时无法抵抗；嵌入和提取时数据尺寸必须一致。
+pub struct DftWatermark;
+
+impl DftWatermark {
+    /// 创建DFT水印算法实例
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// 中频环带内的全部系数对，按离原点由近到远排序
+    ///
+    /// 只取列方向频率为正的半平面，共轭位置落在另一半平面，不会与其他系数对重叠；
+    /// 列方向的奈奎斯特频率是自共轭的，不使用。
+    fn coefficient_pairs(rows: usize, cols: usize) -> Vec<(Frequency, Frequency)> {
+        let radius = |(u, v): Frequency| {
+            ((u as f64 / rows as f64).powi(2) + (v as f64 / cols as f64).powi(2)).sqrt()
+        };
+        let in_band = |f: Frequency| (BAND_LOW..BAND_HIGH).contains(&radius(f));
+
+        let max_u = rows.saturating_sub(1) as i64 / 2;
+        let max_v = cols.saturating_sub(1) as i64 / 2;
+        let mut pairs = Vec::new();
+        for u in -max_u..=max_u {
+            for v in (1..max_v).step_by(2) {
+                let pair = ((u, v), (u, v + 1));
+                if in_band(pair.0) && in_band(pair.1) {
+                    pairs.push(pair);
+                }
+            }
+        }
+        pairs.sort_by(|a, b| radius(a.0).total_cmp(&radius(b.0)).then(a.cmp(b)));
+        pairs
+    }
+
+    /// 频率坐标对应的频谱下标
+    fn index(rows: usize, cols: usize, (u, v): Frequency) -> (usize, usize) {
+        (
+            u.rem_euclid(rows as i64) as usize,
+            v.rem_euclid(cols as i64) as usize,
+        )
+    }
+
+    /// 二维傅里叶变换：先逐行再逐列做一维变换，逆变换不做归一化
+    fn fft2(data: &mut Array2<Complex<f64>>, inverse: bool) {
+        let (rows, cols) = data.dim();
+        let mut planner = FftPlanner::new();
+        let (row_fft, col_fft) = if inverse {
+            (
+                planner.plan_fft_inverse(cols),
+                planner.plan_fft_inverse(rows),
+            )
+        } else {
+            (
+                planner.plan_fft_forward(cols),
+                planner.plan_fft_forward(rows),
+            )
+        };
+
+        for mut row in data.rows_mut() {
+            let mut buffer = row.to_vec();
+            row_fft.process(&mut buffer);
+            row.iter_mut().zip(buffer).for_each(|(x, y)| *x = y);
+        }
+        for mut col in data.columns_mut() {
+            let mut buffer = col.to_vec();
+            col_fft.process(&mut buffer);
+            col.iter_mut().zip(buffer).for_each(|(x, y)| *x = y);
+        }
+    }
+
+    fn spectrum(data: &Array2<f64>) -> Array2<Complex<f64>> {
+        let mut spectrum = data.mapv(|x| Complex::new(x, 0.0));
+        Self::fft2(&mut spectrum, false);
+        spectrum
+    }
+
+    /// 把系数的幅度设为 `magnitude`，相位不变，并同步写入共轭对称位置
+    fn set_magnitude(spectrum: &mut Array2<Complex<f64>>, frequency: Frequency, magnitude: f64) {
+        let (rows, cols) = spectrum.dim();
+        let index = Self::index(rows, cols, frequency);
+        let current = spectrum[index];
+        let value = if current.norm() > f64::EPSILON {
+            current * (magnitude / current.norm())
+        } else {
+            Complex::new(magnitude, 0.0)
+        };
+        spectrum[index] = value;
+        spectrum[Self::index(rows, cols, (-frequency.0, -frequency.1))] = value.conj();
+    }
+
+    /// 每个系数对的幅度差 `|A| - |B|`
+    fn pair_differences(&self, data: &Array2<f64>, expected_length: usize) -> Result<Vec<f64>> {
+        let (rows, cols) = data.dim();
+        let pairs = Self::coefficient_pairs(rows, cols);
+        if expected_length > pairs.len() {
+            return Err(WatermarkError::InvalidArgument(
+                Message::ExpectedExceedsDftRing.format(&[&pairs.len(), &expected_length]),
+            ));
+        }
+
+        let spectrum = Self::spectrum(data);
+        Ok(pairs[..expected_length]
+            .iter()
+            .map(|&(a, b)| {
+                spectrum[Self::index(rows, cols, a)].norm()
+                    - spectrum[Self::index(rows, cols, b)].norm()
+            })
+            .collect())
+    }
+}
+
+impl Default for DftWatermark {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WatermarkAlgorithm for DftWatermark {
+    fn embed(&self, data: &Array2<f64>, watermark: &[u8], strength: f64) -> Result<Array2<f64>> {
+        let (rows, cols) = data.dim();
+        let pairs = Self::coefficient_pairs(rows, cols);
+        WatermarkUtils::ensure_capacity(watermark.len(), pairs.len())?;
+
+        let difference = (MIN_DIFFERENCE + DIFFERENCE_RANGE * strength) * (rows * cols) as f64;
+
+        let mut spectrum = Self::spectrum(data);
+        for (&bit, &(a, b)) in watermark.iter().zip(&pairs) {
+            let magnitude_a = spectrum[Self::index(rows, cols, a)].norm();
+            let magnitude_b = spectrum[Self::index(rows, cols, b)].norm();
+            // 平均幅度不足半个幅度差时较小的一个直接置零，幅度不能为负
+            let mean = ((magnitude_a + magnitude_b) / 2.0).max(difference / 2.0);
+            let (high, low) = (mean + difference / 2.0, mean - difference / 2.0);
+            let (target_a, target_b) = if bit == 1 { (high, low) } else { (low, high) };
+            Self::set_magnitude(&mut spectrum, a, target_a);
+            Self::set_magnitude(&mut spectrum, b, target_b);
+        }
+
+        Self::fft2(&mut spectrum, true);
+        let scale = (rows * cols) as f64;
+        Ok(spectrum.mapv(|c| c.re / scale))
+    }
+
+    fn extract(&self, data: &Array2<f64>, expected_length: usize) -> Result<Vec<u8>> {
+        Ok(self
+            .extract_soft(data, expected_length)?
+            .into_iter()
+            .map(|(bit, _)| bit)
+            .collect())
+    }
+
+    /// 嵌入后所有系数对的幅度差都相同，置信度取每对与其中位数的接近程度；
+    /// 没有水印时幅度差分布分散，置信度明显偏低。
+    fn extract_soft(&self, data: &Array2<f64>, expected_length: usize) -> Result<Vec<(u8, f64)>> {
+        let differences = self.pair_differences(data, expected_length)?;
+
+        let mut magnitudes: Vec<f64> = differences.iter().map(|d| d.abs()).collect();
+        magnitudes.sort_by(f64::total_cmp);
+        let reference = magnitudes.get(magnitudes.len() / 2).copied().unwrap_or(0.0);
+
+        Ok(differences
+            .into_iter()
+            .map(|difference| {
+                let bit = if difference > 0.0 { 1 } else { 0 };
+                let confidence = if reference > f64::EPSILON {
+                    (1.0 - (difference.abs() - reference).abs() / reference).max(0.0)
+                } else {
+                    0.0
+                };
+                (bit, confidence)
+            })
+            .collect())
+    }
+
+    fn capacity(&self, rows: usize, cols: usize) -> usize {
+        Self::coefficient_pairs(rows, cols).len()
+    }
+
+    /// 音频没有二维频谱，暂不支持
+    fn supports(&self, media_type: MediaType) -> bool {
+        !matches!(media_type, MediaType::Audio)
+    }
+
+    fn name(&self) -> &'static str {
+        "DFT"
+    }
+}
@@ -0,0 +1,238 @@
+use crate::cli::EccScheme;
+use crate::error::{Result, WatermarkError};
+use crate::i18n::Message;
+use crate::watermark::r#trait::WatermarkUtils;
+
+/// Hamming(7,4) 每个码字携带的数据比特数
+const HAMMING_DATA_BITS: usize = 4;
+
+/// Hamming(7,4) 每个码字的比特数
+const HAMMING_CODE_BITS: usize = 7;
+
+/// Hamming(7,4) 编码：每4个数据比特附加3个校验比特，组成7比特码字
+///
+/// 码字按位置1-7排列为 `p1 p2 d1 p3 d2 d3 d4`，校验位 p1、p2、p3 分别覆盖位置序号
+/// 第0、1、2位为1的所有位置。数据比特数不是4的倍数时末尾按0补齐。
+pub fn hamming74_encode(bits: &[u8]) -> Vec<u8> {
+    let mut encoded =
+        Vec::with_capacity(bits.len().div_ceil(HAMMING_DATA_BITS) * HAMMING_CODE_BITS);
+    for chunk in bits.chunks(HAMMING_DATA_BITS) {
+        let mut data = [0u8; HAMMING_DATA_BITS];
+        for (slot, &bit) in data.iter_mut().zip(chunk) {
+            *slot = bit & 1;
+        }
+        let [d1, d2, d3, d4] = data;
+        encoded.extend_from_slice(&[d1 ^ d2 ^ d4, d1 ^ d3 ^ d4, d1, d2 ^ d3 ^ d4, d2, d3, d4]);
+    }
+    encoded
+}
+
+/// Hamming(7,4) 解码，返回数据比特和纠正的比特数
+///
+/// 每个码字能纠正1个错误比特；同一码字内有2个及以上错误时无法发现，
+/// 会被“纠正”为错误的数据，须由签名或文本校验等上层检查发现。末尾不足7比特的部分被忽略。
+pub fn hamming74_decode(bits: &[u8]) -> (Vec<u8>, usize) {
+    let mut decoded = Vec::with_capacity(bits.len() / HAMMING_CODE_BITS * HAMMING_DATA_BITS);
+    let mut corrected = 0;
+    for chunk in bits.chunks_exact(HAMMING_CODE_BITS) {
+        let mut code = [0u8; HAMMING_CODE_BITS];
+        for (slot, &bit) in code.iter_mut().zip(chunk) {
+            *slot = bit & 1;
+        }
+        // 校验子即出错比特的位置（1-7），0 表示没有错误
+        let syndrome = (code[0] ^ code[2] ^ code[4] ^ code[6]) as usize
+            | ((code[1] ^ code[2] ^ code[5] ^ code[6]) as usize) << 1
+            | ((code[3] ^ code[4] ^ code[5] ^ code[6]) as usize) << 2;
+        if syndrome != 0 {
+            code[syndrome - 1] ^= 1;
+            corrected += 1;
+        }
+        decoded.extend_from_slice(&[code[2], code[4], code[5], code[6]]);
+    }
+    (decoded, corrected)
+}
+
+/// `data_len` 字节的载荷经过纠错编码后的字节数
+pub fn encoded_len(scheme: EccScheme, data_len: usize) -> usize {
+    match scheme {
+        EccScheme::Hamming => hamming_code_bits(data_len).div_ceil(8),
+    }
+}
+
+/// `data_len` 字节经 Hamming(7,4) 编码后的比特数，每字节为两个码字
+fn hamming_code_bits(data_len: usize) -> usize {
+    data_len * 8 / HAMMING_DATA_BITS * HAMMING_CODE_BITS
+}
+
+/// 容量为 `capacity` 字节时纠错编码前最多可容纳的载荷字节数，对编码后的长度求值即得到编码前的长度
+pub fn max_data_len(scheme: EccScheme, capacity: usize) -> usize {
+    match scheme {
+        EccScheme::Hamming => capacity * 8 / HAMMING_CODE_BITS * HAMMING_DATA_BITS / 8,
+    }
+}
+
+/// 对字节载荷做纠错编码，按高位在前展开为比特，编码后末尾不足8位的部分按0补齐
+pub fn encode(scheme: EccScheme, payload: &[u8]) -> Vec<u8> {
+    let bits = WatermarkUtils::bytes_to_bits(payload);
+    let encoded = match scheme {
+        EccScheme::Hamming => hamming74_encode(&bits),
+    };
+    WatermarkUtils::bits_to_bytes(&encoded)
+}
+
+/// 解码提取到的纠错编码载荷，还原 `data_len` 字节并返回纠正的比特数
+pub fn decode(scheme: EccScheme, encoded: &[u8], data_len: usize) -> Result<(Vec<u8>, usize)> {
+    if encoded.len() < encoded_len(scheme, data_len) {
+        return Err(WatermarkError::InvalidArgument(
+            Message::EccPayloadTooShort.format(&[
+                &encoded.len(),
+                &data_len,
+                &encoded_len(scheme, data_len),
+            ]),
+        ));
+    }
+    let bits = WatermarkUtils::bytes_to_bits(encoded);
+    let (mut decoded, corrected) = match scheme {
+        EccScheme::Hamming => hamming74_decode(&bits[..hamming_code_bits(data_len)]),
+    };
+    decoded.truncate(data_len * 8);
+    Ok((WatermarkUtils::bits_to_bytes(&decoded), corrected))
+}
+
+/// 块交织：按 `depth` 行逐行写入、逐列读出，相邻的输出比特在输入中相隔约 `比特数/depth` 位
+///
+/// 长度不足 `depth` 的整数倍时最后一列只读出存在的位置。连续不超过 `depth` 个比特的突发错误
+/// 在解交织后分散到各行，行长不小于码字长度时每个码字最多只有1个错误比特。
+pub fn interleave(bits: &[u8], depth: usize) -> Vec<u8> {
+    interleave_order(bits.len(), depth)
+        .into_iter()
+        .map(|index| bits[index])
+        .collect()
+}
+
+/// `interleave` 的逆变换
+pub fn deinterleave(bits: &[u8], depth: usize) -> Vec<u8> {
+    let mut restored = vec![0u8; bits.len()];
+    for (&bit, index) in bits.iter().zip(interleave_order(bits.len(), depth)) {
+        restored[index] = bit;
+    }
+    restored
+}
+
+/// 交织后第k个比特在交织前的位置
+fn interleave_order(len: usize, depth: usize) -> Vec<usize> {
+    let depth = depth.clamp(1, len.max(1));
+    let row_len = len.div_ceil(depth);
+    (0..row_len)
+        .flat_map(|column| (0..depth).map(move |row| row * row_len + column))
+        .filter(|&index| index < len)
+        .collect()
+}
+
+/// 载荷的纠错编码参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EccOptions {
+    /// 纠错编码方式
+    pub scheme: EccScheme,
+    /// 编码后按比特交织的深度，`None` 表示不交织
+    pub interleave: Option<usize>,
+}
+
+impl EccOptions {
+    /// 纠错编码后再按比特交织
+    pub fn encode(&self, payload: &[u8]) -> Vec<u8> {
+        let encoded = encode(self.scheme, payload);
+        match self.interleave {
+            Some(depth) => {
+                let bits = interleave(&WatermarkUtils::bytes_to_bits(&encoded), depth);
+                WatermarkUtils::bits_to_bytes(&bits)
+            }
+            None => encoded,
+        }
+    }
+
+    /// 解交织后纠错解码，编码前的长度由编码后的字节数推出，同时返回纠正的比特数
+    pub fn decode(&self, encoded: &[u8]) -> Result<(Vec<u8>, usize)> {
+        let data_len = max_data_len(self.scheme, encoded.len());
+        match self.interleave {
+            Some(depth) => {
+                let bits = deinterleave(&WatermarkUtils::bytes_to_bits(encoded), depth);
+                decode(self.scheme, &WatermarkUtils::bits_to_bytes(&bits), data_len)
+            }
+            None => decode(self.scheme, encoded, data_len),
+        }
+    }
+
+    /// `data_len` 字节的载荷编码后的字节数，交织不改变长度
+    pub fn encoded_len(&self, data_len: usize) -> usize {
+        encoded_len(self.scheme, data_len)
+    }
+
+    /// 容量为 `capacity` 字节时编码前最多可容纳的载荷字节数
+    pub fn max_data_len(&self, capacity: usize) -> usize {
+        max_data_len(self.scheme, capacity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_flipped_bit_per_codeword_is_corrected() {
+        let payload = b"ID-4711";
+        let encoded = WatermarkUtils::bytes_to_bits(&encode(EccScheme::Hamming, payload));
+        let codewords = payload.len() * 8 / HAMMING_DATA_BITS;
+
+        // 每个码字翻转同一位置的1个比特，7个位置逐一覆盖
+        for position in 0..HAMMING_CODE_BITS {
+            let mut damaged = encoded.clone();
+            for codeword in 0..codewords {
+                damaged[codeword * HAMMING_CODE_BITS + position] ^= 1;
+            }
+            let damaged = WatermarkUtils::bits_to_bytes(&damaged);
+            let (decoded, corrected) = decode(EccScheme::Hamming, &damaged, payload.len()).unwrap();
+            assert_eq!(decoded, payload);
+            assert_eq!(corrected, codewords);
+        }
+    }
+
+    #[test]
+    fn interleaving_spreads_a_burst_across_codewords() {
+        let payload = b"burst errors";
+        let burst = |options: EccOptions| {
+            let mut bits = WatermarkUtils::bytes_to_bits(&options.encode(payload));
+            // 连续翻转8个比特，相当于一小块区域被涂改
+            for bit in &mut bits[20..28] {
+                *bit ^= 1;
+            }
+            options
+                .decode(&WatermarkUtils::bits_to_bytes(&bits))
+                .unwrap()
+                .0
+        };
+
+        let plain = EccOptions {
+            scheme: EccScheme::Hamming,
+            interleave: None,
+        };
+        assert_ne!(burst(plain), payload);
+
+        let interleaved = EccOptions {
+            interleave: Some(8),
+            ..plain
+        };
+        assert_eq!(burst(interleaved), payload);
+    }
+
+    #[test]
+    fn two_flipped_bits_in_a_codeword_are_not_recovered() {
+        let data = [1, 0, 1, 1];
+        let mut code = hamming74_encode(&data);
+        code[0] ^= 1;
+        code[5] ^= 1;
+        let (decoded, corrected) = hamming74_decode(&code);
+        assert_ne!(decoded, data);
+        assert_eq!(corrected, 1);
+    }
+}
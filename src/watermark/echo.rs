@@ -0,0 +1,246 @@
+use crate::error::{Result, WatermarkError};
+use crate::i18n::Message;
+use crate::media::MediaType;
+use crate::watermark::prng::SeededRng;
+use crate::watermark::r#trait::{WatermarkAlgorithm, WatermarkUtils};
+use ndarray::Array2;
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// 每个比特占用的样本数（44.1kHz 下约93ms）
+const SEGMENT_LEN: usize = 4096;
+/// 第一个回声的延迟（样本数，约0.45ms），更短的延迟落在频谱包络的倒谱区间内
+const FIRST_DELAY: usize = 20;
+/// 回声个数，各回声的延迟依次加1，正负号由伪随机序列决定
+const ECHO_TAPS: usize = 63;
+/// 生成回声正负号序列的固定种子，嵌入和提取两端必须一致
+const ECHO_SEED: u64 = 0x4543_484F;
+/// 强度为0时回声的总幅度（所有回声能量之和的平方根）
+const MIN_ECHO: f64 = 0.1;
+/// 强度从0到1时回声总幅度的增量
+const ECHO_RANGE: f64 = 0.3;
+/// 相邻分段比特不同时两种回声交叉渐变的样本数，避免回声突变产生咔嗒声
+const TRANSITION_LEN: usize = 512;
+/// 估计倒谱噪声水平时使用的延迟范围，位于全部回声之后
+const NOISE_LAGS: std::ops::Range<usize> = 100..300;
+/// 平均能量低于该值的分段视为静音，不参与判决
+const SILENCE_ENERGY: f64 = 1e-8;
+
+/// 回声隐藏音频水印
+///
+/// 把样本按固定长度分段，每段叠加一组 0.45-1.9ms 的短回声（时间扩展回声）：
+/// 各回声的正负号取自固定的伪随机序列，比特1使用该序列，比特0使用其相反数，
+/// 听起来只是音色和混响略有变化。提取时计算每段的倒谱，与回声序列做相关后按符号判决；
+/// 音乐本身在这些延迟上的倒谱与伪随机序列不相关，单一延迟回声容易受音高干扰的问题因此被平均掉。
+/// 载荷在所有分段上循环重复，按软判决累计。只能处理一维的音频样本，不支持图片。
+pub struct EchoWatermark {
+    fft: Arc<dyn Fft<f64>>,
+    ifft: Arc<dyn Fft<f64>>,
+    /// 每个回声的正负号
+    signs: Vec<f64>,
+}
+
+impl EchoWatermark {
+    /// 创建回声隐藏水印算法实例
+    pub fn new() -> Self {
+        let mut planner = FftPlanner::new();
+        let mut rng = SeededRng::new(ECHO_SEED);
+        Self {
+            fft: planner.plan_fft_forward(SEGMENT_LEN),
+            ifft: planner.plan_fft_inverse(SEGMENT_LEN),
+            signs: (0..ECHO_TAPS)
+                .map(|_| if rng.next_u64() & 1 == 1 { 1.0 } else { -1.0 })
+                .collect(),
+        }
+    }
+
+    /// 每个样本处回声序列的符号，比特1为+1、比特0为-1，分段交界处按升余弦过渡
+    fn mixer(sample_count: usize, watermark: &[u8]) -> Vec<f64> {
+        let segments = sample_count / SEGMENT_LEN;
+        let bit_of = |segment: usize| {
+            if watermark[segment.min(segments - 1) % watermark.len()] == 1 {
+                1.0
+            } else {
+                -1.0
+            }
+        };
+
+        let mut mixer: Vec<f64> = (0..sample_count).map(|i| bit_of(i / SEGMENT_LEN)).collect();
+        for segment in 1..segments {
+            let (previous, next) = (bit_of(segment - 1), bit_of(segment));
+            if previous == next {
+                continue;
+            }
+            let start = segment * SEGMENT_LEN - TRANSITION_LEN / 2;
+            for (i, m) in mixer[start..start + TRANSITION_LEN].iter_mut().enumerate() {
+                let w = 0.5 - 0.5 * (PI * i as f64 / TRANSITION_LEN as f64).cos();
+                *m = previous + (next - previous) * w;
+            }
+        }
+        mixer
+    }
+
+    /// 加汉宁窗后的实倒谱
+    fn cepstrum(&self, segment: &[f64]) -> Vec<f64> {
+        let mut spectrum: Vec<Complex<f64>> = segment
+            .iter()
+            .enumerate()
+            .map(|(i, &s)| {
+                let window = 0.5 - 0.5 * (2.0 * PI * i as f64 / SEGMENT_LEN as f64).cos();
+                Complex::new(s * window, 0.0)
+            })
+            .collect();
+        self.fft.process(&mut spectrum);
+
+        let mut log_spectrum: Vec<Complex<f64>> = spectrum
+            .iter()
+            .map(|c| Complex::new(c.norm().max(1e-12).ln(), 0.0))
+            .collect();
+        self.ifft.process(&mut log_spectrum);
+        log_spectrum
+            .iter()
+            .map(|c| c.re / SEGMENT_LEN as f64)
+            .collect()
+    }
+
+    /// 回声之后一段延迟上的倒谱均方根，作为判决的噪声水平
+    fn cepstrum_noise(cepstrum: &[f64]) -> f64 {
+        let values = &cepstrum[NOISE_LAGS];
+        (values.iter().map(|v| v * v).sum::<f64>() / values.len() as f64).sqrt()
+    }
+
+    fn audio_only() -> WatermarkError {
+        WatermarkError::Algorithm(Message::EchoAudioOnly.text().to_string())
+    }
+}
+
+impl Default for EchoWatermark {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WatermarkAlgorithm for EchoWatermark {
+    fn embed(&self, _data: &Array2<f64>, _watermark: &[u8], _strength: f64) -> Result<Array2<f64>> {
+        Err(Self::audio_only())
+    }
+
+    fn extract(&self, _data: &Array2<f64>, _expected_length: usize) -> Result<Vec<u8>> {
+        Err(Self::audio_only())
+    }
+
+    fn embed_audio(
+        &self,
+        _data: &Array2<f64>,
+        _watermark: &[u8],
+        _strength: f64,
+    ) -> Result<Array2<f64>> {
+        Err(Self::audio_only())
+    }
+
+    fn extract_audio(&self, _data: &Array2<f64>, _expected_length: usize) -> Result<Vec<u8>> {
+        Err(Self::audio_only())
+    }
+
+    fn extract_audio_soft(
+        &self,
+        _data: &Array2<f64>,
+        _expected_length: usize,
+    ) -> Result<Vec<(u8, f64)>> {
+        Err(Self::audio_only())
+    }
+
+    /// 叠加的回声若超出满幅则限幅到 [-1.0, 1.0]
+    fn embed_samples(&self, samples: &[f64], watermark: &[u8], strength: f64) -> Result<Vec<f64>> {
+        WatermarkUtils::ensure_capacity(watermark.len(), samples.len() / SEGMENT_LEN)?;
+        if watermark.is_empty() {
+            return Ok(samples.to_vec());
+        }
+
+        // 每个回声的幅度按回声个数均分总能量
+        let tap = (MIN_ECHO + ECHO_RANGE * strength) / (ECHO_TAPS as f64).sqrt();
+        let mixer = Self::mixer(samples.len(), watermark);
+
+        Ok(samples
+            .iter()
+            .zip(&mixer)
+            .enumerate()
+            .map(|(i, (&sample, &m))| {
+                let echoes: f64 = self
+                    .signs
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(k, sign)| {
+                        let j = i.checked_sub(FIRST_DELAY + k)?;
+                        Some(sign * samples[j])
+                    })
+                    .sum();
+                (sample + m * tap * echoes).clamp(-1.0, 1.0)
+            })
+            .collect())
+    }
+
+    /// 每段倒谱与回声序列的相关值按噪声水平归一化，没有水印时近似服从标准正态分布；
+    /// 同一比特的各段累计后，置信度由偏离随机情况的程度换算，没有水印时约为0.1。
+    fn extract_samples_soft(
+        &self,
+        samples: &[f64],
+        expected_length: usize,
+    ) -> Result<Vec<(u8, f64)>> {
+        WatermarkUtils::ensure_capacity(expected_length, samples.len() / SEGMENT_LEN)?;
+        if expected_length == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut scores = vec![0.0; expected_length];
+        let mut counts = vec![0usize; expected_length];
+        for (index, segment) in samples.chunks_exact(SEGMENT_LEN).enumerate() {
+            let energy = segment.iter().map(|s| s * s).sum::<f64>() / SEGMENT_LEN as f64;
+            if energy < SILENCE_ENERGY {
+                continue;
+            }
+            let cepstrum = self.cepstrum(segment);
+            let noise = Self::cepstrum_noise(&cepstrum);
+            if noise <= f64::EPSILON {
+                continue;
+            }
+            let correlation: f64 = self
+                .signs
+                .iter()
+                .enumerate()
+                .map(|(k, sign)| sign * cepstrum[FIRST_DELAY + k])
+                .sum();
+            scores[index % expected_length] += correlation / (noise * (ECHO_TAPS as f64).sqrt());
+            counts[index % expected_length] += 1;
+        }
+
+        Ok(scores
+            .into_iter()
+            .zip(counts)
+            .map(|(score, count)| {
+                let bit = if score > 0.0 { 1 } else { 0 };
+                let confidence = if count == 0 {
+                    0.0
+                } else {
+                    let z = score / (count as f64).sqrt();
+                    1.0 - (-z * z / 8.0).exp()
+                };
+                (bit, confidence)
+            })
+            .collect())
+    }
+
+    fn capacity(&self, rows: usize, cols: usize) -> usize {
+        rows * cols / SEGMENT_LEN
+    }
+
+    fn supports(&self, media_type: MediaType) -> bool {
+        matches!(media_type, MediaType::Audio)
+    }
+
+    fn name(&self) -> &'static str {
+        "ECHO"
+    }
+}
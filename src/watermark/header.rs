@@ -0,0 +1,217 @@
+use crate::cli::{Algorithm, EccScheme, ImageChannel};
+use crate::error::{Result, WatermarkError};
+use crate::i18n::Message;
+use crate::watermark::{DctWatermark, WatermarkAlgorithm, WatermarkUtils};
+
+/// 自描述头部的魔数，与加密载荷和分片头部一样以 0xFE 开头
+const MAGIC: [u8; 2] = [0xFE, 0x03];
+
+/// 头部格式版本；版本2增加了规范尺寸
+const VERSION: u8 = 2;
+
+/// 头部字节数
+///
+/// 头部格式: `魔数(2) | 版本(1) | 算法(1) | 块边长(1, 0为默认) | 纠错编码(1, 0为无) |
+/// 交织深度(1, 0为不交织) | 颜色通道(1) | 标志(1) | 载荷长度(2, 大端) |
+/// 规范宽度(2, 大端) | 规范高度(2, 大端, 宽高均为0表示未规范化) | CRC32高16位(2)`
+pub const HEADER_LEN: usize = 17;
+
+/// 头部的比特数
+pub const HEADER_BITS: usize = HEADER_LEN * 8;
+
+/// 头部区域至少容纳的头部副本数，实际副本数为区域容量能放下的最大份数
+const MIN_COPIES: usize = 5;
+
+/// 头部区域按DCT默认的8x8块划分
+const REGION_BLOCK: usize = 8;
+
+const FLAG_BINARY: u8 = 1;
+const FLAG_ENCRYPTED: u8 = 1 << 1;
+const FLAG_SIGNED: u8 = 1 << 2;
+
+/// 记录嵌入参数的自描述头部，提取时据此自动配置，无需再指定算法和长度
+#[derive(Debug, Clone)]
+pub struct WatermarkHeader {
+    /// 载荷使用的算法
+    pub algorithm: Algorithm,
+    /// DCT块边长，`None` 表示默认的8x8块
+    pub block_size: Option<usize>,
+    /// 纠错编码方式
+    pub ecc: Option<EccScheme>,
+    /// 比特交织深度
+    pub interleave: Option<usize>,
+    /// 载荷所在的颜色通道
+    pub channel: ImageChannel,
+    /// 载荷是二进制内容而不是文本
+    pub binary: bool,
+    /// 载荷经过口令加密
+    pub encrypted: bool,
+    /// 载荷附带签名
+    pub signed: bool,
+    /// 实际嵌入的载荷字节数（含签名、加密和纠错编码的开销）
+    pub payload_len: usize,
+    /// 嵌入前缩放到的规范尺寸（宽, 高），`None` 表示按原尺寸嵌入
+    pub normalize_size: Option<(u32, u32)>,
+}
+
+impl WatermarkHeader {
+    /// 编码为 `HEADER_LEN` 字节，字段超出头部的表示范围时报错
+    pub fn encode(&self) -> Result<[u8; HEADER_LEN]> {
+        let byte = |value: Option<usize>, name: &str| -> Result<u8> {
+            u8::try_from(value.unwrap_or(0)).map_err(|_| {
+                WatermarkError::InvalidArgument(Message::HeaderFieldTooLarge.format(&[&name]))
+            })
+        };
+        let payload_len = u16::try_from(self.payload_len).map_err(|_| {
+            WatermarkError::InvalidArgument(
+                Message::HeaderPayloadTooLarge.format(&[&(u16::MAX), &self.payload_len]),
+            )
+        })?;
+        let (normalize_width, normalize_height) = match self.normalize_size {
+            Some((width, height)) => {
+                let dimension = |value: u32| {
+                    u16::try_from(value).map_err(|_| {
+                        WatermarkError::InvalidArgument(
+                            Message::HeaderSizeTooLarge.format(&[&(u16::MAX)]),
+                        )
+                    })
+                };
+                (dimension(width)?, dimension(height)?)
+            }
+            None => (0, 0),
+        };
+
+        let mut bytes = [0u8; HEADER_LEN];
+        bytes[..2].copy_from_slice(&MAGIC);
+        bytes[2] = VERSION;
+        bytes[3] = match self.algorithm {
+            Algorithm::Dct => 0,
+            Algorithm::Dft => 1,
+            Algorithm::Echo => 2,
+            Algorithm::Lsb => 3,
+        };
+        bytes[4] = byte(self.block_size, Message::HeaderBlockSize.text())?;
+        bytes[5] = match self.ecc {
+            None => 0,
+            Some(EccScheme::Hamming) => 1,
+        };
+        bytes[6] = byte(self.interleave, Message::HeaderInterleave.text())?;
+        bytes[7] = match self.channel {
+            ImageChannel::All => 0,
+            ImageChannel::R => 1,
+            ImageChannel::G => 2,
+            ImageChannel::B => 3,
+            ImageChannel::Luma => 4,
+        };
+        bytes[8] = [
+            (self.binary, FLAG_BINARY),
+            (self.encrypted, FLAG_ENCRYPTED),
+            (self.signed, FLAG_SIGNED),
+        ]
+        .iter()
+        .filter(|(set, _)| *set)
+        .fold(0, |flags, (_, flag)| flags | flag);
+        bytes[9..11].copy_from_slice(&payload_len.to_be_bytes());
+        bytes[11..13].copy_from_slice(&normalize_width.to_be_bytes());
+        bytes[13..15].copy_from_slice(&normalize_height.to_be_bytes());
+        let crc = crc16(&bytes[..15]);
+        bytes[15..].copy_from_slice(&crc);
+        Ok(bytes)
+    }
+
+    /// 解析头部字节，魔数、版本、CRC或字段取值不符时返回 `None`
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let bytes: &[u8; HEADER_LEN] = bytes.get(..HEADER_LEN)?.try_into().ok()?;
+        if bytes[..2] != MAGIC || bytes[2] != VERSION || bytes[15..] != crc16(&bytes[..15]) {
+            return None;
+        }
+        let optional = |value: u8| (value != 0).then_some(value as usize);
+        let normalize_width = u16::from_be_bytes([bytes[11], bytes[12]]) as u32;
+        let normalize_height = u16::from_be_bytes([bytes[13], bytes[14]]) as u32;
+        Some(Self {
+            algorithm: match bytes[3] {
+                0 => Algorithm::Dct,
+                1 => Algorithm::Dft,
+                2 => Algorithm::Echo,
+                3 => Algorithm::Lsb,
+                _ => return None,
+            },
+            block_size: optional(bytes[4]),
+            ecc: match bytes[5] {
+                0 => None,
+                1 => Some(EccScheme::Hamming),
+                _ => return None,
+            },
+            interleave: optional(bytes[6]),
+            channel: match bytes[7] {
+                0 => ImageChannel::All,
+                1 => ImageChannel::R,
+                2 => ImageChannel::G,
+                3 => ImageChannel::B,
+                4 => ImageChannel::Luma,
+                _ => return None,
+            },
+            binary: bytes[8] & FLAG_BINARY != 0,
+            encrypted: bytes[8] & FLAG_ENCRYPTED != 0,
+            signed: bytes[8] & FLAG_SIGNED != 0,
+            payload_len: u16::from_be_bytes([bytes[9], bytes[10]]) as usize,
+            normalize_size: match (normalize_width, normalize_height) {
+                (0, 0) => None,
+                (0, _) | (_, 0) => return None,
+                size => Some(size),
+            },
+        })
+    }
+
+    /// 在 `capacity` 比特中按顺序重复放置尽可能多份头部，返回按字节表示的全部副本
+    pub fn encode_repeated(&self, capacity: usize) -> Result<Vec<u8>> {
+        Ok(self.encode()?.repeat(capacity / HEADER_BITS))
+    }
+
+    /// 对各副本的软判决逐比特按置信度加权投票后解析头部
+    pub fn decode_repeated(decisions: &[(u8, f64)]) -> Option<Self> {
+        let copies = decisions.len() / HEADER_BITS;
+        if copies == 0 {
+            return None;
+        }
+        let bits: Vec<u8> = (0..HEADER_BITS)
+            .map(|i| {
+                let score: f64 = (0..copies)
+                    .map(|copy| match decisions[copy * HEADER_BITS + i] {
+                        (1, confidence) => confidence,
+                        (_, confidence) => -confidence,
+                    })
+                    .sum();
+                u8::from(score >= 0.0)
+            })
+            .collect();
+        Self::parse(&WatermarkUtils::bits_to_bytes(&bits))
+    }
+}
+
+/// 宽度为 `width` 像素的图片顶部用于放置头部的行数，按整块行取整，保证至少放下 `MIN_COPIES` 份头部
+///
+/// 宽度不足一个块时返回0。
+pub fn region_rows(width: usize) -> usize {
+    let blocks_per_row = width / REGION_BLOCK;
+    if blocks_per_row == 0 {
+        return 0;
+    }
+    (HEADER_BITS * MIN_COPIES).div_ceil(blocks_per_row) * REGION_BLOCK
+}
+
+/// 头部区域使用的算法：默认参数的分块DCT，提取时无需任何参数即可读取
+pub fn region_algorithm() -> DctWatermark {
+    DctWatermark::new()
+}
+
+/// 头部区域的容量（比特）
+pub fn region_capacity(rows: usize, width: usize) -> usize {
+    region_algorithm().capacity(rows, width)
+}
+
+/// CRC32 的高16位，用于校验投票后的头部
+fn crc16(bytes: &[u8]) -> [u8; 2] {
+    let crc = crc32fast::hash(bytes).to_be_bytes();
+    [crc[0], crc[1]]
+}
@@ -0,0 +1,139 @@
+use crate::error::{Result, WatermarkError};
+use crate::i18n::Message;
+use crate::watermark::r#trait::{WatermarkAlgorithm, WatermarkUtils};
+use ndarray::Array2;
+
+/// 图片像素的量化级数（u8）
+const IMAGE_LEVELS: f64 = u8::MAX as f64;
+/// 音频样本的量化级数（16位PCM）
+const AUDIO_LEVELS: f64 = i16::MAX as f64;
+/// 最多可使用的位平面数
+const MAX_BIT_PLANES: usize = 8;
+
+/// LSB（最低有效位）隐写算法
+///
+/// 将数据反标准化为整数量化值后，把水印比特直接写入最低的若干个位平面，
+/// 容量大且在无损格式（PNG/WAV）中可以逐位精确恢复。
+/// 任何有损重新编码（JPEG、MP3、H.264 等）都会破坏水印，`strength` 参数不起作用。
+pub struct LsbWatermark {
+    bit_planes: usize,
+}
+
+impl LsbWatermark {
+    /// 创建只使用最低一个位平面的LSB算法实例
+    pub fn new() -> Self {
+        Self { bit_planes: 1 }
+    }
+
+    /// 设置使用的位平面数（1-8），位平面越多容量越大、失真也越大
+    pub fn with_bit_planes(mut self, bit_planes: usize) -> Self {
+        self.bit_planes = bit_planes;
+        self
+    }
+
+    fn validate_bit_planes(&self) -> Result<()> {
+        if self.bit_planes == 0 || self.bit_planes > MAX_BIT_PLANES {
+            return Err(WatermarkError::InvalidArgument(
+                Message::BitPlanesInvalid.format(&[&MAX_BIT_PLANES, &self.bit_planes]),
+            ));
+        }
+        Ok(())
+    }
+
+    /// 按给定量化级数写入水印比特，每个数值依次承载 `bit_planes` 个比特
+    fn embed_with_levels(
+        &self,
+        data: &Array2<f64>,
+        watermark: &[u8],
+        levels: f64,
+    ) -> Result<Array2<f64>> {
+        self.validate_bit_planes()?;
+
+        WatermarkUtils::ensure_capacity(
+            watermark.len(),
+            self.capacity(data.nrows(), data.ncols()),
+        )?;
+
+        let mut result = data.clone();
+        for (value, bits) in result.iter_mut().zip(watermark.chunks(self.bit_planes)) {
+            let mut level = (*value * levels).round() as i64;
+            for (plane, &bit) in bits.iter().enumerate() {
+                level = (level & !(1 << plane)) | (i64::from(bit & 1) << plane);
+            }
+            *value = level as f64 / levels;
+        }
+
+        Ok(result)
+    }
+
+    /// 按给定量化级数读取水印比特
+    fn extract_with_levels(
+        &self,
+        data: &Array2<f64>,
+        expected_length: usize,
+        levels: f64,
+    ) -> Result<Vec<u8>> {
+        self.validate_bit_planes()?;
+
+        let capacity = self.capacity(data.nrows(), data.ncols());
+        if expected_length > capacity {
+            return Err(WatermarkError::InvalidArgument(
+                Message::ExpectedExceedsBits.format(&[&expected_length, &capacity]),
+            ));
+        }
+
+        let bit_planes = self.bit_planes;
+        let bits = data
+            .iter()
+            .flat_map(|&value| {
+                let level = (value * levels).round() as i64;
+                (0..bit_planes).map(move |plane| ((level >> plane) & 1) as u8)
+            })
+            .take(expected_length)
+            .collect();
+
+        Ok(bits)
+    }
+}
+
+impl Default for LsbWatermark {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WatermarkAlgorithm for LsbWatermark {
+    fn embed(&self, data: &Array2<f64>, watermark: &[u8], _strength: f64) -> Result<Array2<f64>> {
+        self.embed_with_levels(data, watermark, IMAGE_LEVELS)
+    }
+
+    fn extract(&self, data: &Array2<f64>, expected_length: usize) -> Result<Vec<u8>> {
+        self.extract_with_levels(data, expected_length, IMAGE_LEVELS)
+    }
+
+    fn embed_audio(
+        &self,
+        data: &Array2<f64>,
+        watermark: &[u8],
+        _strength: f64,
+    ) -> Result<Array2<f64>> {
+        self.embed_with_levels(data, watermark, AUDIO_LEVELS)
+    }
+
+    fn extract_audio(&self, data: &Array2<f64>, expected_length: usize) -> Result<Vec<u8>> {
+        self.extract_with_levels(data, expected_length, AUDIO_LEVELS)
+    }
+
+    fn capacity(&self, rows: usize, cols: usize) -> usize {
+        rows * cols * self.bit_planes
+    }
+
+    /// 最低位在有损压缩和任何像素或样本修改后都会丢失
+    fn robust(&self) -> bool {
+        false
+    }
+
+    fn name(&self) -> &'static str {
+        "LSB"
+    }
+}
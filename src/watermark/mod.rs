@@ -1,20 +1,132 @@
+pub mod crypto;
 pub mod dct;
+pub mod dft;
+pub mod ecc;
+pub mod echo;
+pub mod header;
+pub mod lsb;
+pub(crate) mod prng;
+pub mod psychoacoustic;
+pub mod repetition;
+pub mod split;
 pub mod r#trait;
 
 pub use dct::DctWatermark;
-pub use r#trait::{WatermarkAlgorithm, WatermarkUtils};
+pub use dft::DftWatermark;
+pub use echo::EchoWatermark;
+pub use header::WatermarkHeader;
+pub use lsb::LsbWatermark;
+pub use psychoacoustic::PsychoacousticWatermark;
+pub use r#trait::{StripPlan, WatermarkAlgorithm, WatermarkUtils};
+pub use repetition::{Repetition, RepetitionWatermark};
 
-use crate::cli::Algorithm;
+use crate::cli::{Algorithm, DctMode, FrequencyBand};
+use clap::ValueEnum;
 use std::sync::Arc;
 
+/// 创建水印算法时的可选参数
+#[derive(Debug, Clone, Default)]
+pub struct AlgorithmOptions {
+    /// 块置换种子，`None` 表示按光栅顺序嵌入
+    pub seed: Option<u64>,
+    /// DCT块边长，`None` 表示使用默认的8x8块
+    pub block_size: Option<usize>,
+    /// 自定义DCT系数位置列表，`None` 表示使用默认中频位置
+    pub positions: Option<Vec<(usize, usize)>>,
+    /// LSB算法使用的位平面数，`None` 表示只使用最低位
+    pub bit_planes: Option<usize>,
+    /// DCT扩频模式每个比特的码片数，`None` 表示使用单系数符号嵌入
+    pub spread_chips: Option<usize>,
+    /// 重复编码方式，`None` 表示载荷只写入一份
+    pub repeat: Option<Repetition>,
+    /// DCT分块模式的图块边长，`None` 表示整幅图像只嵌入一份载荷
+    pub tile_size: Option<usize>,
+    /// DCT差分模式：比特由一对系数的大小关系表示
+    pub differential: bool,
+    /// DCT量化索引调制的步长，`None` 表示使用条件符号嵌入
+    pub qim_step: Option<f64>,
+    /// DCT变换方式，默认分块DCT
+    pub dct_mode: DctMode,
+    /// DCT分块模式承载水印的频带，默认中频；设置了 `positions` 时不起作用
+    pub band: FrequencyBand,
+    /// 使用基于STFT子频带的心理声学音频水印，替代所选算法
+    pub psychoacoustic: bool,
+    /// DCT感知加权的块方差阈值（与数据同一尺度），`None` 表示使用默认阈值
+    pub perceptual_threshold: Option<f64>,
+}
+
 /// 水印算法工厂
 pub struct WatermarkFactory;
 
 impl WatermarkFactory {
     /// 根据算法类型创建水印算法实例
     pub fn create_algorithm(algorithm: Algorithm) -> Arc<dyn WatermarkAlgorithm + Send + Sync> {
-        match algorithm {
-            Algorithm::Dct => Arc::new(DctWatermark::new()),
+        Self::create_algorithm_with_options(algorithm, &AlgorithmOptions::default())
+    }
+
+    /// 以默认参数创建全部可选算法，按命令行 `-a` 的顺序排列
+    pub fn all_algorithms() -> Vec<(Algorithm, Arc<dyn WatermarkAlgorithm + Send + Sync>)> {
+        Algorithm::value_variants()
+            .iter()
+            .map(|algorithm| (algorithm.clone(), Self::create_algorithm(algorithm.clone())))
+            .collect()
+    }
+
+    /// 根据算法类型和可选参数创建水印算法实例
+    pub fn create_algorithm_with_options(
+        algorithm: Algorithm,
+        options: &AlgorithmOptions,
+    ) -> Arc<dyn WatermarkAlgorithm + Send + Sync> {
+        let algorithm: Arc<dyn WatermarkAlgorithm + Send + Sync> = match algorithm {
+            _ if options.psychoacoustic => Arc::new(PsychoacousticWatermark::new()),
+            Algorithm::Dct => {
+                let mut dct = DctWatermark::new();
+                if let Some(block_size) = options.block_size {
+                    dct = dct.with_block_size(block_size);
+                }
+                if let Some(seed) = options.seed {
+                    dct = dct.with_seed(seed);
+                }
+                if let Some(positions) = &options.positions {
+                    dct = dct.with_positions(positions.clone());
+                }
+                if let Some(chips) = options.spread_chips {
+                    dct = dct.with_spread_spectrum(chips);
+                }
+                if let Some(tile_size) = options.tile_size {
+                    dct = dct.with_tiles(tile_size);
+                }
+                if options.differential {
+                    dct = dct.with_differential(true);
+                }
+                if let Some(step) = options.qim_step {
+                    dct = dct.with_qim(step);
+                }
+                if options.dct_mode != DctMode::Block {
+                    dct = dct.with_mode(options.dct_mode);
+                }
+                if options.band != FrequencyBand::Mid {
+                    dct = dct.with_band(options.band);
+                }
+                if let Some(threshold) = options.perceptual_threshold {
+                    dct = dct.with_perceptual_threshold(threshold);
+                }
+                Arc::new(dct)
+            }
+            Algorithm::Dft => Arc::new(DftWatermark::new()),
+            Algorithm::Echo => Arc::new(EchoWatermark::new()),
+            Algorithm::Lsb => {
+                let mut lsb = LsbWatermark::new();
+                if let Some(bit_planes) = options.bit_planes {
+                    lsb = lsb.with_bit_planes(bit_planes);
+                }
+                Arc::new(lsb)
+            }
+        };
+
+        match options.repeat {
+            Some(repetition) => Arc::new(RepetitionWatermark::new(algorithm, repetition)),
+            None => algorithm,
         }
     }
 }
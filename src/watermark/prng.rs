@@ -0,0 +1,39 @@
+/// 基于 SplitMix64 的确定性伪随机数生成器
+///
+/// 相同的种子总是产生相同的序列，嵌入与提取两端据此还原同一个置换。
+#[derive(Debug, Clone)]
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    /// 使用给定种子创建生成器
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// 生成下一个64位随机数
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// 生成 [0, bound) 范围内的随机整数
+    pub fn next_below(&mut self, bound: usize) -> usize {
+        if bound == 0 {
+            return 0;
+        }
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    /// Fisher-Yates 洗牌
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.next_below(i + 1);
+            items.swap(i, j);
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use crate::error::{Result, WatermarkError};
+use crate::i18n::Message;
+use crate::media::MediaType;
+use crate::watermark::r#trait::WatermarkAlgorithm;
+use ndarray::Array2;
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use std::f64::consts::PI;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// 每帧的样本数（44.1kHz 下约46ms）
+const FRAME_SIZE: usize = 2048;
+/// 音频流水线统一的采样率
+const SAMPLE_RATE: f64 = 44_100.0;
+/// 承载水印的频段下限 (Hz)
+const BAND_LOW_HZ: f64 = 1_000.0;
+/// 承载水印的频段上限 (Hz)，更高的频段不使用，低通滤波不会影响水印
+const BAND_HIGH_HZ: f64 = 6_000.0;
+/// 每帧划分的子频带数，每个子频带承载一个比特
+const BANDS_PER_FRAME: usize = 8;
+/// 子频带能量量化步长 (dB)
+const STEP_DB: f64 = 3.0;
+/// 低于该电平 (dB) 的子频带视为静音，无法掩蔽修改，不承载水印
+const SILENCE_FLOOR_DB: f64 = -70.0;
+/// 帧边缘的渐变长度，避免修改量在帧边界处突变产生咔嗒声
+const TAPER_LEN: usize = FRAME_SIZE / 16;
+
+/// 心理声学音频水印
+///
+/// 将音频按帧做短时傅里叶变换，在 1-6kHz 的子频带能量（dB）上做量化索引调制：
+/// 每个子频带整体乘以一个增益，使其电平落到比特对应的量化格点上。
+/// 修改量与子频带自身能量成正比，被该频带原有的较响内容掩蔽；
+/// 过安静的子频带不承载水印。载荷比特在所有子频带上循环重复，提取时按软判决投票。
+pub struct PsychoacousticWatermark {
+    fft: Arc<dyn Fft<f64>>,
+    ifft: Arc<dyn Fft<f64>>,
+}
+
+impl PsychoacousticWatermark {
+    /// 创建心理声学水印算法实例
+    pub fn new() -> Self {
+        let mut planner = FftPlanner::new();
+        Self {
+            fft: planner.plan_fft_forward(FRAME_SIZE),
+            ifft: planner.plan_fft_inverse(FRAME_SIZE),
+        }
+    }
+
+    /// 第 `band` 个子频带对应的FFT频点范围
+    fn band_bins(band: usize) -> Range<usize> {
+        let to_bin = |hz: f64| (hz * FRAME_SIZE as f64 / SAMPLE_RATE).round() as usize;
+        let low = to_bin(BAND_LOW_HZ);
+        let width = (to_bin(BAND_HIGH_HZ) - low) / BANDS_PER_FRAME;
+        low + band * width..low + (band + 1) * width
+    }
+
+    /// 子频带的平均电平 (dB)，满幅正弦约为0dB
+    fn band_level_db(spectrum: &[Complex<f64>], bins: Range<usize>) -> f64 {
+        let full_scale = (FRAME_SIZE as f64 / 2.0).powi(2);
+        let count = bins.len() as f64;
+        let energy: f64 = spectrum[bins].iter().map(|c| c.norm_sqr()).sum();
+        10.0 * (energy / (count * full_scale)).max(1e-30).log10()
+    }
+
+    /// 比特对应的最近量化格点：比特0位于 `k*STEP_DB`，比特1位于 `(k+0.5)*STEP_DB`
+    fn quantize_level(level: f64, bit: u8) -> f64 {
+        let offset = if bit == 1 { STEP_DB / 2.0 } else { 0.0 };
+        ((level - offset) / STEP_DB).round() * STEP_DB + offset
+    }
+
+    /// 帧内的渐变权重
+    fn taper(i: usize) -> f64 {
+        let edge = i.min(FRAME_SIZE - 1 - i);
+        if edge >= TAPER_LEN {
+            1.0
+        } else {
+            0.5 - 0.5 * (PI * edge as f64 / TAPER_LEN as f64).cos()
+        }
+    }
+
+    /// 对一维样本逐帧嵌入，`strength` 决定向格点移动的比例
+    fn embed_samples(&self, samples: &mut [f64], watermark: &[u8], strength: f64) {
+        if watermark.is_empty() {
+            return;
+        }
+
+        // 部分补偿的量化索引调制：强度为1时完全移到格点，强度为0时也至少移动四分之三
+        let alpha = 0.75 + 0.25 * strength;
+
+        for (frame_idx, frame) in samples.chunks_exact_mut(FRAME_SIZE).enumerate() {
+            let mut spectrum: Vec<Complex<f64>> =
+                frame.iter().map(|&s| Complex::new(s, 0.0)).collect();
+            self.fft.process(&mut spectrum);
+
+            let mut delta = vec![Complex::new(0.0, 0.0); FRAME_SIZE];
+            for band in 0..BANDS_PER_FRAME {
+                let bins = Self::band_bins(band);
+                let level = Self::band_level_db(&spectrum, bins.clone());
+                if level < SILENCE_FLOOR_DB {
+                    continue;
+                }
+
+                let bit = watermark[(frame_idx * BANDS_PER_FRAME + band) % watermark.len()];
+                let target = level + alpha * (Self::quantize_level(level, bit) - level);
+                let gain = 10f64.powf((target - level) / 20.0) - 1.0;
+
+                // 同时修改共轭对称的负频率，保证逆变换后仍为实信号
+                for k in bins {
+                    delta[k] = spectrum[k] * gain;
+                    delta[FRAME_SIZE - k] = spectrum[FRAME_SIZE - k] * gain;
+                }
+            }
+
+            self.ifft.process(&mut delta);
+            for (i, (sample, d)) in frame.iter_mut().zip(&delta).enumerate() {
+                *sample += d.re / FRAME_SIZE as f64 * Self::taper(i);
+            }
+        }
+    }
+
+    /// 逐帧读取子频带电平，按比特位置累计软判决
+    fn extract_samples(&self, samples: &[f64], expected_length: usize) -> Vec<(u8, f64)> {
+        if expected_length == 0 {
+            return Vec::new();
+        }
+
+        let mut scores = vec![0.0; expected_length];
+        let mut counts = vec![0usize; expected_length];
+
+        for (frame_idx, frame) in samples.chunks_exact(FRAME_SIZE).enumerate() {
+            let mut spectrum: Vec<Complex<f64>> =
+                frame.iter().map(|&s| Complex::new(s, 0.0)).collect();
+            self.fft.process(&mut spectrum);
+
+            for band in 0..BANDS_PER_FRAME {
+                let level = Self::band_level_db(&spectrum, Self::band_bins(band));
+                if level < SILENCE_FLOOR_DB {
+                    continue;
+                }
+
+                // 相对格点的相位：比特0附近为+1，比特1附近为-1
+                let phase = (level / STEP_DB).rem_euclid(1.0);
+                let idx = (frame_idx * BANDS_PER_FRAME + band) % expected_length;
+                scores[idx] += (2.0 * PI * phase).cos();
+                counts[idx] += 1;
+            }
+        }
+
+        scores
+            .into_iter()
+            .zip(counts)
+            .map(|(score, count)| {
+                let bit = if score < 0.0 { 1 } else { 0 };
+                let confidence = if count == 0 {
+                    0.0
+                } else {
+                    (score.abs() / count as f64).min(1.0)
+                };
+                (bit, confidence)
+            })
+            .collect()
+    }
+}
+
+impl Default for PsychoacousticWatermark {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WatermarkAlgorithm for PsychoacousticWatermark {
+    /// 按行展开数据矩阵后处理，与音频折叠矩阵的样本顺序一致
+    fn embed(&self, data: &Array2<f64>, watermark: &[u8], strength: f64) -> Result<Array2<f64>> {
+        let mut samples: Vec<f64> = data.iter().copied().collect();
+        self.embed_samples(&mut samples, watermark, strength);
+        Array2::from_shape_vec(data.dim(), samples).map_err(|e| {
+            WatermarkError::ProcessingError(Message::SampleReshapeFailed.format(&[&e]))
+        })
+    }
+
+    fn extract(&self, data: &Array2<f64>, expected_length: usize) -> Result<Vec<u8>> {
+        Ok(self
+            .extract_soft(data, expected_length)?
+            .into_iter()
+            .map(|(bit, _)| bit)
+            .collect())
+    }
+
+    fn extract_soft(&self, data: &Array2<f64>, expected_length: usize) -> Result<Vec<(u8, f64)>> {
+        let samples: Vec<f64> = data.iter().copied().collect();
+        Ok(self.extract_samples(&samples, expected_length))
+    }
+
+    fn extract_audio_soft(
+        &self,
+        data: &Array2<f64>,
+        expected_length: usize,
+    ) -> Result<Vec<(u8, f64)>> {
+        self.extract_soft(data, expected_length)
+    }
+
+    fn capacity(&self, rows: usize, cols: usize) -> usize {
+        rows * cols / FRAME_SIZE * BANDS_PER_FRAME
+    }
+
+    fn supports(&self, media_type: MediaType) -> bool {
+        matches!(media_type, MediaType::Audio)
+    }
+
+    fn name(&self) -> &'static str {
+        "STFT"
+    }
+}
@@ -0,0 +1,149 @@
+use crate::error::{Result, WatermarkError};
+use crate::i18n::Message;
+use crate::media::MediaType;
+use crate::watermark::r#trait::WatermarkAlgorithm;
+use ndarray::Array2;
+use std::sync::Arc;
+
+/// 重复编码的次数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Repetition {
+    /// 按容量自动计算，尽可能多地重复以填满所有可用位置
+    Auto,
+    /// 固定重复次数
+    Times(usize),
+}
+
+/// 重复编码包装器
+///
+/// 把载荷比特按 `[第1份][第2份]...` 的顺序重复写入内层算法，
+/// 提取时对每个比特位置在所有副本上做软判决加权的多数表决。
+/// 短水印因此能利用空闲的块容量，在局部损伤和强压缩下更稳健。
+pub struct RepetitionWatermark {
+    inner: Arc<dyn WatermarkAlgorithm + Send + Sync>,
+    repetition: Repetition,
+}
+
+impl RepetitionWatermark {
+    /// 用给定的重复方式包装内层算法
+    pub fn new(inner: Arc<dyn WatermarkAlgorithm + Send + Sync>, repetition: Repetition) -> Self {
+        Self { inner, repetition }
+    }
+
+    /// 计算给定尺寸下 `bits` 个载荷比特的重复次数
+    fn copies(&self, rows: usize, cols: usize, bits: usize) -> Result<usize> {
+        let copies = match self.repetition {
+            Repetition::Auto => self.inner.capacity(rows, cols) / bits.max(1),
+            Repetition::Times(times) => times,
+        };
+        if copies == 0 {
+            return Err(WatermarkError::InvalidArgument(
+                Message::RepetitionTooFew.format(&[&self.inner.capacity(rows, cols), &bits]),
+            ));
+        }
+        Ok(copies)
+    }
+
+    /// 对每个比特位置的所有副本做多数表决，置信度为加权票差占副本数的比例
+    fn majority_decode(soft_bits: &[(u8, f64)], expected_length: usize) -> Vec<(u8, f64)> {
+        if expected_length == 0 {
+            return Vec::new();
+        }
+
+        let copies = soft_bits.len() / expected_length;
+        (0..expected_length)
+            .map(|i| {
+                let score: f64 = soft_bits
+                    .iter()
+                    .skip(i)
+                    .step_by(expected_length)
+                    .map(|&(bit, confidence)| if bit == 1 { confidence } else { -confidence })
+                    .sum();
+                let bit = if score > 0.0 { 1 } else { 0 };
+                (bit, (score.abs() / copies as f64).min(1.0))
+            })
+            .collect()
+    }
+}
+
+impl WatermarkAlgorithm for RepetitionWatermark {
+    fn embed(&self, data: &Array2<f64>, watermark: &[u8], strength: f64) -> Result<Array2<f64>> {
+        let copies = self.copies(data.nrows(), data.ncols(), watermark.len())?;
+        self.inner.embed(data, &watermark.repeat(copies), strength)
+    }
+
+    fn extract(&self, data: &Array2<f64>, expected_length: usize) -> Result<Vec<u8>> {
+        Ok(self
+            .extract_soft(data, expected_length)?
+            .into_iter()
+            .map(|(bit, _)| bit)
+            .collect())
+    }
+
+    fn extract_soft(&self, data: &Array2<f64>, expected_length: usize) -> Result<Vec<(u8, f64)>> {
+        let copies = self.copies(data.nrows(), data.ncols(), expected_length)?;
+        let soft_bits = self.inner.extract_soft(data, expected_length * copies)?;
+        Ok(Self::majority_decode(&soft_bits, expected_length))
+    }
+
+    fn embed_audio(
+        &self,
+        data: &Array2<f64>,
+        watermark: &[u8],
+        strength: f64,
+    ) -> Result<Array2<f64>> {
+        let copies = self.copies(data.nrows(), data.ncols(), watermark.len())?;
+        self.inner
+            .embed_audio(data, &watermark.repeat(copies), strength)
+    }
+
+    fn extract_audio(&self, data: &Array2<f64>, expected_length: usize) -> Result<Vec<u8>> {
+        Ok(self
+            .extract_audio_soft(data, expected_length)?
+            .into_iter()
+            .map(|(bit, _)| bit)
+            .collect())
+    }
+
+    fn extract_audio_soft(
+        &self,
+        data: &Array2<f64>,
+        expected_length: usize,
+    ) -> Result<Vec<(u8, f64)>> {
+        let copies = self.copies(data.nrows(), data.ncols(), expected_length)?;
+        let soft_bits = self
+            .inner
+            .extract_audio_soft(data, expected_length * copies)?;
+        Ok(Self::majority_decode(&soft_bits, expected_length))
+    }
+
+    /// 所有副本所在的块都计入置信度图
+    fn block_confidence(&self, data: &Array2<f64>, expected_length: usize) -> Result<Array2<f64>> {
+        let copies = self.copies(data.nrows(), data.ncols(), expected_length)?;
+        self.inner.block_confidence(data, expected_length * copies)
+    }
+
+    fn capacity(&self, rows: usize, cols: usize) -> usize {
+        let capacity = self.inner.capacity(rows, cols);
+        match self.repetition {
+            Repetition::Auto => capacity,
+            Repetition::Times(times) => capacity / times.max(1),
+        }
+    }
+
+    fn alignment(&self) -> usize {
+        self.inner.alignment()
+    }
+
+    fn supports(&self, media_type: MediaType) -> bool {
+        self.inner.supports(media_type)
+    }
+
+    fn robust(&self) -> bool {
+        self.inner.robust()
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
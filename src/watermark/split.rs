@@ -0,0 +1,198 @@
+use crate::error::{Result, WatermarkError};
+use crate::i18n::Message;
+
+/// 分片载荷的魔数，与加密载荷一样以 0xFE 开头，不会与UTF-8文本混淆
+const MAGIC: [u8; 2] = [0xFE, 0x02];
+
+/// 每个分片前附加的头部字节数
+///
+/// 头部格式: `魔数(2) | 分片序号(1, 从0开始) | 分片总数(1) | 本片数据长度(2, 大端)`
+pub const PART_HEADER_LEN: usize = MAGIC.len() + 4;
+
+/// 单个载荷最多拆分的分片数
+pub const MAX_PARTS: usize = u8::MAX as usize;
+
+/// 分片头部
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartHeader {
+    /// 分片序号，从0开始
+    pub index: usize,
+    /// 分片总数
+    pub count: usize,
+    /// 本片携带的载荷字节数（不含头部）
+    pub chunk_len: usize,
+}
+
+impl PartHeader {
+    /// 从提取到的字节开头解析分片头部，不是分片格式时返回 `None`
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < PART_HEADER_LEN || !bytes.starts_with(&MAGIC) {
+            return None;
+        }
+        let header = Self {
+            index: bytes[2] as usize,
+            count: bytes[3] as usize,
+            chunk_len: u16::from_be_bytes([bytes[4], bytes[5]]) as usize,
+        };
+        (header.count > 0 && header.index < header.count).then_some(header)
+    }
+
+    fn encode(&self) -> [u8; PART_HEADER_LEN] {
+        let len = (self.chunk_len as u16).to_be_bytes();
+        [
+            MAGIC[0],
+            MAGIC[1],
+            self.index as u8,
+            self.count as u8,
+            len[0],
+            len[1],
+        ]
+    }
+}
+
+/// 按各图片的容量（字节）依次把载荷拆成带头部的分片
+///
+/// 每张图片放入 `容量 - PART_HEADER_LEN` 字节的数据，连头部都放不下的图片被跳过，
+/// 载荷放完后剩余的图片不再使用；返回（图片下标, 分片），分片数可能少于图片数。
+/// 容量不足时报错并给出还缺少的字节数。
+pub fn split_payload(payload: &[u8], capacities: &[usize]) -> Result<Vec<(usize, Vec<u8>)>> {
+    let mut chunks = Vec::new();
+    let mut remaining = payload;
+    for (target, &capacity) in capacities.iter().enumerate() {
+        if remaining.is_empty() && !chunks.is_empty() {
+            break;
+        }
+        let room = capacity
+            .saturating_sub(PART_HEADER_LEN)
+            .min(u16::MAX as usize);
+        if room == 0 {
+            continue;
+        }
+        let (chunk, rest) = remaining.split_at(room.min(remaining.len()));
+        chunks.push((target, chunk));
+        remaining = rest;
+    }
+
+    if !remaining.is_empty() {
+        return Err(WatermarkError::InvalidArgument(
+            Message::SplitCapacityShort.format(&[&capacities.len(), &remaining.len()]),
+        ));
+    }
+    if chunks.len() > MAX_PARTS {
+        return Err(WatermarkError::InvalidArgument(
+            Message::SplitTooManyParts.format(&[&chunks.len(), &MAX_PARTS]),
+        ));
+    }
+
+    let count = chunks.len();
+    Ok(chunks
+        .into_iter()
+        .enumerate()
+        .map(|(index, (target, chunk))| {
+            let header = PartHeader {
+                index,
+                count,
+                chunk_len: chunk.len(),
+            };
+            (target, [header.encode().as_slice(), chunk].concat())
+        })
+        .collect())
+}
+
+/// 载荷在每张图片容量均为 `capacity` 字节时需要的分片数，单张图片连头部都放不下时返回 `None`
+pub fn parts_needed(payload_len: usize, capacity: usize) -> Option<usize> {
+    let room = capacity
+        .saturating_sub(PART_HEADER_LEN)
+        .min(u16::MAX as usize);
+    (room > 0).then(|| payload_len.div_ceil(room).max(1))
+}
+
+/// 按序号把分片重新拼接为完整载荷
+///
+/// 各分片声明的总数必须一致且序号不重复、不缺失，否则报错并列出缺少的分片序号（从1开始）。
+pub fn join_parts(mut parts: Vec<(PartHeader, Vec<u8>)>) -> Result<Vec<u8>> {
+    let count = parts
+        .first()
+        .map(|(header, _)| header.count)
+        .ok_or(WatermarkError::ExtractionFailed)?;
+    if parts.iter().any(|(header, _)| header.count != count) {
+        return Err(WatermarkError::ProcessingError(
+            Message::SplitCountMismatch.text().to_string(),
+        ));
+    }
+
+    parts.sort_by_key(|(header, _)| header.index);
+    parts.dedup_by_key(|(header, _)| header.index);
+    if parts.len() != count {
+        let missing: Vec<String> = (0..count)
+            .filter(|i| !parts.iter().any(|(header, _)| header.index == *i))
+            .map(|i| (i + 1).to_string())
+            .collect();
+        return Err(WatermarkError::ProcessingError(
+            Message::SplitPartsMissing.format(&[&count, &missing.join("、")]),
+        ));
+    }
+
+    Ok(parts.into_iter().flat_map(|(_, chunk)| chunk).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 按头部解析分片，模拟从各图片提取后的结果
+    fn parsed(parts: &[(usize, Vec<u8>)]) -> Vec<(PartHeader, Vec<u8>)> {
+        parts
+            .iter()
+            .map(|(_, part)| {
+                let header = PartHeader::parse(part).unwrap();
+                (header, part[PART_HEADER_LEN..].to_vec())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn payload_split_across_three_images_reassembles() {
+        let payload: Vec<u8> = (0..100).collect();
+        let parts = split_payload(&payload, &[40, 46, 60, 30]).unwrap();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(
+            parts
+                .iter()
+                .map(|(target, part)| (*target, part.len()))
+                .collect::<Vec<_>>(),
+            [(0, 40), (1, 46), (2, 26 + PART_HEADER_LEN)]
+        );
+
+        // 提取顺序与嵌入顺序无关
+        let mut shuffled = parsed(&parts);
+        shuffled.reverse();
+        assert_eq!(join_parts(shuffled).unwrap(), payload);
+    }
+
+    #[test]
+    fn missing_part_or_capacity_is_reported() {
+        let payload = [7u8; 50];
+        let mut parts = parsed(&split_payload(&payload, &[26, 26, 26]).unwrap());
+        parts.remove(1);
+        assert!(matches!(
+            join_parts(parts),
+            Err(WatermarkError::ProcessingError(message)) if message.contains('2')
+        ));
+
+        assert!(split_payload(&payload, &[20, 20]).is_err());
+        assert_eq!(parts_needed(50, 26), Some(3));
+        assert_eq!(parts_needed(50, PART_HEADER_LEN), None);
+    }
+
+    #[test]
+    fn image_too_small_for_a_header_is_skipped_by_index() {
+        let payload: Vec<u8> = (0..60).collect();
+        let parts = split_payload(&payload, &[40, PART_HEADER_LEN, 40]).unwrap();
+        assert_eq!(
+            parts.iter().map(|(target, _)| *target).collect::<Vec<_>>(),
+            [0, 2]
+        );
+        assert_eq!(join_parts(parsed(&parts)).unwrap(), payload);
+    }
+}
@@ -1,5 +1,48 @@
-use crate::error::Result;
+use crate::error::{Result, WatermarkError};
+use crate::i18n::Message;
+use crate::media::MediaType;
 use ndarray::Array2;
+use std::ops::Range;
+
+/// 按水平条带分段处理大图时的分块规划，由 `strip_plan` 对整幅图的尺寸生成
+#[derive(Debug, Clone)]
+pub struct StripPlan {
+    /// 块边长，条带按整块行切分
+    pub block_size: usize,
+    /// 整幅图的块网格（行数, 列数）
+    pub grid: (usize, usize),
+    /// 按行优先排列的每个块承载的比特序号，未承载比特的块为 `None`
+    pub bit_index: Vec<Option<usize>>,
+    /// 承载的比特总数
+    pub bits: usize,
+}
+
+impl StripPlan {
+    /// 把 `rows` 行切分为每段约 `strip_rows` 行的条带
+    ///
+    /// 条带高度向下取整到块边长的倍数且至少一个块，末尾不足一块的行并入最后一个条带，
+    /// 保证每个条带都从块边界开始、填充方式与整图处理一致。
+    pub fn strips(&self, rows: usize, strip_rows: usize) -> Vec<Range<usize>> {
+        let step = (strip_rows / self.block_size).max(1) * self.block_size;
+        let mut strips = Vec::new();
+        let mut start = 0;
+        while start < rows {
+            let end = if rows - start < step + self.block_size {
+                rows
+            } else {
+                start + step
+            };
+            strips.push(start..end);
+            start = end;
+        }
+        strips
+    }
+
+    /// 第 `block_row` 行第 `block_x` 列的块承载的比特序号
+    pub fn bit_at(&self, block_row: usize, block_x: usize) -> Option<usize> {
+        self.bit_index[block_row * self.grid.1 + block_x]
+    }
+}
 
 /// 水印算法的通用接口
 pub trait WatermarkAlgorithm {
@@ -24,6 +67,154 @@ pub trait WatermarkAlgorithm {
     /// 提取的水印数据
     fn extract(&self, data: &Array2<f64>, expected_length: usize) -> Result<Vec<u8>>;
 
+    /// 嵌入音频水印，默认与 `embed` 相同，算法可针对音频数据覆盖此方法
+    fn embed_audio(
+        &self,
+        data: &Array2<f64>,
+        watermark: &[u8],
+        strength: f64,
+    ) -> Result<Array2<f64>> {
+        self.embed(data, watermark, strength)
+    }
+
+    /// 提取音频水印，默认与 `extract` 相同，需与 `embed_audio` 配对覆盖
+    fn extract_audio(&self, data: &Array2<f64>, expected_length: usize) -> Result<Vec<u8>> {
+        self.extract(data, expected_length)
+    }
+
+    /// 提取水印并给出每个比特的软判决置信度 (0.0-1.0)
+    ///
+    /// 默认实现无法衡量判决可靠性，所有比特的置信度均为1.0。
+    fn extract_soft(&self, data: &Array2<f64>, expected_length: usize) -> Result<Vec<(u8, f64)>> {
+        let bits = self.extract(data, expected_length)?;
+        Ok(bits.into_iter().map(|bit| (bit, 1.0)).collect())
+    }
+
+    /// 提取音频水印并给出每个比特的软判决置信度，默认基于 `extract_audio`
+    fn extract_audio_soft(
+        &self,
+        data: &Array2<f64>,
+        expected_length: usize,
+    ) -> Result<Vec<(u8, f64)>> {
+        let bits = self.extract_audio(data, expected_length)?;
+        Ok(bits.into_iter().map(|bit| (bit, 1.0)).collect())
+    }
+
+    /// 直接在一维音频样本上嵌入水印，不经过矩阵折叠
+    ///
+    /// 只有专为音频设计的算法实现此方法，默认返回错误。
+    fn embed_samples(
+        &self,
+        _samples: &[f64],
+        _watermark: &[u8],
+        _strength: f64,
+    ) -> Result<Vec<f64>> {
+        Err(WatermarkError::InvalidArgument(
+            Message::SamplesUnsupported.format(&[&self.name()]),
+        ))
+    }
+
+    /// 从一维音频样本中提取水印并给出软判决置信度，需与 `embed_samples` 配对覆盖
+    fn extract_samples_soft(
+        &self,
+        _samples: &[f64],
+        _expected_length: usize,
+    ) -> Result<Vec<(u8, f64)>> {
+        Err(WatermarkError::InvalidArgument(
+            Message::SamplesUnsupported.format(&[&self.name()]),
+        ))
+    }
+
+    /// 按块网格给出承载前 `expected_length` 个比特的各块的提取置信度
+    ///
+    /// 返回矩阵的行列与块网格一一对应，置信度在0-1之间，未承载比特的块为 `NaN`；
+    /// 不分块的算法返回错误。
+    fn block_confidence(
+        &self,
+        _data: &Array2<f64>,
+        _expected_length: usize,
+    ) -> Result<Array2<f64>> {
+        Err(WatermarkError::InvalidArgument(
+            Message::ConfidenceMapUnsupported.format(&[&self.name()]),
+        ))
+    }
+
+    /// 为 `rows`×`cols` 的整幅图生成承载 `bits` 个比特的条带分块规划
+    ///
+    /// 只有逐块独立处理的算法可以按条带处理，其余算法返回错误。
+    fn strip_plan(&self, _rows: usize, _cols: usize, _bits: usize) -> Result<StripPlan> {
+        Err(WatermarkError::InvalidArgument(
+            Message::StripUnsupported.format(&[&self.name()]),
+        ))
+    }
+
+    /// 在从第 `first_block_row` 个块行开始的条带中嵌入规划落在其中的比特
+    fn embed_strip(
+        &self,
+        _plan: &StripPlan,
+        _strip: &Array2<f64>,
+        _first_block_row: usize,
+        _watermark: &[u8],
+        _strength: f64,
+    ) -> Result<Array2<f64>> {
+        Err(WatermarkError::InvalidArgument(
+            Message::StripUnsupported.format(&[&self.name()]),
+        ))
+    }
+
+    /// 读取条带中各块承载的比特，返回（比特序号, 软判决）
+    fn extract_strip_soft(
+        &self,
+        _plan: &StripPlan,
+        _strip: &Array2<f64>,
+        _first_block_row: usize,
+    ) -> Result<Vec<(usize, (u8, f64))>> {
+        Err(WatermarkError::InvalidArgument(
+            Message::StripUnsupported.format(&[&self.name()]),
+        ))
+    }
+
+    /// 嵌入任意字节载荷，按高位在前展开为比特后调用 `embed`
+    fn embed_bytes(
+        &self,
+        data: &Array2<f64>,
+        payload: &[u8],
+        strength: f64,
+    ) -> Result<Array2<f64>> {
+        self.embed(data, &WatermarkUtils::bytes_to_bits(payload), strength)
+    }
+
+    /// 提取指定字节数的载荷，不做任何文本校验
+    fn extract_bytes(&self, data: &Array2<f64>, byte_length: usize) -> Result<Vec<u8>> {
+        let bits = self.extract(data, byte_length * 8)?;
+        Ok(WatermarkUtils::bits_to_bytes(&bits))
+    }
+
+    /// 给定尺寸的数据矩阵最多可承载的水印比特数
+    ///
+    /// 默认按每个完整的8x8块承载一个比特估计，不足一块的边缘不计；
+    /// 嵌入方式不同的算法应覆盖此方法，否则容量检查可能过松或过严。
+    fn capacity(&self, rows: usize, cols: usize) -> usize {
+        (rows / 8) * (cols / 8)
+    }
+
+    /// 数据矩阵的行数和列数需要对齐到的倍数，分块处理的算法为块的边长，默认不要求对齐
+    ///
+    /// 音频样本折叠成矩阵时按此补齐边长，并只把完整落在原始样本内的块计入容量。
+    fn alignment(&self) -> usize {
+        1
+    }
+
+    /// 能否用于该类媒体；视频逐帧按图片处理
+    fn supports(&self, _media_type: MediaType) -> bool {
+        true
+    }
+
+    /// 水印能否经受有损压缩等常见处理；脆弱水印只适合无损保存和完整性校验
+    fn robust(&self) -> bool {
+        true
+    }
+
     /// 获取算法名称
     fn name(&self) -> &'static str;
 }
@@ -32,10 +223,45 @@ pub trait WatermarkAlgorithm {
 pub struct WatermarkUtils;
 
 impl WatermarkUtils {
+    /// 检测水印是否存在时探测的比特数（对应前4字节载荷）
+    pub const DETECTION_PROBE_BITS: usize = 32;
+
+    /// 存在性得分的判定阈值
+    ///
+    /// 平滑图片带水印后得分接近1，无水印时接近0；纹理丰富的自然图片无水印时约0.35-0.4，
+    /// 纯随机噪声图约0.6。音频带同步标记时接近1，无水印时约0.4，见各媒体的检测测试
+    pub const DETECTION_THRESHOLD: f64 = 0.7;
+
+    /// 校验水印强度必须是 [0.0, 1.0] 范围内的有限数
+    pub fn validate_strength(strength: f64) -> Result<()> {
+        if !strength.is_finite() || !(0.0..=1.0).contains(&strength) {
+            return Err(WatermarkError::InvalidArgument(
+                Message::StrengthOutOfRange.format(&[&strength]),
+            ));
+        }
+        Ok(())
+    }
+
+    /// 校验载荷比特数不超过容量，超出时返回 `CapacityExceeded`
+    pub fn ensure_capacity(required_bits: usize, capacity: usize) -> Result<()> {
+        if required_bits > capacity {
+            return Err(WatermarkError::CapacityExceeded {
+                needed: required_bits,
+                available: capacity,
+            });
+        }
+        Ok(())
+    }
+
     /// 将字符串转换为二进制数据
     pub fn string_to_bits(s: &str) -> Vec<u8> {
-        let mut bits = Vec::new();
-        for byte in s.bytes() {
+        Self::bytes_to_bits(s.as_bytes())
+    }
+
+    /// 将字节数据转换为二进制数据（高位在前）
+    pub fn bytes_to_bits(bytes: &[u8]) -> Vec<u8> {
+        let mut bits = Vec::with_capacity(bytes.len() * 8);
+        for &byte in bytes {
             for i in (0..8).rev() {
                 bits.push((byte >> i) & 1);
             }
@@ -43,30 +269,79 @@ impl WatermarkUtils {
         bits
     }
 
-    /// 将二进制数据转换为字符串（严格模式）
+    /// 将二进制数据转换为字节数据，末尾不足8位的部分按0补齐
+    pub fn bits_to_bytes(bits: &[u8]) -> Vec<u8> {
+        bits.chunks(8)
+            .map(|chunk| {
+                chunk.iter().enumerate().fold(0u8, |byte, (i, &bit)| {
+                    if bit != 0 {
+                        byte | (1 << (7 - i))
+                    } else {
+                        byte
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// 软判决结果的平均置信度，没有比特时为0.0
+    pub fn mean_confidence(decisions: &[(u8, f64)]) -> f64 {
+        if decisions.is_empty() {
+            return 0.0;
+        }
+        decisions
+            .iter()
+            .map(|&(_, confidence)| confidence)
+            .sum::<f64>()
+            / decisions.len() as f64
+    }
+
+    /// 将二进制数据转换为字符串（严格模式），在第一个空字节处截断
     pub fn bits_to_string(bits: &[u8]) -> Result<String> {
-        if bits.len() % 8 != 0 {
+        if !bits.len().is_multiple_of(8) {
             return Err(crate::error::WatermarkError::InvalidWatermark);
         }
 
-        let mut bytes = Vec::new();
-        for chunk in bits.chunks(8) {
-            let mut byte = 0u8;
-            for (i, &bit) in chunk.iter().enumerate() {
-                if bit != 0 {
-                    byte |= 1 << (7 - i);
-                }
-            }
-            bytes.push(byte);
-        }
+        Self::bytes_to_string(Self::bits_to_bytes(bits))
+    }
 
+    /// 将字节转换为字符串（严格模式），与视频提取一样在第一个空字节处截断
+    ///
+    /// 提取长度大于实际载荷时多出的字节是补零，截断后不会留在文本末尾；
+    /// 截断前的部分必须是合法的UTF-8。
+    pub fn bytes_to_string(mut bytes: Vec<u8>) -> Result<String> {
+        if let Some(null_pos) = bytes.iter().position(|&b| b == 0) {
+            bytes.truncate(null_pos);
+        }
         String::from_utf8(bytes).map_err(|_| crate::error::WatermarkError::InvalidWatermark)
     }
 
+    /// 直接在数据矩阵上嵌入文本水印，适用于传感器读数等非媒体数据
+    ///
+    /// 文本按UTF-8字节展开为比特后调用算法的 `embed`，提取时用 `extract_text` 并传入相同的字节数。
+    pub fn embed_text(
+        algorithm: &dyn WatermarkAlgorithm,
+        data: &Array2<f64>,
+        text: &str,
+        strength: f64,
+    ) -> Result<Array2<f64>> {
+        algorithm.embed(data, &Self::string_to_bits(text), strength)
+    }
+
+    /// 从数据矩阵中提取 `length` 字节的文本水印，严格按UTF-8解码并在第一个空字节处截断
+    pub fn extract_text(
+        algorithm: &dyn WatermarkAlgorithm,
+        data: &Array2<f64>,
+        length: usize,
+    ) -> Result<String> {
+        let bits = algorithm.extract(data, length * 8)?;
+        Self::bits_to_string(&bits)
+    }
+
     /// 将二进制数据转换为字符串（宽松模式，用于调试）
     pub fn bits_to_string_lossy(bits: &[u8]) -> String {
-        if bits.len() % 8 != 0 {
-            return format!("[错误: 长度{}不是8的倍数]", bits.len());
+        if !bits.len().is_multiple_of(8) {
+            return format!("[{}]", Message::BitsNotByteAligned.format(&[&bits.len()]));
         }
 
         let mut bytes = Vec::new();
@@ -85,31 +360,39 @@ impl WatermarkUtils {
 
     /// 分析提取的比特数据，提供调试信息
     pub fn analyze_extracted_bits(bits: &[u8]) -> String {
-        let mut analysis = "比特数据分析:\n".to_string();
-        analysis.push_str(&format!("- 总长度: {} 比特\n", bits.len()));
+        let mut analysis = format!("{}\n", Message::BitsAnalysis.text());
         analysis.push_str(&format!(
-            "- 字节数: {} ({}完整)\n",
-            bits.len() / 8,
-            if bits.len() % 8 == 0 { "" } else { "不" }
+            "- {}\n",
+            Message::BitsTotal.format(&[&bits.len()])
         ));
+        let bytes_line = if bits.len().is_multiple_of(8) {
+            Message::BitsBytesComplete
+        } else {
+            Message::BitsBytesIncomplete
+        };
+        analysis.push_str(&format!("- {}\n", bytes_line.format(&[&(bits.len() / 8)])));
 
         // 统计0和1的分布
         let ones = bits.iter().filter(|&&bit| bit == 1).count();
         let zeros = bits.len() - ones;
         analysis.push_str(&format!(
-            "- 1的数量: {} ({:.1}%)\n",
-            ones,
-            ones as f32 * 100.0 / bits.len() as f32
+            "- {}\n",
+            Message::BitsOnes.format(&[
+                &ones,
+                &format!("{:.1}", ones as f32 * 100.0 / bits.len() as f32)
+            ])
         ));
         analysis.push_str(&format!(
-            "- 0的数量: {} ({:.1}%)\n",
-            zeros,
-            zeros as f32 * 100.0 / bits.len() as f32
+            "- {}\n",
+            Message::BitsZeros.format(&[
+                &zeros,
+                &format!("{:.1}", zeros as f32 * 100.0 / bits.len() as f32)
+            ])
         ));
 
         // 尝试转换为字节并显示
-        if bits.len() % 8 == 0 {
-            analysis.push_str("- 字节值: [");
+        if bits.len().is_multiple_of(8) {
+            analysis.push_str(&format!("- {}: [", Message::BitsByteValues.text()));
             for chunk in bits.chunks(8) {
                 let mut byte = 0u8;
                 for (i, &bit) in chunk.iter().enumerate() {
@@ -134,10 +417,12 @@ impl WatermarkUtils {
             }
 
             match String::from_utf8(bytes.clone()) {
-                Ok(string) => analysis.push_str(&format!("- UTF-8解码: '{string}'\n")),
+                Ok(string) => {
+                    analysis.push_str(&format!("- {}\n", Message::BitsUtf8.format(&[&string])))
+                }
                 Err(_) => analysis.push_str(&format!(
-                    "- UTF-8解码失败，使用lossy: '{}'\n",
-                    String::from_utf8_lossy(&bytes)
+                    "- {}\n",
+                    Message::BitsUtf8Lossy.format(&[&String::from_utf8_lossy(&bytes)])
                 )),
             }
         }
@@ -185,3 +470,65 @@ impl WatermarkUtils {
         Ok(final_bits)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 只实现必需方法的算法，用于检查各默认实现
+    struct Minimal;
+
+    impl WatermarkAlgorithm for Minimal {
+        fn embed(
+            &self,
+            data: &Array2<f64>,
+            _watermark: &[u8],
+            _strength: f64,
+        ) -> Result<Array2<f64>> {
+            Ok(data.clone())
+        }
+
+        fn extract(&self, _data: &Array2<f64>, expected_length: usize) -> Result<Vec<u8>> {
+            Ok(vec![0; expected_length])
+        }
+
+        fn name(&self) -> &'static str {
+            "Minimal"
+        }
+    }
+
+    #[test]
+    fn default_capacity_is_one_bit_per_full_8x8_block() {
+        assert_eq!(Minimal.capacity(64, 64), 64);
+        assert_eq!(Minimal.capacity(70, 63), 56);
+        assert_eq!(Minimal.capacity(7, 1000), 0);
+    }
+
+    #[test]
+    fn strength_must_be_a_finite_value_in_the_unit_range() {
+        for strength in [0.0, 1.0] {
+            assert!(WatermarkUtils::validate_strength(strength).is_ok());
+        }
+        for strength in [-0.01, 1.01, 1.5, f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(
+                matches!(
+                    WatermarkUtils::validate_strength(strength),
+                    Err(WatermarkError::InvalidArgument(_))
+                ),
+                "{strength} was accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn text_round_trips_through_dct() {
+        let mut rng = crate::watermark::prng::SeededRng::new(7);
+        let data = Array2::from_shape_fn((128, 128), |_| rng.next_below(256) as f64 / 255.0);
+        let algorithm = crate::watermark::DctWatermark::new();
+        let text = "seal 水印";
+
+        let marked = WatermarkUtils::embed_text(&algorithm, &data, text, 0.1).unwrap();
+        let extracted = WatermarkUtils::extract_text(&algorithm, &marked, text.len()).unwrap();
+        assert_eq!(extracted, text);
+    }
+}